@@ -20,6 +20,7 @@ mod utils;
 mod youtube;
 
 pub use types::{UnlockItem, default_unlock_items};
+pub use utils::country_code_to_emoji;
 
 use bahamut::check_bahamut_anime;
 use bilibili::{check_bilibili_china_mainland, check_bilibili_hk_mc_tw};
@@ -4,22 +4,33 @@ use crate::config::profiles;
 use crate::utils::window_manager::WindowManager;
 use crate::{
     config::{
-        Config, IProfiles, PrfItem, PrfOption,
+        Config, IProfiles, PrfChangeSummary, PrfItem, PrfOption,
+        diff::ProfileDiff,
         profiles::{
             PROFILE_WRITE_LOCK, profiles_append_item_with_filedata_safe, profiles_patch_item_safe,
             profiles_reorder_safe, profiles_save_file_safe,
         },
         profiles_append_item_safe,
+        revision::{self, PrfRevision},
     },
     core::{CoreManager, handle, timer::Timer, tray::Tray, validate::ValidationOutcome},
+    enhance::{
+        self,
+        preview::EnhancePreview,
+        script::{self, ScriptTestReport},
+    },
     feat,
-    utils::{dirs, help},
+    utils::{cron::CronSchedule, dirs, help, qr, resolve},
 };
 use clash_verge_draft::{Draft, SharedDraft};
 use clash_verge_logging::{Type, logging, logging_error};
 use scopeguard::defer;
 use smartstring::alias::String;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tauri_plugin_clipboard_manager::ClipboardExt as _;
 
 static CURRENT_SWITCHING_PROFILE: AtomicBool = AtomicBool::new(false);
 
@@ -64,6 +75,41 @@ pub async fn enhance_profiles() -> CmdResult<ValidationOutcome> {
     }
 }
 
+/// 试运行增强流程，返回每个阶段的配置快照与改动；`index` 可预览非当前订阅
+#[tauri::command]
+pub async fn preview_enhance(index: Option<String>) -> CmdResult<EnhancePreview> {
+    let mut profiles = Config::profiles().await.latest_arc().as_ref().clone();
+    if let Some(index) = index {
+        profiles.get_item(&index).stringify_err()?;
+        profiles.current = Some(index);
+    }
+    enhance::preview(&profiles)
+        .await
+        .with_error_code("PROFILE_ENHANCE_FAILED")
+}
+
+/// 用测试套件中的样例配置运行脚本，返回逐条用例的结果
+#[tauri::command]
+pub async fn test_profile_script(index: String, suite_path: std::string::String) -> CmdResult<ScriptTestReport> {
+    let item = Config::profiles()
+        .await
+        .latest_arc()
+        .get_item(&index)
+        .stringify_err()?
+        .clone();
+    if item.itype.as_deref() != Some("script") {
+        return Err(coded_error(
+            "SCRIPT_TEST_FAILED",
+            format!("profile \"{index}\" is not a script"),
+        ));
+    }
+    let script = item.read_file().await.with_error_code("SCRIPT_TEST_FAILED")?;
+    let cases = script::read_script_tests(Path::new(&suite_path))
+        .await
+        .with_error_code("SCRIPT_TEST_FAILED")?;
+    Ok(script::run_script_tests(&script, cases).await)
+}
+
 /// 导入配置文件
 #[tauri::command]
 pub async fn import_profile(url: std::string::String, option: Option<PrfOption>) -> CmdResult {
@@ -102,6 +148,43 @@ pub async fn import_profile(url: std::string::String, option: Option<PrfOption>)
     Ok(())
 }
 
+/// 从二维码图片导入订阅，`path` 为空时读取剪贴板中的图片，返回交给深链接导入的链接
+#[tauri::command]
+pub async fn import_profile_from_qr(path: Option<std::string::String>) -> CmdResult<String> {
+    let decoded = crate::process::AsyncHandler::spawn_blocking(move || match path {
+        Some(path) => qr::decode_image(&std::fs::read(path)?),
+        None => {
+            let image = handle::Handle::app_handle().clipboard().read_image()?;
+            qr::decode_rgba(image.width(), image.height(), image.rgba())
+        }
+    })
+    .await
+    .stringify_err()?;
+    let text = decoded.map_err(|e| {
+        logging!(warn, Type::Cmd, "[导入订阅] 二维码识别失败: {e:#}");
+        coded_error("PROFILE_IMPORT_FAILED", format!("{e:#}"))
+    })?;
+
+    let Some(link) = qr::subscription_link(&text) else {
+        logging!(
+            warn,
+            Type::Cmd,
+            "[导入订阅] 二维码内容不是订阅链接: {}",
+            help::mask_url(&text)
+        );
+        return Err(coded_error(
+            "PROFILE_IMPORT_FAILED",
+            "the QR code does not contain a subscription link",
+        ));
+    };
+    logging!(info, Type::Cmd, "[导入订阅] 从二维码导入: {}", help::mask_url(&link));
+    resolve::scheme::import_link(&link).await.map_err(|e| {
+        logging!(warn, Type::Cmd, "[导入订阅] 二维码订阅导入失败: {e:#}");
+        coded_error("PROFILE_IMPORT_FAILED", format!("{e:#}"))
+    })?;
+    Ok(link.into())
+}
+
 /// 调整profile的顺序
 #[tauri::command]
 pub async fn reorder_profile(active_id: String, over_id: String) -> CmdResult {
@@ -150,6 +233,51 @@ pub async fn update_profile(index: String, option: Option<PrfOption>) -> CmdResu
     }
 }
 
+/// 批量更新远程订阅与规则集（可只更新 uids 中的项），结束后最多重载一次内核
+#[tauri::command]
+pub async fn update_all_profiles(
+    uids: Option<Vec<String>>,
+    concurrency: Option<usize>,
+) -> CmdResult<feat::BatchSummary> {
+    feat::update_all_profiles(uids, concurrency)
+        .await
+        .with_error_code("PROFILE_UPDATE_FAILED")
+}
+
+/// 取消正在进行的批量更新
+#[tauri::command]
+pub fn cancel_update_all_profiles() -> bool {
+    feat::cancel_update_all_profiles()
+}
+
+/// 获取订阅最近一次更新带来的变化摘要
+#[tauri::command]
+pub async fn get_profile_change_summary(index: String) -> CmdResult<Option<PrfChangeSummary>> {
+    Ok(Config::profiles()
+        .await
+        .latest_arc()
+        .get_item(&index)
+        .stringify_err()?
+        .last_change
+        .clone())
+}
+
+/// 接受被暂存的订阅更新
+#[tauri::command]
+pub async fn accept_held_update(index: String) -> CmdResult {
+    feat::accept_held_update(&index)
+        .await
+        .with_error_code("PROFILE_UPDATE_FAILED")
+}
+
+/// 拒绝被暂存的订阅更新，保留当前内容
+#[tauri::command]
+pub async fn reject_held_update(index: String) -> CmdResult {
+    feat::reject_held_update(&index)
+        .await
+        .with_error_code("PROFILE_UPDATE_FAILED")
+}
+
 /// 删除配置文件
 #[tauri::command]
 pub async fn delete_profile(index: String) -> CmdResult {
@@ -369,15 +497,42 @@ pub async fn patch_profile(index: String, profile: PrfItem) -> CmdResult {
     let should_refresh_timer = if let Ok(old_profile) = profiles.latest_arc().get_item(&index)
         && let Some(new_option) = profile.option.as_ref()
     {
-        let old_interval = old_profile.option.as_ref().and_then(|o| o.update_interval);
-        let new_interval = new_option.update_interval;
-        let old_allow_auto_update = old_profile.option.as_ref().and_then(|o| o.allow_auto_update);
-        let new_allow_auto_update = new_option.allow_auto_update;
-        (old_interval != new_interval) || (old_allow_auto_update != new_allow_auto_update)
+        let old_option = old_profile.option.as_ref();
+        (old_option.and_then(|o| o.update_interval) != new_option.update_interval)
+            || (old_option.and_then(|o| o.allow_auto_update) != new_option.allow_auto_update)
+            || (old_option.and_then(|o| o.update_cron.as_ref()) != new_option.update_cron.as_ref())
+            || (old_option.and_then(|o| o.update_jitter) != new_option.update_jitter)
     } else {
         false
     };
 
+    if let Some(expr) = profile
+        .option
+        .as_ref()
+        .and_then(|o| o.update_cron.as_ref())
+        .filter(|e| !e.trim().is_empty())
+    {
+        CronSchedule::parse(expr).with_error_code("PROFILE_UPDATE_FAILED")?;
+    }
+
+    // 组合订阅的来源与主订阅在创建时已校验，编辑时同样不能留空或指向来源之外
+    if let Some(option) = profile.option.as_ref()
+        && let Some(sources) = option.sources.as_ref()
+    {
+        if sources.is_empty() {
+            return Err(coded_error(
+                "PROFILE_UPDATE_FAILED",
+                "a composite profile needs at least one source",
+            ));
+        }
+        if option.primary.as_ref().is_some_and(|p| !sources.contains(p)) {
+            return Err(coded_error(
+                "PROFILE_UPDATE_FAILED",
+                "the primary profile should be one of the sources",
+            ));
+        }
+    }
+
     // A selection written from the UI or the chain proxy is newer than anything a restore still
     // in flight captured; without this it would be pushed back to the older node moments later.
     let records_a_selection = profile.selected.is_some();
@@ -475,6 +630,43 @@ pub async fn get_next_update_time(uid: String) -> CmdResult<Option<i64>> {
     Ok(next_time)
 }
 
+/// 列出订阅历史版本
+#[tauri::command]
+pub async fn list_profile_revisions(index: String) -> CmdResult<Vec<PrfRevision>> {
+    revision::list_revisions(&index)
+        .await
+        .with_error_code("PROFILE_REVISION_FAILED")
+}
+
+/// 对比两个订阅历史版本
+#[tauri::command]
+pub async fn diff_profile_revisions(index: String, from: String, to: String) -> CmdResult<ProfileDiff> {
+    revision::diff_revisions(&index, &from, &to)
+        .await
+        .with_error_code("PROFILE_REVISION_FAILED")
+}
+
+/// 回滚到订阅历史版本
+#[tauri::command]
+pub async fn rollback_profile_revision(index: String, revision: String) -> CmdResult<ValidationOutcome> {
+    match feat::rollback_profile(&index, &revision).await {
+        Ok(outcome) if outcome.is_valid() => {
+            handle::Handle::refresh_clash();
+            handle::Handle::notify_profile_changed(&index);
+            Ok(outcome)
+        }
+        Ok(outcome) => {
+            logging!(warn, Type::Cmd, "Profile rollback failed validation: {}", outcome);
+            handle_validation_notice(&outcome, ValidationNoticeTarget::Runtime, "运行时配置");
+            Ok(outcome)
+        }
+        Err(e) => {
+            logging!(error, Type::Cmd, "{}", e);
+            Err(coded_error("PROFILE_ROLLBACK_FAILED", e))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{commit_current_profile, run_profile_config_update_transition};
@@ -1,5 +1,14 @@
 use super::CmdResult;
-use crate::{cmd::StringifyErr as _, config::Config, core::CoreManager, utils::yaml_emitter};
+use crate::{
+    cmd::StringifyErr as _,
+    config::Config,
+    convert::{
+        share_link_export::{self, GroupShare, ShareLink},
+        sing_box_export::{self, SingBoxExport},
+    },
+    core::CoreManager,
+    utils::yaml_emitter,
+};
 use anyhow::{Context as _, anyhow};
 use clash_verge_logging::{Type, logging};
 use serde_yaml_ng::Mapping;
@@ -29,6 +38,69 @@ pub async fn get_runtime_yaml() -> CmdResult<String> {
         .stringify_err()
 }
 
+/// 将运行时配置导出为 sing-box 配置
+#[tauri::command]
+pub async fn export_runtime_sing_box() -> CmdResult<SingBoxExport> {
+    let runtime = Config::runtime().await;
+    let runtime = runtime.latest_arc();
+
+    let config = runtime
+        .config
+        .as_ref()
+        .ok_or_else(|| anyhow!("the runtime config is not ready"))
+        .stringify_err()?;
+    let export = sing_box_export::export_config(config);
+    if !export.warnings.is_empty() {
+        logging!(
+            info,
+            Type::Config,
+            "[sing-box 导出] {} 项未能转换: {:?}",
+            export.warnings.len(),
+            export.warnings
+        );
+    }
+    Ok(export)
+}
+
+/// 将运行时配置中的节点导出为分享链接
+#[tauri::command]
+pub async fn export_proxy_share_link(name: String) -> CmdResult<ShareLink> {
+    let runtime = Config::runtime().await;
+    let runtime = runtime.latest_arc();
+
+    let config = runtime
+        .config
+        .as_ref()
+        .ok_or_else(|| anyhow!("the runtime config is not ready"))
+        .stringify_err()?;
+    share_link_export::export_proxy(config, &name).stringify_err()
+}
+
+/// 将运行时配置中的代理组导出为 base64 订阅
+#[tauri::command]
+pub async fn export_group_subscription(name: String) -> CmdResult<GroupShare> {
+    let runtime = Config::runtime().await;
+    let runtime = runtime.latest_arc();
+
+    let config = runtime
+        .config
+        .as_ref()
+        .ok_or_else(|| anyhow!("the runtime config is not ready"))
+        .stringify_err()?;
+    let share = share_link_export::export_group(config, &name).stringify_err()?;
+    if !share.skipped.is_empty() {
+        logging!(
+            info,
+            Type::Config,
+            "[分享链接导出] 代理组 {} 中有 {} 项未能导出: {:?}",
+            name,
+            share.skipped.len(),
+            share.skipped
+        );
+    }
+    Ok(share)
+}
+
 /// 获取运行时存在的键
 #[tauri::command]
 pub async fn get_runtime_exists() -> CmdResult<HashSet<String>> {
@@ -33,7 +33,8 @@ pub async fn save_profile_file(index: String, file_data: Option<String>) -> CmdR
         let profiles = Config::profiles().await;
         let profiles_guard = profiles.latest_arc();
         let item = profiles_guard.get_item(&index).stringify_err()?;
-        let is_merge = item.itype.as_ref().is_some_and(|t| t == "merge");
+        // 过滤规则和合并配置一样只是片段，仅做语法检查
+        let is_merge = item.itype.as_ref().is_some_and(|t| t == "merge" || t == "filter");
         let path = item.file.clone().ok_or("file field is null")?;
         let is_script = item.itype.as_ref().is_some_and(|t| t == "script") || path.ends_with(".js");
         let affects_runtime = profile_affects_runtime(&profiles_guard, &index);
@@ -125,6 +126,12 @@ fn profile_affects_runtime(profiles: &IProfiles, index: &str) -> bool {
         item.current_groups().map_or("Groups", String::as_str),
     ]
     .contains(&index)
+        || item.current_filter().is_some_and(|filter| filter == index)
+        || item
+            .option
+            .as_ref()
+            .and_then(|o| o.rule_sets.as_ref())
+            .is_some_and(|rule_sets| rule_sets.iter().any(|uid| uid == index))
 }
 
 async fn handle_saved_profile_file(
@@ -68,7 +68,7 @@ const fn notice_key(kind: ValidationErrorKind, target: ValidationNoticeTarget) -
 
 pub fn handle_validation_notice(outcome: &ValidationOutcome, target: ValidationNoticeTarget, file_type: &str) {
     match outcome {
-        ValidationOutcome::Invalid { kind, message } => {
+        ValidationOutcome::Invalid { kind, message, .. } => {
             let status = notice_key(*kind, target);
             logging!(warn, Type::Config, "{} 验证失败: {}", file_type, message);
             handle::Handle::notice_message(status, message.to_owned());
@@ -0,0 +1,321 @@
+//! Composite profiles: one profile assembled from several subscriptions.
+//!
+//! A composite item owns no nodes of its own. Its `sources` name remote or local profiles, and
+//! each time the runtime config is generated their files are read and stitched together before
+//! the merge/script chain runs, so a provider update shows up without touching the composite.
+//!
+//! The primary source contributes everything except nodes from elsewhere: groups, rules, DNS and
+//! the rest. Every other source adds its `proxies` and `proxy-providers`, renamed with the source
+//! profile's name where they would clash, plus a selector of its own that is offered in the
+//! primary's first `select` group. A renamed provider gets a `path` of its own as well, since
+//! mihomo writes each provider to its path and two sources must not share a file.
+
+use super::{IProfiles, PrfItem};
+use crate::utils::{dirs, help};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
+use std::collections::HashSet;
+
+/// One profile feeding a composite.
+pub struct CompositeSource {
+    /// profile name, used as the prefix for clashing names
+    pub name: String,
+    pub config: Mapping,
+}
+
+/// Whether an item of this type may feed a composite.
+fn is_source_type(itype: Option<&String>) -> bool {
+    itype.is_some_and(|t| t == "remote" || t == "local")
+}
+
+/// Stitch `others` onto `primary`. Proxy names, group names and provider names stay unique.
+pub fn compose_profiles(primary: CompositeSource, others: Vec<CompositeSource>) -> Mapping {
+    let mut config = primary.config;
+    let mut proxies = sequence(&config, "proxies");
+    let mut groups = sequence(&config, "proxy-groups");
+    let mut providers = config
+        .get("proxy-providers")
+        .and_then(Value::as_mapping)
+        .cloned()
+        .unwrap_or_default();
+
+    // proxies and groups share one namespace in mihomo, providers have their own
+    let mut taken = proxies
+        .iter()
+        .chain(groups.iter())
+        .filter_map(entry_name)
+        .map(Into::into)
+        .collect::<HashSet<String>>();
+    let mut taken_providers = providers
+        .keys()
+        .filter_map(Value::as_str)
+        .map(Into::into)
+        .collect::<HashSet<String>>();
+    let mut taken_paths = providers
+        .values()
+        .filter_map(|provider| provider.get("path").and_then(Value::as_str))
+        .map(Into::into)
+        .collect::<HashSet<String>>();
+
+    let mut source_groups = Vec::new();
+    for source in others {
+        let mut members = Vec::new();
+        for mut proxy in sequence(&source.config, "proxies") {
+            let Some(name) = entry_name(&proxy).map(String::from) else {
+                continue;
+            };
+            let name = claim_name(&mut taken, &source.name, name);
+            if let Some(entry) = proxy.as_mapping_mut() {
+                entry.insert("name".into(), name.as_str().into());
+            }
+            members.push(Value::from(name.as_str()));
+            proxies.push(proxy);
+        }
+
+        let mut used = Vec::new();
+        if let Some(source_providers) = source.config.get("proxy-providers").and_then(Value::as_mapping) {
+            for (original, provider) in source_providers {
+                let Some(original) = original.as_str() else {
+                    continue;
+                };
+                let key = claim_name(&mut taken_providers, &source.name, original.into());
+                let mut provider = provider.clone();
+                if let Some(entry) = provider.as_mapping_mut()
+                    && let Some(path) = entry.get("path").and_then(Value::as_str)
+                {
+                    let path = claim_path(&mut taken_paths, path, &key, key.as_str() != original);
+                    entry.insert("path".into(), path.as_str().into());
+                }
+                used.push(Value::from(key.as_str()));
+                providers.insert(key.as_str().into(), provider);
+            }
+        }
+
+        if members.is_empty() && used.is_empty() {
+            continue;
+        }
+        let group_name = claim_name(&mut taken, "", source.name.clone());
+        let mut group = Mapping::new();
+        group.insert("name".into(), group_name.as_str().into());
+        group.insert("type".into(), "select".into());
+        if !members.is_empty() {
+            group.insert("proxies".into(), Value::Sequence(members));
+        }
+        if !used.is_empty() {
+            group.insert("use".into(), Value::Sequence(used));
+        }
+        source_groups.push((group_name, Value::Mapping(group)));
+    }
+
+    if let Some(selector) = groups
+        .iter_mut()
+        .filter_map(Value::as_mapping_mut)
+        .find(|group| group.get("type").and_then(Value::as_str) == Some("select"))
+    {
+        let entry = selector
+            .entry("proxies".into())
+            .or_insert_with(|| Value::Sequence(Vec::new()));
+        if let Some(members) = entry.as_sequence_mut() {
+            members.extend(source_groups.iter().map(|(name, _)| Value::from(name.as_str())));
+        }
+    }
+    groups.extend(source_groups.into_iter().map(|(_, group)| group));
+
+    config.insert("proxies".into(), Value::Sequence(proxies));
+    config.insert("proxy-groups".into(), Value::Sequence(groups));
+    if !providers.is_empty() {
+        config.insert("proxy-providers".into(), Value::Mapping(providers));
+    }
+    config
+}
+
+fn sequence(config: &Mapping, key: &str) -> Vec<Value> {
+    config
+        .get(key)
+        .and_then(Value::as_sequence)
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn entry_name(entry: &Value) -> Option<&str> {
+    entry.get("name").and_then(Value::as_str)
+}
+
+/// `name` if it is still free, otherwise `"<prefix> <name>"`, then `"<prefix> <name> 2"`...
+fn claim_name(taken: &mut HashSet<String>, prefix: &str, name: String) -> String {
+    let mut candidate = name.clone();
+    if taken.contains(&candidate) && !prefix.is_empty() {
+        candidate = format!("{prefix} {name}").into();
+    }
+    let base = candidate.clone();
+    let mut n = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{base} {n}").into();
+        n += 1;
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+/// The file a provider taken in as `key` keeps: its own `path` while that is free and the provider
+/// kept its name, otherwise a file named after `key` in the same directory.
+fn claim_path(taken: &mut HashSet<String>, path: &str, key: &str, renamed: bool) -> String {
+    if !renamed && taken.insert(path.into()) {
+        return path.into();
+    }
+    let (dir, file) = path.rsplit_once('/').unwrap_or((".", path));
+    let extension = file.rsplit_once('.').map_or("yaml", |(_, extension)| extension);
+    let stem = key
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .collect::<String>();
+    let mut candidate: String = format!("{dir}/{stem}.{extension}").into();
+    let mut n = 2;
+    while !taken.insert(candidate.clone()) {
+        candidate = format!("{dir}/{stem}-{n}.{extension}").into();
+        n += 1;
+    }
+    candidate
+}
+
+impl IProfiles {
+    /// The mapping a composite item stands for, read fresh from its sources.
+    ///
+    /// A source that can not be read is left out with a warning so one broken subscription does
+    /// not take the others down; the primary is the exception, as it carries the groups and rules.
+    pub(super) async fn composite_mapping(&self, item: &PrfItem) -> Result<Mapping> {
+        let option = item.option.as_ref();
+        let sources = option.and_then(|o| o.sources.clone()).unwrap_or_default();
+        let Some(primary_uid) = option
+            .and_then(|o| o.primary.clone())
+            .or_else(|| sources.first().cloned())
+        else {
+            bail!("the composite profile has no sources");
+        };
+
+        let primary = self.read_composite_source(&primary_uid).await?;
+        let mut others = Vec::new();
+        for uid in sources.iter().filter(|uid| **uid != primary_uid) {
+            match self.read_composite_source(uid).await {
+                Ok(source) => others.push(source),
+                Err(err) => logging!(warn, Type::Config, "组合订阅跳过来源 {}: {}", uid, err),
+            }
+        }
+        Ok(compose_profiles(primary, others))
+    }
+
+    async fn read_composite_source(&self, uid: &String) -> Result<CompositeSource> {
+        let item = self.get_item(uid)?;
+        if !is_source_type(item.itype.as_ref()) {
+            bail!("profile \"{uid}\" can not be a composite source");
+        }
+        let Some(file) = item.file.as_ref() else {
+            bail!("failed to get the file field");
+        };
+        let config = help::read_mapping(&dirs::app_profiles_dir()?.join(file.as_str())).await?;
+        Ok(CompositeSource {
+            name: item.name.clone().unwrap_or_else(|| uid.clone()),
+            config,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{CompositeSource, compose_profiles};
+    use serde_yaml_ng::{Mapping, Value};
+
+    fn source(name: &str, yaml: &str) -> CompositeSource {
+        CompositeSource {
+            name: name.into(),
+            config: serde_yaml_ng::from_str(yaml).expect("fixture should be valid yaml"),
+        }
+    }
+
+    fn names(config: &Mapping, key: &str) -> Vec<std::string::String> {
+        config[key]
+            .as_sequence()
+            .expect("key should hold a sequence")
+            .iter()
+            .filter_map(|entry| entry.get("name").and_then(Value::as_str).map(Into::into))
+            .collect()
+    }
+
+    #[test]
+    fn concatenates_nodes_and_prefixes_clashing_names() {
+        let primary = source(
+            "Alpha",
+            r"
+proxies:
+  - { name: HK, type: ss, server: a.example.com, port: 1 }
+proxy-groups:
+  - { name: PROXY, type: select, proxies: [HK, DIRECT] }
+rules:
+  - MATCH,PROXY
+",
+        );
+        let other = source(
+            "Beta",
+            r"
+proxies:
+  - { name: HK, type: ss, server: b.example.com, port: 1 }
+  - { name: JP, type: ss, server: b.example.com, port: 2 }
+proxy-providers:
+  extra: { type: http, url: https://b.example.com/p, path: ./p.yaml }
+proxy-groups:
+  - { name: Ignored, type: select, proxies: [HK] }
+rules:
+  - MATCH,DIRECT
+",
+        );
+
+        let config = compose_profiles(primary, vec![other]);
+        assert_eq!(names(&config, "proxies"), ["HK", "Beta HK", "JP"]);
+        assert_eq!(names(&config, "proxy-groups"), ["PROXY", "Beta"]);
+        assert_eq!(
+            config["proxy-groups"][0]["proxies"],
+            serde_yaml_ng::from_str::<Value>("[HK, DIRECT, Beta]").expect("valid yaml")
+        );
+        assert_eq!(
+            config["proxy-groups"][1]["use"],
+            serde_yaml_ng::from_str::<Value>("[extra]").expect("valid yaml")
+        );
+        assert_eq!(config["proxy-providers"]["extra"]["path"], "./p.yaml");
+        assert_eq!(
+            config["rules"],
+            serde_yaml_ng::from_str::<Value>("['MATCH,PROXY']").expect("valid yaml")
+        );
+    }
+
+    #[test]
+    fn providers_taken_in_never_share_a_file() {
+        let primary = source(
+            "Alpha",
+            r"
+proxy-providers:
+  sub: { type: http, url: https://a.example.com/p, path: ./providers/sub.yaml }
+proxy-groups:
+  - { name: PROXY, type: select, use: [sub] }
+",
+        );
+        let other = source(
+            "Beta",
+            r"
+proxy-providers:
+  sub: { type: http, url: https://b.example.com/p, path: ./providers/sub.yaml }
+  backup: { type: http, url: https://b.example.com/q, path: ./providers/sub.yaml }
+  local: { type: file, path: ./local.txt }
+",
+        );
+
+        let config = compose_profiles(primary, vec![other]);
+        let providers = &config["proxy-providers"];
+        assert_eq!(providers["sub"]["path"], "./providers/sub.yaml");
+        assert_eq!(providers["Beta sub"]["path"], "./providers/Beta_sub.yaml");
+        assert_eq!(providers["backup"]["path"], "./providers/backup.yaml");
+        assert_eq!(providers["local"]["path"], "./local.txt");
+    }
+}
@@ -5,8 +5,9 @@ use crate::{
     core::{
         CoreManager,
         handle::{self, Handle},
+        manager::last_good::remember_last_good,
         tray,
-        validate::CoreConfigValidator,
+        validate::{CoreConfigValidator, ValidationOutcome},
     },
     enhance,
     process::AsyncHandler,
@@ -189,12 +190,22 @@ impl Config {
             match CoreConfigValidator::global().validate_config_outcome().await {
                 Ok(outcome) if outcome.is_valid() => {
                     logging!(info, Type::Config, "配置验证成功");
+                    if let Ok(run_path) = config_result.as_ref() {
+                        remember_last_good(run_path).await;
+                    }
                     // 前端没有必要知道验证成功的消息，也没有事件驱动
                     // Some(("config_validate::success", String::new()))
                     Ok(None)
                 }
                 Ok(outcome) => {
                     let error_msg: String = outcome.to_string().into();
+                    if let ValidationOutcome::Invalid { kind, .. } = outcome
+                        && CoreManager::global()
+                            .use_last_good_config(kind, "config_validate::boot_error", &error_msg)
+                            .await?
+                    {
+                        return Ok(Some(("config_validate::boot_error", error_msg)));
+                    }
                     logging!(
                         warn,
                         Type::Config,
@@ -249,7 +260,7 @@ impl Config {
     }
 
     pub(crate) async fn generate_with_profiles(profiles: &IProfiles) -> Result<()> {
-        let (mut config, exists_keys, logs) = enhance::enhance(profiles).await?;
+        let (mut config, exists_keys, logs, script_errors) = enhance::enhance(profiles).await?;
 
         sanitize_tunnels_proxy(&mut config);
 
@@ -258,6 +269,7 @@ impl Config {
                 config: Some(config),
                 exists_keys,
                 chain_logs: logs,
+                script_errors,
             }
         });
 
@@ -0,0 +1,312 @@
+//! Structural diff between two mihomo profiles.
+//!
+//! A textual diff of two subscriptions is mostly noise: providers reorder keys, re-quote strings
+//! and shuffle nodes between downloads. What a user wants to know is which nodes appeared or
+//! disappeared, which groups changed members, and how the rule list moved.
+
+use serde::Serialize;
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupChange {
+    pub name: String,
+    pub members_added: Vec<String>,
+    pub members_removed: Vec<String>,
+    /// anything besides membership changed: type, url, interval...
+    pub options_changed: bool,
+}
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+pub struct ProxyRename {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileDiff {
+    pub proxies_added: Vec<String>,
+    pub proxies_removed: Vec<String>,
+    /// same name, different settings
+    pub proxies_changed: Vec<String>,
+    /// same settings, different name; not listed as added or removed
+    pub proxies_renamed: Vec<ProxyRename>,
+    pub proxies_before: usize,
+    pub proxies_after: usize,
+    pub groups_added: Vec<String>,
+    pub groups_removed: Vec<String>,
+    pub groups_changed: Vec<GroupChange>,
+    pub providers_added: Vec<String>,
+    pub providers_removed: Vec<String>,
+    pub rules_added: Vec<String>,
+    pub rules_removed: Vec<String>,
+    pub rules_before: usize,
+    pub rules_after: usize,
+}
+
+impl ProfileDiff {
+    pub const fn is_empty(&self) -> bool {
+        self.proxies_added.is_empty()
+            && self.proxies_removed.is_empty()
+            && self.proxies_changed.is_empty()
+            && self.proxies_renamed.is_empty()
+            && self.providers_added.is_empty()
+            && self.providers_removed.is_empty()
+            && self.groups_added.is_empty()
+            && self.groups_removed.is_empty()
+            && self.groups_changed.is_empty()
+            && self.rules_added.is_empty()
+            && self.rules_removed.is_empty()
+    }
+}
+
+/// Compare `old` against `new`; every list keeps the order of the profile it was taken from.
+pub fn diff_profiles(old: &Mapping, new: &Mapping) -> ProfileDiff {
+    let old_proxies = named_entries(old, "proxies");
+    let new_proxies = named_entries(new, "proxies");
+    let (mut proxies_added, mut proxies_removed, proxies_changed) = diff_named(&old_proxies, &new_proxies);
+    let proxies_renamed = take_renames(&old_proxies, &new_proxies, &mut proxies_added, &mut proxies_removed);
+
+    let old_groups = named_entries(old, "proxy-groups");
+    let new_groups = named_entries(new, "proxy-groups");
+    let (groups_added, groups_removed, changed) = diff_named(&old_groups, &new_groups);
+    let old_lookup = old_groups.iter().cloned().collect::<HashMap<_, _>>();
+    let new_lookup = new_groups.iter().cloned().collect::<HashMap<_, _>>();
+    let groups_changed = changed
+        .into_iter()
+        .filter_map(|name| Some(diff_group(name.clone(), old_lookup.get(&name)?, new_lookup.get(&name)?)))
+        .collect();
+
+    let old_providers = mapping_keys(old, "proxy-providers");
+    let new_providers = mapping_keys(new, "proxy-providers");
+    let (providers_added, providers_removed) = diff_lists(&old_providers, &new_providers);
+
+    let old_rules = string_list(old.get("rules"));
+    let new_rules = string_list(new.get("rules"));
+    let (rules_added, rules_removed) = diff_lists(&old_rules, &new_rules);
+
+    ProfileDiff {
+        proxies_added,
+        proxies_removed,
+        proxies_changed,
+        proxies_renamed,
+        proxies_before: old_proxies.len(),
+        proxies_after: new_proxies.len(),
+        groups_added,
+        groups_removed,
+        groups_changed,
+        providers_added,
+        providers_removed,
+        rules_added,
+        rules_removed,
+        rules_before: old_rules.len(),
+        rules_after: new_rules.len(),
+    }
+}
+
+fn named_entries<'a>(config: &'a Mapping, key: &str) -> Vec<(String, &'a Mapping)> {
+    config
+        .get(key)
+        .and_then(Value::as_sequence)
+        .map(|seq| {
+            seq.iter()
+                .filter_map(Value::as_mapping)
+                .filter_map(|entry| Some((entry.get("name")?.as_str()?.into(), entry)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn mapping_keys(config: &Mapping, key: &str) -> Vec<String> {
+    config
+        .get(key)
+        .and_then(Value::as_mapping)
+        .map(|map| map.keys().filter_map(Value::as_str).map(Into::into).collect())
+        .unwrap_or_default()
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_sequence)
+        .map(|seq| seq.iter().filter_map(Value::as_str).map(Into::into).collect())
+        .unwrap_or_default()
+}
+
+fn diff_named(old: &[(String, &Mapping)], new: &[(String, &Mapping)]) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let old_lookup = old
+        .iter()
+        .map(|(name, entry)| (name, *entry))
+        .collect::<HashMap<_, _>>();
+    let new_names = new.iter().map(|(name, _)| name).collect::<HashSet<_>>();
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for (name, entry) in new {
+        match old_lookup.get(name) {
+            None => added.push(name.clone()),
+            Some(previous) if previous != entry => changed.push(name.clone()),
+            Some(_) => {}
+        }
+    }
+    let removed = old
+        .iter()
+        .filter(|(name, _)| !new_names.contains(name))
+        .map(|(name, _)| name.clone())
+        .collect();
+    (added, removed, changed)
+}
+
+fn diff_lists(old: &[String], new: &[String]) -> (Vec<String>, Vec<String>) {
+    let old_set = old.iter().collect::<HashSet<_>>();
+    let new_set = new.iter().collect::<HashSet<_>>();
+    let added = new.iter().filter(|v| !old_set.contains(v)).cloned().collect();
+    let removed = old.iter().filter(|v| !new_set.contains(v)).cloned().collect();
+    (added, removed)
+}
+
+/// Pair removed entries with added ones carrying the same settings, taking them out of both lists.
+fn take_renames(
+    old: &[(String, &Mapping)],
+    new: &[(String, &Mapping)],
+    added: &mut Vec<String>,
+    removed: &mut Vec<String>,
+) -> Vec<ProxyRename> {
+    let old_lookup = old.iter().cloned().collect::<HashMap<_, _>>();
+    let new_lookup = new.iter().cloned().collect::<HashMap<_, _>>();
+    let mut unmatched = added
+        .iter()
+        .filter_map(|name| Some((name.clone(), without_name(new_lookup.get(name)?))))
+        .collect::<Vec<_>>();
+
+    let mut renames = Vec::new();
+    removed.retain(|from| {
+        let Some(settings) = old_lookup.get(from).copied().map(without_name) else {
+            return true;
+        };
+        match unmatched.iter().position(|(_, candidate)| *candidate == settings) {
+            Some(index) => {
+                let (to, _) = unmatched.remove(index);
+                renames.push(ProxyRename { from: from.clone(), to });
+                false
+            }
+            None => true,
+        }
+    });
+    let renamed = renames.iter().map(|rename| &rename.to).collect::<HashSet<_>>();
+    added.retain(|name| !renamed.contains(name));
+    renames
+}
+
+fn without_name(entry: &Mapping) -> Mapping {
+    let mut entry = entry.clone();
+    entry.remove("name");
+    entry
+}
+
+fn diff_group(name: String, old: &Mapping, new: &Mapping) -> GroupChange {
+    let (members_added, members_removed) =
+        diff_lists(&string_list(old.get("proxies")), &string_list(new.get("proxies")));
+    let without_members = |group: &Mapping| {
+        let mut group = group.clone();
+        group.remove("proxies");
+        group
+    };
+    GroupChange {
+        name,
+        members_added,
+        members_removed,
+        options_changed: without_members(old) != without_members(new),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{ProxyRename, diff_profiles};
+    use serde_yaml_ng::Mapping;
+
+    fn parse(yaml: &str) -> Mapping {
+        serde_yaml_ng::from_str(yaml).expect("fixture should be valid yaml")
+    }
+
+    #[test]
+    fn reports_nodes_groups_and_rules_that_moved() {
+        let old = parse(
+            r"
+proxies:
+  - { name: HK, type: ss, server: hk.example.com, port: 1 }
+  - { name: JP, type: ss, server: jp.example.com, port: 1 }
+proxy-groups:
+  - { name: PROXY, type: select, proxies: [HK, JP] }
+  - { name: Gone, type: select, proxies: [DIRECT] }
+rules:
+  - DOMAIN,a.example.com,PROXY
+  - MATCH,PROXY
+",
+        );
+        let new = parse(
+            r"
+proxies:
+  - { name: HK, type: ss, server: hk2.example.com, port: 1 }
+  - { name: SG, type: ss, server: sg.example.com, port: 1 }
+proxy-groups:
+  - { name: PROXY, type: select, proxies: [HK, SG] }
+rules:
+  - MATCH,PROXY
+",
+        );
+
+        let diff = diff_profiles(&old, &new);
+        assert_eq!(diff.proxies_added, vec!["SG"]);
+        assert_eq!(diff.proxies_removed, vec!["JP"]);
+        assert_eq!(diff.proxies_changed, vec!["HK"]);
+        assert_eq!(diff.groups_removed, vec!["Gone"]);
+        assert_eq!(diff.groups_changed.len(), 1);
+        assert_eq!(diff.groups_changed[0].members_added, vec!["SG"]);
+        assert_eq!(diff.groups_changed[0].members_removed, vec!["JP"]);
+        assert!(!diff.groups_changed[0].options_changed);
+        assert_eq!(diff.rules_removed, vec!["DOMAIN,a.example.com,PROXY"]);
+        assert_eq!((diff.rules_before, diff.rules_after), (2, 1));
+        assert!(diff_profiles(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn renamed_nodes_and_new_providers_are_told_apart() {
+        let old = parse(
+            r"
+proxies:
+  - { name: HK 01, type: ss, server: hk.example.com, port: 1 }
+  - { name: JP 01, type: ss, server: jp.example.com, port: 1 }
+proxy-providers:
+  old: { type: http, url: https://a.example.com }
+",
+        );
+        let new = parse(
+            r"
+proxies:
+  - { name: 🇭🇰 HK 01, type: ss, server: hk.example.com, port: 1 }
+  - { name: JP 02, type: ss, server: jp2.example.com, port: 1 }
+proxy-providers:
+  old: { type: http, url: https://a.example.com }
+  extra: { type: http, url: https://b.example.com }
+",
+        );
+
+        let diff = diff_profiles(&old, &new);
+        assert_eq!(
+            diff.proxies_renamed,
+            vec![ProxyRename {
+                from: "HK 01".into(),
+                to: "🇭🇰 HK 01".into(),
+            }]
+        );
+        assert_eq!(diff.proxies_added, vec!["JP 02"]);
+        assert_eq!(diff.proxies_removed, vec!["JP 01"]);
+        assert_eq!((diff.proxies_before, diff.proxies_after), (2, 2));
+        assert_eq!(diff.providers_added, vec!["extra"]);
+        assert!(diff.providers_removed.is_empty());
+    }
+}
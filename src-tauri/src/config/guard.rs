@@ -0,0 +1,235 @@
+//! Checks on a downloaded subscription before it replaces the stored one.
+//!
+//! Providers do not always fail loudly. An expired token or a captive portal can answer with a
+//! 200 carrying a login page or a JSON error, and a provider in the middle of maintenance can serve
+//! a profile that lost most of its nodes. When the profile asks for it with `update_guard`, the
+//! first kind is refused outright and the second is held next to the profile for the user to accept
+//! or reject.
+
+use super::{HoldReason, PrfUpdateGuard, diff::diff_profiles};
+use serde_yaml_ng::{Mapping, Value};
+
+/// how much of a body is looked at to recognise an error page
+const SNIFF_CHARS: usize = 4096;
+const MAX_MESSAGE_CHARS: usize = 120;
+
+/// What `data` is when it is an error page rather than a profile, e.g. `an HTML page "Login"`.
+pub fn error_body(data: &str) -> Option<std::string::String> {
+    let sample = data
+        .trim_start()
+        .chars()
+        .take(SNIFF_CHARS)
+        .collect::<std::string::String>();
+    let lower = sample.to_ascii_lowercase();
+
+    if lower.starts_with("<!doctype html")
+        || lower.starts_with("<html")
+        || (lower.starts_with('<') && (lower.contains("<head") || lower.contains("<body")))
+    {
+        return Some(match html_title(&sample, &lower) {
+            Some(title) => format!("an HTML page \"{title}\""),
+            None => "an HTML page".into(),
+        });
+    }
+
+    // e.g. {"error": "token expired"} or {"code": 403, "message": "..."}
+    if lower.starts_with('{') {
+        let body = serde_yaml_ng::from_str::<Mapping>(data).ok()?;
+        if body.contains_key("proxies") || body.contains_key("proxy-providers") {
+            return None;
+        }
+        let message = ["error", "message", "msg", "detail"]
+            .into_iter()
+            .find_map(|key| match body.get(key)? {
+                Value::String(message) => Some(message.as_str()),
+                Value::Mapping(error) => error.get("message")?.as_str(),
+                _ => None,
+            })?;
+        return Some(format!("an error response: {}", truncate(message)));
+    }
+
+    None
+}
+
+/// `lower` is `sample` lowercased, so byte offsets found in one hold for the other.
+fn html_title(sample: &str, lower: &str) -> Option<std::string::String> {
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = sample[start..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then(|| truncate(&title))
+}
+
+fn truncate(message: &str) -> std::string::String {
+    let mut chars = message.chars();
+    let mut short = chars.by_ref().take(MAX_MESSAGE_CHARS).collect::<std::string::String>();
+    if chars.next().is_some() {
+        short.push('…');
+    }
+    short
+}
+
+/// Why a download was held, and the counts behind it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuardTrip {
+    pub reason: HoldReason,
+    pub nodes_before: usize,
+    pub nodes_after: usize,
+    pub removed: usize,
+}
+
+/// Whether `new` has to be held instead of replacing `old`, which is `None` when there is no
+/// readable stored profile to compare with.
+///
+/// `removed` comes from [`diff_profiles`], which pairs a removed node with an added one of the same
+/// settings as a rename and leaves it out of `proxies_removed`. A provider renaming every node thus
+/// does not trip `max_removed_percent`, while a node renamed and moved to another server does.
+pub fn check_update(guard: &PrfUpdateGuard, old: Option<&Mapping>, new: &Mapping) -> Option<GuardTrip> {
+    let nodes_after = node_count(new);
+    let (nodes_before, removed) = old.map_or((0, 0), |old| {
+        (node_count(old), diff_profiles(old, new).proxies_removed.len())
+    });
+    let trip = |reason| {
+        Some(GuardTrip {
+            reason,
+            nodes_before,
+            nodes_after,
+            removed,
+        })
+    };
+
+    // the nodes of a provider are only known once the core has loaded it
+    if let Some(min_nodes) = guard.min_nodes
+        && !new.contains_key("proxy-providers")
+        && nodes_after < min_nodes
+    {
+        return trip(HoldReason::TooFewNodes);
+    }
+    if let Some(max_percent) = guard.max_removed_percent
+        && nodes_before > 0
+        && removed * 100 > usize::from(max_percent) * nodes_before
+    {
+        return trip(HoldReason::TooManyRemoved);
+    }
+    None
+}
+
+fn node_count(config: &Mapping) -> usize {
+    config.get("proxies").and_then(Value::as_sequence).map_or(0, Vec::len)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{GuardTrip, check_update, error_body};
+    use crate::config::{HoldReason, PrfUpdateGuard};
+    use serde_yaml_ng::Mapping;
+
+    fn profile(nodes: &[&str]) -> Mapping {
+        let proxies = nodes
+            .iter()
+            .map(|name| format!("  - {{name: {name}, type: ss, server: {name}.example, port: 443}}\n"))
+            .collect::<String>();
+        serde_yaml_ng::from_str(&format!("proxies:\n{proxies}")).expect("fixture should parse")
+    }
+
+    #[test]
+    fn error_pages_are_recognised() {
+        assert_eq!(
+            error_body("\n<!DOCTYPE html><html><head><title>\n  Sign in\n</title></head></html>").as_deref(),
+            Some("an HTML page \"Sign in\"")
+        );
+        assert_eq!(
+            error_body("<HTML><BODY>502</BODY></HTML>").as_deref(),
+            Some("an HTML page")
+        );
+        assert_eq!(
+            error_body(r#"{"code": 403, "message": "token expired"}"#).as_deref(),
+            Some("an error response: token expired")
+        );
+        assert_eq!(
+            error_body(r#"{"error": {"message": "quota exceeded"}}"#).as_deref(),
+            Some("an error response: quota exceeded")
+        );
+
+        assert_eq!(error_body("proxies:\n  - {name: a, type: ss}\n"), None);
+        assert_eq!(error_body(r#"{"proxies": [], "message": "hello"}"#), None);
+        assert_eq!(
+            error_body("c3M6Ly9ZV1Z6TFRJMU5pMW5ZMjA2Y0dGemMzZHZjbVE9QDEuMi4zLjQ6NDQzIw=="),
+            None
+        );
+    }
+
+    #[test]
+    fn too_few_nodes_are_held_unless_providers_supply_them() {
+        let guard = PrfUpdateGuard {
+            min_nodes: Some(3),
+            ..PrfUpdateGuard::default()
+        };
+        let new = profile(&["a", "b"]);
+        assert_eq!(
+            check_update(&guard, None, &new),
+            Some(GuardTrip {
+                reason: HoldReason::TooFewNodes,
+                nodes_before: 0,
+                nodes_after: 2,
+                removed: 0,
+            })
+        );
+        assert_eq!(check_update(&guard, None, &profile(&["a", "b", "c"])), None);
+
+        let mut with_provider = new;
+        with_provider.insert("proxy-providers".into(), Mapping::new().into());
+        assert_eq!(check_update(&guard, None, &with_provider), None);
+    }
+
+    #[test]
+    fn removing_too_many_stored_nodes_is_held() {
+        let guard = PrfUpdateGuard {
+            max_removed_percent: Some(50),
+            ..PrfUpdateGuard::default()
+        };
+        let old = profile(&["a", "b", "c", "d"]);
+
+        // half of them gone is still allowed, renamed nodes count as removed when their server changed
+        assert_eq!(
+            check_update(&guard, Some(&old), &profile(&["a", "b", "x", "y", "z"])),
+            None
+        );
+        assert_eq!(
+            check_update(&guard, Some(&old), &profile(&["a", "x"])),
+            Some(GuardTrip {
+                reason: HoldReason::TooManyRemoved,
+                nodes_before: 4,
+                nodes_after: 2,
+                removed: 3,
+            })
+        );
+        assert_eq!(check_update(&guard, Some(&Mapping::new()), &profile(&[])), None);
+        assert_eq!(
+            check_update(&PrfUpdateGuard::default(), Some(&old), &profile(&[])),
+            None
+        );
+    }
+
+    #[test]
+    fn renamed_nodes_are_not_removed() {
+        let guard = PrfUpdateGuard {
+            max_removed_percent: Some(25),
+            ..PrfUpdateGuard::default()
+        };
+        let old = profile(&["a", "b", "c", "d"]);
+        // every node kept its settings under a new name
+        let renamed: Mapping = serde_yaml_ng::from_str(
+            r"
+proxies:
+  - {name: A, type: ss, server: a.example, port: 443}
+  - {name: B, type: ss, server: b.example, port: 443}
+  - {name: C, type: ss, server: c.example, port: 443}
+  - {name: D, type: ss, server: d.example, port: 443}
+",
+        )
+        .expect("fixture should parse");
+        assert_eq!(check_update(&guard, Some(&old), &renamed), None);
+    }
+}
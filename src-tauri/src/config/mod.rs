@@ -1,11 +1,15 @@
 mod clash;
+pub mod composite;
 #[allow(clippy::module_inception)]
 mod config;
+pub mod diff;
 mod encrypt;
+pub mod guard;
 mod mixed_port;
 mod port;
 mod prfitem;
 pub mod profiles;
+pub mod revision;
 pub mod runtime;
 pub(crate) mod snapshot;
 mod verge;
@@ -1,5 +1,6 @@
 use crate::{
-    config::profiles,
+    config::{diff::ProfileDiff, guard, profiles},
+    convert::{self, ConversionReport, rule_list},
     utils::{
         dirs, help,
         network::{NetworkManager, ProxyType},
@@ -7,10 +8,15 @@ use crate::{
     },
 };
 use anyhow::{Context as _, Result, bail};
+use clash_verge_logging::{Type, logging};
+use reqwest::{
+    StatusCode,
+    header::{ETAG, HeaderMap, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+};
 use serde::{Deserialize, Serialize};
 use serde_yaml_ng::Mapping;
 use smartstring::alias::String;
-use std::time::Duration;
+use std::{fmt, time::Duration};
 use tokio::fs;
 // TODO, use other re-export
 use reqwest_dav::re_exports::url::form_urlencoded;
@@ -21,7 +27,7 @@ pub struct PrfItem {
     pub uid: Option<String>,
 
     /// profile item type
-    /// enum value: remote | local | script | merge
+    /// enum value: remote | local | composite | script | merge | rules | proxies | groups | filter
     #[serde(rename = "type")]
     pub itype: Option<String>,
 
@@ -39,6 +45,11 @@ pub struct PrfItem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
 
+    /// for `remote` profile
+    /// other urls serving the same subscription, tried in order when `url` fails
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirrors: Option<Vec<String>>,
+
     /// selected information
     #[serde(skip_serializing_if = "Option::is_none")]
     pub selected: Option<Vec<PrfSelected>>,
@@ -58,6 +69,37 @@ pub struct PrfItem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub home: Option<String>,
 
+    /// `ETag` of the last download, sent back as `If-None-Match`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+
+    /// `Last-Modified` of the last download, sent back as `If-Modified-Since`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+
+    /// traffic and expiry alerts already shown for `extra`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert: Option<PrfAlertState>,
+
+    /// how the last updates of a `remote` or `ruleset` item went
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_state: Option<PrfUpdateState>,
+
+    /// for `remote` profile
+    /// a download that failed the `update_guard` checks, waiting to be accepted or rejected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub held: Option<PrfHeldUpdate>,
+
+    /// for `remote` profile
+    /// what the last update that replaced the file changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_change: Option<PrfChangeSummary>,
+
+    /// for `remote` profile
+    /// what the converter left out when the subscription was not a mihomo profile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversion: Option<ConversionReport>,
+
     /// the file data
     #[serde(skip)]
     pub file_data: Option<String>,
@@ -77,6 +119,379 @@ pub struct PrfExtra {
     pub expire: u64,
 }
 
+/// What a conditional re-fetch of a remote item brought back.
+#[derive(Debug)]
+pub enum RemoteFetch {
+    /// a new download, to replace the stored file
+    Modified(Box<PrfItem>),
+    /// the server's 304: the stored file is still current, the usage it reports may not be
+    NotModified { extra: Option<PrfExtra> },
+}
+
+/// What a remote item's url serves, which decides how the download is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteKind {
+    /// a subscription, for `remote` items
+    Profile,
+    /// a rule list, for `ruleset` items
+    RuleSet,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfAlertState {
+    /// the highest usage threshold notified, in percent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<u8>,
+
+    /// the `expire` an "expires soon" notice was shown for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiring: Option<u64>,
+
+    /// the `expire` an "expired" notice was shown for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expired: Option<u64>,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfUpdateState {
+    /// unix seconds of the last update that was started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_attempt: Option<i64>,
+
+    /// unix seconds of the last update that succeeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success: Option<i64>,
+
+    /// failed updates since the last success
+    #[serde(default)]
+    pub consecutive_failures: u32,
+
+    /// unix seconds of the last update that was held by the `update_guard`, cleared by a success
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_held: Option<i64>,
+
+    /// what the last failure was, cleared by a success
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<UpdateErrorCategory>,
+
+    /// the last failure's message, urls masked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error_message: Option<String>,
+
+    /// the mirror the last success came from, tried first next time
+    /// `None` when it was `url` itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_mirror: Option<String>,
+
+    /// the urls that failed during the last update, with why
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirror_errors: Vec<PrfMirrorError>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PrfHeldUpdate {
+    pub reason: HoldReason,
+    pub nodes_before: usize,
+    pub nodes_after: usize,
+    /// nodes of the stored profile missing from the download
+    pub removed: usize,
+    /// unix seconds
+    pub fetched_at: i64,
+    /// the file in the profiles dir holding the download
+    pub file: String,
+
+    /// what the download came with, stored once it is accepted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra: Option<PrfExtra>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub home: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversion: Option<ConversionReport>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HoldReason {
+    /// fewer nodes than `min_nodes`
+    TooFewNodes,
+    /// more of the stored nodes gone than `max_removed_percent` allows
+    TooManyRemoved,
+}
+
+/// how many names a `PrfChangeList` keeps
+const CHANGE_LIST_LIMIT: usize = 20;
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfChangeSummary {
+    /// unix seconds
+    pub updated_at: i64,
+    pub nodes_before: usize,
+    pub nodes_after: usize,
+    #[serde(default)]
+    pub nodes_added: PrfChangeList,
+    #[serde(default)]
+    pub nodes_removed: PrfChangeList,
+    /// `old -> new`
+    #[serde(default)]
+    pub nodes_renamed: PrfChangeList,
+    #[serde(default)]
+    pub groups_added: PrfChangeList,
+    #[serde(default)]
+    pub groups_removed: PrfChangeList,
+    /// groups whose members or options changed
+    #[serde(default)]
+    pub groups_changed: PrfChangeList,
+    #[serde(default)]
+    pub providers_added: PrfChangeList,
+    pub rules_before: usize,
+    pub rules_after: usize,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfChangeList {
+    pub count: usize,
+    /// the first few of them, a profile can rename hundreds of nodes at once
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>,
+}
+
+impl PrfChangeList {
+    fn new<'a>(names: impl ExactSizeIterator<Item = &'a String>) -> Self {
+        Self {
+            count: names.len(),
+            names: names.take(CHANGE_LIST_LIMIT).cloned().collect(),
+        }
+    }
+}
+
+impl PrfChangeSummary {
+    pub fn new(diff: &ProfileDiff, updated_at: i64) -> Self {
+        let renamed = diff
+            .proxies_renamed
+            .iter()
+            .map(|rename| format!("{} -> {}", rename.from, rename.to).into())
+            .collect::<Vec<String>>();
+        Self {
+            updated_at,
+            nodes_before: diff.proxies_before,
+            nodes_after: diff.proxies_after,
+            nodes_added: PrfChangeList::new(diff.proxies_added.iter()),
+            nodes_removed: PrfChangeList::new(diff.proxies_removed.iter()),
+            nodes_renamed: PrfChangeList::new(renamed.iter()),
+            groups_added: PrfChangeList::new(diff.groups_added.iter()),
+            groups_removed: PrfChangeList::new(diff.groups_removed.iter()),
+            groups_changed: PrfChangeList::new(diff.groups_changed.iter().map(|group| &group.name)),
+            providers_added: PrfChangeList::new(diff.providers_added.iter()),
+            rules_before: diff.rules_before,
+            rules_after: diff.rules_after,
+        }
+    }
+
+    /// Whether the update changed none of what is summarised.
+    pub const fn is_empty(&self) -> bool {
+        self.nodes_added.count == 0
+            && self.nodes_removed.count == 0
+            && self.nodes_renamed.count == 0
+            && self.groups_added.count == 0
+            && self.groups_removed.count == 0
+            && self.groups_changed.count == 0
+            && self.providers_added.count == 0
+            && self.rules_before == self.rules_after
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfMirrorError {
+    pub url: String,
+    pub category: UpdateErrorCategory,
+    /// urls masked
+    pub message: String,
+}
+
+/// How an update ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    /// the download replaced the file, or the server said it had not changed
+    Succeeded,
+    /// the download failed the `update_guard` checks and waits to be accepted or rejected
+    Held,
+    Failed(UpdateErrorCategory, String),
+}
+
+impl UpdateOutcome {
+    /// The outcome of an update that failed with `err`, urls masked.
+    pub fn failed(err: &anyhow::Error) -> Self {
+        Self::Failed(
+            UpdateErrorCategory::classify(err),
+            help::mask_err(&format!("{err:#}")).into(),
+        )
+    }
+}
+
+impl PrfUpdateState {
+    /// A held download is neither a success nor a failure: only the attempt moves.
+    pub fn record(&mut self, attempted: i64, outcome: UpdateOutcome) {
+        self.last_attempt = Some(attempted);
+        match outcome {
+            UpdateOutcome::Succeeded => {
+                self.last_success = Some(attempted);
+                self.consecutive_failures = 0;
+                self.last_held = None;
+                self.last_error = None;
+                self.last_error_message = None;
+            }
+            UpdateOutcome::Held => {
+                self.last_held = Some(attempted);
+            }
+            UpdateOutcome::Failed(category, message) => {
+                self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+                self.last_error = Some(category);
+                self.last_error_message = Some(message);
+            }
+        }
+    }
+
+    /// Keep which of `primary` and its mirrors answered, and which did not.
+    pub fn record_mirrors(&mut self, primary: Option<&String>, report: &MirrorReport) {
+        if let Some(used) = &report.used {
+            self.last_mirror = Some(used.clone()).filter(|used| primary.is_none_or(|p| p.trim() != used.trim()));
+        }
+        self.mirror_errors = report.errors.clone();
+    }
+}
+
+/// A download that came back but can not be used.
+#[derive(Debug)]
+pub enum FetchError {
+    /// answered with anything but a success, `what` names what was fetched
+    Status { what: &'static str, status: StatusCode },
+    /// answered with something that is not what was asked for
+    InvalidContent(std::string::String),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Status { what, status } => write!(f, "failed to fetch {what} with status {status}"),
+            Self::InvalidContent(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Whether rustls turned the connection down. hyper hands its error over inside `io::Error`s,
+/// whose `source` skips the error they wrap, so those are unwrapped by hand.
+fn is_tls_error(err: &reqwest::Error) -> bool {
+    let mut cause = std::error::Error::source(err);
+    while let Some(err) = cause {
+        if err.is::<rustls::Error>() {
+            return true;
+        }
+        cause = match err.downcast_ref::<std::io::Error>() {
+            Some(io) => io.get_ref().map(|inner| inner as &(dyn std::error::Error + 'static)),
+            None => err.source(),
+        };
+    }
+    false
+}
+
+/// How trying a remote profile's urls in turn went.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MirrorReport {
+    /// the url that answered
+    pub used: Option<String>,
+    /// the last error of each url that failed and did not answer later
+    pub errors: Vec<PrfMirrorError>,
+}
+
+impl MirrorReport {
+    fn failed(&mut self, url: &str, err: &anyhow::Error) {
+        let error = PrfMirrorError {
+            url: url.into(),
+            category: UpdateErrorCategory::classify(err),
+            message: help::mask_err(&format!("{err:#}")).into(),
+        };
+        match self.errors.iter_mut().find(|e| e.url == url) {
+            Some(existing) => *existing = error,
+            None => self.errors.push(error),
+        }
+    }
+
+    fn succeeded(&mut self, url: &str) {
+        self.errors.retain(|e| e.url != url);
+        self.used = Some(url.into());
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateErrorCategory {
+    Network,
+    Timeout,
+    Tls,
+    /// 5xx, 408 or 429
+    ServerError,
+    /// any other non-success status
+    ClientError,
+    InvalidContent,
+    Other,
+}
+
+impl UpdateErrorCategory {
+    /// Sort a fetch error by the typed errors in its chain: our own [`FetchError`], the yaml parser's
+    /// and reqwest's. Anything else, such as an invalid url, is [`Self::Other`].
+    pub fn classify(err: &anyhow::Error) -> Self {
+        for cause in err.chain() {
+            if let Some(fetch) = cause.downcast_ref::<FetchError>() {
+                return match fetch {
+                    FetchError::Status { status, .. } => Self::from_status(*status),
+                    FetchError::InvalidContent(_) => Self::InvalidContent,
+                };
+            }
+            if cause.is::<serde_yaml_ng::Error>() {
+                return Self::InvalidContent;
+            }
+            if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+                return Self::from_reqwest(err);
+            }
+        }
+        Self::Other
+    }
+
+    fn from_status(status: StatusCode) -> Self {
+        if status.is_server_error() || status == StatusCode::REQUEST_TIMEOUT || status == StatusCode::TOO_MANY_REQUESTS
+        {
+            Self::ServerError
+        } else {
+            Self::ClientError
+        }
+    }
+
+    fn from_reqwest(err: &reqwest::Error) -> Self {
+        if let Some(status) = err.status() {
+            Self::from_status(status)
+        } else if err.is_timeout() {
+            Self::Timeout
+        } else if is_tls_error(err) {
+            Self::Tls
+        } else if err.is_connect() || err.is_request() || err.is_body() {
+            Self::Network
+        } else {
+            Self::Other
+        }
+    }
+
+    /// Whether trying again a little later could help.
+    pub const fn is_transient(self) -> bool {
+        matches!(self, Self::Network | Self::Timeout | Self::ServerError)
+    }
+}
+
 #[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct PrfOption {
     /// for `remote` profile's http request
@@ -97,6 +512,22 @@ pub struct PrfOption {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub update_interval: Option<u64>,
 
+    /// for `remote` profile
+    /// cron expression in local time, e.g. `0 6 * * *`; replaces `update_interval` when set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_cron: Option<String>,
+
+    /// for `remote` profile with `update_cron`
+    /// each run is delayed by up to this many minutes, so clients do not all arrive at once
+    /// default is 5 minutes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_jitter: Option<u64>,
+
+    /// for `remote` profile
+    /// checks a download has to pass before it replaces the stored file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_guard: Option<PrfUpdateGuard>,
+
     /// for `remote` profile
     /// HTTP request timeout in seconds
     /// default is 60 seconds
@@ -122,6 +553,83 @@ pub struct PrfOption {
     pub proxies: Option<String>,
 
     pub groups: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+
+    /// for `composite` profile
+    /// uids of the remote/local profiles it is assembled from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<String>>,
+
+    /// for `composite` profile
+    /// the source its groups and rules come from, default is the first one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary: Option<String>,
+
+    /// generate per-region groups from node names
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region_groups: Option<PrfRegionGroups>,
+
+    /// uids of the `ruleset` items applied to this profile, in order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_sets: Option<Vec<String>>,
+
+    /// for `ruleset` item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_set: Option<PrfRuleSet>,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfRuleSet {
+    /// the policy matched traffic is sent to
+    pub policy: String,
+
+    /// `provider`: a `rule-providers` entry reading the stored list
+    /// `inline`: the rules themselves
+    /// default is `provider`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfUpdateGuard {
+    /// hold a download with fewer nodes than this
+    /// not checked when the profile has `proxy-providers`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_nodes: Option<usize>,
+
+    /// hold a download that drops more than this percentage of the stored nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_removed_percent: Option<u8>,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfRegionGroups {
+    #[serde(default)]
+    pub enable: bool,
+
+    /// `url-test` or `fallback`
+    /// default is `url-test`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_type: Option<String>,
+
+    /// the selector group the region groups are added to
+    /// default is the first `select` group
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+
+    /// only generate groups for these region codes, e.g. `[HK, JP]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regions: Option<Vec<String>>,
+
+    /// health check url of the generated groups
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// health check interval in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
 }
 
 impl PrfOption {
@@ -136,12 +644,21 @@ impl PrfOption {
                     b_ref.danger_accept_invalid_certs.or(result.danger_accept_invalid_certs);
                 result.allow_auto_update = b_ref.allow_auto_update.or(result.allow_auto_update);
                 result.update_interval = b_ref.update_interval.or(result.update_interval);
+                result.update_cron = b_ref.update_cron.clone().or(result.update_cron);
+                result.update_jitter = b_ref.update_jitter.or(result.update_jitter);
+                result.update_guard = b_ref.update_guard.clone().or(result.update_guard);
                 result.merge = b_ref.merge.clone().or(result.merge);
                 result.script = b_ref.script.clone().or(result.script);
                 result.rules = b_ref.rules.clone().or(result.rules);
                 result.proxies = b_ref.proxies.clone().or(result.proxies);
                 result.groups = b_ref.groups.clone().or(result.groups);
+                result.filter = b_ref.filter.clone().or(result.filter);
                 result.timeout_seconds = b_ref.timeout_seconds.or(result.timeout_seconds);
+                result.sources = b_ref.sources.clone().or(result.sources);
+                result.primary = b_ref.primary.clone().or(result.primary);
+                result.region_groups = b_ref.region_groups.clone().or(result.region_groups);
+                result.rule_sets = b_ref.rule_sets.clone().or(result.rule_sets);
+                result.rule_set = b_ref.rule_set.clone().or(result.rule_set);
                 Some(result)
             }
             (Some(a_ref), None) => Some(a_ref.clone()),
@@ -165,6 +682,26 @@ impl PrfItem {
             .ok_or_else(|| anyhow::anyhow!("type should not be null"))?;
         match itype.as_str() {
             "remote" => {
+                if item.url.is_none() {
+                    bail!("url should not be null");
+                }
+                let name = item.name.as_ref();
+                let desc = item.desc.as_ref();
+                let option = item.option.as_ref();
+                Self::from_urls(&item.remote_urls(), name, desc, option).await
+            }
+            "local" => {
+                let name = item.name.clone().unwrap_or_else(|| "Local File".into());
+                let desc = item.desc.clone().unwrap_or_else(|| "".into());
+                let option = item.option.as_ref();
+                match file_data {
+                    Some(data) if convert::is_ios_profile(&data) => {
+                        Self::from_ios_profile(name, desc, &data, option).await
+                    }
+                    file_data => Self::from_local(name, desc, file_data, option).await,
+                }
+            }
+            "ruleset" => {
                 let url = item
                     .url
                     .as_ref()
@@ -172,13 +709,15 @@ impl PrfItem {
                 let name = item.name.as_ref();
                 let desc = item.desc.as_ref();
                 let option = item.option.as_ref();
-                Self::from_url(url, name, desc, option).await
+                let mut rule_set = Self::from_rule_set(url, name, desc, option).await?;
+                rule_set.mirrors = item.mirrors.clone().filter(|m| !m.is_empty());
+                Ok(rule_set)
             }
-            "local" => {
-                let name = item.name.clone().unwrap_or_else(|| "Local File".into());
+            "composite" => {
+                let name = item.name.clone().unwrap_or_else(|| "Composite".into());
                 let desc = item.desc.clone().unwrap_or_else(|| "".into());
                 let option = item.option.as_ref();
-                Self::from_local(name, desc, file_data, option).await
+                Self::from_composite(name, desc, option).await
             }
             typ => bail!("invalid profile item type \"{typ}\""),
         }
@@ -194,39 +733,8 @@ impl PrfItem {
     ) -> Result<Self> {
         let uid = help::get_uid("L").into();
         let file = format!("{uid}.yaml").into();
-        let opt_ref = option.as_ref();
-        let update_interval = opt_ref.and_then(|o| o.update_interval);
-        let mut merge = opt_ref.and_then(|o| o.merge.clone());
-        let mut script = opt_ref.and_then(|o| o.script.clone());
-        let mut rules = opt_ref.and_then(|o| o.rules.clone());
-        let mut proxies = opt_ref.and_then(|o| o.proxies.clone());
-        let mut groups = opt_ref.and_then(|o| o.groups.clone());
-
-        if merge.is_none() {
-            let merge_item = &mut Self::from_merge(None)?;
-            profiles::profiles_append_item_safe(merge_item).await?;
-            merge = merge_item.uid.clone();
-        }
-        if script.is_none() {
-            let script_item = &mut Self::from_script(None)?;
-            profiles::profiles_append_item_safe(script_item).await?;
-            script = script_item.uid.clone();
-        }
-        if rules.is_none() {
-            let rules_item = &mut Self::from_rules()?;
-            profiles::profiles_append_item_safe(rules_item).await?;
-            rules = rules_item.uid.clone();
-        }
-        if proxies.is_none() {
-            let proxies_item = &mut Self::from_proxies()?;
-            profiles::profiles_append_item_safe(proxies_item).await?;
-            proxies = proxies_item.uid.clone();
-        }
-        if groups.is_none() {
-            let groups_item = &mut Self::from_groups()?;
-            profiles::profiles_append_item_safe(groups_item).await?;
-            groups = groups_item.uid.clone();
-        }
+        let update_interval = option.and_then(|o| o.update_interval);
+        let chain = Self::chain_items(option).await?;
         Ok(Self {
             uid: Some(uid),
             itype: Some("local".into()),
@@ -234,23 +742,70 @@ impl PrfItem {
             desc: Some(desc),
             file: Some(file),
             url: None,
+            mirrors: None,
             selected: None,
             extra: None,
             option: Some(PrfOption {
                 update_interval,
-                merge,
-                script,
-                rules,
-                proxies,
-                groups,
-                ..PrfOption::default()
+                ..chain
             }),
             home: None,
+            etag: None,
+            last_modified: None,
+            alert: None,
+            update_state: None,
+            held: None,
+            last_change: None,
+            conversion: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
             file_data: Some(file_data.unwrap_or_else(|| tmpl::ITEM_LOCAL.into())),
         })
     }
 
+    /// ## Local type (Surge, Quantumult X, Loon)
+    /// create a new local item from an iOS client profile, converted to mihomo,
+    /// keeping the report of what did not convert
+    pub async fn from_ios_profile(name: String, desc: String, data: &str, option: Option<&PrfOption>) -> Result<Self> {
+        let Some(converted) = convert::convert_ios_profile(data) else {
+            bail!("the file is not a Surge, Quantumult X or Loon profile");
+        };
+        let mut item = Self::from_local(name, desc, Some(converted.data), option).await?;
+        item.conversion = converted.report;
+        Ok(item)
+    }
+
+    /// ## Composite type
+    /// create a new item assembled from the `sources` profiles
+    pub async fn from_composite(name: String, desc: String, option: Option<&PrfOption>) -> Result<Self> {
+        let sources = option.and_then(|o| o.sources.clone()).unwrap_or_default();
+        if sources.is_empty() {
+            bail!("a composite profile needs at least one source");
+        }
+        let primary = option.and_then(|o| o.primary.clone());
+        if primary.as_ref().is_some_and(|p| !sources.contains(p)) {
+            bail!("the primary profile should be one of the sources");
+        }
+
+        let uid = help::get_uid("C").into();
+        let file = format!("{uid}.yaml").into();
+        let chain = Self::chain_items(option).await?;
+        Ok(Self {
+            uid: Some(uid),
+            itype: Some("composite".into()),
+            name: Some(name),
+            desc: Some(desc),
+            file: Some(file),
+            option: Some(PrfOption {
+                sources: Some(sources),
+                primary,
+                ..chain
+            }),
+            updated: Some(chrono::Local::now().timestamp() as usize),
+            file_data: Some(tmpl::ITEM_COMPOSITE.into()),
+            ..Default::default()
+        })
+    }
+
     /// ## Remote type
     /// create a new item from url
     pub async fn from_url(
@@ -259,6 +814,116 @@ impl PrfItem {
         desc: Option<&String>,
         option: Option<&PrfOption>,
     ) -> Result<Self> {
+        Self::from_urls(&[url.into()], name, desc, option).await
+    }
+
+    /// ## Remote type (mirrors)
+    /// create a new item from the first of `urls` that answers;
+    /// the first url becomes `url` and the rest `mirrors`
+    pub async fn from_urls(
+        urls: &[String],
+        name: Option<&String>,
+        desc: Option<&String>,
+        option: Option<&PrfOption>,
+    ) -> Result<Self> {
+        let Some((primary, mirrors)) = urls.split_first() else {
+            bail!("url should not be null");
+        };
+        let RemoteFetch::Modified(mut item) = Self::fetch_mirrors(
+            RemoteKind::Profile,
+            urls,
+            name,
+            desc,
+            option,
+            None,
+            &mut MirrorReport::default(),
+        )
+        .await?
+        else {
+            bail!("the remote profile was not modified");
+        };
+        item.url = Some(fix_dirty_url(primary)?.as_str().into());
+        item.mirrors = Some(mirrors.to_vec()).filter(|m| !m.is_empty());
+        Ok(*item)
+    }
+
+    /// ## Remote type (conditional)
+    /// re-fetch a remote item from the first of `urls` that answers, sending back the
+    /// `ETag`/`Last-Modified` of `cached`
+    pub async fn from_url_if_modified(
+        kind: RemoteKind,
+        urls: &[String],
+        option: Option<&PrfOption>,
+        cached: Option<&Self>,
+        report: &mut MirrorReport,
+    ) -> Result<RemoteFetch> {
+        Self::fetch_mirrors(kind, urls, None, None, option, cached, report).await
+    }
+
+    /// `url` and then `mirrors`, the mirror the last update came from first.
+    pub fn remote_urls(&self) -> Vec<String> {
+        let mirrors = self.mirrors.iter().flatten();
+        // 记住的镜像已被移除时不再优先使用
+        let last = self
+            .update_state
+            .as_ref()
+            .and_then(|s| s.last_mirror.as_ref())
+            .filter(|last| mirrors.clone().any(|m| m.trim() == last.trim()));
+        let mut urls = Vec::new();
+        for url in last.into_iter().chain(self.url.iter()).chain(mirrors) {
+            let url: String = url.trim().into();
+            if !url.is_empty() && !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+        urls
+    }
+
+    async fn fetch_mirrors(
+        kind: RemoteKind,
+        urls: &[String],
+        name: Option<&String>,
+        desc: Option<&String>,
+        option: Option<&PrfOption>,
+        cached: Option<&Self>,
+        report: &mut MirrorReport,
+    ) -> Result<RemoteFetch> {
+        let mut last_err = None;
+        for url in urls {
+            let fetched = match kind {
+                RemoteKind::Profile => Self::fetch_remote(url, name, desc, option, cached).await,
+                RemoteKind::RuleSet => Self::fetch_rule_set(url, name, desc, option, cached).await,
+            };
+            match fetched {
+                Ok(fetched) => {
+                    report.succeeded(url);
+                    return Ok(fetched);
+                }
+                Err(err) => {
+                    if urls.len() > 1 {
+                        logging!(
+                            warn,
+                            Type::Config,
+                            "Warning: [订阅更新] {} 获取失败，尝试下一个地址: {}",
+                            help::mask_url(url),
+                            help::mask_err(&format!("{err:#}"))
+                        );
+                    }
+                    report.failed(url, &err);
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| anyhow::anyhow!("url should not be null")))
+    }
+
+    async fn fetch_remote(
+        url: &str,
+        name: Option<&String>,
+        desc: Option<&String>,
+        option: Option<&PrfOption>,
+        cached: Option<&Self>,
+    ) -> Result<RemoteFetch> {
         let with_proxy = option.is_some_and(|o| o.with_proxy.unwrap_or(false));
         let self_proxy = option.is_some_and(|o| o.self_proxy.unwrap_or(false));
         let accept_invalid_certs = option.is_some_and(|o| o.danger_accept_invalid_certs.unwrap_or(false));
@@ -266,11 +931,6 @@ impl PrfItem {
         let user_agent = option.and_then(|o| o.user_agent.clone());
         let update_interval = option.and_then(|o| o.update_interval);
         let timeout = option.and_then(|o| o.timeout_seconds).unwrap_or(20);
-        let mut merge = option.and_then(|o| o.merge.clone());
-        let mut script = option.and_then(|o| o.script.clone());
-        let mut rules = option.and_then(|o| o.rules.clone());
-        let mut proxies = option.and_then(|o| o.proxies.clone());
-        let mut groups = option.and_then(|o| o.groups.clone());
 
         // 选择代理类型
         let proxy_type = if self_proxy {
@@ -291,6 +951,7 @@ impl PrfItem {
                 Some(timeout),
                 user_agent.clone(),
                 accept_invalid_certs,
+                cached.map(conditional_headers).unwrap_or_default(),
             )
             .await
         {
@@ -302,33 +963,20 @@ impl PrfItem {
         };
 
         let status_code = resp.status();
-        if !status_code.is_success() {
-            bail!("failed to fetch remote profile with status {status_code}")
-        }
-
         let header = resp.headers();
 
         // parse the Subscription UserInfo
-        let extra;
-        'extra: {
-            for (k, v) in header.iter() {
-                let key_lower = k.as_str().to_ascii_lowercase();
-                // Accept standard custom-metadata prefixes (x-amz-meta-, x-obs-meta-, x-cos-meta-, etc.).
-                if key_lower
-                    .strip_suffix("subscription-userinfo")
-                    .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with('-'))
-                {
-                    let sub_info = v.to_str().unwrap_or("");
-                    extra = Some(PrfExtra {
-                        upload: help::parse_str(sub_info, "upload").unwrap_or(0),
-                        download: help::parse_str(sub_info, "download").unwrap_or(0),
-                        total: help::parse_str(sub_info, "total").unwrap_or(0),
-                        expire: help::parse_str(sub_info, "expire").unwrap_or(0),
-                    });
-                    break 'extra;
-                }
-            }
-            extra = None;
+        let extra = subscription_userinfo(header);
+
+        // 304 时文件不变，但流量信息仍随响应头更新
+        if status_code == StatusCode::NOT_MODIFIED && cached.is_some() {
+            return Ok(RemoteFetch::NotModified { extra });
+        }
+        if !status_code.is_success() {
+            bail!(FetchError::Status {
+                what: "remote profile",
+                status: status_code,
+            })
         }
 
         // parse the Content-Disposition
@@ -374,6 +1022,9 @@ impl PrfItem {
             None => None,
         };
 
+        let etag = header.get(ETAG).and_then(|v| v.to_str().ok()).map(Into::into);
+        let last_modified = header.get(LAST_MODIFIED).and_then(|v| v.to_str().ok()).map(Into::into);
+
         let uid = help::get_uid("R").into();
         let file = format!("{uid}.yaml").into();
         let name = name
@@ -384,64 +1035,214 @@ impl PrfItem {
         // process the charset "UTF-8 with BOM"
         let data = data.trim_start_matches('\u{feff}');
 
-        // check the data whether the valid yaml format
-        let yaml = serde_yaml_ng::from_str::<Mapping>(data).context("the remote profile data is invalid yaml")?;
-
-        if !yaml.contains_key("proxies") && !yaml.contains_key("proxy-providers") {
-            bail!("profile does not contain `proxies` or `proxy-providers`");
+        // a login page or an error answered with 200 would otherwise end up as "invalid yaml"
+        if option.is_some_and(|o| o.update_guard.is_some())
+            && let Some(body) = guard::error_body(data)
+        {
+            bail!(FetchError::InvalidContent(format!(
+                "the subscription returned {body} instead of a profile"
+            )));
         }
 
-        if merge.is_none() {
-            let merge_item = &mut Self::from_merge(None)?;
-            profiles::profiles_append_item_safe(merge_item).await?;
-            merge = merge_item.uid.clone();
-        }
-        if script.is_none() {
-            let script_item = &mut Self::from_script(None)?;
-            profiles::profiles_append_item_safe(script_item).await?;
-            script = script_item.uid.clone();
-        }
-        if rules.is_none() {
-            let rules_item = &mut Self::from_rules()?;
-            profiles::profiles_append_item_safe(rules_item).await?;
-            rules = rules_item.uid.clone();
-        }
-        if proxies.is_none() {
-            let proxies_item = &mut Self::from_proxies()?;
-            profiles::profiles_append_item_safe(proxies_item).await?;
-            proxies = proxies_item.uid.clone();
+        // check the data whether the valid yaml format,
+        // falling back to the converters for share-link and sing-box subscriptions
+        let (data, conversion) = match serde_yaml_ng::from_str::<Mapping>(data) {
+            Ok(yaml) if yaml.contains_key("proxies") || yaml.contains_key("proxy-providers") => (data.to_owned(), None),
+            // sing-box JSON parses as a YAML mapping too
+            Ok(_) => match convert::convert_subscription(data) {
+                Some(converted) => (converted.data, converted.report),
+                None => bail!(FetchError::InvalidContent(
+                    "profile does not contain `proxies` or `proxy-providers`".into()
+                )),
+            },
+            Err(err) => match convert::convert_subscription(data) {
+                Some(converted) => (converted.data, converted.report),
+                None => return Err(err).context("the remote profile data is invalid yaml"),
+            },
+        };
+        if let Some(report) = conversion.as_ref().filter(|report| !report.nodes.is_empty()) {
+            logging!(
+                warn,
+                Type::Config,
+                "[订阅转换] {} 订阅中有 {} 个节点未完整转换: {:?}",
+                report.format,
+                report.nodes.len(),
+                report.nodes
+            );
         }
-        if groups.is_none() {
-            let groups_item = &mut Self::from_groups()?;
-            profiles::profiles_append_item_safe(groups_item).await?;
-            groups = groups_item.uid.clone();
+        if let Some(report) = conversion.as_ref().filter(|report| !report.dropped.is_empty()) {
+            logging!(
+                warn,
+                Type::Config,
+                "[订阅转换] {} 订阅中有 {} 项未转换: {:?}",
+                report.format,
+                report.dropped.len(),
+                report.dropped
+            );
         }
 
-        Ok(Self {
+        let chain = Self::chain_items(option).await?;
+
+        Ok(RemoteFetch::Modified(Box::new(Self {
             uid: Some(uid),
             itype: Some("remote".into()),
             name: Some(name),
             desc: desc.cloned(),
             file: Some(file),
             url: Some(url.as_str().into()),
+            mirrors: None,
             selected: None,
             extra,
             option: Some(PrfOption {
                 update_interval,
-                merge,
-                script,
-                rules,
-                proxies,
-                groups,
+                update_cron: option.and_then(|o| o.update_cron.clone()),
+                update_jitter: option.and_then(|o| o.update_jitter),
+                update_guard: option.and_then(|o| o.update_guard.clone()),
                 allow_auto_update,
-                ..PrfOption::default()
+                ..chain
             }),
             home,
+            etag,
+            last_modified,
+            alert: None,
+            update_state: None,
+            held: None,
+            last_change: None,
+            conversion,
             updated: Some(chrono::Local::now().timestamp() as usize),
             file_data: Some(data.into()),
+        })))
+    }
+
+    /// ## Ruleset type
+    /// download a rule list and store it as a mihomo rule-provider file
+    pub async fn from_rule_set(
+        url: &str,
+        name: Option<&String>,
+        desc: Option<&String>,
+        option: Option<&PrfOption>,
+    ) -> Result<Self> {
+        let RemoteFetch::Modified(mut item) = Self::fetch_rule_set(url, name, desc, option, None).await? else {
+            bail!("the rule set was not modified");
+        };
+        // 创建时的下载设置随规则集保存，之后的更新沿用
+        item.option = PrfOption::merge(option, item.option.as_ref());
+        Ok(*item)
+    }
+
+    /// Fetch a rule list, sending back the `ETag`/`Last-Modified` of `cached`. The item carries
+    /// only what a download decides, so a proxy fallback does not end up in the stored option.
+    async fn fetch_rule_set(
+        url: &str,
+        name: Option<&String>,
+        desc: Option<&String>,
+        option: Option<&PrfOption>,
+        cached: Option<&Self>,
+    ) -> Result<RemoteFetch> {
+        let Some(rule_set) = option.and_then(|o| o.rule_set.clone()) else {
+            bail!("the rule set policy should not be null");
+        };
+        let with_proxy = option.is_some_and(|o| o.with_proxy.unwrap_or(false));
+        let self_proxy = option.is_some_and(|o| o.self_proxy.unwrap_or(false));
+        let accept_invalid_certs = option.is_some_and(|o| o.danger_accept_invalid_certs.unwrap_or(false));
+        let user_agent = option.and_then(|o| o.user_agent.clone());
+        let timeout = option.and_then(|o| o.timeout_seconds).unwrap_or(20);
+        // 规则列表变化缓慢，默认每天更新一次
+        let update_interval = option.and_then(|o| o.update_interval).or(Some(24 * 60));
+
+        let proxy_type = if self_proxy {
+            ProxyType::Localhost
+        } else if with_proxy {
+            ProxyType::System
+        } else {
+            ProxyType::None
+        };
+
+        let url = fix_dirty_url(url)?;
+        let resp = NetworkManager::new()
+            .get_with_interrupt(
+                url.as_str(),
+                proxy_type,
+                Some(timeout),
+                user_agent,
+                accept_invalid_certs,
+                cached.map(conditional_headers).unwrap_or_default(),
+            )
+            .await
+            .context("failed to fetch the rule set")?;
+        let status_code = resp.status();
+        if status_code == StatusCode::NOT_MODIFIED && cached.is_some() {
+            return Ok(RemoteFetch::NotModified { extra: None });
+        }
+        if !status_code.is_success() {
+            bail!(FetchError::Status {
+                what: "the rule set",
+                status: status_code,
+            })
+        }
+
+        let header = resp.headers();
+        let etag = header.get(ETAG).and_then(|v| v.to_str().ok()).map(Into::into);
+        let last_modified = header.get(LAST_MODIFIED).and_then(|v| v.to_str().ok()).map(Into::into);
+        let data = resp.text_with_charset()?;
+        let Some(list) = rule_list::parse_rule_list(&data) else {
+            bail!(FetchError::InvalidContent(
+                "the rule set is empty or in an unknown format".into()
+            ));
+        };
+
+        let uid = help::get_uid("S").into();
+        let file = format!("{uid}.yaml").into();
+        let name = name
+            .cloned()
+            .or_else(|| help::get_last_part_and_decode(url.as_str()))
+            .unwrap_or_else(|| "Rule Set".into());
+
+        Ok(RemoteFetch::Modified(Box::new(Self {
+            uid: Some(uid),
+            itype: Some("ruleset".into()),
+            name: Some(name),
+            desc: desc.cloned(),
+            file: Some(file),
+            url: Some(url.as_str().into()),
+            option: Some(PrfOption {
+                update_interval,
+                allow_auto_update: Some(allow_auto_update_enabled(option)),
+                rule_set: Some(rule_set),
+                ..PrfOption::default()
+            }),
+            etag,
+            last_modified,
+            updated: Some(chrono::Local::now().timestamp() as usize),
+            file_data: Some(list.to_provider_file().into()),
+            ..Default::default()
+        })))
+    }
+
+    /// The enhancement items a main profile owns, created for those `option` does not name yet.
+    async fn chain_items(option: Option<&PrfOption>) -> Result<PrfOption> {
+        let named = |pick: fn(&PrfOption) -> Option<&String>| option.and_then(pick).cloned();
+        Ok(PrfOption {
+            merge: Self::chain_item(named(|o| o.merge.as_ref()), || Self::from_merge(None)).await?,
+            script: Self::chain_item(named(|o| o.script.as_ref()), || Self::from_script(None)).await?,
+            rules: Self::chain_item(named(|o| o.rules.as_ref()), Self::from_rules).await?,
+            proxies: Self::chain_item(named(|o| o.proxies.as_ref()), Self::from_proxies).await?,
+            groups: Self::chain_item(named(|o| o.groups.as_ref()), Self::from_groups).await?,
+            filter: Self::chain_item(named(|o| o.filter.as_ref()), Self::from_filter).await?,
+            ..PrfOption::default()
         })
     }
 
+    /// `uid` when the profile already names one, otherwise the uid of a new item from `create`.
+    async fn chain_item(uid: Option<String>, create: impl FnOnce() -> Result<Self> + Send) -> Result<Option<String>> {
+        if uid.is_some() {
+            return Ok(uid);
+        }
+        let item = &mut create()?;
+        profiles::profiles_append_item_safe(item).await?;
+        Ok(item.uid.clone())
+    }
+
     /// ## Merge type (enhance)
     /// create the enhanced item by using `merge` rule
     pub fn from_merge(uid: Option<String>) -> Result<Self> {
@@ -526,6 +1327,21 @@ impl PrfItem {
         })
     }
 
+    /// ## Filter type (enhance)
+    pub fn from_filter() -> Result<Self> {
+        let uid = help::get_uid("f").into();
+        let file = format!("{uid}.yaml").into(); // yaml ext
+
+        Ok(Self {
+            uid: Some(uid),
+            itype: Some("filter".into()),
+            file: Some(file),
+            updated: Some(chrono::Local::now().timestamp() as usize),
+            file_data: Some(tmpl::ITEM_FILTER.into()),
+            ..Default::default()
+        })
+    }
+
     /// get the file data
     pub async fn read_file(&self) -> Result<String> {
         let file = self
@@ -539,6 +1355,17 @@ impl PrfItem {
         Ok(content.into())
     }
 
+    /// whether the item's file is still on disk
+    pub async fn file_exists(&self) -> bool {
+        let Some(file) = self.file.as_ref() else {
+            return false;
+        };
+        match dirs::app_profiles_dir() {
+            Ok(dir) => fs::try_exists(dir.join(file.as_str())).await.unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
     /// save the file data
     pub async fn save_file(&self, data: String) -> Result<()> {
         let file = self
@@ -553,6 +1380,13 @@ impl PrfItem {
 }
 
 impl PrfItem {
+    /// 是否为可作为 current 的主订阅（remote/local/composite）
+    pub fn is_main_profile(&self) -> bool {
+        self.itype
+            .as_ref()
+            .is_some_and(|t| t == "remote" || t == "local" || t == "composite")
+    }
+
     /// 获取current指向的订阅的merge
     pub fn current_merge(&self) -> Option<&String> {
         self.option.as_ref().and_then(|o| o.merge.as_ref())
@@ -577,6 +1411,11 @@ impl PrfItem {
     pub fn current_groups(&self) -> Option<&String> {
         self.option.as_ref().and_then(|o| o.groups.as_ref())
     }
+
+    /// 获取current指向的订阅的filter
+    pub fn current_filter(&self) -> Option<&String> {
+        self.option.as_ref().and_then(|o| o.filter.as_ref())
+    }
 }
 
 // 向前兼容，默认为订阅启用自动更新
@@ -589,6 +1428,42 @@ fn allow_auto_update_enabled(option: Option<&PrfOption>) -> bool {
     option.and_then(|o| o.allow_auto_update).unwrap_or(true)
 }
 
+/// `If-None-Match`/`If-Modified-Since` from what the last download of `cached` answered with.
+fn conditional_headers(cached: &PrfItem) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(etag) = cached.etag.as_ref().and_then(|v| HeaderValue::from_str(v).ok()) {
+        headers.insert(IF_NONE_MATCH, etag);
+    }
+    if let Some(modified) = cached
+        .last_modified
+        .as_ref()
+        .and_then(|v| HeaderValue::from_str(v).ok())
+    {
+        headers.insert(IF_MODIFIED_SINCE, modified);
+    }
+    headers
+}
+
+/// The `subscription-userinfo` header, which 304 answers carry as well.
+fn subscription_userinfo(header: &HeaderMap) -> Option<PrfExtra> {
+    header.iter().find_map(|(k, v)| {
+        let key_lower = k.as_str().to_ascii_lowercase();
+        // Accept standard custom-metadata prefixes (x-amz-meta-, x-obs-meta-, x-cos-meta-, etc.).
+        key_lower
+            .strip_suffix("subscription-userinfo")
+            .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with('-'))
+            .then(|| {
+                let sub_info = v.to_str().unwrap_or("");
+                PrfExtra {
+                    upload: help::parse_str(sub_info, "upload").unwrap_or(0),
+                    download: help::parse_str(sub_info, "download").unwrap_or(0),
+                    total: help::parse_str(sub_info, "total").unwrap_or(0),
+                    expire: help::parse_str(sub_info, "expire").unwrap_or(0),
+                }
+            })
+    })
+}
+
 /// Fix URLs where query parameters are incorrectly appended to the path segment
 ///
 /// Incorrect Example: https://example.com/path&param1=value1
@@ -619,8 +1494,101 @@ fn fix_dirty_url(input: &str) -> Result<Url> {
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::panic, reason = "tests assert by panicking")]
 mod tests {
-    use super::{PrfOption, allow_auto_update_enabled};
+    use super::{
+        CHANGE_LIST_LIMIT, FetchError, MirrorReport, PrfChangeSummary, PrfItem, PrfOption, PrfRuleSet, PrfUpdateGuard,
+        PrfUpdateState, RemoteFetch, UpdateErrorCategory, UpdateOutcome, allow_auto_update_enabled,
+        conditional_headers,
+    };
+    use crate::config::diff::{ProfileDiff, ProxyRename};
+    use anyhow::anyhow;
+    use reqwest::{
+        StatusCode,
+        header::{IF_MODIFIED_SINCE, IF_NONE_MATCH},
+    };
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    /// Answer one request on a loopback port with `response`, handing back what was asked.
+    async fn serve_once(response: &'static str) -> (std::string::String, tokio::task::JoinHandle<std::string::String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/sub", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(response.as_bytes()).await.unwrap();
+            std::string::String::from_utf8_lossy(&request).to_ascii_lowercase()
+        });
+        (url, server)
+    }
+
+    fn cached_item() -> PrfItem {
+        PrfItem {
+            etag: Some("\"v1\"".into()),
+            last_modified: Some("Sat, 17 Oct 2026 08:00:00 GMT".into()),
+            ..PrfItem::default()
+        }
+    }
+
+    #[test]
+    fn conditional_requests_send_back_the_stored_validators() {
+        let headers = conditional_headers(&cached_item());
+        assert_eq!(headers.get(IF_NONE_MATCH).unwrap(), "\"v1\"");
+        assert_eq!(headers.get(IF_MODIFIED_SINCE).unwrap(), "Sat, 17 Oct 2026 08:00:00 GMT");
+
+        let unsendable = PrfItem {
+            etag: Some("\"v1\"\n".into()),
+            ..PrfItem::default()
+        };
+        assert!(conditional_headers(&unsendable).is_empty());
+        assert!(conditional_headers(&PrfItem::default()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn not_modified_keeps_the_file_but_refreshes_the_usage() {
+        let (url, server) = serve_once(
+            "HTTP/1.1 304 Not Modified\r\n\
+             x-amz-meta-subscription-userinfo: upload=1; download=2; total=30; expire=1800000000\r\n\
+             connection: close\r\n\r\n",
+        )
+        .await;
+
+        let fetched = PrfItem::fetch_remote(&url, None, None, None, Some(&cached_item()))
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+        assert!(request.contains("if-none-match: \"v1\""), "{request}");
+        assert!(
+            request.contains("if-modified-since: sat, 17 oct 2026 08:00:00 gmt"),
+            "{request}"
+        );
+        let RemoteFetch::NotModified { extra: Some(extra) } = fetched else {
+            panic!("expected a 304 with usage, got {fetched:?}");
+        };
+        assert_eq!(
+            (extra.upload, extra.download, extra.total, extra.expire),
+            (1, 2, 30, 1_800_000_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn not_modified_without_a_stored_file_is_a_failure() {
+        let (url, server) = serve_once("HTTP/1.1 304 Not Modified\r\nconnection: close\r\n\r\n").await;
+
+        let fetched = PrfItem::fetch_remote(&url, None, None, None, None).await;
+        let request = server.await.unwrap();
+        assert!(!request.contains("if-none-match"), "{request}");
+        let err = format!("{:#}", fetched.unwrap_err());
+        assert!(err.contains("with status 304"), "{err}");
+    }
 
     #[test]
     fn auto_update_defaults_to_enabled_and_preserves_explicit_false() {
@@ -632,4 +1600,212 @@ mod tests {
         };
         assert!(!allow_auto_update_enabled(Some(&disabled)));
     }
+
+    #[tokio::test]
+    async fn rule_sets_are_fetched_conditionally() {
+        let option = PrfOption {
+            user_agent: Some("fallback".into()),
+            rule_set: Some(PrfRuleSet {
+                policy: "DIRECT".into(),
+                mode: None,
+            }),
+            ..PrfOption::default()
+        };
+
+        let (url, server) = serve_once("HTTP/1.1 304 Not Modified\r\nconnection: close\r\n\r\n").await;
+        let fetched = PrfItem::fetch_rule_set(&url, None, None, Some(&option), Some(&cached_item()))
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+        assert!(request.contains("if-none-match: \"v1\""), "{request}");
+        assert!(
+            matches!(fetched, RemoteFetch::NotModified { extra: None }),
+            "{fetched:?}"
+        );
+
+        let (url, server) = serve_once(
+            "HTTP/1.1 200 OK\r\netag: \"v2\"\r\ncontent-length: 26\r\nconnection: close\r\n\r\nDOMAIN-SUFFIX,example.com\n",
+        )
+        .await;
+        let fetched = PrfItem::fetch_rule_set(&url, None, None, Some(&option), Some(&cached_item()))
+            .await
+            .unwrap();
+        server.await.unwrap();
+        let RemoteFetch::Modified(item) = fetched else {
+            panic!("expected a new download, got {fetched:?}");
+        };
+        assert_eq!(item.itype.as_deref(), Some("ruleset"));
+        assert_eq!(item.etag.as_deref(), Some("\"v2\""));
+        // what the update was fetched with is not stored back
+        let stored = item.option.unwrap();
+        assert_eq!(stored.user_agent, None);
+        assert_eq!(stored.rule_set, option.rule_set);
+    }
+
+    #[tokio::test]
+    async fn fetch_errors_are_sorted_by_what_went_wrong() {
+        let fetch = async |url: &str, option: Option<&PrfOption>| {
+            let err = PrfItem::fetch_remote(url, None, None, option, None).await.unwrap_err();
+            UpdateErrorCategory::classify(&err)
+        };
+
+        let (url, server) =
+            serve_once("HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n").await;
+        assert_eq!(fetch(&url, None).await, UpdateErrorCategory::ServerError);
+        server.await.unwrap();
+        let (url, server) =
+            serve_once("HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n").await;
+        assert_eq!(fetch(&url, None).await, UpdateErrorCategory::ClientError);
+        server.await.unwrap();
+        let (url, server) = serve_once(
+            "HTTP/1.1 200 OK\r\ncontent-length: 34\r\nconnection: close\r\n\r\n<html><title>Login</title></html>\n",
+        )
+        .await;
+        assert_eq!(fetch(&url, None).await, UpdateErrorCategory::InvalidContent);
+        server.await.unwrap();
+        // only a guarded profile tells an error page from broken yaml
+        let (url, server) = serve_once(
+            "HTTP/1.1 200 OK\r\ncontent-length: 34\r\nconnection: close\r\n\r\n<html><title>Login</title></html>\n",
+        )
+        .await;
+        let guarded = PrfOption {
+            update_guard: Some(PrfUpdateGuard::default()),
+            ..PrfOption::default()
+        };
+        let err = PrfItem::fetch_remote(&url, None, None, Some(&guarded), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("an HTML page \"Login\""), "{err:#}");
+        assert_eq!(UpdateErrorCategory::classify(&err), UpdateErrorCategory::InvalidContent);
+        server.await.unwrap();
+
+        // nothing listens on a port just given back
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed = format!("http://{}/sub", listener.local_addr().unwrap());
+        drop(listener);
+        assert_eq!(fetch(&closed, None).await, UpdateErrorCategory::Network);
+
+        // the kernel accepts the connection, nobody answers it
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let silent = format!("http://{}/sub", listener.local_addr().unwrap());
+        let impatient = PrfOption {
+            timeout_seconds: Some(1),
+            ..PrfOption::default()
+        };
+        assert_eq!(fetch(&silent, Some(&impatient)).await, UpdateErrorCategory::Timeout);
+        drop(listener);
+
+        assert_eq!(
+            UpdateErrorCategory::classify(&anyhow!("failed to fetch remote profile with status 503")),
+            UpdateErrorCategory::Other
+        );
+        assert!(!UpdateErrorCategory::InvalidContent.is_transient());
+    }
+
+    #[test]
+    fn a_success_resets_the_failure_streak() {
+        let mut state = PrfUpdateState::default();
+        state.record(
+            10,
+            UpdateOutcome::Failed(UpdateErrorCategory::Timeout, "timed out".into()),
+        );
+        state.record(
+            20,
+            UpdateOutcome::Failed(UpdateErrorCategory::Network, "refused".into()),
+        );
+        assert_eq!(state.consecutive_failures, 2);
+        assert_eq!(state.last_error, Some(UpdateErrorCategory::Network));
+        assert_eq!(state.last_success, None);
+
+        state.record(30, UpdateOutcome::Succeeded);
+        assert_eq!(state.consecutive_failures, 0);
+        assert_eq!((state.last_attempt, state.last_success), (Some(30), Some(30)));
+        assert_eq!(state.last_error, None);
+    }
+
+    #[test]
+    fn a_held_download_is_not_a_success() {
+        let mut state = PrfUpdateState::default();
+        state.record(10, UpdateOutcome::Succeeded);
+        state.record(
+            20,
+            UpdateOutcome::Failed(UpdateErrorCategory::Timeout, "timed out".into()),
+        );
+        state.record(30, UpdateOutcome::Held);
+        assert_eq!((state.last_attempt, state.last_success), (Some(30), Some(10)));
+        assert_eq!(state.last_held, Some(30));
+        assert_eq!(state.consecutive_failures, 1);
+        assert_eq!(state.last_error, Some(UpdateErrorCategory::Timeout));
+
+        state.record(40, UpdateOutcome::Succeeded);
+        assert_eq!(state.last_held, None);
+    }
+
+    #[test]
+    fn the_mirror_that_answered_last_is_tried_first() {
+        let mut item = PrfItem {
+            url: Some("https://a.example/sub".into()),
+            mirrors: Some(vec!["https://b.example/sub".into(), "https://c.example/sub ".into()]),
+            ..PrfItem::default()
+        };
+        assert_eq!(
+            item.remote_urls(),
+            [
+                "https://a.example/sub",
+                "https://b.example/sub",
+                "https://c.example/sub"
+            ]
+        );
+
+        let mut report = MirrorReport::default();
+        report.failed("https://a.example/sub", &anyhow!("failed to fetch remote profile"));
+        report.failed(
+            "https://b.example/sub",
+            &anyhow!(FetchError::Status {
+                what: "remote profile",
+                status: StatusCode::FORBIDDEN,
+            }),
+        );
+        report.succeeded("https://c.example/sub");
+        let state = item.update_state.get_or_insert_default();
+        state.record_mirrors(item.url.as_ref(), &report);
+        assert_eq!(state.last_mirror.as_deref(), Some("https://c.example/sub"));
+        assert_eq!(state.mirror_errors.len(), 2);
+        assert_eq!(state.mirror_errors[1].category, UpdateErrorCategory::ClientError);
+        assert_eq!(
+            item.remote_urls(),
+            [
+                "https://c.example/sub",
+                "https://a.example/sub",
+                "https://b.example/sub"
+            ]
+        );
+
+        // a mirror that was removed since is not preferred
+        item.mirrors = Some(vec!["https://b.example/sub".into()]);
+        assert_eq!(item.remote_urls(), ["https://a.example/sub", "https://b.example/sub"]);
+    }
+
+    #[test]
+    fn change_summary_counts_everything_but_lists_a_few() {
+        let diff = ProfileDiff {
+            proxies_added: (0..30).map(|i| format!("node {i}").into()).collect(),
+            proxies_renamed: vec![ProxyRename {
+                from: "HK".into(),
+                to: "HK 01".into(),
+            }],
+            proxies_before: 10,
+            proxies_after: 40,
+            rules_before: 5,
+            rules_after: 5,
+            ..ProfileDiff::default()
+        };
+        let summary = PrfChangeSummary::new(&diff, 1);
+        assert_eq!(summary.nodes_added.count, 30);
+        assert_eq!(summary.nodes_added.names.len(), CHANGE_LIST_LIMIT);
+        assert_eq!(summary.nodes_renamed.names, ["HK -> HK 01"]);
+        assert_eq!((summary.nodes_before, summary.nodes_after), (10, 40));
+        assert!(!summary.is_empty());
+        assert!(PrfChangeSummary::new(&ProfileDiff::default(), 1).is_empty());
+    }
 }
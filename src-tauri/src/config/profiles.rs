@@ -1,6 +1,7 @@
 use super::{
     PrfOption,
-    prfitem::{PrfItem, PrfSelected},
+    prfitem::{MirrorReport, PrfAlertState, PrfHeldUpdate, PrfItem, PrfSelected, UpdateOutcome},
+    revision,
 };
 use crate::{
     core::{handle, tray::Tray},
@@ -33,14 +34,17 @@ pub(crate) static PROFILE_WRITE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mute
 /// Regex to check profile file names, eg.
 /// R12345678.yaml (remote)
 /// L12345678.yaml (local)
+/// C12345678.yaml (composite)
 /// m12345678.yaml (merge)
 /// s12345678.js (script)
 /// r12345678.yaml (rules)
 /// p12345678.yaml (proxies)
 /// g12345678.yaml (groups)
+/// f12345678.yaml (filter)
+/// S12345678.yaml (ruleset)
 #[allow(clippy::unwrap_used)]
 static REGEX_PROFILE_FILE: LazyLock<regex::Regex> =
-    LazyLock::new(|| regex::Regex::new(r"^(?:[RLmrpg][a-zA-Z0-9]+\.yaml|s[a-zA-Z0-9]+\.js)$").unwrap());
+    LazyLock::new(|| regex::Regex::new(r"^(?:[RLCSmrpgf][a-zA-Z0-9]+\.yaml|s[a-zA-Z0-9]+\.js)$").unwrap());
 
 // activate selected nodes task handle
 static ACTIVATE_SELECTED_TASK: LazyLock<Mutex<Option<JoinHandle<()>>>> = LazyLock::new(|| Mutex::new(None));
@@ -91,6 +95,7 @@ pub struct CleanupResult {
 }
 
 pub(crate) struct ProfileDeletePlan {
+    uid: String,
     files: Vec<String>,
 }
 
@@ -104,6 +109,9 @@ impl ProfileDeletePlan {
                 logging!(warn, Type::Config, "清理已删除订阅文件失败: {file} - {error}");
             }
         }
+        if let Err(error) = revision::remove_revisions(&self.uid).await {
+            logging!(warn, Type::Config, "清理已删除订阅历史版本失败: {} - {error}", self.uid);
+        }
     }
 }
 
@@ -221,7 +229,7 @@ impl IProfiles {
                 .with_context(|| format!("failed to write to file \"{file}\""))?;
         }
 
-        if self.current.is_none() && (item.itype == Some("remote".into()) || item.itype == Some("local".into())) {
+        if self.current.is_none() && item.is_main_profile() {
             self.current = uid.to_owned();
         }
 
@@ -271,11 +279,17 @@ impl IProfiles {
 
         for each in items.iter_mut() {
             if each.uid.as_ref() == Some(uid) {
+                // validators of the old url say nothing about the new one
+                if item.url.is_some() && item.url != each.url {
+                    each.etag = None;
+                    each.last_modified = None;
+                }
                 patch!(each, item, itype);
                 patch!(each, item, name);
                 patch!(each, item, desc);
                 patch!(each, item, file);
                 patch!(each, item, url);
+                patch!(each, item, mirrors);
                 patch!(each, item, selected);
                 patch!(each, item, extra);
                 patch!(each, item, updated);
@@ -307,7 +321,7 @@ impl IProfiles {
     }
 
     /// be used to update the remote item
-    /// only patch `updated` `extra` `file_data`
+    /// only patch `updated` `extra` `last_change` `file_data`, and `conversion` with the file
     pub async fn update_item(&mut self, uid: &String, item: &mut PrfItem) -> Result<()> {
         if self.items.is_none() {
             self.items = Some(vec![]);
@@ -321,13 +335,21 @@ impl IProfiles {
 
             for each in items.iter_mut() {
                 if each.uid == some_uid {
+                    // the body on disk came from the url and at the time the item had until now
+                    let previous = (each.url.clone(), each.updated);
                     each.extra = item.extra;
                     each.updated = item.updated;
                     each.home = item.home.to_owned();
+                    each.etag = item.etag.to_owned();
+                    each.last_modified = item.last_modified.to_owned();
                     each.option = PrfOption::merge(each.option.as_ref(), item.option.as_ref());
+                    if item.last_change.is_some() {
+                        each.last_change = item.last_change.take();
+                    }
                     // save the file data
                     // move the field value after save
                     if let Some(file_data) = item.file_data.take() {
+                        each.conversion = item.conversion.take();
                         let file = each.file.take();
                         let file =
                             file.unwrap_or_else(|| item.file.take().unwrap_or_else(|| format!("{}.yaml", uid).into()));
@@ -336,6 +358,8 @@ impl IProfiles {
                         each.file = Some(file.clone());
 
                         let path = dirs::app_profiles_dir()?.join(file.as_str());
+                        let current = (item.url.clone().or_else(|| each.url.clone()), item.updated);
+                        record_revisions(uid, previous, current, &path, &file_data).await;
 
                         fs::write(&path, file_data.as_bytes())
                             .await
@@ -359,10 +383,12 @@ impl IProfiles {
                 op.rules.clone(),
                 op.proxies.clone(),
                 op.groups.clone(),
+                op.filter.clone(),
             ]
             .into_iter()
             .collect::<Vec<_>>()
         });
+        let held_file = self.get_item(uid)?.held.as_ref().map(|held| held.file.clone());
         let mut items = self.items.take().unwrap_or_default();
         let mut files = Vec::new();
 
@@ -370,16 +396,31 @@ impl IProfiles {
             files.push(file);
         }
 
+        files.extend(held_file);
+
         for delete_uid in delete_uids {
             if let Some(file) = Self::take_item_file_by_uid(&mut items, delete_uid.as_deref()) {
                 files.push(file);
             }
         }
 
+        // a composite keeps working from its remaining sources, a profile without the rule set
+        for option in items.iter_mut().filter_map(|item| item.option.as_mut()) {
+            if let Some(sources) = option.sources.as_mut() {
+                sources.retain(|source| source != uid);
+                if option.primary.as_ref() == Some(uid) {
+                    option.primary = None;
+                }
+            }
+            if let Some(rule_sets) = option.rule_sets.as_mut() {
+                rule_sets.retain(|rule_set| rule_set != uid);
+            }
+        }
+
         if current == *uid {
             self.current = None;
             for item in items.iter() {
-                if item.itype == Some("remote".into()) || item.itype == Some("local".into()) {
+                if item.is_main_profile() {
                     self.current = item.uid.clone();
                     break;
                 }
@@ -387,7 +428,13 @@ impl IProfiles {
         }
 
         self.items = Some(items);
-        Ok((current == *uid, ProfileDeletePlan { files }))
+        Ok((
+            current == *uid,
+            ProfileDeletePlan {
+                uid: uid.clone(),
+                files,
+            },
+        ))
     }
 
     /// 获取current指向的订阅内容
@@ -395,6 +442,9 @@ impl IProfiles {
         match (self.current.as_ref(), self.items.as_ref()) {
             (Some(current), Some(items)) => {
                 if let Some(item) = items.iter().find(|e| e.uid.as_ref() == Some(current)) {
+                    if item.itype.as_ref().is_some_and(|t| t == "composite") {
+                        return self.composite_mapping(item).await;
+                    }
                     let file_path = match item.file.as_ref() {
                         Some(file) => dirs::app_profiles_dir()?.join(file.as_str()),
                         None => bail!("failed to get the file field"),
@@ -412,6 +462,23 @@ impl IProfiles {
         self.current.as_ref() == Some(index)
     }
 
+    /// 判断 profile 是否参与 current 的生成：current 本身、组合订阅的来源或其引用的规则集
+    pub fn feeds_current_profile(&self, uid: &String) -> bool {
+        if self.is_current_profile_index(uid) {
+            return true;
+        }
+        let Some(option) = self
+            .current
+            .as_ref()
+            .and_then(|current| self.get_item(current).ok())
+            .and_then(|item| item.option.as_ref())
+        else {
+            return false;
+        };
+        let listed = |uids: Option<&Vec<String>>| uids.is_some_and(|uids| uids.contains(uid));
+        listed(option.sources.as_ref()) || listed(option.rule_sets.as_ref())
+    }
+
     /// 获取所有的profiles(uid，名称, 是否为 current)
     pub fn profiles_preview(&self) -> Option<Vec<IProfilePreview<'_>>> {
         self.items.as_ref().map(|items| {
@@ -551,9 +618,8 @@ impl IProfiles {
                     active_files.insert(file);
                 }
 
-                // 对于主 profile 类型（remote/local），还需要收集其关联的扩展文件
-                if let Some(itype) = &item.itype
-                    && (itype == "remote" || itype == "local")
+                // 对于主 profile 类型（remote/local/composite），还需要收集其关联的扩展文件
+                if item.is_main_profile()
                     && let Some(option) = &item.option
                 {
                     // 收集关联的扩展文件
@@ -591,6 +657,13 @@ impl IProfiles {
                     {
                         active_files.insert(file);
                     }
+
+                    if let Some(filter_uid) = &option.filter
+                        && let Ok(filter_item) = self.get_item(filter_uid)
+                        && let Some(file) = &filter_item.file
+                    {
+                        active_files.insert(file);
+                    }
                 }
             }
         }
@@ -607,6 +680,26 @@ impl IProfiles {
 // 特殊的Send-safe helper函数，完全避免跨await持有guard
 use crate::config::Config;
 
+/// Feed the revision history before a profile file is overwritten: the body on disk first, in case
+/// it predates the history, then the new one, each with the `(url, updated)` it came with. Losing
+/// history is not a reason to fail an update.
+async fn record_revisions(
+    uid: &String,
+    previous: (Option<String>, Option<usize>),
+    current: (Option<String>, Option<usize>),
+    path: &Path,
+    data: &str,
+) {
+    if let Ok(body) = fs::read_to_string(path).await
+        && let Err(error) = revision::record_revision(uid, previous.0.as_ref(), previous.1, &body).await
+    {
+        logging!(warn, Type::Config, "记录订阅历史版本失败: {uid} - {error}");
+    }
+    if let Err(error) = revision::record_revision(uid, current.0.as_ref(), current.1, data).await {
+        logging!(warn, Type::Config, "记录订阅历史版本失败: {uid} - {error}");
+    }
+}
+
 pub async fn profiles_append_item_with_filedata_safe(item: &PrfItem, file_data: Option<String>) -> Result<()> {
     let item = &mut PrfItem::from(item, file_data).await?;
     profiles_append_item_safe(item).await
@@ -950,6 +1043,126 @@ pub async fn record_selected_node(group_name: &str, node: &str) -> Result<()> {
     Ok(())
 }
 
+/// Store which subscription alerts each profile has shown, in one write.
+pub async fn record_alert_states(states: Vec<(String, PrfAlertState)>) -> Result<()> {
+    Config::profiles()
+        .await
+        .with_data_modify(move |mut profiles| async move {
+            for item in profiles.items.iter_mut().flatten() {
+                if let Some((_, state)) = states.iter().find(|(uid, _)| item.uid.as_ref() == Some(uid)) {
+                    item.alert = Some(state.clone());
+                }
+            }
+            profiles.save_file().await?;
+            Ok((profiles, ()))
+        })
+        .await
+}
+
+/// Keep a download of `uid` that failed its `update_guard` next to the profile instead of in it.
+/// `true` when the same outcome was already held, so the user has been told about it before.
+pub async fn hold_profile_update(uid: &String, held: PrfHeldUpdate, data: &str) -> Result<bool> {
+    let path = dirs::app_profiles_dir()?.join(held.file.as_str());
+    fs::write(&path, data.as_bytes())
+        .await
+        .with_context(|| format!("failed to write to file \"{}\"", held.file))?;
+
+    let uid = uid.clone();
+    let repeated = Config::profiles()
+        .await
+        .with_data_modify(move |mut profiles| async move {
+            let mut repeated = false;
+            if let Some(item) = profiles
+                .items
+                .iter_mut()
+                .flatten()
+                .find(|item| item.uid.as_ref() == Some(&uid))
+            {
+                repeated = item
+                    .held
+                    .as_ref()
+                    .is_some_and(|previous| previous.reason == held.reason && previous.nodes_after == held.nodes_after);
+                item.held = Some(held);
+                profiles.save_file().await?;
+            }
+            Ok((profiles, repeated))
+        })
+        .await?;
+    handle::Handle::refresh_profiles();
+    Ok(repeated)
+}
+
+/// Take the held update off `uid`, with what was downloaded or `None` when that can no longer be
+/// read. The held file is removed either way.
+pub async fn take_held_update(uid: &String) -> Result<Option<(PrfHeldUpdate, Option<String>)>> {
+    let uid = uid.clone();
+    let held = Config::profiles()
+        .await
+        .with_data_modify(move |mut profiles| async move {
+            let held = profiles
+                .items
+                .iter_mut()
+                .flatten()
+                .find(|item| item.uid.as_ref() == Some(&uid))
+                .and_then(|item| item.held.take());
+            if held.is_some() {
+                profiles.save_file().await?;
+            }
+            Ok((profiles, held))
+        })
+        .await?;
+    let Some(held) = held else {
+        return Ok(None);
+    };
+    handle::Handle::refresh_profiles();
+
+    let path = dirs::app_profiles_dir()?.join(held.file.as_str());
+    let data = match fs::read_to_string(&path).await {
+        Ok(data) => Some(data.into()),
+        Err(error) => {
+            logging!(warn, Type::Config, "读取暂存的订阅更新失败: {} - {error}", held.file);
+            None
+        }
+    };
+    if let Err(error) = path.remove_if_exists().await {
+        logging!(warn, Type::Config, "清理暂存的订阅更新失败: {} - {error}", held.file);
+    }
+    Ok(Some((held, data)))
+}
+
+/// Record how an update of `uid` started at `attempted` went, `mirrors` being how each of its urls
+/// answered.
+pub async fn record_update_result(
+    uid: &String,
+    attempted: i64,
+    outcome: UpdateOutcome,
+    mirrors: Option<MirrorReport>,
+) -> Result<()> {
+    let uid = uid.clone();
+    Config::profiles()
+        .await
+        .with_data_modify(move |mut profiles| async move {
+            if let Some(item) = profiles
+                .items
+                .iter_mut()
+                .flatten()
+                .find(|item| item.uid.as_ref() == Some(&uid))
+            {
+                let url = item.url.clone();
+                let state = item.update_state.get_or_insert_default();
+                state.record(attempted, outcome);
+                if let Some(report) = &mirrors {
+                    state.record_mirrors(url.as_ref(), report);
+                }
+                profiles.save_file().await?;
+            }
+            Ok((profiles, ()))
+        })
+        .await?;
+    handle::Handle::refresh_profiles();
+    Ok(())
+}
+
 async fn persist_reconciled_selected(
     profile_uid: &String,
     original_selected: &[PrfSelected],
@@ -0,0 +1,334 @@
+//! Revision history of profile files.
+//!
+//! `IProfiles::update_item` overwrites a profile in place, so a provider pushing a broken
+//! subscription would otherwise take the last working copy with it. Every distinct body written
+//! for a profile is kept under `profiles/revisions/<uid>/`, newest first, up to
+//! [`MAX_REVISIONS`]. The directory is invisible to the orphan cleanup, which only looks at
+//! files directly inside the profiles dir.
+
+use super::diff::{ProfileDiff, diff_profiles};
+use crate::{
+    core::validate::ValidationOutcome,
+    utils::{dirs, help},
+};
+use anyhow::{Context as _, Result, bail};
+use clash_verge_logging::{Type, logging, logging_error};
+use serde::{Deserialize, Serialize};
+use serde_yaml_ng::Mapping;
+use sha2::{Digest as _, Sha256};
+use smartstring::alias::String;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// how many versions of each profile are kept
+pub const MAX_REVISIONS: usize = 10;
+
+const INDEX_FILE: &str = "index.yaml";
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfRevision {
+    /// revision id, also the file stem
+    pub id: String,
+
+    /// unix timestamp of when the body was downloaded, or stored when that is not known
+    pub created: usize,
+
+    /// source url the body was downloaded from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// body size in bytes
+    pub size: usize,
+
+    /// sha256 of the body, hex encoded
+    pub hash: String,
+}
+
+/// sha256 of a profile body, hex encoded
+pub fn content_hash(data: &str) -> String {
+    format!("{:x}", Sha256::digest(data.as_bytes())).into()
+}
+
+fn revisions_dir(uid: &str) -> Result<PathBuf> {
+    // uids come from the frontend, keep them from walking out of the revisions dir
+    if uid.is_empty() || !uid.chars().all(|c| c.is_ascii_alphanumeric()) {
+        bail!("invalid profile uid \"{uid}\"");
+    }
+    Ok(dirs::app_profiles_dir()?.join("revisions").join(uid))
+}
+
+async fn read_index(dir: &Path) -> Result<Vec<PrfRevision>> {
+    let path = dir.join(INDEX_FILE);
+    if !fs::try_exists(&path).await.unwrap_or(false) {
+        return Ok(Vec::new());
+    }
+    help::read_yaml(&path).await
+}
+
+/// Store `data`, downloaded from `url` at `created`, as the newest revision of `uid`, unless it
+/// is the newest revision already.
+///
+/// Returns whether a revision was written. Revisions beyond [`MAX_REVISIONS`] are dropped
+/// together with their files.
+pub async fn record_revision(uid: &str, url: Option<&String>, created: Option<usize>, data: &str) -> Result<bool> {
+    record_revision_in(&revisions_dir(uid)?, url, created, data).await
+}
+
+async fn record_revision_in(dir: &Path, url: Option<&String>, created: Option<usize>, data: &str) -> Result<bool> {
+    let mut index = read_index(dir).await?;
+    let hash = content_hash(data);
+    if index.first().is_some_and(|newest| newest.hash == hash) {
+        return Ok(false);
+    }
+
+    fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("failed to create \"{}\"", dir.display()))?;
+
+    let created = created.unwrap_or_else(|| chrono::Local::now().timestamp() as usize);
+    let revision = PrfRevision {
+        id: format!("{created}-{}", &hash[..8]).into(),
+        created,
+        url: url.cloned(),
+        size: data.len(),
+        hash,
+    };
+    fs::write(dir.join(format!("{}.yaml", revision.id)), data.as_bytes())
+        .await
+        .context("failed to write the profile revision")?;
+    // an older body coming back under its old timestamp gets the same id, keep only the newest
+    index.retain(|r| r.id != revision.id);
+    index.insert(0, revision);
+
+    for dropped in index.split_off(MAX_REVISIONS.min(index.len())) {
+        let _ = fs::remove_file(dir.join(format!("{}.yaml", dropped.id))).await;
+    }
+    help::save_yaml(&dir.join(INDEX_FILE), &index, None).await?;
+    Ok(true)
+}
+
+/// revisions of `uid`, newest first
+pub async fn list_revisions(uid: &str) -> Result<Vec<PrfRevision>> {
+    read_index(&revisions_dir(uid)?).await
+}
+
+/// body of one revision; only ids listed in the index are readable
+pub async fn read_revision(uid: &str, id: &str) -> Result<String> {
+    read_revision_in(&revisions_dir(uid)?, uid, id).await
+}
+
+async fn read_revision_in(dir: &Path, uid: &str, id: &str) -> Result<String> {
+    let index = read_index(dir).await?;
+    let Some(revision) = index.iter().find(|r| r.id == id) else {
+        bail!("revision \"{id}\" of profile \"{uid}\" not found");
+    };
+    let content = fs::read_to_string(dir.join(format!("{}.yaml", revision.id)))
+        .await
+        .with_context(|| format!("failed to read revision \"{id}\""))?;
+    Ok(content.into())
+}
+
+/// structural diff from revision `from` to revision `to`
+pub async fn diff_revisions(uid: &str, from: &str, to: &str) -> Result<ProfileDiff> {
+    let parse = |data: String, id: &str| {
+        serde_yaml_ng::from_str::<Mapping>(&data).with_context(|| format!("revision \"{id}\" is not a valid profile"))
+    };
+    let old = parse(read_revision(uid, from).await?, from)?;
+    let new = parse(read_revision(uid, to).await?, to)?;
+    Ok(diff_profiles(&old, &new))
+}
+
+/// Write `data` over the profile file at `path` and `apply` it.
+///
+/// When the body is rejected, or applying fails outright, the file goes back to what it was
+/// before, so a rollback never leaves the profile worse off than it found it.
+pub async fn rollback_file<F, Fut>(path: &Path, data: &str, apply: F) -> Result<ValidationOutcome>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<ValidationOutcome>>,
+{
+    let previous = fs::read_to_string(path).await.ok();
+    fs::write(path, data.as_bytes())
+        .await
+        .context("failed to save the file")?;
+
+    match apply().await {
+        Ok(outcome @ ValidationOutcome::Invalid { .. }) => {
+            if let Some(previous) = previous {
+                logging!(warn, Type::Config, "[订阅回滚] {} 校验失败，已还原", path.display());
+                fs::write(path, previous.as_bytes())
+                    .await
+                    .context("failed to restore the file")?;
+            }
+            Ok(outcome)
+        }
+        Ok(outcome) => Ok(outcome),
+        Err(err) => {
+            if let Some(previous) = previous {
+                logging!(
+                    warn,
+                    Type::Config,
+                    "[订阅回滚] {} 应用失败，已还原: {}",
+                    path.display(),
+                    err
+                );
+                fs::write(path, previous.as_bytes())
+                    .await
+                    .context("failed to restore the file")?;
+                // 用还原后的文件重新生成运行配置
+                logging_error!(Type::Config, apply().await);
+            }
+            Err(err)
+        }
+    }
+}
+
+/// drop the whole history of a deleted profile
+pub async fn remove_revisions(uid: &str) -> Result<()> {
+    let dir = revisions_dir(uid)?;
+    if fs::try_exists(&dir).await.unwrap_or(false) {
+        fs::remove_dir_all(&dir)
+            .await
+            .with_context(|| format!("failed to remove \"{}\"", dir.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::panic, reason = "tests assert by panicking")]
+mod tests {
+    use super::{
+        INDEX_FILE, MAX_REVISIONS, content_hash, read_index, read_revision_in, record_revision_in, revisions_dir,
+        rollback_file,
+    };
+    use crate::core::validate::ValidationOutcome;
+    use std::{
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+    use tokio::fs;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("clash-verge-revision-{name}-{}", nanoid::nanoid!()))
+    }
+
+    #[test]
+    fn content_hash_is_stable_hex_sha256() {
+        assert_eq!(
+            content_hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn uids_that_could_escape_the_revisions_dir_are_rejected() {
+        assert!(revisions_dir("../R1").is_err());
+        assert!(revisions_dir("").is_err());
+    }
+
+    #[tokio::test]
+    async fn the_newest_body_is_not_stored_twice() {
+        let dir = temp_dir("dedupe");
+        let url = "https://a.example/sub".into();
+
+        assert!(record_revision_in(&dir, Some(&url), Some(100), "a: 1").await.unwrap());
+        assert!(!record_revision_in(&dir, Some(&url), Some(200), "a: 1").await.unwrap());
+        assert!(record_revision_in(&dir, None, Some(300), "b: 2").await.unwrap());
+        // an older body coming back under its old timestamp moves to the front
+        assert!(record_revision_in(&dir, Some(&url), Some(100), "a: 1").await.unwrap());
+
+        let index = read_index(&dir).await.unwrap();
+        fs::remove_dir_all(&dir).await.unwrap();
+
+        assert_eq!(index.len(), 2);
+        assert_eq!(index[0].created, 100);
+        assert_eq!(index[0].url.as_deref(), Some("https://a.example/sub"));
+        assert_eq!(index[0].hash, content_hash("a: 1"));
+        assert_eq!(index[1].created, 300);
+        assert_eq!(index[1].url, None);
+    }
+
+    #[tokio::test]
+    async fn history_is_pruned_to_max_revisions() {
+        let dir = temp_dir("prune");
+        for n in 0..MAX_REVISIONS + 3 {
+            record_revision_in(&dir, None, Some(n), &format!("n: {n}"))
+                .await
+                .unwrap();
+        }
+
+        let index = read_index(&dir).await.unwrap();
+        let mut files = Vec::new();
+        let mut entries = fs::read_dir(&dir).await.unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            files.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        fs::remove_dir_all(&dir).await.unwrap();
+
+        assert_eq!(index.len(), MAX_REVISIONS);
+        assert_eq!(index[0].created, MAX_REVISIONS + 2);
+        assert_eq!(index[MAX_REVISIONS - 1].created, 3);
+        // the dropped bodies go with their entries
+        assert_eq!(files.len(), MAX_REVISIONS + 1);
+        assert!(files.iter().any(|f| f == INDEX_FILE));
+        for revision in &index {
+            assert!(files.contains(&format!("{}.yaml", revision.id)), "{}", revision.id);
+        }
+    }
+
+    #[tokio::test]
+    async fn only_ids_in_the_index_are_read() {
+        let dir = temp_dir("read");
+        record_revision_in(&dir, None, Some(100), "a: 1").await.unwrap();
+        let id = read_index(&dir).await.unwrap()[0].id.clone();
+        // a file in the dir that the index does not list
+        fs::write(dir.join("1-deadbeef.yaml"), "b: 2").await.unwrap();
+
+        let listed = read_revision_in(&dir, "R1", &id).await;
+        let unlisted = read_revision_in(&dir, "R1", "1-deadbeef").await;
+        let escaping = read_revision_in(&dir, "R1", "../index").await;
+        fs::remove_dir_all(&dir).await.unwrap();
+
+        assert_eq!(listed.unwrap(), "a: 1");
+        assert!(unlisted.is_err());
+        assert!(escaping.is_err());
+    }
+
+    #[tokio::test]
+    async fn rollback_restores_the_file_when_the_body_is_rejected() {
+        let dir = temp_dir("rollback");
+        fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("R1.yaml");
+
+        fs::write(&path, "old: 1").await.unwrap();
+        let rejected = rollback_file(&path, "new: 1", || async {
+            Ok(ValidationOutcome::invalid_from_message("bad config"))
+        })
+        .await
+        .unwrap();
+        let after_rejected = fs::read_to_string(&path).await.unwrap();
+
+        let applied = AtomicUsize::new(0);
+        let failed = rollback_file(&path, "new: 1", || async {
+            applied.fetch_add(1, Ordering::SeqCst);
+            anyhow::bail!("core is down")
+        })
+        .await;
+        let after_failed = fs::read_to_string(&path).await.unwrap();
+
+        let accepted = rollback_file(&path, "new: 1", || async { Ok(ValidationOutcome::Valid) })
+            .await
+            .unwrap();
+        let after_accepted = fs::read_to_string(&path).await.unwrap();
+        fs::remove_dir_all(&dir).await.unwrap();
+
+        assert!(matches!(rejected, ValidationOutcome::Invalid { .. }));
+        assert_eq!(after_rejected, "old: 1");
+        assert!(failed.is_err());
+        // applied once with the revision and once more with the restored file
+        assert_eq!(applied.load(Ordering::SeqCst), 2);
+        assert_eq!(after_failed, "old: 1");
+        assert!(accepted.is_valid());
+        assert_eq!(after_accepted, "new: 1");
+    }
+}
@@ -2,7 +2,7 @@ use serde_yaml_ng::{Mapping, Value};
 use smartstring::alias::String;
 use std::collections::{HashMap, HashSet};
 
-use crate::enhance::field::use_keys;
+use crate::enhance::{field::use_keys, script_error::ScriptError};
 
 const PATCH_CONFIG_INNER: [&str; 5] = ["allow-lan", "ipv6", "log-level", "unified-delay", "tunnels"];
 
@@ -14,6 +14,8 @@ pub struct IRuntime {
     pub exists_keys: HashSet<String>,
     // TODO 或许可以用 FixMap 来存储以提升效率
     pub chain_logs: HashMap<String, Vec<(String, String)>>,
+    // 链中脚本的结构化错误，随 chain_logs 一起生成
+    pub script_errors: Vec<ScriptError>,
 }
 
 impl IRuntime {
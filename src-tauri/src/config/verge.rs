@@ -183,6 +183,18 @@ pub struct IVerge {
     /// Create backups automatically when critical configs change
     pub auto_backup_on_change: Option<bool>,
 
+    /// 订阅流量与到期提醒
+    pub enable_subscription_alerts: Option<bool>,
+
+    /// 流量提醒阈值（百分比）
+    pub subscription_alert_thresholds: Option<Vec<u8>>,
+
+    /// 到期前多少天提醒，0 表示只在到期后提醒
+    pub subscription_alert_expire_days: Option<u64>,
+
+    /// 订阅更新后发送变化摘要通知
+    pub notify_subscription_changes: Option<bool>,
+
     /// verge 的各种 port 用于覆盖 clash 的各种 port
     #[cfg(not(target_os = "windows"))]
     pub verge_redir_port: Option<u16>,
@@ -435,6 +447,10 @@ impl IVerge {
             enable_auto_backup_schedule: Some(false),
             auto_backup_interval_hours: Some(24),
             auto_backup_on_change: Some(true),
+            enable_subscription_alerts: Some(true),
+            subscription_alert_thresholds: Some(vec![80, 95]),
+            subscription_alert_expire_days: Some(3),
+            notify_subscription_changes: Some(false),
             webdav_url: None,
             webdav_username: None,
             webdav_password: None,
@@ -540,6 +556,10 @@ impl IVerge {
         patch!(enable_auto_backup_schedule);
         patch!(auto_backup_interval_hours);
         patch!(auto_backup_on_change);
+        patch!(enable_subscription_alerts);
+        patch!(subscription_alert_thresholds);
+        patch!(subscription_alert_expire_days);
+        patch!(notify_subscription_changes);
 
         patch!(webdav_url);
         patch!(webdav_username);
@@ -46,6 +46,7 @@ pub mod timing {
 pub mod files {
     pub const RUNTIME_CONFIG: &str = "clash-verge.yaml";
     pub const CHECK_CONFIG: &str = "clash-verge-check.yaml";
+    pub const LAST_GOOD_CONFIG: &str = "clash-verge-last-good.yaml";
     pub const DNS_CONFIG: &str = "dns_config.yaml";
     pub const WINDOW_STATE: &str = "window_state.json";
 }
@@ -0,0 +1,1237 @@
+//! Surge, Quantumult X and Loon profiles.
+//!
+//! The three iOS clients share an INI layout — `[Proxy]`, `[Proxy Group]`, `[Rule]` and `[Host]`,
+//! which Quantumult X calls `[server_local]`, `[policy]`, `[filter_local]` and `[host]` — and
+//! mostly differ in how a line inside a section is spelled. Each line is translated on its own and
+//! whatever has no mihomo counterpart is named in the report. Once everything is read, group
+//! members and rule policies pointing at something that did not make it are dropped too, since
+//! mihomo refuses to load a profile with a dangling reference.
+
+use super::{
+    ConversionReport, DEFAULT_SELECTOR, NodeReport, profile_from_proxies,
+    rule_list::{CLASSICAL_TYPES, alias},
+    share_link::base_proxy,
+};
+use serde_yaml_ng::{Mapping, Sequence, Value};
+use std::collections::HashSet;
+
+/// sections only Quantumult X writes
+const QUANTUMULT_X_SECTIONS: [&str; 5] = [
+    "server_local",
+    "policy",
+    "filter_local",
+    "server_remote",
+    "filter_remote",
+];
+/// sections only Loon writes
+const LOON_SECTIONS: [&str; 3] = ["remote proxy", "remote rule", "remote filter"];
+/// Loon capitalises its proxy types, Surge does not
+const LOON_TYPES: [&str; 6] = ["Shadowsocks", "ShadowsocksR", "VMess", "VLESS", "Trojan", "Hysteria2"];
+/// how often rule lists pulled in by `RULE-SET` are refreshed, in seconds
+const RULE_SET_INTERVAL: u64 = 86400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Proxy,
+    Group,
+    Rule,
+    Host,
+    Other,
+}
+
+impl Section {
+    fn of(header: &str) -> Self {
+        match header.to_ascii_lowercase().as_str() {
+            "proxy" | "server_local" => Self::Proxy,
+            "proxy group" | "policy" => Self::Group,
+            "rule" | "filter_local" => Self::Rule,
+            "host" => Self::Host,
+            _ => Self::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flavor {
+    Surge,
+    QuantumultX,
+    Loon,
+}
+
+impl Flavor {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Surge => "surge",
+            Self::QuantumultX => "quantumult-x",
+            Self::Loon => "loon",
+        }
+    }
+
+    fn detect(sections: &[(&str, Vec<&str>)]) -> Self {
+        let has = |names: &[&str]| {
+            sections
+                .iter()
+                .any(|(header, _)| names.iter().any(|name| header.eq_ignore_ascii_case(name)))
+        };
+        let loon_proxy = sections
+            .iter()
+            .filter(|(header, _)| Section::of(header) == Section::Proxy)
+            .flat_map(|(_, lines)| lines)
+            .filter_map(|line| line.split_once('=')?.1.split(',').next())
+            .any(|kind| LOON_TYPES.contains(&kind.trim()));
+        if has(&QUANTUMULT_X_SECTIONS) {
+            Self::QuantumultX
+        } else if has(&LOON_SECTIONS) || loon_proxy {
+            Self::Loon
+        } else {
+            Self::Surge
+        }
+    }
+}
+
+/// The non-empty, non-comment lines of each `[section]`, in order.
+fn read_sections(data: &str) -> Vec<(&str, Vec<&str>)> {
+    let mut sections: Vec<(&str, Vec<&str>)> = Vec::new();
+    for line in data.trim_start_matches('\u{feff}').lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') || line.starts_with("//") {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            sections.push((header.trim(), Vec::new()));
+        } else if let Some((_, lines)) = sections.last_mut() {
+            lines.push(line);
+        }
+    }
+    sections
+}
+
+/// Whether `data` is a Surge, Quantumult X or Loon profile rather than a mihomo one.
+pub fn is_ios_profile(data: &str) -> bool {
+    serde_yaml_ng::from_str::<Mapping>(data).is_err()
+        && read_sections(data)
+            .iter()
+            .any(|(header, _)| Section::of(header) != Section::Other)
+}
+
+/// Parse a Surge, Quantumult X or Loon profile into a mihomo profile and a report of what did
+/// not convert.
+///
+/// `None` when `data` has none of the sections a profile is made of.
+pub fn parse_profile(data: &str) -> Option<(Mapping, ConversionReport)> {
+    let sections = read_sections(data);
+    if !sections.iter().any(|(header, _)| Section::of(header) != Section::Other) {
+        return None;
+    }
+    let flavor = Flavor::detect(&sections);
+    let mut report = ConversionReport {
+        format: flavor.name().into(),
+        ..ConversionReport::default()
+    };
+    let mut parsed = Parsed::default();
+
+    for (header, lines) in &sections {
+        match Section::of(header) {
+            Section::Proxy => lines
+                .iter()
+                .for_each(|line| parsed.read_proxy(flavor, line, &mut report)),
+            Section::Group => lines
+                .iter()
+                .for_each(|line| parsed.read_group(flavor, line, &mut report)),
+            Section::Rule => lines.iter().for_each(|line| parsed.read_rule(line, &mut report)),
+            Section::Host => lines.iter().for_each(|line| parsed.read_host(line, &mut report)),
+            Section::Other => report.dropped.push(format!("section [{header}] is left out")),
+        }
+    }
+
+    report.converted = parsed.proxies.len();
+    Some((parsed.assemble(&mut report), report))
+}
+
+#[derive(Default)]
+struct Parsed {
+    proxies: Vec<Mapping>,
+    groups: Vec<Mapping>,
+    rules: Vec<Rule>,
+    rule_providers: Mapping,
+    hosts: Mapping,
+}
+
+struct Rule {
+    /// the rule without its policy, e.g. `DOMAIN-SUFFIX,example.com`
+    body: String,
+    policy: String,
+    /// `no-resolve` and `src`, which mihomo writes after the policy
+    options: Vec<String>,
+    line: String,
+}
+
+impl Parsed {
+    fn read_proxy(&mut self, flavor: Flavor, line: &str, report: &mut ConversionReport) {
+        let Some((left, right)) = line.split_once('=') else {
+            report.dropped.push(format!("proxy line \"{line}\" is not understood"));
+            return;
+        };
+        let mut unsupported = Vec::new();
+        let (name, proxy) = match flavor {
+            Flavor::QuantumultX => quantumult_x_proxy(left.trim(), right, &mut unsupported),
+            Flavor::Surge | Flavor::Loon => {
+                let name = unquote(left.trim()).to_owned();
+                let proxy = surge_proxy(&name, right, &mut unsupported);
+                (name, proxy)
+            }
+        };
+        match proxy {
+            Ok(Some(proxy)) => {
+                if !unsupported.is_empty() {
+                    report.nodes.push(NodeReport {
+                        name,
+                        skipped: false,
+                        unsupported,
+                    });
+                }
+                self.proxies.push(proxy);
+            }
+            // `DIRECT = direct` and the like, which mihomo has built in
+            Ok(None) => {}
+            Err(reason) => report.nodes.push(NodeReport {
+                name,
+                skipped: true,
+                unsupported: vec![reason],
+            }),
+        }
+    }
+
+    fn read_group(&mut self, flavor: Flavor, line: &str, report: &mut ConversionReport) {
+        let mut unsupported = Vec::new();
+        match parse_group(flavor, line, &mut unsupported) {
+            Ok(group) => {
+                let name = group.get("name").and_then(Value::as_str).unwrap_or_default();
+                if !unsupported.is_empty() {
+                    report
+                        .dropped
+                        .push(format!("group \"{name}\": left out {}", unsupported.join(", ")));
+                }
+                self.groups.push(group);
+            }
+            Err(reason) => report.dropped.push(format!("group \"{line}\" is left out: {reason}")),
+        }
+    }
+
+    fn read_rule(&mut self, line: &str, report: &mut ConversionReport) {
+        let mut unsupported = Vec::new();
+        match parse_rule(line, &mut self.rule_providers, &mut unsupported) {
+            Ok(rule) => {
+                if !unsupported.is_empty() {
+                    report
+                        .dropped
+                        .push(format!("rule \"{line}\": left out {}", unsupported.join(", ")));
+                }
+                self.rules.push(rule);
+            }
+            Err(reason) => report.dropped.push(format!("rule \"{line}\" is left out: {reason}")),
+        }
+    }
+
+    fn read_host(&mut self, line: &str, report: &mut ConversionReport) {
+        let Some((host, value)) = line.split_once('=').map(|(h, v)| (h.trim(), unquote(v.trim()))) else {
+            report.dropped.push(format!("host \"{line}\" is not understood"));
+            return;
+        };
+        if value.is_empty() || value.starts_with("server:") {
+            // `example.com = server:1.1.1.1` picks a DNS server, which mihomo does in `nameserver-policy`
+            report.dropped.push(format!("host \"{line}\" is left out"));
+        } else {
+            self.hosts.insert(host.into(), value.into());
+        }
+    }
+
+    /// The profile, with references to anything left out dropped.
+    fn assemble(self, report: &mut ConversionReport) -> Mapping {
+        let has_groups = !self.groups.is_empty();
+        let mut profile = profile_from_proxies(self.proxies);
+        let mut defined = profile
+            .get("proxies")
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(|proxy| proxy.get("name"))
+            .chain(self.groups.iter().filter_map(|group| group.get("name")))
+            .filter_map(Value::as_str)
+            .map(str::to_owned)
+            .collect::<HashSet<_>>();
+        let own_default = !defined.insert(DEFAULT_SELECTOR.to_owned());
+        let mut policies = Policies {
+            defined,
+            own_default,
+            uses_default: !has_groups && !own_default,
+        };
+
+        let mut groups = self
+            .groups
+            .into_iter()
+            .map(|group| policies.resolve_group(group, report))
+            .collect::<Sequence>();
+        let rules = self
+            .rules
+            .into_iter()
+            .filter_map(|rule| policies.resolve_rule(rule, report))
+            .collect::<Sequence>();
+
+        // the selector over every node stays, in front, when something needs it
+        if policies.uses_default
+            && let Some(Value::Sequence(default)) = profile.get("proxy-groups")
+        {
+            groups = default.iter().cloned().chain(groups).collect();
+        }
+        profile.insert("proxy-groups".into(), Value::Sequence(groups));
+        if !rules.is_empty() {
+            profile.insert("rules".into(), Value::Sequence(rules));
+        }
+        if !self.rule_providers.is_empty() {
+            profile.insert("rule-providers".into(), Value::Mapping(self.rule_providers));
+        }
+        if !self.hosts.is_empty() {
+            profile.insert("hosts".into(), Value::Mapping(self.hosts));
+        }
+        profile
+    }
+}
+
+/// The names groups and rules may point at.
+struct Policies {
+    defined: HashSet<String>,
+    /// the profile names something `PROXY` itself, so the generated selector is never needed
+    own_default: bool,
+    /// something points at the generated selector
+    uses_default: bool,
+}
+
+impl Policies {
+    /// A policy as mihomo names it: a node or group of the profile, or a built-in one.
+    fn resolve(&mut self, policy: &str) -> Option<String> {
+        let resolved = if self.defined.contains(policy) {
+            policy
+        } else {
+            match policy.to_ascii_lowercase().as_str() {
+                "direct" => "DIRECT",
+                "reject" | "reject-tinygif" | "reject-img" | "reject-dict" | "reject-array" | "reject-200"
+                | "reject-no-drop" => "REJECT",
+                "reject-drop" => "REJECT-DROP",
+                // Quantumult X's own "proxy" policy, which the selector over every node stands in for
+                "proxy" => DEFAULT_SELECTOR,
+                _ => return None,
+            }
+        };
+        self.uses_default |= resolved == DEFAULT_SELECTOR && !self.own_default;
+        Some(resolved.to_owned())
+    }
+
+    fn resolve_group(&mut self, mut group: Mapping, report: &mut ConversionReport) -> Value {
+        let name = group.get("name").and_then(Value::as_str).unwrap_or_default().to_owned();
+        let listed = group
+            .get("proxies")
+            .and_then(Value::as_sequence)
+            .cloned()
+            .unwrap_or_default();
+        let mut members = Sequence::new();
+        for member in listed.iter().filter_map(Value::as_str) {
+            match self.resolve(member) {
+                Some(policy) => members.push(policy.into()),
+                None => report
+                    .dropped
+                    .push(format!("group \"{name}\": member \"{member}\" is not defined")),
+            }
+        }
+        let pulls_nodes = group.contains_key("include-all-proxies") || group.contains_key("filter");
+        if members.is_empty() && !pulls_nodes {
+            report
+                .dropped
+                .push(format!("group \"{name}\" has no member left and falls back to DIRECT"));
+            members.push("DIRECT".into());
+        }
+        if !members.is_empty() {
+            group.insert("proxies".into(), Value::Sequence(members));
+        }
+        Value::Mapping(group)
+    }
+
+    fn resolve_rule(&mut self, rule: Rule, report: &mut ConversionReport) -> Option<Value> {
+        let Some(policy) = self.resolve(&rule.policy) else {
+            report.dropped.push(format!(
+                "rule \"{}\" is left out: policy \"{}\" is not defined",
+                rule.line, rule.policy
+            ));
+            return None;
+        };
+        let mut text = format!("{},{policy}", rule.body);
+        for option in rule.options {
+            text.push(',');
+            text.push_str(&option);
+        }
+        Some(text.into())
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim().trim_matches('"')
+}
+
+/// The comma separated items of a line, as positional values and `key=value` options.
+///
+/// Quotes keep a comma or an `=` inside an item, as Loon writes passwords.
+struct Params {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Params {
+    fn parse(text: &str) -> Self {
+        let mut items = Vec::new();
+        let mut current = String::new();
+        let mut quoted = false;
+        let mut in_quotes = false;
+        for c in text.chars() {
+            match c {
+                '"' => {
+                    in_quotes = !in_quotes;
+                    quoted |= current.trim().is_empty();
+                }
+                ',' if !in_quotes => {
+                    items.push((std::mem::take(&mut current), quoted));
+                    quoted = false;
+                }
+                c => current.push(c),
+            }
+        }
+        items.push((current, quoted));
+
+        let mut params = Self {
+            positional: Vec::new(),
+            options: Vec::new(),
+        };
+        for (item, quoted) in items {
+            let item = item.trim();
+            if item.is_empty() {
+                continue;
+            }
+            match item.split_once('=') {
+                Some((key, value))
+                    if !quoted
+                        && key
+                            .trim()
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+                {
+                    params.options.push((key.trim().to_owned(), value.trim().to_owned()));
+                }
+                _ => params.positional.push(item.to_owned()),
+            }
+        }
+        params
+    }
+
+    fn take(&mut self, key: &str) -> Option<String> {
+        let index = self.options.iter().position(|(k, _)| k.eq_ignore_ascii_case(key))?;
+        Some(self.options.remove(index).1).filter(|value| !value.is_empty())
+    }
+
+    fn flag(&mut self, key: &str) -> bool {
+        self.take(key)
+            .is_some_and(|value| value.eq_ignore_ascii_case("true") || value == "1")
+    }
+
+    /// Report every option nobody took.
+    fn finish(self, unsupported: &mut Vec<String>) {
+        unsupported.extend(self.options.into_iter().map(|(key, _)| format!("`{key}`")));
+    }
+}
+
+fn insert_value(proxy: &mut Mapping, key: &str, value: Option<impl Into<Value>>) {
+    if let Some(value) = value {
+        proxy.insert(key.into(), value.into());
+    }
+}
+
+/// Surge's `obfs=http|tls` with `obfs-host`, shared by shadowsocks and snell.
+fn obfs_opts(params: &mut Params) -> Option<Mapping> {
+    let mode = params.take("obfs").or_else(|| params.take("obfs-name"))?;
+    let mut opts = Mapping::new();
+    opts.insert("mode".into(), mode.into());
+    insert_value(&mut opts, "host", params.take("obfs-host"));
+    Some(opts)
+}
+
+fn reality_opts(params: &mut Params, key: &str, short_id: &str) -> Option<Mapping> {
+    let public_key = params.take(key)?;
+    let mut reality = Mapping::new();
+    reality.insert("public-key".into(), public_key.into());
+    insert_value(&mut reality, "short-id", params.take(short_id));
+    Some(reality)
+}
+
+/// An option by name, or else the positional value Loon puts in its place.
+fn option_or_arg(params: &mut Params, key: &str, args: &[String], index: usize) -> Option<String> {
+    params
+        .take(key)
+        .or_else(|| args.get(index).cloned())
+        .filter(|value| !value.is_empty())
+}
+
+/// A Surge or Loon proxy, `name = type, server, port, ...`. `None` for the built-in policies.
+fn surge_proxy(name: &str, text: &str, unsupported: &mut Vec<String>) -> Result<Option<Mapping>, String> {
+    let mut params = Params::parse(text);
+    let args = std::mem::take(&mut params.positional);
+    let kind = args.first().map(|kind| kind.to_ascii_lowercase()).unwrap_or_default();
+    if matches!(
+        kind.as_str(),
+        "direct" | "reject" | "reject-tinygif" | "reject-drop" | "reject-no-drop"
+    ) {
+        return Ok(None);
+    }
+    let mihomo_type = match kind.as_str() {
+        "ss" | "shadowsocks" => "ss",
+        "http" | "https" => "http",
+        "socks5" | "socks5-tls" => "socks5",
+        "tuic" | "tuic-v5" => "tuic",
+        kind @ ("vmess" | "vless" | "trojan" | "snell" | "hysteria2") => kind,
+        other => return Err(format!("mihomo has no \"{other}\" proxy")),
+    };
+    let server = args.get(1).cloned().ok_or("no server")?;
+    let port = args.get(2).and_then(|port| port.parse::<u16>().ok()).ok_or("no port")?;
+    let proxy = base_proxy(mihomo_type, name.to_owned(), server, port);
+    // Loon writes the cipher, password or uuid after the port
+    let args = args.get(3..).unwrap_or_default();
+
+    let mut proxy = match kind.as_str() {
+        "ss" | "shadowsocks" => surge_ss(proxy, &mut params, args)?,
+        "vmess" => surge_vmess(proxy, &mut params, args)?,
+        "vless" => surge_vless(proxy, &mut params, args)?,
+        "trojan" => surge_trojan(proxy, &mut params, args)?,
+        "snell" => surge_snell(proxy, &mut params)?,
+        "tuic" | "tuic-v5" => surge_tuic(proxy, &mut params),
+        "hysteria2" => surge_hysteria2(proxy, &mut params, args),
+        // http, https, socks5 and socks5-tls
+        _ => surge_http(
+            proxy,
+            &mut params,
+            args,
+            matches!(kind.as_str(), "https" | "socks5-tls"),
+        ),
+    };
+    apply_common(&mut proxy, &mut params);
+    params.finish(unsupported);
+    Ok(Some(proxy))
+}
+
+fn surge_ss(mut proxy: Mapping, params: &mut Params, args: &[String]) -> Result<Mapping, String> {
+    let cipher = option_or_arg(params, "encrypt-method", args, 0).ok_or("no cipher")?;
+    let password = option_or_arg(params, "password", args, 1).ok_or("no password")?;
+    proxy.insert("cipher".into(), cipher.into());
+    proxy.insert("password".into(), password.into());
+    if let Some(opts) = obfs_opts(params) {
+        proxy.insert("plugin".into(), "obfs".into());
+        proxy.insert("plugin-opts".into(), Value::Mapping(opts));
+    }
+    Ok(proxy)
+}
+
+fn surge_vmess(mut proxy: Mapping, params: &mut Params, args: &[String]) -> Result<Mapping, String> {
+    let cipher = option_or_arg(params, "encrypt-method", args, 0).unwrap_or_else(|| "auto".into());
+    let uuid = params
+        .take("username")
+        .or_else(|| option_or_arg(params, "uuid", args, 1))
+        .ok_or("no uuid")?;
+    let alter_id = params
+        .take("alterId")
+        .and_then(|id| id.parse::<u64>().ok())
+        .unwrap_or(0);
+    proxy.insert("uuid".into(), uuid.into());
+    proxy.insert("alterId".into(), alter_id.into());
+    proxy.insert("cipher".into(), cipher.into());
+    // AEAD is what mihomo does with `alterId: 0` anyway
+    params.take("vmess-aead");
+    surge_transport(&mut proxy, params)?;
+    surge_tls(&mut proxy, params, false, "servername");
+    Ok(proxy)
+}
+
+fn surge_vless(mut proxy: Mapping, params: &mut Params, args: &[String]) -> Result<Mapping, String> {
+    let uuid = option_or_arg(params, "uuid", args, 0).ok_or("no uuid")?;
+    proxy.insert("uuid".into(), uuid.into());
+    insert_value(&mut proxy, "flow", params.take("flow"));
+    surge_transport(&mut proxy, params)?;
+    surge_tls(&mut proxy, params, false, "servername");
+    let reality = reality_opts(params, "public-key", "short-id");
+    insert_value(&mut proxy, "reality-opts", reality.map(Value::Mapping));
+    Ok(proxy)
+}
+
+fn surge_trojan(mut proxy: Mapping, params: &mut Params, args: &[String]) -> Result<Mapping, String> {
+    let password = option_or_arg(params, "password", args, 0).ok_or("no password")?;
+    proxy.insert("password".into(), password.into());
+    surge_transport(&mut proxy, params)?;
+    surge_tls(&mut proxy, params, true, "sni");
+    Ok(proxy)
+}
+
+/// `http`/`https` and `socks5`/`socks5-tls`, where the type says whether TLS is on.
+fn surge_http(mut proxy: Mapping, params: &mut Params, args: &[String], tls: bool) -> Mapping {
+    insert_value(&mut proxy, "username", option_or_arg(params, "username", args, 0));
+    insert_value(&mut proxy, "password", option_or_arg(params, "password", args, 1));
+    if tls {
+        params.options.push(("tls".into(), "true".into()));
+    }
+    surge_tls(&mut proxy, params, false, "sni");
+    proxy
+}
+
+fn surge_snell(mut proxy: Mapping, params: &mut Params) -> Result<Mapping, String> {
+    proxy.insert("psk".into(), params.take("psk").ok_or("no psk")?.into());
+    let version = params.take("version").and_then(|version| version.parse::<u64>().ok());
+    insert_value(&mut proxy, "version", version);
+    insert_value(&mut proxy, "obfs-opts", obfs_opts(params).map(Value::Mapping));
+    Ok(proxy)
+}
+
+fn surge_tuic(mut proxy: Mapping, params: &mut Params) -> Mapping {
+    insert_value(&mut proxy, "token", params.take("token"));
+    insert_value(&mut proxy, "uuid", params.take("uuid"));
+    insert_value(&mut proxy, "password", params.take("password"));
+    surge_tls(&mut proxy, params, true, "sni");
+    proxy
+}
+
+fn surge_hysteria2(mut proxy: Mapping, params: &mut Params, args: &[String]) -> Mapping {
+    insert_value(&mut proxy, "password", option_or_arg(params, "password", args, 0));
+    // Surge separates port ranges with `;`, mihomo with `,`
+    let ports = params.take("port-hopping").map(|ports| ports.replace(';', ","));
+    let interval = params
+        .take("port-hopping-interval")
+        .and_then(|interval| interval.parse::<u64>().ok());
+    let down = params.take("download-bandwidth").map(|mbps| format!("{mbps} Mbps"));
+    insert_value(&mut proxy, "ports", ports);
+    insert_value(&mut proxy, "hop-interval", interval);
+    insert_value(&mut proxy, "down", down);
+    surge_tls(&mut proxy, params, true, "sni");
+    proxy
+}
+
+/// Surge's `ws=true, ws-path=..., ws-headers=Host:...` and Loon's `transport=ws, path=..., host=...`.
+fn surge_transport(proxy: &mut Mapping, params: &mut Params) -> Result<(), String> {
+    let transport = params.take("transport").map(|t| t.to_ascii_lowercase());
+    let ws = params.flag("ws");
+    let path = params.take("ws-path").or_else(|| params.take("path"));
+    let mut headers = Mapping::new();
+    // `Host:example.com|User-Agent:...`
+    for header in params.take("ws-headers").iter().flat_map(|headers| headers.split('|')) {
+        if let Some((key, value)) = header.split_once(':') {
+            headers.insert(key.trim().into(), value.trim().into());
+        }
+    }
+    if let Some(host) = params.take("host") {
+        headers.insert("Host".into(), host.into());
+    }
+
+    let transport = if ws { Some("ws") } else { transport.as_deref() };
+    match transport {
+        Some("ws") => {}
+        None | Some("tcp") => return Ok(()),
+        Some("http") => {
+            let mut opts = Mapping::new();
+            insert_value(&mut opts, "path", path.map(|path| vec![path]));
+            if let Some(host) = headers.get("Host").cloned() {
+                let mut http_headers = Mapping::new();
+                http_headers.insert("Host".into(), Value::Sequence(vec![host]));
+                opts.insert("headers".into(), Value::Mapping(http_headers));
+            }
+            proxy.insert("network".into(), "http".into());
+            proxy.insert("http-opts".into(), Value::Mapping(opts));
+            return Ok(());
+        }
+        Some(other) => return Err(format!("transport \"{other}\"")),
+    }
+    let mut opts = Mapping::new();
+    insert_value(&mut opts, "path", path);
+    if !headers.is_empty() {
+        opts.insert("headers".into(), Value::Mapping(headers));
+    }
+    proxy.insert("network".into(), "ws".into());
+    proxy.insert("ws-opts".into(), Value::Mapping(opts));
+    Ok(())
+}
+
+/// `implicit` for protocols that are always TLS, which mihomo then does not mark with `tls`.
+fn surge_tls(proxy: &mut Mapping, params: &mut Params, implicit: bool, name_key: &str) {
+    let tls = params.flag("tls");
+    let over_tls = params.flag("over-tls");
+    let server_name = params.take("sni").or_else(|| params.take("tls-name"));
+    let insecure = params.flag("skip-cert-verify");
+    let alpn = params.take("alpn");
+    if !(implicit || tls || over_tls) {
+        return;
+    }
+    if !implicit {
+        proxy.insert("tls".into(), true.into());
+    }
+    insert_value(proxy, name_key, server_name);
+    if insecure {
+        proxy.insert("skip-cert-verify".into(), true.into());
+    }
+    insert_value(proxy, "alpn", alpn.map(|alpn| vec![alpn]));
+}
+
+/// Options every client writes the same way on any proxy.
+fn apply_common(proxy: &mut Mapping, params: &mut Params) {
+    let udp_relay = params.flag("udp-relay");
+    let udp = params.flag("udp");
+    if udp_relay || udp {
+        proxy.insert("udp".into(), true.into());
+    }
+    let tfo = params.flag("tfo");
+    let fast_open = params.flag("fast-open");
+    if tfo || fast_open {
+        proxy.insert("tfo".into(), true.into());
+    }
+    insert_value(proxy, "dialer-proxy", params.take("underlying-proxy"));
+    insert_value(proxy, "interface-name", params.take("interface"));
+}
+
+/// A Quantumult X proxy, `type=server:port, ..., tag=name`, and its name.
+fn quantumult_x_proxy(
+    kind: &str,
+    text: &str,
+    unsupported: &mut Vec<String>,
+) -> (String, Result<Option<Mapping>, String>) {
+    let mut params = Params::parse(text);
+    let address = params.positional.first().cloned().unwrap_or_default();
+    let name = params.take("tag").unwrap_or_else(|| address.clone());
+    let proxy = quantumult_x_node(kind, &address, name.clone(), &mut params).map(Some);
+    params.finish(unsupported);
+    (name, proxy)
+}
+
+fn quantumult_x_node(kind: &str, address: &str, name: String, params: &mut Params) -> Result<Mapping, String> {
+    let (server, port) = address.rsplit_once(':').ok_or("no server")?;
+    let port = port.parse::<u16>().map_err(|_| "no port")?;
+    let server = server.trim_matches(|c| c == '[' || c == ']').to_owned();
+    let kind = kind.to_ascii_lowercase();
+    let mut proxy = match kind.as_str() {
+        "shadowsocks" => quantumult_x_ss(base_proxy("ss", name, server, port), params)?,
+        "vmess" | "vless" => {
+            let mut proxy = base_proxy(&kind, name, server, port);
+            proxy.insert("uuid".into(), params.take("password").ok_or("no uuid")?.into());
+            let method = params.take("method");
+            if kind == "vmess" {
+                // Quantumult X names the cipher the way shadowsocks does
+                let cipher = match method.as_deref() {
+                    Some("chacha20-ietf-poly1305") => "chacha20-poly1305".to_owned(),
+                    Some(method) => method.to_owned(),
+                    None => "auto".to_owned(),
+                };
+                proxy.insert("cipher".into(), cipher.into());
+                proxy.insert("alterId".into(), 0.into());
+                params.take("aead");
+            } else {
+                insert_value(&mut proxy, "flow", params.take("vless-flow"));
+                let reality = reality_opts(params, "reality-base64-pubkey", "reality-hex-shortid");
+                insert_value(&mut proxy, "reality-opts", reality.map(Value::Mapping));
+            }
+            quantumult_x_transport(&mut proxy, params, false, "servername")?;
+            proxy
+        }
+        "trojan" => {
+            let mut proxy = base_proxy("trojan", name, server, port);
+            proxy.insert("password".into(), params.take("password").ok_or("no password")?.into());
+            quantumult_x_transport(&mut proxy, params, true, "sni")?;
+            proxy
+        }
+        "http" | "socks5" => {
+            let mut proxy = base_proxy(&kind, name, server, port);
+            insert_value(&mut proxy, "username", params.take("username"));
+            insert_value(&mut proxy, "password", params.take("password"));
+            quantumult_x_transport(&mut proxy, params, false, "sni")?;
+            proxy
+        }
+        other => return Err(format!("mihomo has no \"{other}\" proxy")),
+    };
+    apply_common(&mut proxy, params);
+    Ok(proxy)
+}
+
+fn quantumult_x_ss(mut proxy: Mapping, params: &mut Params) -> Result<Mapping, String> {
+    proxy.insert("cipher".into(), params.take("method").ok_or("no cipher")?.into());
+    proxy.insert("password".into(), params.take("password").ok_or("no password")?.into());
+    let Some(obfs) = params.take("obfs").map(|obfs| obfs.to_ascii_lowercase()) else {
+        return Ok(proxy);
+    };
+    let mut opts = Mapping::new();
+    let plugin = match obfs.as_str() {
+        "http" | "tls" => {
+            opts.insert("mode".into(), obfs.as_str().into());
+            insert_value(&mut opts, "host", params.take("obfs-host"));
+            "obfs"
+        }
+        "ws" | "wss" => {
+            opts.insert("mode".into(), "websocket".into());
+            if obfs == "wss" {
+                opts.insert("tls".into(), true.into());
+            }
+            insert_value(&mut opts, "host", params.take("obfs-host"));
+            insert_value(&mut opts, "path", params.take("obfs-uri"));
+            "v2ray-plugin"
+        }
+        other => return Err(format!("obfs \"{other}\"")),
+    };
+    proxy.insert("plugin".into(), plugin.into());
+    proxy.insert("plugin-opts".into(), Value::Mapping(opts));
+    Ok(proxy)
+}
+
+/// Quantumult X's `obfs=ws|wss|over-tls|http`, `obfs-host`, `obfs-uri` and TLS options.
+fn quantumult_x_transport(
+    proxy: &mut Mapping,
+    params: &mut Params,
+    implicit: bool,
+    name_key: &str,
+) -> Result<(), String> {
+    let obfs = params.take("obfs").map(|obfs| obfs.to_ascii_lowercase());
+    let host = params.take("obfs-host");
+    let path = params.take("obfs-uri");
+    let over_tls = params.flag("over-tls");
+    let tls_host = params.take("tls-host");
+    let insecure = params
+        .take("tls-verification")
+        .is_some_and(|verify| verify.eq_ignore_ascii_case("false"));
+
+    match obfs.as_deref() {
+        None | Some("over-tls") => {}
+        Some("ws" | "wss") => {
+            let mut opts = Mapping::new();
+            insert_value(&mut opts, "path", path);
+            if let Some(host) = host.clone() {
+                let mut headers = Mapping::new();
+                headers.insert("Host".into(), host.into());
+                opts.insert("headers".into(), Value::Mapping(headers));
+            }
+            proxy.insert("network".into(), "ws".into());
+            proxy.insert("ws-opts".into(), Value::Mapping(opts));
+        }
+        Some("http") => {
+            let mut opts = Mapping::new();
+            insert_value(&mut opts, "path", path.map(|path| vec![path]));
+            if let Some(host) = host.clone() {
+                let mut headers = Mapping::new();
+                headers.insert("Host".into(), Value::Sequence(vec![host.into()]));
+                opts.insert("headers".into(), Value::Mapping(headers));
+            }
+            proxy.insert("network".into(), "http".into());
+            proxy.insert("http-opts".into(), Value::Mapping(opts));
+        }
+        Some(other) => return Err(format!("obfs \"{other}\"")),
+    }
+
+    let tls = implicit || over_tls || matches!(obfs.as_deref(), Some("wss" | "over-tls"));
+    if tls {
+        if !implicit {
+            proxy.insert("tls".into(), true.into());
+        }
+        insert_value(proxy, name_key, tls_host.or(host));
+        if insecure {
+            proxy.insert("skip-cert-verify".into(), true.into());
+        }
+    }
+    Ok(())
+}
+
+/// `name = type, member, ...` in Surge and Loon, `type=name, member, ...` in Quantumult X.
+fn parse_group(flavor: Flavor, line: &str, unsupported: &mut Vec<String>) -> Result<Mapping, String> {
+    let (left, right) = line.split_once('=').ok_or("not understood")?;
+    let mut params = Params::parse(right);
+    let (name, kind) = if flavor == Flavor::QuantumultX {
+        if params.positional.is_empty() {
+            return Err("no name".into());
+        }
+        (params.positional.remove(0), left.trim().to_owned())
+    } else {
+        if params.positional.is_empty() {
+            return Err("no type".into());
+        }
+        let kind = params.positional.remove(0);
+        (unquote(left).to_owned(), kind)
+    };
+
+    let mut group = Mapping::new();
+    group.insert("name".into(), name.into());
+    let (mihomo_type, strategy) = match kind.to_ascii_lowercase().as_str() {
+        "select" | "static" => ("select", None),
+        "url-test" | "url-latency-benchmark" => ("url-test", None),
+        "fallback" | "available" => ("fallback", None),
+        "load-balance" | "round-robin" => ("load-balance", Some("round-robin")),
+        "dest-hash" => ("load-balance", Some("consistent-hashing")),
+        other => return Err(format!("mihomo has no \"{other}\" group")),
+    };
+    group.insert("type".into(), mihomo_type.into());
+    insert_value(&mut group, "strategy", strategy);
+    let members = std::mem::take(&mut params.positional);
+    if !members.is_empty() {
+        group.insert(
+            "proxies".into(),
+            Value::Sequence(members.into_iter().map(Value::from).collect()),
+        );
+    }
+
+    insert_value(&mut group, "url", params.take("url"));
+    let interval = params.take("interval").or_else(|| params.take("check-interval"));
+    insert_value(&mut group, "interval", interval.and_then(|s| s.parse::<u64>().ok()));
+    insert_value(
+        &mut group,
+        "tolerance",
+        params.take("tolerance").and_then(|ms| ms.parse::<u64>().ok()),
+    );
+    // seconds in Surge, milliseconds in mihomo
+    insert_value(
+        &mut group,
+        "timeout",
+        params
+            .take("timeout")
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(|s| s * 1000),
+    );
+    insert_value(&mut group, "icon", params.take("img-url"));
+    insert_value(
+        &mut group,
+        "filter",
+        params
+            .take("policy-regex-filter")
+            .or_else(|| params.take("server-tag-regex")),
+    );
+    if params.flag("include-all-proxies") {
+        group.insert("include-all-proxies".into(), true.into());
+    }
+    if params.flag("hidden") {
+        group.insert("hidden".into(), true.into());
+    }
+    params.take("no-alert");
+    params.finish(unsupported);
+    Ok(group)
+}
+
+/// A rule line, `TYPE,value,policy[,options]` in every client, with the `RULE-SET` and
+/// `DOMAIN-SET` lists it points at added to `providers`.
+fn parse_rule(line: &str, providers: &mut Mapping, unsupported: &mut Vec<String>) -> Result<Rule, String> {
+    let upper = line.to_ascii_uppercase();
+    // `AND,((DOMAIN,a.com),(DST-PORT,443)),Policy` reads the same in mihomo
+    if ["AND,", "OR,", "NOT,"].iter().any(|prefix| upper.starts_with(prefix)) {
+        let (body, policy) = line.rsplit_once(',').ok_or("no policy")?;
+        return Ok(Rule {
+            body: body.trim().to_owned(),
+            policy: policy.trim().to_owned(),
+            options: Vec::new(),
+            line: line.to_owned(),
+        });
+    }
+
+    let mut parts = line.split(',').map(|part| unquote(part).to_owned());
+    let kind = alias(&parts.next().unwrap_or_default().to_ascii_uppercase()).to_owned();
+    let (body, policy) = if matches!(kind.as_str(), "FINAL" | "MATCH") {
+        ("MATCH".to_owned(), parts.next().ok_or("no policy")?)
+    } else {
+        let payload = parts.next().filter(|payload| !payload.is_empty()).ok_or("no value")?;
+        let body = rule_body(&kind, &payload, providers)?;
+        (body, parts.next().ok_or("no policy")?)
+    };
+
+    let mut options = Vec::new();
+    for option in parts {
+        let lower = option.to_ascii_lowercase();
+        if matches!(lower.as_str(), "no-resolve" | "src") {
+            options.push(lower);
+        } else {
+            unsupported.push(format!("`{option}`"));
+        }
+    }
+    Ok(Rule {
+        body,
+        policy,
+        options,
+        line: line.to_owned(),
+    })
+}
+
+/// The rule without its policy, in mihomo's spelling.
+fn rule_body(kind: &str, payload: &str, providers: &mut Mapping) -> Result<String, String> {
+    match kind {
+        "RULE-SET" | "DOMAIN-SET" if payload.eq_ignore_ascii_case("LAN") => Ok("GEOIP,LAN".to_owned()),
+        "RULE-SET" | "DOMAIN-SET" if payload.starts_with("http://") || payload.starts_with("https://") => {
+            let behavior = if kind == "DOMAIN-SET" { "domain" } else { "classical" };
+            Ok(format!("RULE-SET,{}", rule_provider(payload, behavior, providers)))
+        }
+        "RULE-SET" | "DOMAIN-SET" => Err(format!("the built-in \"{payload}\" list")),
+        "SRC-IP" => Ok(format!("SRC-IP-CIDR,{payload}")),
+        kind if CLASSICAL_TYPES.contains(&kind) => Ok(format!("{kind},{payload}")),
+        kind => Err(format!("mihomo has no \"{kind}\" rule")),
+    }
+}
+
+/// The name of the provider fetching `url`, added when it is not there yet.
+fn rule_provider(url: &str, behavior: &str, providers: &mut Mapping) -> String {
+    let existing = providers.iter().find_map(|(name, provider)| {
+        (provider.get("url").and_then(Value::as_str) == Some(url)
+            && provider.get("behavior").and_then(Value::as_str) == Some(behavior))
+        .then(|| name.as_str().map(str::to_owned))
+        .flatten()
+    });
+    if let Some(name) = existing {
+        return name;
+    }
+
+    // `https://example.com/rules/Apple.list?token=1` becomes `Apple`
+    let stem = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .map(|file| file.split('.').next().unwrap_or(file))
+        .filter(|stem| !stem.is_empty())
+        .unwrap_or("rules");
+    let mut name = stem.to_owned();
+    let mut n = 2;
+    while providers.contains_key(name.as_str()) {
+        name = format!("{stem} {n}");
+        n += 1;
+    }
+
+    let mut provider = Mapping::new();
+    provider.insert("type".into(), "http".into());
+    provider.insert("behavior".into(), behavior.into());
+    provider.insert("format".into(), "text".into());
+    provider.insert("url".into(), url.into());
+    provider.insert("interval".into(), RULE_SET_INTERVAL.into());
+    providers.insert(name.as_str().into(), Value::Mapping(provider));
+    name
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{is_ios_profile, parse_profile};
+    use serde_yaml_ng::{Mapping, Value};
+
+    fn seq<'a>(profile: &'a Mapping, key: &str) -> &'a Vec<Value> {
+        profile.get(key).and_then(Value::as_sequence).expect("sequence")
+    }
+
+    fn named<'a>(profile: &'a Mapping, key: &str, name: &str) -> &'a Value {
+        seq(profile, key)
+            .iter()
+            .find(|item| item.get("name").and_then(Value::as_str) == Some(name))
+            .expect("named item")
+    }
+
+    #[test]
+    fn surge_profile() {
+        let data = "\
+#!MANAGED-CONFIG https://example.com/surge.conf
+[General]
+loglevel = notify
+
+[Proxy]
+DIRECT = direct
+HK = ss, hk.example.com, 8388, encrypt-method=aes-128-gcm, password=pw, obfs=http, obfs-host=bing.com, udp-relay=true
+JP = vmess, jp.example.com, 443, username=id, ws=true, ws-path=/ray, ws-headers=Host:cdn.example.com, tls=true, sni=jp.example.com, vmess-aead=true
+US = trojan, us.example.com, 443, password=pw, sni=us.example.com, server-cert-fingerprint-sha256=ab
+WG = wireguard, section-name=home
+
+[Proxy Group]
+Proxy = select, Auto, HK, JP, US, WG
+Auto = url-test, HK, JP, url=http://www.gstatic.com/generate_204, interval=600, tolerance=50
+Home = ssid, default=Proxy, home=DIRECT
+
+[Rule]
+DOMAIN-SUFFIX,google.com,Proxy
+IP-CIDR,10.0.0.0/8,DIRECT,no-resolve
+RULE-SET,https://example.com/rules/Apple.list,DIRECT
+RULE-SET,LAN,DIRECT
+USER-AGENT,Instagram*,Proxy
+DOMAIN,ads.example.com,REJECT-TINYGIF
+DOMAIN,home.example.com,Home
+FINAL,Proxy,dns-failed
+
+[Host]
+router.lan = 192.168.1.1
+*.corp.example.com = server:10.0.0.53
+";
+        assert!(is_ios_profile(data));
+        let (profile, report) = parse_profile(data).expect("surge profile should convert");
+
+        assert_eq!(report.format, "surge");
+        assert_eq!(report.converted, 3);
+        let hk = named(&profile, "proxies", "HK");
+        assert_eq!(hk.get("plugin").and_then(Value::as_str), Some("obfs"));
+        assert_eq!(hk.get("udp").and_then(Value::as_bool), Some(true));
+        let jp = named(&profile, "proxies", "JP");
+        assert_eq!(jp.get("network").and_then(Value::as_str), Some("ws"));
+        assert_eq!(jp.get("servername").and_then(Value::as_str), Some("jp.example.com"));
+        assert_eq!(
+            jp.get("ws-opts")
+                .and_then(|opts| opts.get("headers"))
+                .and_then(|headers| headers.get("Host"))
+                .and_then(Value::as_str),
+            Some("cdn.example.com")
+        );
+
+        let proxy = named(&profile, "proxy-groups", "Proxy");
+        assert_eq!(
+            proxy.get("proxies"),
+            Some(&serde_yaml_ng::from_str::<Value>("[Auto, HK, JP, US]").expect("members"))
+        );
+        assert_eq!(
+            named(&profile, "proxy-groups", "Auto")
+                .get("interval")
+                .and_then(Value::as_u64),
+            Some(600)
+        );
+        assert_eq!(seq(&profile, "proxy-groups").len(), 2, "no catch-all selector needed");
+
+        let rules = seq(&profile, "rules")
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rules,
+            vec![
+                "DOMAIN-SUFFIX,google.com,Proxy",
+                "IP-CIDR,10.0.0.0/8,DIRECT,no-resolve",
+                "RULE-SET,Apple,DIRECT",
+                "GEOIP,LAN,DIRECT",
+                "DOMAIN,ads.example.com,REJECT",
+                "MATCH,Proxy",
+            ]
+        );
+        let apple = profile
+            .get("rule-providers")
+            .and_then(|providers| providers.get("Apple"))
+            .expect("rule provider");
+        assert_eq!(apple.get("format").and_then(Value::as_str), Some("text"));
+        assert_eq!(
+            profile
+                .get("hosts")
+                .and_then(|hosts| hosts.get("router.lan"))
+                .and_then(Value::as_str),
+            Some("192.168.1.1")
+        );
+
+        let nodes = report
+            .nodes
+            .iter()
+            .map(|node| (node.name.as_str(), node.skipped, node.unsupported.join(" ")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            nodes,
+            vec![
+                ("US", false, "`server-cert-fingerprint-sha256`".to_owned()),
+                ("WG", true, "mihomo has no \"wireguard\" proxy".to_owned()),
+            ]
+        );
+        assert_eq!(
+            report.dropped,
+            vec![
+                "section [General] is left out",
+                "group \"Home = ssid, default=Proxy, home=DIRECT\" is left out: mihomo has no \"ssid\" group",
+                "rule \"USER-AGENT,Instagram*,Proxy\" is left out: mihomo has no \"USER-AGENT\" rule",
+                "rule \"FINAL,Proxy,dns-failed\": left out `dns-failed`",
+                "host \"*.corp.example.com = server:10.0.0.53\" is left out",
+                "group \"Proxy\": member \"WG\" is not defined",
+                "rule \"DOMAIN,home.example.com,Home\" is left out: policy \"Home\" is not defined",
+            ]
+        );
+    }
+
+    #[test]
+    fn quantumult_x_profile() {
+        let data = "\
+[server_local]
+shadowsocks=ss.example.com:8388, method=chacha20-ietf-poly1305, password=pw, obfs=wss, obfs-host=cdn.example.com, obfs-uri=/ws, fast-open=false, udp-relay=true, tag=SS
+vmess=vm.example.com:443, method=chacha20-ietf-poly1305, password=id, obfs=over-tls, obfs-host=vm.example.com, tag=VM
+trojan=tr.example.com:443, password=pw, over-tls=true, tls-host=tr.example.com, tls-verification=false, tag=TR
+
+[policy]
+static=Streaming, VM, TR, direct, img-url=https://example.com/tv.png
+url-latency-benchmark=Fastest, SS, VM, TR, check-interval=600
+
+[filter_local]
+host-suffix, netflix.com, Streaming
+ip6-cidr, 2001:db8::/32, direct
+host-keyword, ads, reject
+final, proxy
+";
+        let (profile, report) = parse_profile(data).expect("quantumult x profile should convert");
+
+        assert_eq!(report.format, "quantumult-x");
+        assert!(report.nodes.is_empty(), "{:?}", report.nodes);
+        assert!(report.dropped.is_empty(), "{:?}", report.dropped);
+
+        let ss = named(&profile, "proxies", "SS");
+        assert_eq!(ss.get("plugin").and_then(Value::as_str), Some("v2ray-plugin"));
+        let vm = named(&profile, "proxies", "VM");
+        assert_eq!(vm.get("cipher").and_then(Value::as_str), Some("chacha20-poly1305"));
+        assert_eq!(vm.get("tls").and_then(Value::as_bool), Some(true));
+        assert_eq!(vm.get("servername").and_then(Value::as_str), Some("vm.example.com"));
+        let tr = named(&profile, "proxies", "TR");
+        assert_eq!(tr.get("sni").and_then(Value::as_str), Some("tr.example.com"));
+        assert_eq!(tr.get("skip-cert-verify").and_then(Value::as_bool), Some(true));
+
+        // `final, proxy` needs the selector over every node, which goes first
+        let groups = seq(&profile, "proxy-groups")
+            .iter()
+            .filter_map(|group| group.get("name").and_then(Value::as_str))
+            .collect::<Vec<_>>();
+        assert_eq!(groups, vec!["PROXY", "Streaming", "Fastest"]);
+        assert_eq!(
+            named(&profile, "proxy-groups", "Streaming")
+                .get("icon")
+                .and_then(Value::as_str),
+            Some("https://example.com/tv.png")
+        );
+        let rules = seq(&profile, "rules")
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rules,
+            vec![
+                "DOMAIN-SUFFIX,netflix.com,Streaming",
+                "IP-CIDR6,2001:db8::/32,DIRECT",
+                "DOMAIN-KEYWORD,ads,REJECT",
+                "MATCH,PROXY",
+            ]
+        );
+    }
+
+    #[test]
+    fn loon_proxies_take_positional_secrets() {
+        let data = "\
+[Proxy]
+HK = Shadowsocks,hk.example.com,8388,aes-128-gcm,\"p,w=1\",udp=true
+JP = VMess,jp.example.com,443,aes-128-gcm,\"id\",transport=ws,path=/ray,host=cdn.example.com,over-tls=true,tls-name=jp.example.com
+";
+        let (profile, report) = parse_profile(data).expect("loon profile should convert");
+
+        assert_eq!(report.format, "loon");
+        assert!(report.nodes.is_empty(), "{:?}", report.nodes);
+        let hk = named(&profile, "proxies", "HK");
+        assert_eq!(hk.get("password").and_then(Value::as_str), Some("p,w=1"));
+        let jp = named(&profile, "proxies", "JP");
+        assert_eq!(jp.get("uuid").and_then(Value::as_str), Some("id"));
+        assert_eq!(jp.get("servername").and_then(Value::as_str), Some("jp.example.com"));
+        // no groups of its own, so every node lands in the catch-all selector
+        assert_eq!(seq(&profile, "proxy-groups").len(), 1);
+        assert_eq!(seq(&profile, "rules").len(), 1);
+    }
+
+    #[test]
+    fn mihomo_profiles_are_not_ios_profiles() {
+        assert!(!is_ios_profile("proxies:\n  - {name: a, type: ss}\n"));
+        assert!(!is_ios_profile("[General]\nloglevel = notify\n"));
+        assert!(parse_profile("just text").is_none());
+    }
+}
@@ -0,0 +1,173 @@
+//! Converters for subscription formats mihomo cannot read on its own.
+//!
+//! Each converter turns a foreign body into the profile a remote subscription would otherwise
+//! have served, so everything downstream of the download — the enhance chain, the validator, the
+//! timer — keeps seeing a mihomo profile and never learns where it came from. The exporters go
+//! the other way, for devices that run something other than mihomo.
+
+pub mod ios_profile;
+pub mod rule_list;
+pub mod share_link;
+pub mod share_link_export;
+pub mod sing_box;
+pub mod sing_box_export;
+
+use serde::{Deserialize, Serialize};
+use serde_yaml_ng::{Mapping, Sequence, Value};
+use std::collections::HashSet;
+
+pub use ios_profile::is_ios_profile;
+
+/// The selector every converted profile starts from.
+pub const DEFAULT_SELECTOR: &str = "PROXY";
+
+/// What a converter could not carry over, node by node.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ConversionReport {
+    /// the format converted from, e.g. `sing-box`
+    pub format: String,
+    /// nodes that made it into the profile
+    pub converted: usize,
+    /// nodes converted with settings left out, or skipped
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<NodeReport>,
+    /// everything else left out: groups, rules, hosts, whole sections
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dropped: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct NodeReport {
+    pub name: String,
+    /// the node is not in the profile at all
+    #[serde(default)]
+    pub skipped: bool,
+    /// the settings left out, as `tls.ech` style paths, or why the node was skipped
+    pub unsupported: Vec<String>,
+}
+
+/// A subscription body converted to mihomo profile YAML.
+#[derive(Debug, Clone)]
+pub struct Converted {
+    pub data: String,
+    /// `None` for formats that have nothing to report
+    pub report: Option<ConversionReport>,
+}
+
+/// Turn a subscription body in a foreign format into mihomo profile YAML.
+///
+/// `None` means the body is not in any format a converter recognises, which callers report the
+/// same way they report a body that is not YAML at all.
+pub fn convert_subscription(data: &str) -> Option<Converted> {
+    if is_ios_profile(data) {
+        return convert_ios_profile(data);
+    }
+    let (proxies, report) = match sing_box::parse_config(data) {
+        Some((proxies, report)) => (proxies, Some(report)),
+        None => (share_link::parse_subscription(data)?, None),
+    };
+    let profile = profile_from_proxies(proxies);
+    let data = crate::utils::yaml_emitter::to_mihomo_config_string(&profile).ok()?;
+    Some(Converted { data, report })
+}
+
+/// Turn a Surge, Quantumult X or Loon profile into mihomo profile YAML.
+pub fn convert_ios_profile(data: &str) -> Option<Converted> {
+    let (profile, report) = ios_profile::parse_profile(data)?;
+    let data = crate::utils::yaml_emitter::to_mihomo_config_string(&profile).ok()?;
+    Some(Converted {
+        data,
+        report: Some(report),
+    })
+}
+
+/// Wrap converted proxies in a profile: one selector over every node and a catch-all rule.
+///
+/// Node names are made unique on the way in, because providers happily ship two nodes with the
+/// same remark and mihomo refuses to load a profile that has them.
+pub fn profile_from_proxies(proxies: Vec<Mapping>) -> Mapping {
+    let proxies = dedupe_proxy_names(proxies);
+    let mut members = proxies
+        .iter()
+        .filter_map(|proxy| proxy.get("name").cloned())
+        .collect::<Sequence>();
+    members.push("DIRECT".into());
+
+    let mut selector = Mapping::new();
+    selector.insert("name".into(), DEFAULT_SELECTOR.into());
+    selector.insert("type".into(), "select".into());
+    selector.insert("proxies".into(), Value::Sequence(members));
+
+    let mut profile = Mapping::new();
+    profile.insert(
+        "proxies".into(),
+        Value::Sequence(proxies.into_iter().map(Value::Mapping).collect()),
+    );
+    profile.insert("proxy-groups".into(), Value::Sequence(vec![Value::Mapping(selector)]));
+    profile.insert(
+        "rules".into(),
+        Value::Sequence(vec![format!("MATCH,{DEFAULT_SELECTOR}").into()]),
+    );
+    profile
+}
+
+fn dedupe_proxy_names(proxies: Vec<Mapping>) -> Vec<Mapping> {
+    let mut seen = HashSet::new();
+    proxies
+        .into_iter()
+        .map(|mut proxy| {
+            let name = proxy.get("name").and_then(Value::as_str).unwrap_or("node").to_owned();
+            proxy.insert("name".into(), unique_name(&mut seen, &name).into());
+            proxy
+        })
+        .collect()
+}
+
+/// `name`, or `"<name> 2"`, `"<name> 3"`... when it is already in `seen`.
+fn unique_name(seen: &mut HashSet<String>, name: &str) -> String {
+    let mut unique = name.to_owned();
+    let mut suffix = 2;
+    while !seen.insert(unique.clone()) {
+        unique = format!("{name} {suffix}");
+        suffix += 1;
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DEFAULT_SELECTOR, profile_from_proxies};
+    use serde_yaml_ng::{Mapping, Value};
+
+    fn named(name: &str) -> Mapping {
+        let mut proxy = Mapping::new();
+        proxy.insert("name".into(), name.into());
+        proxy
+    }
+
+    #[test]
+    fn duplicate_remarks_are_made_unique_and_all_land_in_the_selector() {
+        let profile = profile_from_proxies(vec![named("HK"), named("HK"), named("JP")]);
+
+        let names = profile
+            .get("proxies")
+            .and_then(Value::as_sequence)
+            .map(|seq| seq.iter().filter_map(|p| p.get("name")?.as_str()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        assert_eq!(names, vec!["HK", "HK 2", "JP"]);
+
+        let selector = profile
+            .get("proxy-groups")
+            .and_then(Value::as_sequence)
+            .and_then(|groups| groups.first())
+            .and_then(Value::as_mapping)
+            .cloned()
+            .unwrap_or_default();
+        assert_eq!(selector.get("name").and_then(Value::as_str), Some(DEFAULT_SELECTOR));
+        assert_eq!(
+            selector.get("proxies").and_then(Value::as_sequence).map(Vec::len),
+            Some(4),
+            "every node plus DIRECT"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Rule lists in the formats rule-set subscriptions are published in.
+//!
+//! Three shapes are recognised: a mihomo rule-provider file (`payload:` in YAML), a plain list of
+//! domains or CIDRs one per line (Surge `DOMAIN-SET` style), and classical rule lines as Surge
+//! and Quantumult X write them. Whatever comes in is normalised into a mihomo rule-provider
+//! payload: `domain` or `ipcidr` when every entry is a bare domain or a bare CIDR, `classical`
+//! otherwise. Policies written into the list are dropped, the subscriber picks one.
+
+use serde::Deserialize;
+use std::net::IpAddr;
+
+/// Rule types mihomo understands inside a classical rule-provider, after aliasing.
+pub(super) const CLASSICAL_TYPES: &[&str] = &[
+    "DOMAIN",
+    "DOMAIN-SUFFIX",
+    "DOMAIN-KEYWORD",
+    "DOMAIN-WILDCARD",
+    "DOMAIN-REGEX",
+    "GEOSITE",
+    "IP-CIDR",
+    "IP-CIDR6",
+    "IP-SUFFIX",
+    "IP-ASN",
+    "GEOIP",
+    "SRC-IP-CIDR",
+    "SRC-IP-SUFFIX",
+    "SRC-IP-ASN",
+    "SRC-GEOIP",
+    "DST-PORT",
+    "SRC-PORT",
+    "IN-PORT",
+    "PROCESS-NAME",
+    "PROCESS-PATH",
+    "PROCESS-NAME-REGEX",
+    "PROCESS-PATH-REGEX",
+    "NETWORK",
+];
+
+/// Surge and Quantumult X names for the same rule types.
+pub(super) fn alias(kind: &str) -> &str {
+    match kind {
+        "HOST" => "DOMAIN",
+        "HOST-SUFFIX" => "DOMAIN-SUFFIX",
+        "HOST-KEYWORD" => "DOMAIN-KEYWORD",
+        "HOST-WILDCARD" => "DOMAIN-WILDCARD",
+        "IP6-CIDR" => "IP-CIDR6",
+        "IP-ASN" | "ASN" => "IP-ASN",
+        "DEST-PORT" => "DST-PORT",
+        other => other,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behavior {
+    Domain,
+    IpCidr,
+    Classical,
+}
+
+impl Behavior {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Domain => "domain",
+            Self::IpCidr => "ipcidr",
+            Self::Classical => "classical",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleList {
+    pub behavior: Behavior,
+    pub payload: Vec<String>,
+    /// lines that were not understood or name a rule type mihomo lacks
+    pub skipped: usize,
+}
+
+enum Entry {
+    Domain(String),
+    Cidr(String),
+    Rule(String),
+}
+
+#[derive(Deserialize)]
+struct ProviderFile {
+    payload: Vec<String>,
+}
+
+fn parse_cidr(value: &str) -> Option<String> {
+    let (addr, prefix) = match value.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (value, None),
+    };
+    let ip = addr.parse::<IpAddr>().ok()?;
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix.parse::<u8>().ok().filter(|p| *p <= max)?,
+        None => max,
+    };
+    Some(format!("{ip}/{prefix}"))
+}
+
+fn is_domain(value: &str) -> bool {
+    let host = value.trim_start_matches("+.").trim_start_matches('.');
+    !host.is_empty()
+        && host.contains('.')
+        && host
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | '*'))
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    if !line.contains(',') {
+        if let Some(cidr) = parse_cidr(line) {
+            return Some(Entry::Cidr(cidr));
+        }
+        if is_domain(line) {
+            // Surge reads `.example.com` as the domain and its subdomains, mihomo spells that `+.`
+            let domain = match line.strip_prefix('.') {
+                Some(rest) if !line.starts_with("+.") => format!("+.{rest}"),
+                _ => line.to_owned(),
+            };
+            return Some(Entry::Domain(domain));
+        }
+        return None;
+    }
+
+    let mut parts = line.split(',').map(str::trim);
+    let kind = alias(&parts.next()?.to_ascii_uppercase()).to_owned();
+    let value = parts.next().filter(|v| !v.is_empty())?;
+    if !CLASSICAL_TYPES.contains(&kind.as_str()) {
+        return None;
+    }
+    // whatever follows is a policy, except the options mihomo keeps on the rule itself
+    let options = parts
+        .filter(|p| matches!(p.to_ascii_lowercase().as_str(), "no-resolve" | "src"))
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>();
+    let mut rule = format!("{kind},{value}");
+    for option in options {
+        rule.push(',');
+        rule.push_str(&option);
+    }
+    Some(Entry::Rule(rule))
+}
+
+/// Turn a bare domain or CIDR into the classical rule that matches the same traffic.
+fn to_classical(entry: Entry) -> String {
+    match entry {
+        Entry::Rule(rule) => rule,
+        Entry::Cidr(cidr) if cidr.contains(':') => format!("IP-CIDR6,{cidr}"),
+        Entry::Cidr(cidr) => format!("IP-CIDR,{cidr}"),
+        Entry::Domain(domain) => match domain.strip_prefix("+.") {
+            Some(rest) => format!("DOMAIN-SUFFIX,{rest}"),
+            None if domain.contains('*') || domain.starts_with('.') => format!("DOMAIN-WILDCARD,{domain}"),
+            None => format!("DOMAIN,{domain}"),
+        },
+    }
+}
+
+/// Parse a downloaded rule list. `None` when nothing in it is a rule.
+pub fn parse_rule_list(data: &str) -> Option<RuleList> {
+    let data = data.trim_start_matches('\u{feff}');
+    let lines = match serde_yaml_ng::from_str::<ProviderFile>(data) {
+        Ok(file) => file.payload,
+        Err(_) => data.lines().map(str::to_owned).collect(),
+    };
+
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+    for line in &lines {
+        let line = line.trim().trim_matches(|c| c == '\'' || c == '"');
+        if line.is_empty() || line.starts_with('#') || line.starts_with("//") || line.starts_with(';') {
+            continue;
+        }
+        match parse_entry(line) {
+            Some(entry) => entries.push(entry),
+            None => skipped += 1,
+        }
+    }
+    if entries.is_empty() {
+        return None;
+    }
+
+    let behavior = if entries.iter().all(|e| matches!(e, Entry::Domain(_))) {
+        Behavior::Domain
+    } else if entries.iter().all(|e| matches!(e, Entry::Cidr(_))) {
+        Behavior::IpCidr
+    } else {
+        Behavior::Classical
+    };
+    let payload = entries
+        .into_iter()
+        .map(|entry| match (behavior, entry) {
+            (Behavior::Domain, Entry::Domain(value)) | (Behavior::IpCidr, Entry::Cidr(value)) => value,
+            (_, entry) => to_classical(entry),
+        })
+        .collect();
+    Some(RuleList {
+        behavior,
+        payload,
+        skipped,
+    })
+}
+
+impl RuleList {
+    /// The list as inline rules sending matches to `policy`.
+    pub fn to_rules(&self, policy: &str) -> Vec<String> {
+        self.payload
+            .iter()
+            .map(|value| {
+                let rule = match self.behavior {
+                    Behavior::Classical => value.clone(),
+                    Behavior::Domain => to_classical(Entry::Domain(value.clone())),
+                    Behavior::IpCidr => to_classical(Entry::Cidr(value.clone())),
+                };
+                // options such as `no-resolve` go after the policy
+                let mut parts = rule.split(',').collect::<Vec<_>>();
+                let at = parts
+                    .iter()
+                    .rposition(|p| !matches!(*p, "no-resolve" | "src"))
+                    .map_or(parts.len(), |i| i + 1);
+                parts.insert(at, policy);
+                parts.join(",")
+            })
+            .collect()
+    }
+
+    /// The list as a mihomo rule-provider file.
+    pub fn to_provider_file(&self) -> String {
+        let mut out = String::from("payload:\n");
+        for value in &self.payload {
+            let quoted = serde_json::to_string(value).unwrap_or_default();
+            out.push_str(&format!("  - {quoted}\n"));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{Behavior, parse_rule_list};
+
+    #[test]
+    fn a_plain_domain_list_stays_a_domain_provider() {
+        let list = parse_rule_list("# comment\n.example.com\nfoo.example.org\n").expect("list should parse");
+        assert_eq!(list.behavior, Behavior::Domain);
+        assert_eq!(list.payload, ["+.example.com", "foo.example.org"]);
+        assert_eq!(
+            list.to_rules("PROXY"),
+            ["DOMAIN-SUFFIX,example.com,PROXY", "DOMAIN,foo.example.org,PROXY"]
+        );
+    }
+
+    #[test]
+    fn quantumult_x_lines_lose_their_policy_and_take_mihomo_names() {
+        let list = parse_rule_list(
+            "HOST-SUFFIX,example.com,Proxy\nIP6-CIDR,2001:db8::/32,Proxy,no-resolve\nUSER-AGENT,Foo*,Proxy\n",
+        )
+        .expect("list should parse");
+        assert_eq!(list.behavior, Behavior::Classical);
+        assert_eq!(
+            list.payload,
+            ["DOMAIN-SUFFIX,example.com", "IP-CIDR6,2001:db8::/32,no-resolve"]
+        );
+        assert_eq!(list.skipped, 1);
+        assert_eq!(list.to_rules("DIRECT")[1], "IP-CIDR6,2001:db8::/32,DIRECT,no-resolve");
+    }
+
+    #[test]
+    fn a_provider_file_round_trips() {
+        let list = parse_rule_list("payload:\n  - '10.0.0.0/8'\n  - '192.168.1.1'\n").expect("list should parse");
+        assert_eq!(list.behavior, Behavior::IpCidr);
+        assert_eq!(list.payload, ["10.0.0.0/8", "192.168.1.1/32"]);
+        assert_eq!(
+            parse_rule_list(&list.to_provider_file()).expect("file should parse"),
+            list
+        );
+    }
+}
@@ -0,0 +1,527 @@
+//! Share-link subscriptions: a list of `scheme://` URIs, one node per line, usually base64 encoded
+//! as a whole. This is what most v2rayN-style panels hand out when the client does not ask for a
+//! Clash profile.
+
+use base64::{Engine as _, engine::general_purpose};
+use clash_verge_logging::{Type, logging};
+use percent_encoding::percent_decode_str;
+use reqwest_dav::re_exports::url::form_urlencoded;
+use serde_json::Value as JsonValue;
+use serde_yaml_ng::{Mapping, Value};
+use std::collections::HashMap;
+use tauri::Url;
+
+/// Parse a share-link subscription body into mihomo proxies.
+///
+/// Returns `None` when the body holds no link we understand, so that callers can tell "not a
+/// share-link subscription" apart from "a share-link subscription with a few odd lines in it".
+pub fn parse_subscription(data: &str) -> Option<Vec<Mapping>> {
+    let data = data.trim();
+    let decoded;
+    let text = if data.contains("://") {
+        data
+    } else {
+        decoded = decode_base64(data)?;
+        decoded.as_str()
+    };
+
+    let proxies = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| {
+            let proxy = parse_link(line);
+            if proxy.is_none() {
+                let scheme = line.split_once("://").map_or("unknown", |(scheme, _)| scheme);
+                logging!(debug, Type::Config, "skip unsupported share link: {scheme}://");
+            }
+            proxy
+        })
+        .collect::<Vec<_>>();
+
+    (!proxies.is_empty()).then_some(proxies)
+}
+
+/// Parse a single share link into a mihomo proxy.
+pub fn parse_link(line: &str) -> Option<Mapping> {
+    let (scheme, _) = line.split_once("://")?;
+    match scheme.to_ascii_lowercase().as_str() {
+        "ss" => parse_ss(line),
+        "vmess" => parse_vmess(line),
+        "vless" => parse_vless(line),
+        "trojan" => parse_trojan(line),
+        "hysteria2" | "hy2" => parse_hysteria2(line),
+        "tuic" => parse_tuic(line),
+        _ => None,
+    }
+}
+
+/// Decode base64 in whichever alphabet and padding the provider picked, ignoring line breaks.
+pub fn decode_base64(data: &str) -> Option<String> {
+    let compact = data.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    if compact.is_empty() {
+        return None;
+    }
+    let bytes = if compact.contains(['-', '_']) {
+        general_purpose::URL_SAFE_NO_PAD_INDIFFERENT.decode(compact.as_bytes())
+    } else {
+        general_purpose::STANDARD_NO_PAD_INDIFFERENT.decode(compact.as_bytes())
+    }
+    .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn decode_component(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().into_owned()
+}
+
+fn split_name(line: &str) -> (&str, Option<String>) {
+    match line.split_once('#') {
+        Some((rest, name)) if !name.is_empty() => (rest, Some(decode_component(name))),
+        Some((rest, _)) => (rest, None),
+        None => (line, None),
+    }
+}
+
+fn split_host_port(value: &str) -> Option<(String, u16)> {
+    let (host, port) = value.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = port.trim_end_matches('/').parse().ok()?;
+    (!host.is_empty()).then(|| (host.to_owned(), port))
+}
+
+fn fallback_name(server: &str, port: u16) -> String {
+    format!("{server}:{port}")
+}
+
+pub(super) fn base_proxy(kind: &str, name: String, server: String, port: u16) -> Mapping {
+    let mut proxy = Mapping::new();
+    proxy.insert("name".into(), name.into());
+    proxy.insert("type".into(), kind.into());
+    proxy.insert("server".into(), server.into());
+    proxy.insert("port".into(), port.into());
+    proxy
+}
+
+fn insert_str(proxy: &mut Mapping, key: &str, value: Option<&str>) {
+    if let Some(value) = value.filter(|v| !v.is_empty()) {
+        proxy.insert(key.into(), value.into());
+    }
+}
+
+fn is_truthy(value: Option<&str>) -> bool {
+    matches!(value, Some("1" | "true" | "True" | "TRUE"))
+}
+
+fn split_list(value: &str) -> Value {
+    Value::Sequence(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(Value::from)
+            .collect(),
+    )
+}
+
+/// Host and path as URLs spell them, plus the gRPC service name, mapped onto mihomo transport
+/// options. `tcp` and unknown transports leave the proxy untouched.
+fn apply_transport(proxy: &mut Mapping, network: &str, host: Option<&str>, path: Option<&str>, service: Option<&str>) {
+    let host = host.filter(|v| !v.is_empty());
+    let path = path.filter(|v| !v.is_empty());
+    match network {
+        "ws" | "httpupgrade" => {
+            let mut opts = Mapping::new();
+            insert_str(&mut opts, "path", path);
+            if let Some(host) = host {
+                let mut headers = Mapping::new();
+                headers.insert("Host".into(), host.into());
+                opts.insert("headers".into(), Value::Mapping(headers));
+            }
+            if network == "httpupgrade" {
+                opts.insert("v2ray-http-upgrade".into(), true.into());
+            }
+            proxy.insert("network".into(), "ws".into());
+            proxy.insert("ws-opts".into(), Value::Mapping(opts));
+        }
+        "grpc" => {
+            let mut opts = Mapping::new();
+            insert_str(&mut opts, "grpc-service-name", service.or(path));
+            proxy.insert("network".into(), "grpc".into());
+            proxy.insert("grpc-opts".into(), Value::Mapping(opts));
+        }
+        "h2" => {
+            let mut opts = Mapping::new();
+            if let Some(host) = host {
+                opts.insert("host".into(), split_list(host));
+            }
+            insert_str(&mut opts, "path", path);
+            proxy.insert("network".into(), "h2".into());
+            proxy.insert("h2-opts".into(), Value::Mapping(opts));
+        }
+        "http" => {
+            let mut opts = Mapping::new();
+            if let Some(path) = path {
+                opts.insert("path".into(), split_list(path));
+            }
+            if let Some(host) = host {
+                let mut headers = Mapping::new();
+                headers.insert("Host".into(), split_list(host));
+                opts.insert("headers".into(), Value::Mapping(headers));
+            }
+            proxy.insert("network".into(), "http".into());
+            proxy.insert("http-opts".into(), Value::Mapping(opts));
+        }
+        _ => {}
+    }
+}
+
+fn query_map(url: &Url) -> HashMap<String, String> {
+    url.query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect()
+}
+
+/// Server, port and name of a URL-shaped link, which every scheme but vmess and legacy ss is.
+fn url_endpoint(url: &Url) -> Option<(String, u16, String)> {
+    let server = url.host_str()?.trim_start_matches('[').trim_end_matches(']').to_owned();
+    let port = url.port()?;
+    let name = url
+        .fragment()
+        .filter(|f| !f.is_empty())
+        .map(decode_component)
+        .unwrap_or_else(|| fallback_name(&server, port));
+    Some((server, port, name))
+}
+
+/// `ss://` in both shapes found in the wild: SIP002 (`userinfo@host:port`, userinfo base64 or
+/// percent-encoded) and the legacy form that base64-encodes everything before the fragment.
+fn parse_ss(line: &str) -> Option<Mapping> {
+    let (rest, name) = split_name(line.get("ss://".len()..)?);
+    let (rest, query) = match rest.split_once('?') {
+        Some((rest, query)) => (rest.trim_end_matches('/'), Some(query)),
+        None => (rest.trim_end_matches('/'), None),
+    };
+
+    let (userinfo, endpoint) = match rest.rsplit_once('@') {
+        Some((userinfo, endpoint)) => {
+            let userinfo = decode_base64(userinfo)
+                .filter(|v| v.contains(':'))
+                .unwrap_or_else(|| decode_component(userinfo));
+            (userinfo, endpoint.to_owned())
+        }
+        None => {
+            let decoded = decode_base64(rest)?;
+            let (userinfo, endpoint) = decoded.rsplit_once('@')?;
+            (userinfo.to_owned(), endpoint.to_owned())
+        }
+    };
+    let (cipher, password) = userinfo.split_once(':')?;
+    let (server, port) = split_host_port(&endpoint)?;
+    let name = name.unwrap_or_else(|| fallback_name(&server, port));
+
+    let mut proxy = base_proxy("ss", name, server, port);
+    proxy.insert("cipher".into(), cipher.into());
+    proxy.insert("password".into(), password.into());
+    proxy.insert("udp".into(), true.into());
+
+    let plugin = query.and_then(|query| {
+        form_urlencoded::parse(query.as_bytes())
+            .find(|(k, _)| k == "plugin")
+            .map(|(_, v)| v.into_owned())
+    });
+    if let Some(plugin) = plugin {
+        apply_ss_plugin(&mut proxy, &plugin)?;
+    }
+    Some(proxy)
+}
+
+/// SIP003 plugin strings look like `obfs-local;obfs=http;obfs-host=example.com`. Only the plugins
+/// mihomo implements are carried over; any other plugin makes the node unusable, so it is dropped.
+pub(super) fn apply_ss_plugin(proxy: &mut Mapping, plugin: &str) -> Option<()> {
+    let mut parts = plugin.split(';');
+    let kind = parts.next()?.trim();
+    let params = parts
+        .map(|part| part.split_once('=').unwrap_or((part, "")))
+        .collect::<Vec<_>>();
+    let param = |key: &str| params.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+
+    let mut opts = Mapping::new();
+    match kind {
+        "obfs-local" | "simple-obfs" | "obfs" => {
+            insert_str(&mut opts, "mode", param("obfs"));
+            insert_str(&mut opts, "host", param("obfs-host"));
+            proxy.insert("plugin".into(), "obfs".into());
+        }
+        "v2ray-plugin" => {
+            opts.insert("mode".into(), param("mode").unwrap_or("websocket").into());
+            if params.iter().any(|(k, _)| *k == "tls") {
+                opts.insert("tls".into(), true.into());
+            }
+            insert_str(&mut opts, "host", param("host"));
+            insert_str(&mut opts, "path", param("path"));
+            proxy.insert("plugin".into(), "v2ray-plugin".into());
+        }
+        "" => return Some(()),
+        _ => return None,
+    }
+    proxy.insert("plugin-opts".into(), Value::Mapping(opts));
+    Some(())
+}
+
+/// `vmess://` carries a base64 v2rayN JSON object, whose numbers are as often strings as not.
+fn parse_vmess(line: &str) -> Option<Mapping> {
+    let decoded = decode_base64(line.get("vmess://".len()..)?)?;
+    let json = serde_json::from_str::<JsonValue>(&decoded).ok()?;
+    let field = |key: &str| -> Option<String> {
+        match json.get(key)? {
+            JsonValue::String(v) => Some(v.trim().to_owned()),
+            JsonValue::Number(v) => Some(v.to_string()),
+            _ => None,
+        }
+        .filter(|v| !v.is_empty())
+    };
+
+    let server = field("add")?;
+    let port = field("port")?.parse::<u16>().ok()?;
+    let name = field("ps").unwrap_or_else(|| fallback_name(&server, port));
+
+    let mut proxy = base_proxy("vmess", name, server, port);
+    proxy.insert("uuid".into(), field("id")?.into());
+    let alter_id = field("aid").and_then(|v| v.parse::<u32>().ok()).unwrap_or(0);
+    proxy.insert("alterId".into(), alter_id.into());
+    proxy.insert(
+        "cipher".into(),
+        field("scy").unwrap_or_else(|| "auto".to_owned()).into(),
+    );
+    proxy.insert("udp".into(), true.into());
+
+    if field("tls").as_deref() == Some("tls") {
+        proxy.insert("tls".into(), true.into());
+        insert_str(&mut proxy, "servername", field("sni").as_deref());
+        insert_str(&mut proxy, "client-fingerprint", field("fp").as_deref());
+        if let Some(alpn) = field("alpn") {
+            proxy.insert("alpn".into(), split_list(&alpn));
+        }
+    }
+
+    let mut network = field("net").unwrap_or_else(|| "tcp".to_owned());
+    if network == "tcp" && field("type").as_deref() == Some("http") {
+        network = "http".to_owned();
+    }
+    apply_transport(
+        &mut proxy,
+        &network,
+        field("host").as_deref(),
+        field("path").as_deref(),
+        field("path").as_deref(),
+    );
+    Some(proxy)
+}
+
+/// Shared TLS / REALITY and transport handling for vless and trojan links, which spell these
+/// options the same way.
+fn apply_stream_settings(proxy: &mut Mapping, query: &HashMap<String, String>, default_tls: bool) {
+    let get = |key: &str| query.get(key).map(String::as_str).filter(|v| !v.is_empty());
+    let security = get("security").unwrap_or(if default_tls { "tls" } else { "none" });
+
+    if matches!(security, "tls" | "reality" | "xtls") {
+        proxy.insert("tls".into(), true.into());
+        insert_str(proxy, "servername", get("sni").or_else(|| get("peer")));
+        insert_str(proxy, "client-fingerprint", get("fp"));
+        if let Some(alpn) = get("alpn") {
+            proxy.insert("alpn".into(), split_list(alpn));
+        }
+        if is_truthy(get("allowInsecure")) || is_truthy(get("insecure")) {
+            proxy.insert("skip-cert-verify".into(), true.into());
+        }
+    }
+    if security == "reality" {
+        let mut opts = Mapping::new();
+        insert_str(&mut opts, "public-key", get("pbk"));
+        insert_str(&mut opts, "short-id", get("sid"));
+        proxy.insert("reality-opts".into(), Value::Mapping(opts));
+    }
+
+    let network = get("type").unwrap_or("tcp");
+    let network = if network == "tcp" && get("headerType") == Some("http") {
+        "http"
+    } else {
+        network
+    };
+    apply_transport(proxy, network, get("host"), get("path"), get("serviceName"));
+}
+
+fn parse_vless(line: &str) -> Option<Mapping> {
+    let url = Url::parse(line).ok()?;
+    let (server, port, name) = url_endpoint(&url)?;
+    let uuid = decode_component(url.username());
+    if uuid.is_empty() {
+        return None;
+    }
+    let query = query_map(&url);
+
+    let mut proxy = base_proxy("vless", name, server, port);
+    proxy.insert("uuid".into(), uuid.into());
+    proxy.insert("udp".into(), true.into());
+    insert_str(&mut proxy, "flow", query.get("flow").map(String::as_str));
+    apply_stream_settings(&mut proxy, &query, false);
+    Some(proxy)
+}
+
+fn parse_trojan(line: &str) -> Option<Mapping> {
+    let url = Url::parse(line).ok()?;
+    let (server, port, name) = url_endpoint(&url)?;
+    let password = decode_component(url.username());
+    if password.is_empty() {
+        return None;
+    }
+    let query = query_map(&url);
+
+    let mut proxy = base_proxy("trojan", name, server, port);
+    proxy.insert("password".into(), password.into());
+    proxy.insert("udp".into(), true.into());
+    apply_stream_settings(&mut proxy, &query, true);
+    // trojan is always TLS; mihomo reads `sni` rather than `servername` for it
+    proxy.remove("tls");
+    if let Some(servername) = proxy.remove("servername") {
+        proxy.insert("sni".into(), servername);
+    }
+    Some(proxy)
+}
+
+fn parse_hysteria2(line: &str) -> Option<Mapping> {
+    let url = Url::parse(line).ok()?;
+    let (server, port, name) = url_endpoint(&url)?;
+    let query = query_map(&url);
+    let get = |key: &str| query.get(key).map(String::as_str).filter(|v| !v.is_empty());
+
+    // the password sits in the userinfo, either alone or as `user:pass` for userpass auth
+    let password = match url.password() {
+        Some(password) => format!("{}:{}", decode_component(url.username()), decode_component(password)),
+        None => decode_component(url.username()),
+    };
+
+    let mut proxy = base_proxy("hysteria2", name, server, port);
+    insert_str(&mut proxy, "password", Some(&password));
+    insert_str(&mut proxy, "sni", get("sni"));
+    insert_str(&mut proxy, "obfs", get("obfs").filter(|v| *v != "none"));
+    insert_str(&mut proxy, "obfs-password", get("obfs-password"));
+    insert_str(&mut proxy, "fingerprint", get("pinSHA256"));
+    insert_str(&mut proxy, "ports", get("mport"));
+    if let Some(alpn) = get("alpn") {
+        proxy.insert("alpn".into(), split_list(alpn));
+    }
+    if is_truthy(get("insecure")) {
+        proxy.insert("skip-cert-verify".into(), true.into());
+    }
+    Some(proxy)
+}
+
+fn parse_tuic(line: &str) -> Option<Mapping> {
+    let url = Url::parse(line).ok()?;
+    let (server, port, name) = url_endpoint(&url)?;
+    let query = query_map(&url);
+    let get = |key: &str| query.get(key).map(String::as_str).filter(|v| !v.is_empty());
+
+    let uuid = decode_component(url.username());
+    let password = url.password().map(decode_component)?;
+    if uuid.is_empty() {
+        return None;
+    }
+
+    let mut proxy = base_proxy("tuic", name, server, port);
+    proxy.insert("uuid".into(), uuid.into());
+    proxy.insert("password".into(), password.into());
+    insert_str(&mut proxy, "sni", get("sni"));
+    insert_str(&mut proxy, "congestion-controller", get("congestion_control"));
+    insert_str(&mut proxy, "udp-relay-mode", get("udp_relay_mode"));
+    if let Some(alpn) = get("alpn") {
+        proxy.insert("alpn".into(), split_list(alpn));
+    }
+    if is_truthy(get("allow_insecure")) || is_truthy(get("insecure")) {
+        proxy.insert("skip-cert-verify".into(), true.into());
+    }
+    if is_truthy(get("disable_sni")) {
+        proxy.insert("disable-sni".into(), true.into());
+    }
+    Some(proxy)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{parse_link, parse_subscription};
+    use base64::{Engine as _, engine::general_purpose};
+    use serde_yaml_ng::{Mapping, Value};
+
+    fn str_of<'a>(proxy: &'a Mapping, key: &str) -> Option<&'a str> {
+        proxy.get(key).and_then(Value::as_str)
+    }
+
+    #[test]
+    fn base64_body_with_mixed_schemes_is_decoded() {
+        let links = "ss://YWVzLTI1Ni1nY206cGFzcw@1.2.3.4:8388#HK%2001\n\
+                     trojan://secret@example.com:443?sni=cdn.example.com#JP\n\
+                     wireguard://ignored@example.com:51820#WG\n";
+        let body = general_purpose::STANDARD.encode(links);
+
+        let proxies = parse_subscription(&body).expect("share-link body should parse");
+        assert_eq!(proxies.len(), 2, "unsupported schemes are skipped");
+        assert_eq!(str_of(&proxies[0], "name"), Some("HK 01"));
+        assert_eq!(str_of(&proxies[0], "cipher"), Some("aes-256-gcm"));
+        assert_eq!(str_of(&proxies[1], "sni"), Some("cdn.example.com"));
+    }
+
+    #[test]
+    fn body_without_links_is_not_a_share_link_subscription() {
+        assert!(parse_subscription("<html>rate limited</html>").is_none());
+        assert!(parse_subscription("").is_none());
+    }
+
+    #[test]
+    fn vmess_accepts_numbers_spelled_as_strings() {
+        let json = r#"{"v":"2","ps":"US","add":"us.example.com","port":"443","id":"uuid","aid":0,
+            "net":"ws","host":"cdn.example.com","path":"/ray","tls":"tls","sni":"cdn.example.com"}"#;
+        let link = format!("vmess://{}", general_purpose::STANDARD.encode(json));
+
+        let proxy = parse_link(&link).expect("vmess link should parse");
+        assert_eq!(proxy.get("port").and_then(Value::as_u64), Some(443));
+        assert_eq!(str_of(&proxy, "network"), Some("ws"));
+        assert_eq!(
+            proxy
+                .get("ws-opts")
+                .and_then(|opts| opts.get("headers"))
+                .and_then(|headers| headers.get("Host"))
+                .and_then(Value::as_str),
+            Some("cdn.example.com")
+        );
+    }
+
+    #[test]
+    fn vless_reality_carries_public_key_and_short_id() {
+        let link = "vless://uuid@[2001:db8::1]:443?security=reality&pbk=KEY&sid=ab&fp=chrome\
+                    &flow=xtls-rprx-vision&sni=www.example.com#R";
+
+        let proxy = parse_link(link).expect("vless link should parse");
+        assert_eq!(str_of(&proxy, "server"), Some("2001:db8::1"));
+        assert_eq!(str_of(&proxy, "flow"), Some("xtls-rprx-vision"));
+        let reality = proxy.get("reality-opts").expect("reality opts");
+        assert_eq!(reality.get("public-key").and_then(Value::as_str), Some("KEY"));
+        assert_eq!(reality.get("short-id").and_then(Value::as_str), Some("ab"));
+    }
+
+    #[test]
+    fn hysteria2_and_tuic_map_their_query_options() {
+        let hy2 = parse_link("hy2://pass@h.example.com:8443/?obfs=salamander&obfs-password=o&insecure=1#H")
+            .expect("hysteria2 link should parse");
+        assert_eq!(str_of(&hy2, "type"), Some("hysteria2"));
+        assert_eq!(str_of(&hy2, "obfs"), Some("salamander"));
+        assert_eq!(hy2.get("skip-cert-verify").and_then(Value::as_bool), Some(true));
+
+        let tuic = parse_link("tuic://uuid:pw@t.example.com:443?congestion_control=bbr&alpn=h3#T")
+            .expect("tuic link should parse");
+        assert_eq!(str_of(&tuic, "password"), Some("pw"));
+        assert_eq!(str_of(&tuic, "congestion-controller"), Some("bbr"));
+    }
+}
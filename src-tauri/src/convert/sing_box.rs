@@ -0,0 +1,866 @@
+//! sing-box subscriptions: a sing-box config, or just its `outbounds` array, in JSON.
+//!
+//! Each outbound is read field by field and the fields are taken off it as they are mapped, so
+//! whatever is left afterwards is what mihomo has no equivalent for. A node missing a setting it
+//! cannot work without is skipped; one that only loses an extra (ECH, a TLS version range...) is
+//! kept. Both end up in the report instead of failing the whole subscription.
+
+use super::{
+    ConversionReport, NodeReport,
+    share_link::{apply_ss_plugin, base_proxy},
+    unique_name,
+};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use serde_yaml_ng::{Mapping, Value};
+use std::collections::{HashMap, HashSet};
+
+/// outbound types that route traffic somewhere else rather than being a node
+const NOT_NODES: [&str; 5] = ["direct", "block", "dns", "selector", "urltest"];
+
+/// Parse a sing-box config into mihomo proxies and a report of what did not convert.
+///
+/// `None` when the body is not sing-box JSON or none of its outbounds is a node mihomo can use.
+pub fn parse_config(data: &str) -> Option<(Vec<Mapping>, ConversionReport)> {
+    let json = serde_json::from_str::<JsonValue>(data.trim_start_matches('\u{feff}')).ok()?;
+    let (outbounds, endpoints) = match &json {
+        JsonValue::Object(config) => (
+            config.get("outbounds")?.as_array()?,
+            config.get("endpoints").and_then(JsonValue::as_array),
+        ),
+        JsonValue::Array(outbounds) => (outbounds, None),
+        _ => return None,
+    };
+
+    let mut report = ConversionReport {
+        format: "sing-box".into(),
+        ..ConversionReport::default()
+    };
+    let mut direct = HashSet::new();
+    let mut nodes = Vec::new();
+    for object in outbounds
+        .iter()
+        .chain(endpoints.into_iter().flatten())
+        .filter_map(JsonValue::as_object)
+    {
+        let kind = object.get("type").and_then(JsonValue::as_str).unwrap_or_default();
+        if kind == "direct" {
+            direct.insert(node_name(object));
+        }
+        if NOT_NODES.contains(&kind) {
+            continue;
+        }
+        let tag = node_name(object);
+        nodes.push((tag.clone(), convert_outbound(object.clone(), tag)));
+    }
+
+    // names are made unique here rather than in `profile_from_proxies`, so detours follow the renames
+    let mut seen = HashSet::new();
+    let mut names = HashMap::new();
+    for (tag, node) in &mut nodes {
+        if let Ok((proxy, _)) = node {
+            let name = unique_name(&mut seen, tag);
+            proxy.insert("name".into(), name.as_str().into());
+            names.entry(tag.clone()).or_insert(name);
+        }
+    }
+
+    let mut proxies = Vec::new();
+    for (tag, node) in nodes {
+        match node {
+            Ok((mut proxy, mut unsupported)) => {
+                resolve_detour(&mut proxy, &names, &direct, &mut unsupported);
+                if !unsupported.is_empty() {
+                    report.nodes.push(NodeReport {
+                        name: proxy.get("name").and_then(Value::as_str).unwrap_or_default().to_owned(),
+                        skipped: false,
+                        unsupported,
+                    });
+                }
+                proxies.push(proxy);
+            }
+            Err(reason) => report.nodes.push(NodeReport {
+                name: tag,
+                skipped: true,
+                unsupported: vec![reason],
+            }),
+        }
+    }
+
+    report.converted = proxies.len();
+    (!proxies.is_empty()).then_some((proxies, report))
+}
+
+/// Point `dialer-proxy`, still holding the sing-box `detour` tag, at the name that node ended up
+/// with. A detour through a `direct` outbound is the same as none; one through a selector, or a
+/// node that was skipped, can not be followed and is reported.
+fn resolve_detour(
+    proxy: &mut Mapping,
+    names: &HashMap<String, String>,
+    direct: &HashSet<String>,
+    unsupported: &mut Vec<String>,
+) {
+    let Some(detour) = proxy.get("dialer-proxy").and_then(Value::as_str).map(str::to_owned) else {
+        return;
+    };
+    match names.get(&detour) {
+        Some(name) => {
+            proxy.insert("dialer-proxy".into(), name.as_str().into());
+        }
+        None => {
+            proxy.remove("dialer-proxy");
+            if !direct.contains(&detour) {
+                unsupported.push("detour".into());
+            }
+        }
+    }
+}
+
+fn node_name(object: &JsonMap<String, JsonValue>) -> String {
+    let field = |key: &str| object.get(key).and_then(JsonValue::as_str).filter(|v| !v.is_empty());
+    match (field("tag"), field("server")) {
+        (Some(tag), _) => tag.to_owned(),
+        (None, Some(server)) => server.to_owned(),
+        (None, None) => "node".to_owned(),
+    }
+}
+
+/// The proxy and the settings left out of it, or why there is no proxy.
+fn convert_outbound(object: JsonMap<String, JsonValue>, name: String) -> Result<(Mapping, Vec<String>), String> {
+    let mut fields = Fields::new(String::new(), object);
+    let mut unsupported = Vec::new();
+    let kind = fields.str("type").unwrap_or_default();
+    fields.take("tag");
+
+    let mut proxy = match kind.as_str() {
+        "shadowsocks" => shadowsocks(&mut fields, name, &mut unsupported)?,
+        "vmess" => vmess(&mut fields, name, &mut unsupported)?,
+        "vless" => vless(&mut fields, name, &mut unsupported)?,
+        "trojan" => trojan(&mut fields, name, &mut unsupported)?,
+        "hysteria2" => hysteria2(&mut fields, name, &mut unsupported)?,
+        "tuic" => tuic(&mut fields, name, &mut unsupported)?,
+        "wireguard" => wireguard(&mut fields, name, &mut unsupported)?,
+        other => return Err(format!("type \"{other}\"")),
+    };
+    apply_dial(&mut proxy, &mut fields, &mut unsupported);
+    fields.finish(&mut unsupported);
+    Ok((proxy, unsupported))
+}
+
+/// The fields of one sing-box object, taken off as they are converted.
+struct Fields {
+    path: String,
+    map: JsonMap<String, JsonValue>,
+}
+
+impl Fields {
+    const fn new(path: String, map: JsonMap<String, JsonValue>) -> Self {
+        Self { path, map }
+    }
+
+    fn take(&mut self, key: &str) -> Option<JsonValue> {
+        self.map.remove(key)
+    }
+
+    fn str(&mut self, key: &str) -> Option<String> {
+        match self.take(key)? {
+            JsonValue::String(value) => Some(value).filter(|v| !v.is_empty()),
+            JsonValue::Number(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    fn u64(&mut self, key: &str) -> Option<u64> {
+        match self.take(key)? {
+            JsonValue::Number(value) => value.as_u64(),
+            JsonValue::String(value) => value.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn bool(&mut self, key: &str) -> bool {
+        matches!(self.take(key), Some(JsonValue::Bool(true)))
+    }
+
+    /// A list of strings, which sing-box also accepts as a single string.
+    fn strings(&mut self, key: &str) -> Vec<String> {
+        match self.take(key) {
+            Some(JsonValue::String(value)) if !value.is_empty() => vec![value],
+            Some(JsonValue::Array(values)) => values
+                .into_iter()
+                .filter_map(|v| v.as_str().filter(|v| !v.is_empty()).map(str::to_owned))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn object(&mut self, key: &str) -> Option<Self> {
+        match self.take(key)? {
+            JsonValue::Object(map) => Some(Self::new(self.child_path(key), map)),
+            _ => None,
+        }
+    }
+
+    /// A nested object that is `enabled`; a disabled one goes away with all its settings.
+    fn enabled(&mut self, key: &str) -> Option<Self> {
+        let mut object = self.object(key)?;
+        object.bool("enabled").then_some(object)
+    }
+
+    fn child_path(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{}.{key}", self.path)
+        }
+    }
+
+    /// Report every field nobody took.
+    fn finish(self, unsupported: &mut Vec<String>) {
+        unsupported.extend(self.map.keys().map(|key| self.child_path(key)));
+    }
+}
+
+fn insert_value(proxy: &mut Mapping, key: &str, value: Option<impl Into<Value>>) {
+    if let Some(value) = value {
+        proxy.insert(key.into(), value.into());
+    }
+}
+
+fn insert_list(proxy: &mut Mapping, key: &str, values: Vec<String>) {
+    if !values.is_empty() {
+        proxy.insert(
+            key.into(),
+            Value::Sequence(values.into_iter().map(Value::from).collect()),
+        );
+    }
+}
+
+fn endpoint(fields: &mut Fields, kind: &str, name: String) -> Result<Mapping, String> {
+    let server = fields.str("server").ok_or("missing `server`")?;
+    let port = fields
+        .u64("server_port")
+        .and_then(|port| u16::try_from(port).ok())
+        .ok_or("missing `server_port`")?;
+    Ok(base_proxy(kind, name, server, port))
+}
+
+fn required(fields: &mut Fields, key: &str) -> Result<String, String> {
+    fields.str(key).ok_or_else(|| format!("missing `{key}`"))
+}
+
+/// `network` limits an outbound to tcp or udp; mihomo can only turn udp off.
+fn apply_network(proxy: &mut Mapping, fields: &mut Fields, unsupported: &mut Vec<String>) {
+    match fields.str("network").as_deref() {
+        Some("tcp") => {
+            proxy.insert("udp".into(), false.into());
+        }
+        Some("udp") => unsupported.push(fields.child_path("network")),
+        _ => {
+            proxy.insert("udp".into(), true.into());
+        }
+    }
+}
+
+fn shadowsocks(fields: &mut Fields, name: String, unsupported: &mut Vec<String>) -> Result<Mapping, String> {
+    let mut proxy = endpoint(fields, "ss", name)?;
+    proxy.insert("cipher".into(), required(fields, "method")?.into());
+    proxy.insert("password".into(), required(fields, "password")?.into());
+    apply_network(&mut proxy, fields, unsupported);
+    match fields.take("udp_over_tcp") {
+        Some(JsonValue::Bool(true)) => {
+            proxy.insert("udp-over-tcp".into(), true.into());
+        }
+        Some(JsonValue::Object(uot)) if uot.get("enabled").and_then(JsonValue::as_bool) == Some(true) => {
+            proxy.insert("udp-over-tcp".into(), true.into());
+            insert_value(
+                &mut proxy,
+                "udp-over-tcp-version",
+                uot.get("version").and_then(JsonValue::as_u64),
+            );
+        }
+        _ => {}
+    }
+    if let Some(plugin) = fields.str("plugin") {
+        let opts = fields.str("plugin_opts").unwrap_or_default();
+        apply_ss_plugin(&mut proxy, &format!("{plugin};{opts}")).ok_or_else(|| format!("plugin \"{plugin}\""))?;
+    }
+    Ok(proxy)
+}
+
+fn vmess(fields: &mut Fields, name: String, unsupported: &mut Vec<String>) -> Result<Mapping, String> {
+    let mut proxy = endpoint(fields, "vmess", name)?;
+    proxy.insert("uuid".into(), required(fields, "uuid")?.into());
+    proxy.insert("alterId".into(), fields.u64("alter_id").unwrap_or(0).into());
+    proxy.insert(
+        "cipher".into(),
+        fields.str("security").unwrap_or_else(|| "auto".into()).into(),
+    );
+    if fields.bool("global_padding") {
+        proxy.insert("global-padding".into(), true.into());
+    }
+    if fields.bool("authenticated_length") {
+        proxy.insert("authenticated-length".into(), true.into());
+    }
+    insert_value(&mut proxy, "packet-encoding", fields.str("packet_encoding"));
+    apply_network(&mut proxy, fields, unsupported);
+    apply_tls(&mut proxy, fields, TlsStyle::Optional, unsupported);
+    apply_transport(&mut proxy, fields, unsupported)?;
+    apply_multiplex(&mut proxy, fields, unsupported);
+    Ok(proxy)
+}
+
+fn vless(fields: &mut Fields, name: String, unsupported: &mut Vec<String>) -> Result<Mapping, String> {
+    let mut proxy = endpoint(fields, "vless", name)?;
+    proxy.insert("uuid".into(), required(fields, "uuid")?.into());
+    insert_value(&mut proxy, "flow", fields.str("flow"));
+    insert_value(&mut proxy, "packet-encoding", fields.str("packet_encoding"));
+    apply_network(&mut proxy, fields, unsupported);
+    apply_tls(&mut proxy, fields, TlsStyle::Optional, unsupported);
+    apply_transport(&mut proxy, fields, unsupported)?;
+    apply_multiplex(&mut proxy, fields, unsupported);
+    Ok(proxy)
+}
+
+fn trojan(fields: &mut Fields, name: String, unsupported: &mut Vec<String>) -> Result<Mapping, String> {
+    let mut proxy = endpoint(fields, "trojan", name)?;
+    proxy.insert("password".into(), required(fields, "password")?.into());
+    apply_network(&mut proxy, fields, unsupported);
+    apply_tls(&mut proxy, fields, TlsStyle::Implicit, unsupported);
+    apply_transport(&mut proxy, fields, unsupported)?;
+    apply_multiplex(&mut proxy, fields, unsupported);
+    Ok(proxy)
+}
+
+fn hysteria2(fields: &mut Fields, name: String, unsupported: &mut Vec<String>) -> Result<Mapping, String> {
+    // sing-box spells port ranges `a:b`, mihomo `a-b`
+    let ports = fields
+        .strings("server_ports")
+        .iter()
+        .map(|range| range.replace(':', "-"))
+        .collect::<Vec<_>>();
+    if let Some(range) = ports.first()
+        && !fields.map.contains_key("server_port")
+    {
+        let first = range.split('-').next().unwrap_or_default().to_owned();
+        fields.map.insert("server_port".into(), first.into());
+    }
+
+    let mut proxy = endpoint(fields, "hysteria2", name)?;
+    insert_value(&mut proxy, "password", fields.str("password"));
+    if !ports.is_empty() {
+        proxy.insert("ports".into(), ports.join(",").into());
+    }
+    insert_value(
+        &mut proxy,
+        "hop-interval",
+        fields
+            .str("hop_interval")
+            .and_then(|v| duration_ms(&v))
+            .map(|ms| ms / 1000),
+    );
+    insert_value(&mut proxy, "up", fields.u64("up_mbps").map(|v| format!("{v} Mbps")));
+    insert_value(&mut proxy, "down", fields.u64("down_mbps").map(|v| format!("{v} Mbps")));
+    if let Some(mut obfs) = fields.object("obfs") {
+        insert_value(&mut proxy, "obfs", obfs.str("type"));
+        insert_value(&mut proxy, "obfs-password", obfs.str("password"));
+        obfs.finish(unsupported);
+    }
+    apply_network(&mut proxy, fields, unsupported);
+    apply_tls(&mut proxy, fields, TlsStyle::Implicit, unsupported);
+    Ok(proxy)
+}
+
+fn tuic(fields: &mut Fields, name: String, unsupported: &mut Vec<String>) -> Result<Mapping, String> {
+    let mut proxy = endpoint(fields, "tuic", name)?;
+    proxy.insert("uuid".into(), required(fields, "uuid")?.into());
+    insert_value(&mut proxy, "password", fields.str("password"));
+    insert_value(&mut proxy, "congestion-controller", fields.str("congestion_control"));
+    insert_value(&mut proxy, "udp-relay-mode", fields.str("udp_relay_mode"));
+    if fields.bool("udp_over_stream") {
+        proxy.insert("udp-over-stream".into(), true.into());
+    }
+    if fields.bool("zero_rtt_handshake") {
+        proxy.insert("reduce-rtt".into(), true.into());
+    }
+    insert_value(
+        &mut proxy,
+        "heartbeat-interval",
+        fields.str("heartbeat").and_then(|v| duration_ms(&v)),
+    );
+    apply_network(&mut proxy, fields, unsupported);
+    apply_tls(&mut proxy, fields, TlsStyle::Implicit, unsupported);
+    Ok(proxy)
+}
+
+/// Both the legacy outbound, with one peer in its own fields, and the endpoint of sing-box 1.11,
+/// with a `peers` list. mihomo takes one peer in the node's own fields.
+fn wireguard(fields: &mut Fields, name: String, unsupported: &mut Vec<String>) -> Result<Mapping, String> {
+    let peers = match fields.take("peers") {
+        Some(JsonValue::Array(peers)) => peers,
+        _ => Vec::new(),
+    };
+    if peers.len() > 1 {
+        unsupported.extend((1..peers.len()).map(|i| fields.child_path(&format!("peers[{i}]"))));
+    }
+    let mut peer = match peers.into_iter().next() {
+        Some(JsonValue::Object(peer)) => Fields::new(fields.child_path("peers[0]"), peer),
+        _ => Fields::new(String::new(), JsonMap::new()),
+    };
+    // the endpoint form keeps the server, port and keys in its peer
+    for (from, to) in [
+        ("address", "server"),
+        ("port", "server_port"),
+        ("public_key", "peer_public_key"),
+        ("pre_shared_key", "pre_shared_key"),
+        ("reserved", "reserved"),
+        ("allowed_ips", "allowed_ips"),
+    ] {
+        if let Some(value) = peer.take(from) {
+            fields.map.insert(to.into(), value);
+        }
+    }
+    if !peer.path.is_empty() {
+        peer.finish(unsupported);
+    }
+
+    let mut proxy = endpoint(fields, "wireguard", name)?;
+    proxy.insert("private-key".into(), required(fields, "private_key")?.into());
+    proxy.insert("public-key".into(), required(fields, "peer_public_key")?.into());
+    insert_value(&mut proxy, "pre-shared-key", fields.str("pre_shared_key"));
+    let mut addresses = fields.strings("local_address");
+    addresses.extend(fields.strings("address"));
+    for address in addresses {
+        let ip = address.split('/').next().unwrap_or_default().to_owned();
+        let key = if ip.contains(':') { "ipv6" } else { "ip" };
+        if !proxy.contains_key(key) {
+            proxy.insert(key.into(), ip.into());
+        }
+    }
+    match fields.take("reserved") {
+        Some(JsonValue::String(reserved)) => {
+            proxy.insert("reserved".into(), reserved.into());
+        }
+        Some(JsonValue::Array(bytes)) => {
+            let bytes = bytes.iter().filter_map(JsonValue::as_u64).map(Value::from).collect();
+            proxy.insert("reserved".into(), Value::Sequence(bytes));
+        }
+        _ => {}
+    }
+    insert_list(&mut proxy, "allowed-ips", fields.strings("allowed_ips"));
+    insert_value(&mut proxy, "mtu", fields.u64("mtu"));
+    proxy.insert("udp".into(), true.into());
+    // only meaningful to sing-box's own interface handling
+    fields.take("system_interface");
+    fields.take("gso");
+    Ok(proxy)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TlsStyle {
+    /// vmess and vless: `tls: true` turns it on, `servername` names the server
+    Optional,
+    /// always TLS, named with `sni`
+    Implicit,
+}
+
+fn apply_tls(proxy: &mut Mapping, fields: &mut Fields, style: TlsStyle, unsupported: &mut Vec<String>) {
+    let Some(mut tls) = fields.enabled("tls") else {
+        return;
+    };
+    let sni_key = match style {
+        TlsStyle::Optional => {
+            proxy.insert("tls".into(), true.into());
+            "servername"
+        }
+        TlsStyle::Implicit => "sni",
+    };
+    insert_value(proxy, sni_key, tls.str("server_name"));
+    if tls.bool("insecure") {
+        proxy.insert("skip-cert-verify".into(), true.into());
+    }
+    insert_list(proxy, "alpn", tls.strings("alpn"));
+    if tls.bool("disable_sni") {
+        if proxy.get("type").and_then(Value::as_str) == Some("tuic") {
+            proxy.insert("disable-sni".into(), true.into());
+        } else {
+            unsupported.push(tls.child_path("disable_sni"));
+        }
+    }
+    if let Some(mut utls) = tls.enabled("utls") {
+        // sing-box falls back to chrome when no fingerprint is named
+        let fingerprint = utls.str("fingerprint").unwrap_or_else(|| "chrome".into());
+        proxy.insert("client-fingerprint".into(), fingerprint.into());
+        utls.finish(unsupported);
+    }
+    if let Some(mut reality) = tls.enabled("reality") {
+        let mut opts = Mapping::new();
+        insert_value(&mut opts, "public-key", reality.str("public_key"));
+        insert_value(&mut opts, "short-id", reality.str("short_id"));
+        proxy.insert("reality-opts".into(), Value::Mapping(opts));
+        reality.finish(unsupported);
+    }
+    tls.finish(unsupported);
+}
+
+/// A transport mihomo lacks (QUIC) leaves the node unusable, so it is skipped.
+fn apply_transport(proxy: &mut Mapping, fields: &mut Fields, unsupported: &mut Vec<String>) -> Result<(), String> {
+    let Some(mut transport) = fields.object("transport") else {
+        return Ok(());
+    };
+    let kind = transport.str("type").unwrap_or_default();
+    let has_tls = proxy.get("tls").and_then(Value::as_bool) == Some(true)
+        || proxy.get("type").and_then(Value::as_str) == Some("trojan");
+
+    let (network, opts) = match kind.as_str() {
+        "ws" => ("ws", ws_opts(&mut transport, false, unsupported)),
+        "httpupgrade" => ("ws", ws_opts(&mut transport, true, unsupported)),
+        "grpc" => {
+            let mut opts = Mapping::new();
+            insert_value(&mut opts, "grpc-service-name", transport.str("service_name"));
+            ("grpc", opts)
+        }
+        // sing-box speaks HTTP/2 over this transport when TLS is on, plain HTTP/1.1 otherwise
+        "http" if has_tls => {
+            let mut opts = Mapping::new();
+            insert_list(&mut opts, "host", transport.strings("host"));
+            insert_value(&mut opts, "path", transport.str("path"));
+            ("h2", opts)
+        }
+        "http" => ("http", http_opts(&mut transport)),
+        other => return Err(format!("transport \"{other}\"")),
+    };
+    proxy.insert("network".into(), network.into());
+    proxy.insert(format!("{network}-opts").into(), Value::Mapping(opts));
+    transport.finish(unsupported);
+    Ok(())
+}
+
+/// `ws-opts` of a websocket or, with `upgrade`, an HTTP upgrade transport.
+fn ws_opts(transport: &mut Fields, upgrade: bool, unsupported: &mut Vec<String>) -> Mapping {
+    let mut opts = Mapping::new();
+    insert_value(&mut opts, "path", transport.str("path"));
+    let mut headers = transport.object("headers");
+    let host = transport
+        .str("host")
+        .or_else(|| headers.as_mut()?.strings("Host").into_iter().next());
+    if let Some(host) = host {
+        let mut header_map = Mapping::new();
+        header_map.insert("Host".into(), host.into());
+        opts.insert("headers".into(), Value::Mapping(header_map));
+    }
+    if let Some(headers) = headers {
+        headers.finish(unsupported);
+    }
+    if upgrade {
+        opts.insert("v2ray-http-upgrade".into(), true.into());
+    } else {
+        insert_value(&mut opts, "max-early-data", transport.u64("max_early_data"));
+        insert_value(
+            &mut opts,
+            "early-data-header-name",
+            transport.str("early_data_header_name"),
+        );
+    }
+    opts
+}
+
+fn http_opts(transport: &mut Fields) -> Mapping {
+    let mut opts = Mapping::new();
+    insert_value(&mut opts, "method", transport.str("method"));
+    insert_list(&mut opts, "path", transport.str("path").into_iter().collect());
+    let hosts = transport.strings("host");
+    if !hosts.is_empty() {
+        let mut headers = Mapping::new();
+        insert_list(&mut headers, "Host", hosts);
+        opts.insert("headers".into(), Value::Mapping(headers));
+    }
+    opts
+}
+
+fn apply_multiplex(proxy: &mut Mapping, fields: &mut Fields, unsupported: &mut Vec<String>) {
+    let Some(mut multiplex) = fields.enabled("multiplex") else {
+        return;
+    };
+    let mut smux = Mapping::new();
+    smux.insert("enabled".into(), true.into());
+    insert_value(&mut smux, "protocol", multiplex.str("protocol"));
+    insert_value(&mut smux, "max-connections", multiplex.u64("max_connections"));
+    insert_value(&mut smux, "min-streams", multiplex.u64("min_streams"));
+    insert_value(&mut smux, "max-streams", multiplex.u64("max_streams"));
+    if multiplex.bool("padding") {
+        smux.insert("padding".into(), true.into());
+    }
+    proxy.insert("smux".into(), Value::Mapping(smux));
+    multiplex.finish(unsupported);
+}
+
+/// The dial fields every outbound shares.
+fn apply_dial(proxy: &mut Mapping, fields: &mut Fields, unsupported: &mut Vec<String>) {
+    insert_value(proxy, "dialer-proxy", fields.str("detour"));
+    insert_value(proxy, "interface-name", fields.str("bind_interface"));
+    insert_value(proxy, "routing-mark", fields.u64("routing_mark"));
+    if fields.bool("tcp_fast_open") {
+        proxy.insert("tfo".into(), true.into());
+    }
+    if fields.bool("tcp_multi_path") {
+        proxy.insert("mptcp".into(), true.into());
+    }
+    if let Some(strategy) = fields.str("domain_strategy") {
+        let version = match strategy.as_str() {
+            "prefer_ipv4" => Some("ipv4-prefer"),
+            "prefer_ipv6" => Some("ipv6-prefer"),
+            "ipv4_only" => Some("ipv4"),
+            "ipv6_only" => Some("ipv6"),
+            _ => None,
+        };
+        match version {
+            Some(version) => {
+                proxy.insert("ip-version".into(), version.into());
+            }
+            None => unsupported.push(fields.child_path("domain_strategy")),
+        }
+    }
+}
+
+/// Milliseconds of a Go duration such as `30s`, `1m30s` or `500ms`.
+fn duration_ms(value: &str) -> Option<u64> {
+    let mut total = 0f64;
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let number_end = rest.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let (number, tail) = rest.split_at(number_end);
+        let unit_end = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_end);
+        let factor = match unit {
+            "ns" => 1e-6,
+            "us" | "µs" => 1e-3,
+            "ms" => 1.0,
+            "s" => 1e3,
+            "m" => 60e3,
+            "h" => 3_600e3,
+            _ => return None,
+        };
+        total += number.parse::<f64>().ok()? * factor;
+        rest = tail;
+    }
+    Some(total.round() as u64)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{duration_ms, parse_config};
+    use serde_yaml_ng::{Mapping, Value};
+
+    fn str_of<'a>(proxy: &'a Mapping, key: &str) -> Option<&'a str> {
+        proxy.get(key).and_then(Value::as_str)
+    }
+
+    #[test]
+    fn vless_reality_with_utls_and_ws_transport() {
+        let config = r#"{
+            "outbounds": [
+                {"type": "selector", "tag": "proxy", "outbounds": ["r"]},
+                {
+                    "type": "vless", "tag": "r", "server": "r.example.com", "server_port": 443,
+                    "uuid": "id", "flow": "xtls-rprx-vision",
+                    "tls": {
+                        "enabled": true, "server_name": "www.example.com",
+                        "utls": {"enabled": true, "fingerprint": "safari"},
+                        "reality": {"enabled": true, "public_key": "KEY", "short_id": "ab"},
+                        "ech": {"enabled": true}
+                    },
+                    "multiplex": {"enabled": false, "protocol": "h2mux"}
+                },
+                {
+                    "type": "vmess", "tag": "w", "server": "w.example.com", "server_port": 80,
+                    "uuid": "id", "security": "aes-128-gcm",
+                    "transport": {"type": "ws", "path": "/ray", "headers": {"Host": "cdn.example.com"}}
+                },
+                {"type": "direct", "tag": "direct"}
+            ]
+        }"#;
+
+        let (proxies, report) = parse_config(config).expect("sing-box config should convert");
+        assert_eq!(proxies.len(), 2);
+        let reality = &proxies[0];
+        assert_eq!(str_of(reality, "type"), Some("vless"));
+        assert_eq!(reality.get("tls").and_then(Value::as_bool), Some(true));
+        assert_eq!(str_of(reality, "servername"), Some("www.example.com"));
+        assert_eq!(str_of(reality, "client-fingerprint"), Some("safari"));
+        assert_eq!(
+            reality
+                .get("reality-opts")
+                .and_then(|opts| opts.get("public-key"))
+                .and_then(Value::as_str),
+            Some("KEY")
+        );
+        assert!(!reality.contains_key("smux"), "disabled multiplex is dropped");
+
+        let ws = &proxies[1];
+        assert_eq!(str_of(ws, "cipher"), Some("aes-128-gcm"));
+        assert_eq!(str_of(ws, "network"), Some("ws"));
+        assert_eq!(
+            ws.get("ws-opts")
+                .and_then(|opts| opts.get("headers"))
+                .and_then(|headers| headers.get("Host"))
+                .and_then(Value::as_str),
+            Some("cdn.example.com")
+        );
+
+        assert_eq!(report.converted, 2);
+        assert_eq!(report.nodes.len(), 1);
+        assert_eq!(report.nodes[0].name, "r");
+        assert!(!report.nodes[0].skipped);
+        assert_eq!(report.nodes[0].unsupported, vec!["tls.ech"]);
+    }
+
+    #[test]
+    fn hysteria2_tuic_and_wireguard_outbounds() {
+        let outbounds = r#"[
+            {
+                "type": "hysteria2", "tag": "hy", "server": "h.example.com",
+                "server_ports": ["20000:30000"], "hop_interval": "30s", "up_mbps": 50,
+                "password": "pw", "obfs": {"type": "salamander", "password": "o"},
+                "tls": {"enabled": true, "server_name": "h.example.com", "insecure": true}
+            },
+            {
+                "type": "tuic", "tag": "tu", "server": "t.example.com", "server_port": 443,
+                "uuid": "id", "password": "pw", "congestion_control": "bbr", "heartbeat": "10s",
+                "tls": {"enabled": true, "alpn": "h3", "disable_sni": true}
+            },
+            {
+                "type": "wireguard", "tag": "wg", "server": "w.example.com", "server_port": 51820,
+                "local_address": ["172.16.0.2/32", "fd01::2/128"], "private_key": "priv",
+                "peer_public_key": "pub", "reserved": [1, 2, 3], "mtu": 1280
+            }
+        ]"#;
+
+        let (proxies, report) = parse_config(outbounds).expect("outbound array should convert");
+        assert!(report.nodes.is_empty(), "{:?}", report.nodes);
+
+        let hy = &proxies[0];
+        assert_eq!(hy.get("port").and_then(Value::as_u64), Some(20000));
+        assert_eq!(str_of(hy, "ports"), Some("20000-30000"));
+        assert_eq!(hy.get("hop-interval").and_then(Value::as_u64), Some(30));
+        assert_eq!(str_of(hy, "up"), Some("50 Mbps"));
+        assert_eq!(str_of(hy, "obfs"), Some("salamander"));
+        assert_eq!(str_of(hy, "sni"), Some("h.example.com"));
+        assert_eq!(hy.get("skip-cert-verify").and_then(Value::as_bool), Some(true));
+
+        let tuic = &proxies[1];
+        assert_eq!(str_of(tuic, "congestion-controller"), Some("bbr"));
+        assert_eq!(tuic.get("heartbeat-interval").and_then(Value::as_u64), Some(10_000));
+        assert_eq!(tuic.get("disable-sni").and_then(Value::as_bool), Some(true));
+
+        let wg = &proxies[2];
+        assert_eq!(str_of(wg, "ip"), Some("172.16.0.2"));
+        assert_eq!(str_of(wg, "ipv6"), Some("fd01::2"));
+        assert_eq!(str_of(wg, "public-key"), Some("pub"));
+        assert_eq!(wg.get("reserved").and_then(Value::as_sequence).map(Vec::len), Some(3));
+    }
+
+    #[test]
+    fn wireguard_endpoints_read_their_first_peer() {
+        let config = r#"{
+            "outbounds": [{"type": "direct", "tag": "direct"}],
+            "endpoints": [{
+                "type": "wireguard", "tag": "wg", "address": ["10.0.0.2/32"], "private_key": "priv",
+                "peers": [
+                    {"address": "a.example.com", "port": 51820, "public_key": "pub", "allowed_ips": ["0.0.0.0/0"]},
+                    {"address": "b.example.com", "port": 51820, "public_key": "pub2"}
+                ]
+            }]
+        }"#;
+
+        let (proxies, report) = parse_config(config).expect("endpoint should convert");
+        assert_eq!(str_of(&proxies[0], "server"), Some("a.example.com"));
+        assert_eq!(str_of(&proxies[0], "ip"), Some("10.0.0.2"));
+        assert_eq!(report.nodes[0].unsupported, vec!["peers[1]"]);
+    }
+
+    #[test]
+    fn unusable_nodes_are_skipped_and_reported() {
+        let outbounds = r#"[
+            {"type": "shadowsocks", "tag": "ok", "server": "s.example.com", "server_port": 8388,
+             "method": "aes-128-gcm", "password": "pw", "network": "tcp"},
+            {"type": "vmess", "tag": "quic", "server": "q.example.com", "server_port": 443, "uuid": "id",
+             "transport": {"type": "quic"}},
+            {"type": "shadowtls", "tag": "stls", "server": "x.example.com", "server_port": 443},
+            {"type": "trojan", "tag": "bare", "server": "t.example.com"}
+        ]"#;
+
+        let (proxies, report) = parse_config(outbounds).expect("one node should convert");
+        assert_eq!(proxies.len(), 1);
+        assert_eq!(proxies[0].get("udp").and_then(Value::as_bool), Some(false));
+        let skipped = report
+            .nodes
+            .iter()
+            .map(|node| (node.name.as_str(), node.skipped, node.unsupported[0].as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            vec![
+                ("quic", true, "transport \"quic\""),
+                ("stls", true, "type \"shadowtls\""),
+                ("bare", true, "missing `server_port`"),
+            ]
+        );
+    }
+
+    #[test]
+    fn detours_follow_the_deduplicated_names() {
+        let outbounds = r#"[
+            {"type": "shadowsocks", "tag": "hop", "server": "a.example.com", "server_port": 1,
+             "method": "aes-128-gcm", "password": "pw"},
+            {"type": "shadowsocks", "tag": "hop", "server": "b.example.com", "server_port": 2,
+             "method": "aes-128-gcm", "password": "pw", "detour": "hop", "udp_fragment": true},
+            {"type": "shadowsocks", "tag": "out", "server": "c.example.com", "server_port": 3,
+             "method": "aes-128-gcm", "password": "pw", "detour": "auto"},
+            {"type": "shadowsocks", "tag": "plain", "server": "d.example.com", "server_port": 4,
+             "method": "aes-128-gcm", "password": "pw", "detour": "direct"},
+            {"type": "urltest", "tag": "auto", "outbounds": ["hop"]},
+            {"type": "direct", "tag": "direct"}
+        ]"#;
+
+        let (proxies, report) = parse_config(outbounds).expect("outbound array should convert");
+        let names = proxies.iter().filter_map(|p| str_of(p, "name")).collect::<Vec<_>>();
+        assert_eq!(names, vec!["hop", "hop 2", "out", "plain"]);
+        assert_eq!(str_of(&proxies[1], "dialer-proxy"), Some("hop"));
+        assert_eq!(str_of(&proxies[2], "dialer-proxy"), None);
+        assert_eq!(str_of(&proxies[3], "dialer-proxy"), None);
+
+        let reported = report
+            .nodes
+            .iter()
+            .map(|node| (node.name.as_str(), node.unsupported.clone()))
+            .collect::<Vec<_>>();
+        // reported under the name the node has in the profile
+        assert_eq!(
+            reported,
+            vec![
+                ("hop 2", vec!["udp_fragment".to_owned()]),
+                ("out", vec!["detour".to_owned()])
+            ]
+        );
+    }
+
+    #[test]
+    fn other_bodies_are_not_sing_box() {
+        assert!(parse_config("proxies:\n  - {name: a, type: ss}\n").is_none());
+        assert!(parse_config(r#"{"proxies": []}"#).is_none());
+        assert!(parse_config(r#"{"outbounds": [{"type": "direct"}]}"#).is_none());
+        assert!(parse_config("[1, 2]").is_none());
+    }
+
+    #[test]
+    fn go_durations() {
+        assert_eq!(duration_ms("30s"), Some(30_000));
+        assert_eq!(duration_ms("1m30s"), Some(90_000));
+        assert_eq!(duration_ms("1.5h"), Some(5_400_000));
+        assert_eq!(duration_ms("250ms"), Some(250));
+        assert_eq!(duration_ms("10"), None);
+    }
+}
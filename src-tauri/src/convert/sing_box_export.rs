@@ -0,0 +1,1049 @@
+//! The runtime config as a sing-box config, for devices that run sing-box instead of mihomo.
+//!
+//! Nodes become outbounds (WireGuard an endpoint), `select` and `url-test` groups become `selector`
+//! and `urltest`, and rules become route rules written with the rule actions of sing-box 1.11.
+//! Whatever sing-box has no way to say — a node type it lacks, a relay group, a rule on a
+//! rule-provider — is left out and listed in the warnings, so the exported config always loads even
+//! when it routes less than the runtime did.
+
+use base64::{Engine as _, engine::general_purpose};
+use serde::Serialize;
+use serde_json::{Map as JsonMap, Value as JsonValue, json};
+use serde_yaml_ng::{Mapping, Value};
+use std::collections::{BTreeSet, HashSet};
+
+const DIRECT_TAG: &str = "DIRECT";
+/// the outbounds mihomo has built in, which rules and groups mean when they name them
+const BUILT_IN: [&str; 5] = ["DIRECT", "REJECT", "REJECT-DROP", "PASS", "COMPATIBLE"];
+/// the rule sets sing-box publishes in place of the geoip and geosite databases it dropped
+const GEOIP_RULE_SETS: &str = "https://raw.githubusercontent.com/SagerNet/sing-geoip/rule-set";
+const GEOSITE_RULE_SETS: &str = "https://raw.githubusercontent.com/SagerNet/sing-geosite/rule-set";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SingBoxExport {
+    pub config: JsonValue,
+    /// what was left out or changed on the way, one line each
+    pub warnings: Vec<String>,
+}
+
+/// Export a mihomo config, normally the enhanced runtime one, as a sing-box config.
+pub fn export_config(config: &Mapping) -> SingBoxExport {
+    let mut warnings = Vec::new();
+
+    let mut nodes = Vec::new();
+    let mut endpoints = Vec::new();
+    let mut tags = HashSet::from([DIRECT_TAG.to_owned()]);
+    for proxy in sequence(config, "proxies").filter_map(Value::as_mapping) {
+        let name = proxy.get("name").and_then(Value::as_str).unwrap_or_default().to_owned();
+        if let Some(reason) = taken(&name, &tags) {
+            warnings.push(format!("proxy \"{name}\" is left out: {reason}"));
+            continue;
+        }
+        match export_proxy(proxy.clone(), &name) {
+            Ok((outbound, left)) => {
+                if !left.is_empty() {
+                    warnings.push(format!("proxy \"{name}\": left out {}", left.join(", ")));
+                }
+                if outbound["type"] == "wireguard" {
+                    endpoints.push(outbound);
+                } else {
+                    nodes.push(outbound);
+                }
+                tags.insert(name);
+            }
+            Err(reason) => warnings.push(format!("proxy \"{name}\" is left out: {reason}")),
+        }
+    }
+
+    let mut groups = Vec::new();
+    for group in sequence(config, "proxy-groups").filter_map(Value::as_mapping) {
+        let name = group.get("name").and_then(Value::as_str).unwrap_or_default();
+        match taken(name, &tags) {
+            Some(reason) => warnings.push(format!("group \"{name}\" is left out: {reason}")),
+            None => groups.push(group),
+        }
+    }
+    // a group may list a group defined after it, so every exported name is known up front
+    tags.extend(
+        groups
+            .iter()
+            .filter(|group| group_type(group).is_some())
+            .filter_map(|group| group.get("name").and_then(Value::as_str).map(str::to_owned)),
+    );
+    let mut outbounds = groups
+        .iter()
+        .filter_map(|group| export_group(group, &tags, &mut warnings))
+        .collect::<Vec<_>>();
+    outbounds.extend(nodes);
+    outbounds.push(json!({ "type": "direct", "tag": DIRECT_TAG }));
+
+    let route = export_route(config, &tags, &mut warnings);
+    if config
+        .get("dns")
+        .and_then(|dns| dns.get("enable"))
+        .and_then(Value::as_bool)
+        == Some(true)
+    {
+        warnings.push("the `dns` section is left out, sing-box resolves with the system DNS".into());
+    }
+
+    let mut export = JsonMap::new();
+    if let Some(inbound) = mixed_inbound(config) {
+        export.insert("inbounds".into(), json!([inbound]));
+    }
+    export.insert("outbounds".into(), outbounds.into());
+    if !endpoints.is_empty() {
+        export.insert("endpoints".into(), endpoints.into());
+    }
+    export.insert("route".into(), route);
+    SingBoxExport {
+        config: export.into(),
+        warnings,
+    }
+}
+
+/// Why `name` can not be the tag of an outbound of its own, `None` while it is free. A node or group
+/// shadowing a built-in would take over the references meant for the built-in.
+fn taken(name: &str, tags: &HashSet<String>) -> Option<&'static str> {
+    if BUILT_IN.contains(&name) {
+        Some("the name belongs to a built-in outbound")
+    } else if tags.contains(name) {
+        Some("the name is already used")
+    } else {
+        None
+    }
+}
+
+pub(super) fn sequence<'a>(config: &'a Mapping, key: &str) -> impl Iterator<Item = &'a Value> {
+    config.get(key).and_then(Value::as_sequence).into_iter().flatten()
+}
+
+/// The mixed port the runtime listens on, so the export can stand in for it.
+fn mixed_inbound(config: &Mapping) -> Option<JsonValue> {
+    let port = config
+        .get("mixed-port")
+        .and_then(Value::as_u64)
+        .filter(|port| *port > 0)?;
+    let allow_lan = config.get("allow-lan").and_then(Value::as_bool) == Some(true);
+    Some(json!({
+        "type": "mixed",
+        "tag": "mixed-in",
+        "listen": if allow_lan { "::" } else { "127.0.0.1" },
+        "listen_port": port,
+    }))
+}
+
+fn set(object: &mut JsonMap<String, JsonValue>, key: &str, value: Option<impl Into<JsonValue>>) {
+    if let Some(value) = value {
+        object.insert(key.into(), value.into());
+    }
+}
+
+fn set_list(object: &mut JsonMap<String, JsonValue>, key: &str, values: Vec<String>) {
+    if !values.is_empty() {
+        object.insert(key.into(), values.into());
+    }
+}
+
+/// The fields of one mihomo proxy, taken off as they are exported.
+pub(super) struct Node {
+    path: String,
+    map: Mapping,
+}
+
+impl Node {
+    pub(super) const fn new(path: String, map: Mapping) -> Self {
+        Self { path, map }
+    }
+
+    pub(super) fn take(&mut self, key: &str) -> Option<Value> {
+        self.map.remove(key)
+    }
+
+    pub(super) fn str(&mut self, key: &str) -> Option<String> {
+        match self.take(key)? {
+            Value::String(value) => Some(value).filter(|v| !v.is_empty()),
+            Value::Number(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    pub(super) fn u64(&mut self, key: &str) -> Option<u64> {
+        match self.take(key)? {
+            Value::Number(value) => value.as_u64(),
+            Value::String(value) => value.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub(super) fn bool(&mut self, key: &str) -> bool {
+        matches!(self.take(key), Some(Value::Bool(true)))
+    }
+
+    pub(super) fn strings(&mut self, key: &str) -> Vec<String> {
+        match self.take(key) {
+            Some(Value::String(value)) => value
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            Some(Value::Sequence(values)) => values
+                .iter()
+                .filter_map(|v| v.as_str().filter(|v| !v.is_empty()).map(str::to_owned))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub(super) fn mapping(&mut self, key: &str) -> Option<Self> {
+        match self.take(key)? {
+            Value::Mapping(map) => Some(Self::new(self.child_path(key), map)),
+            _ => None,
+        }
+    }
+
+    pub(super) fn child_path(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{}.{key}", self.path)
+        }
+    }
+
+    /// Report every field nobody took.
+    pub(super) fn finish(self, left: &mut Vec<String>) {
+        left.extend(
+            self.map
+                .keys()
+                .filter_map(Value::as_str)
+                .map(|key| format!("`{}`", self.child_path(key))),
+        );
+    }
+}
+
+/// The outbound or endpoint of a proxy and the settings left out of it, or why there is none.
+fn export_proxy(proxy: Mapping, name: &str) -> Result<(JsonValue, Vec<String>), String> {
+    let mut node = Node::new(String::new(), proxy);
+    let mut out = JsonMap::new();
+    let mut left = Vec::new();
+    let kind = node.str("type").unwrap_or_default();
+    node.take("name");
+    out.insert("tag".into(), name.into());
+
+    match kind.as_str() {
+        "ss" => shadowsocks(&mut node, &mut out, &mut left)?,
+        "vmess" => vmess(&mut node, &mut out, &mut left)?,
+        "vless" => vless(&mut node, &mut out, &mut left)?,
+        "trojan" => trojan(&mut node, &mut out, &mut left)?,
+        "hysteria2" => hysteria2(&mut node, &mut out, &mut left)?,
+        "tuic" => tuic(&mut node, &mut out, &mut left)?,
+        "wireguard" => wireguard(&mut node, &mut out, &mut left)?,
+        "socks5" | "http" => socks_or_http(&kind, &mut node, &mut out, &mut left)?,
+        other => return Err(format!("sing-box has no \"{other}\" outbound")),
+    }
+    export_dial(&mut node, &mut out, &mut left);
+    // sing-box relays UDP whenever the protocol can
+    node.take("udp");
+    node.finish(&mut left);
+    Ok((out.into(), left))
+}
+
+fn server(node: &mut Node, out: &mut JsonMap<String, JsonValue>, kind: &str) -> Result<(), String> {
+    let server = node.str("server").ok_or("no `server`")?;
+    let port = node.u64("port").ok_or("no `port`")?;
+    out.insert("type".into(), kind.into());
+    out.insert("server".into(), server.into());
+    out.insert("server_port".into(), port.into());
+    Ok(())
+}
+
+fn required(node: &mut Node, key: &str) -> Result<String, String> {
+    node.str(key).ok_or_else(|| format!("no `{key}`"))
+}
+
+/// `udp: false` is the only way mihomo limits a node to one network.
+fn export_network(node: &Node, out: &mut JsonMap<String, JsonValue>) {
+    if node.map.get("udp").and_then(Value::as_bool) == Some(false) {
+        out.insert("network".into(), "tcp".into());
+    }
+}
+
+fn shadowsocks(node: &mut Node, out: &mut JsonMap<String, JsonValue>, left: &mut Vec<String>) -> Result<(), String> {
+    server(node, out, "shadowsocks")?;
+    out.insert("method".into(), required(node, "cipher")?.into());
+    out.insert("password".into(), required(node, "password")?.into());
+    if node.bool("udp-over-tcp") {
+        let version = node.u64("udp-over-tcp-version").unwrap_or(1);
+        out.insert("udp_over_tcp".into(), json!({ "enabled": true, "version": version }));
+    }
+    if let Some(plugin) = node.str("plugin") {
+        let mut opts = node
+            .mapping("plugin-opts")
+            .unwrap_or_else(|| Node::new(node.child_path("plugin-opts"), Mapping::new()));
+        let (plugin, opts) = match plugin.as_str() {
+            "obfs" => {
+                let mut parts = vec![format!("obfs={}", opts.str("mode").unwrap_or_else(|| "http".into()))];
+                parts.extend(opts.str("host").map(|host| format!("obfs-host={host}")));
+                opts.finish(left);
+                ("obfs-local", parts.join(";"))
+            }
+            "v2ray-plugin" => {
+                let mut parts = vec![format!(
+                    "mode={}",
+                    opts.str("mode").unwrap_or_else(|| "websocket".into())
+                )];
+                if opts.bool("tls") {
+                    parts.push("tls".into());
+                }
+                parts.extend(opts.str("host").map(|host| format!("host={host}")));
+                parts.extend(opts.str("path").map(|path| format!("path={path}")));
+                if opts.bool("mux") {
+                    parts.push("mux=1".into());
+                }
+                opts.finish(left);
+                ("v2ray-plugin", parts.join(";"))
+            }
+            other => return Err(format!("sing-box has no \"{other}\" plugin")),
+        };
+        out.insert("plugin".into(), plugin.into());
+        out.insert("plugin_opts".into(), opts.into());
+    }
+    export_network(node, out);
+    export_multiplex(node, out, left);
+    Ok(())
+}
+
+fn vmess(node: &mut Node, out: &mut JsonMap<String, JsonValue>, left: &mut Vec<String>) -> Result<(), String> {
+    server(node, out, "vmess")?;
+    out.insert("uuid".into(), required(node, "uuid")?.into());
+    out.insert(
+        "security".into(),
+        node.str("cipher").unwrap_or_else(|| "auto".into()).into(),
+    );
+    set(out, "alter_id", node.u64("alterId").filter(|id| *id > 0));
+    if node.bool("global-padding") {
+        out.insert("global_padding".into(), true.into());
+    }
+    if node.bool("authenticated-length") {
+        out.insert("authenticated_length".into(), true.into());
+    }
+    export_packet_encoding(node, out);
+    export_network(node, out);
+    let tls = node.bool("tls");
+    export_tls(node, out, tls, left);
+    export_transport(node, out, left)?;
+    export_multiplex(node, out, left);
+    Ok(())
+}
+
+fn vless(node: &mut Node, out: &mut JsonMap<String, JsonValue>, left: &mut Vec<String>) -> Result<(), String> {
+    server(node, out, "vless")?;
+    out.insert("uuid".into(), required(node, "uuid")?.into());
+    set(out, "flow", node.str("flow"));
+    export_packet_encoding(node, out);
+    export_network(node, out);
+    let tls = node.bool("tls");
+    export_tls(node, out, tls, left);
+    export_transport(node, out, left)?;
+    export_multiplex(node, out, left);
+    Ok(())
+}
+
+fn export_packet_encoding(node: &mut Node, out: &mut JsonMap<String, JsonValue>) {
+    let encoding = node.str("packet-encoding");
+    if node.bool("xudp") {
+        out.insert("packet_encoding".into(), "xudp".into());
+    } else {
+        set(out, "packet_encoding", encoding);
+    }
+}
+
+fn trojan(node: &mut Node, out: &mut JsonMap<String, JsonValue>, left: &mut Vec<String>) -> Result<(), String> {
+    server(node, out, "trojan")?;
+    out.insert("password".into(), required(node, "password")?.into());
+    export_network(node, out);
+    export_tls(node, out, true, left);
+    export_transport(node, out, left)?;
+    export_multiplex(node, out, left);
+    Ok(())
+}
+
+fn hysteria2(node: &mut Node, out: &mut JsonMap<String, JsonValue>, left: &mut Vec<String>) -> Result<(), String> {
+    server(node, out, "hysteria2")?;
+    set(out, "password", node.str("password"));
+    // mihomo spells port ranges `a-b`, sing-box `a:b`
+    let ports = node
+        .strings("ports")
+        .iter()
+        .flat_map(|ports| {
+            ports
+                .split('/')
+                .map(|range| range.replace('-', ":"))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    set_list(out, "server_ports", ports);
+    set(out, "hop_interval", node.u64("hop-interval").map(|s| format!("{s}s")));
+    for (from, to) in [("up", "up_mbps"), ("down", "down_mbps")] {
+        let path = node.child_path(from);
+        match node.str(from).map(|speed| mbps(&speed)) {
+            Some(Some(speed)) => {
+                out.insert(to.into(), speed.into());
+            }
+            Some(None) => left.push(format!("`{path}`")),
+            None => {}
+        }
+    }
+    if let Some(kind) = node.str("obfs") {
+        out.insert(
+            "obfs".into(),
+            json!({ "type": kind, "password": node.str("obfs-password").unwrap_or_default() }),
+        );
+    }
+    export_network(node, out);
+    export_tls(node, out, true, left);
+    Ok(())
+}
+
+/// Mbps of a mihomo bandwidth, which is either a bare number of Mbps or has a unit.
+fn mbps(speed: &str) -> Option<u64> {
+    let speed = speed.trim();
+    let digits = speed.find(|c: char| !c.is_ascii_digit()).unwrap_or(speed.len());
+    let value = speed[..digits].parse::<u64>().ok()?;
+    match speed[digits..].trim().to_ascii_lowercase().as_str() {
+        "" | "m" | "mbps" => Some(value),
+        "g" | "gbps" => Some(value * 1000),
+        _ => None,
+    }
+}
+
+fn tuic(node: &mut Node, out: &mut JsonMap<String, JsonValue>, left: &mut Vec<String>) -> Result<(), String> {
+    server(node, out, "tuic")?;
+    out.insert("uuid".into(), required(node, "uuid")?.into());
+    set(out, "password", node.str("password"));
+    set(out, "congestion_control", node.str("congestion-controller"));
+    set(out, "udp_relay_mode", node.str("udp-relay-mode"));
+    if node.bool("udp-over-stream") {
+        out.insert("udp_over_stream".into(), true.into());
+    }
+    if node.bool("reduce-rtt") {
+        out.insert("zero_rtt_handshake".into(), true.into());
+    }
+    set(
+        out,
+        "heartbeat",
+        node.u64("heartbeat-interval").map(|ms| format!("{ms}ms")),
+    );
+    export_network(node, out);
+    export_tls(node, out, true, left);
+    Ok(())
+}
+
+/// An endpoint, since sing-box 1.11 deprecated the WireGuard outbound.
+fn wireguard(node: &mut Node, out: &mut JsonMap<String, JsonValue>, left: &mut Vec<String>) -> Result<(), String> {
+    out.insert("type".into(), "wireguard".into());
+    let mut addresses = Vec::new();
+    for (key, prefix) in [("ip", "/32"), ("ipv6", "/128")] {
+        if let Some(ip) = node.str(key) {
+            addresses.push(if ip.contains('/') { ip } else { format!("{ip}{prefix}") });
+        }
+    }
+    set_list(out, "address", addresses);
+    out.insert("private_key".into(), required(node, "private-key")?.into());
+    set(out, "mtu", node.u64("mtu"));
+
+    let mut peers = match node.take("peers") {
+        Some(Value::Sequence(peers)) => peers
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, peer)| match peer {
+                Value::Mapping(peer) => Some(Node::new(node.child_path(&format!("peers[{i}]")), peer)),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    // without `peers`, the one peer is described by the node's own fields
+    if peers.is_empty() {
+        let mut own = Node::new(String::new(), Mapping::new());
+        for key in [
+            "server",
+            "port",
+            "public-key",
+            "pre-shared-key",
+            "reserved",
+            "allowed-ips",
+        ] {
+            if let Some(value) = node.take(key) {
+                own.map.insert(key.into(), value);
+            }
+        }
+        peers.push(own);
+    }
+    let peers = peers
+        .into_iter()
+        .map(|peer| export_wireguard_peer(peer, left))
+        .collect::<Result<Vec<_>, _>>()?;
+    out.insert("peers".into(), peers.into());
+    Ok(())
+}
+
+fn export_wireguard_peer(mut peer: Node, left: &mut Vec<String>) -> Result<JsonValue, String> {
+    let mut out = JsonMap::new();
+    out.insert("address".into(), required(&mut peer, "server")?.into());
+    out.insert("port".into(), peer.u64("port").ok_or("no `port`")?.into());
+    out.insert("public_key".into(), required(&mut peer, "public-key")?.into());
+    set(&mut out, "pre_shared_key", peer.str("pre-shared-key"));
+    let allowed_ips = peer.strings("allowed-ips");
+    out.insert(
+        "allowed_ips".into(),
+        if allowed_ips.is_empty() {
+            json!(["0.0.0.0/0", "::/0"])
+        } else {
+            allowed_ips.into()
+        },
+    );
+    // mihomo takes the three reserved bytes as a list or in base64
+    let reserved = match peer.take("reserved") {
+        Some(Value::Sequence(bytes)) => Some(bytes.iter().filter_map(Value::as_u64).collect::<Vec<_>>()),
+        Some(Value::String(encoded)) => general_purpose::STANDARD
+            .decode(encoded.trim())
+            .ok()
+            .map(|bytes| bytes.into_iter().map(u64::from).collect()),
+        _ => None,
+    };
+    set(&mut out, "reserved", reserved);
+    peer.finish(left);
+    Ok(out.into())
+}
+
+fn socks_or_http(
+    kind: &str,
+    node: &mut Node,
+    out: &mut JsonMap<String, JsonValue>,
+    left: &mut Vec<String>,
+) -> Result<(), String> {
+    server(node, out, if kind == "socks5" { "socks" } else { "http" })?;
+    set(out, "username", node.str("username"));
+    set(out, "password", node.str("password"));
+    if kind == "http" {
+        let tls = node.bool("tls");
+        export_tls(node, out, tls, left);
+    } else {
+        export_network(node, out);
+        if node.bool("tls") {
+            left.push("`tls`".into());
+        }
+    }
+    Ok(())
+}
+
+fn export_tls(node: &mut Node, out: &mut JsonMap<String, JsonValue>, enabled: bool, left: &mut Vec<String>) {
+    // taken either way, so a TLS name on a plain node is not reported as lost
+    let server_name = node.str("servername").or_else(|| node.str("sni"));
+    let insecure = node.bool("skip-cert-verify");
+    let alpn = node.strings("alpn");
+    let fingerprint = node.str("client-fingerprint");
+    let reality = node.mapping("reality-opts");
+    let disable_sni = node.bool("disable-sni");
+    if !enabled {
+        return;
+    }
+
+    let mut tls = JsonMap::new();
+    tls.insert("enabled".into(), true.into());
+    set(&mut tls, "server_name", server_name);
+    if insecure {
+        tls.insert("insecure".into(), true.into());
+    }
+    if disable_sni {
+        tls.insert("disable_sni".into(), true.into());
+    }
+    set_list(&mut tls, "alpn", alpn);
+    // REALITY only works through uTLS in sing-box
+    let fingerprint = fingerprint.or_else(|| reality.as_ref().map(|_| "chrome".into()));
+    if let Some(fingerprint) = fingerprint {
+        tls.insert("utls".into(), json!({ "enabled": true, "fingerprint": fingerprint }));
+    }
+    if let Some(mut reality) = reality {
+        tls.insert(
+            "reality".into(),
+            json!({
+                "enabled": true,
+                "public_key": reality.str("public-key").unwrap_or_default(),
+                "short_id": reality.str("short-id").unwrap_or_default(),
+            }),
+        );
+        reality.finish(left);
+    }
+    out.insert("tls".into(), tls.into());
+}
+
+/// A network sing-box has no transport for (kcp, for one) leaves the node unusable.
+fn export_transport(
+    node: &mut Node,
+    out: &mut JsonMap<String, JsonValue>,
+    left: &mut Vec<String>,
+) -> Result<(), String> {
+    let network = node.str("network").unwrap_or_else(|| "tcp".into());
+    let transport = match network.as_str() {
+        "tcp" => return Ok(()),
+        "ws" => node.mapping("ws-opts").map(|opts| ws_transport(opts, left)),
+        "grpc" => node.mapping("grpc-opts").map(|mut opts| {
+            let service_name = opts.str("grpc-service-name");
+            opts.finish(left);
+            json!({ "type": "grpc", "service_name": service_name.unwrap_or_default() })
+        }),
+        "h2" => node.mapping("h2-opts").map(|mut opts| {
+            let mut transport = JsonMap::new();
+            transport.insert("type".into(), "http".into());
+            set_list(&mut transport, "host", opts.strings("host"));
+            set(&mut transport, "path", opts.str("path"));
+            opts.finish(left);
+            transport.into()
+        }),
+        "http" => node.mapping("http-opts").map(|opts| http_transport(opts, left)),
+        other => return Err(format!("sing-box has no \"{other}\" transport")),
+    };
+    let transport = transport.unwrap_or_else(|| {
+        let kind = if network == "h2" { "http" } else { network.as_str() };
+        json!({ "type": kind })
+    });
+    out.insert("transport".into(), transport);
+    Ok(())
+}
+
+fn ws_transport(mut opts: Node, left: &mut Vec<String>) -> JsonValue {
+    let path = opts.str("path");
+    let mut headers = opts.mapping("headers");
+    let host = headers.as_mut().and_then(|headers| headers.str("Host"));
+    if let Some(headers) = headers {
+        headers.finish(left);
+    }
+
+    let mut transport = JsonMap::new();
+    if opts.bool("v2ray-http-upgrade") {
+        transport.insert("type".into(), "httpupgrade".into());
+        set(&mut transport, "host", host);
+    } else {
+        transport.insert("type".into(), "ws".into());
+        set(&mut transport, "headers", host.map(|host| json!({ "Host": host })));
+        set(&mut transport, "max_early_data", opts.u64("max-early-data"));
+        set(
+            &mut transport,
+            "early_data_header_name",
+            opts.str("early-data-header-name"),
+        );
+    }
+    set(&mut transport, "path", path);
+    opts.finish(left);
+    transport.into()
+}
+
+fn http_transport(mut opts: Node, left: &mut Vec<String>) -> JsonValue {
+    let mut transport = JsonMap::new();
+    transport.insert("type".into(), "http".into());
+    set(&mut transport, "method", opts.str("method"));
+    // sing-box sends one path, mihomo picks one of several at random
+    set(&mut transport, "path", opts.strings("path").into_iter().next());
+    if let Some(mut headers) = opts.mapping("headers") {
+        set_list(&mut transport, "host", headers.strings("Host"));
+        headers.finish(left);
+    }
+    opts.finish(left);
+    transport.into()
+}
+
+fn export_multiplex(node: &mut Node, out: &mut JsonMap<String, JsonValue>, left: &mut Vec<String>) {
+    let Some(mut smux) = node.mapping("smux") else {
+        return;
+    };
+    if smux.bool("enabled") {
+        let mut multiplex = JsonMap::new();
+        multiplex.insert("enabled".into(), true.into());
+        set(&mut multiplex, "protocol", smux.str("protocol"));
+        set(&mut multiplex, "max_connections", smux.u64("max-connections"));
+        set(&mut multiplex, "min_streams", smux.u64("min-streams"));
+        set(&mut multiplex, "max_streams", smux.u64("max-streams"));
+        if smux.bool("padding") {
+            multiplex.insert("padding".into(), true.into());
+        }
+        out.insert("multiplex".into(), multiplex.into());
+        smux.finish(left);
+    }
+}
+
+/// The dial fields every outbound shares.
+fn export_dial(node: &mut Node, out: &mut JsonMap<String, JsonValue>, left: &mut Vec<String>) {
+    set(out, "detour", node.str("dialer-proxy"));
+    set(out, "bind_interface", node.str("interface-name"));
+    set(out, "routing_mark", node.u64("routing-mark"));
+    if node.bool("tfo") {
+        out.insert("tcp_fast_open".into(), true.into());
+    }
+    if node.bool("mptcp") {
+        out.insert("tcp_multi_path".into(), true.into());
+    }
+    let path = node.child_path("ip-version");
+    match node.str("ip-version").as_deref() {
+        None | Some("dual") => {}
+        Some("ipv4") => set(out, "domain_strategy", Some("ipv4_only")),
+        Some("ipv6") => set(out, "domain_strategy", Some("ipv6_only")),
+        Some("ipv4-prefer") => set(out, "domain_strategy", Some("prefer_ipv4")),
+        Some("ipv6-prefer") => set(out, "domain_strategy", Some("prefer_ipv6")),
+        Some(_) => left.push(format!("`{path}`")),
+    }
+}
+
+/// The sing-box type a group is exported as, `None` when it has none.
+fn group_type(group: &Mapping) -> Option<&'static str> {
+    match group.get("type").and_then(Value::as_str)? {
+        "select" => Some("selector"),
+        "url-test" | "fallback" | "load-balance" => Some("urltest"),
+        _ => None,
+    }
+}
+
+fn export_group(group: &Mapping, tags: &HashSet<String>, warnings: &mut Vec<String>) -> Option<JsonValue> {
+    let name = group.get("name").and_then(Value::as_str).unwrap_or_default();
+    let kind = group.get("type").and_then(Value::as_str).unwrap_or_default();
+    let Some(sing_box_type) = group_type(group) else {
+        warnings.push(format!(
+            "group \"{name}\" is left out: sing-box has no \"{kind}\" group"
+        ));
+        return None;
+    };
+    if matches!(kind, "fallback" | "load-balance") {
+        warnings.push(format!("group \"{name}\": \"{kind}\" is exported as urltest"));
+    }
+    if ["use", "include-all", "include-all-proxies", "include-all-providers"]
+        .iter()
+        .any(|key| group.get(*key).is_some_and(|value| value != &Value::Bool(false)))
+    {
+        warnings.push(format!("group \"{name}\": the nodes of proxy providers are left out"));
+    }
+
+    let mut members = Vec::new();
+    for member in group
+        .get("proxies")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        if tags.contains(member) {
+            members.push(member.to_owned());
+        } else {
+            warnings.push(format!("group \"{name}\": member \"{member}\" is left out"));
+        }
+    }
+    if members.is_empty() {
+        warnings.push(format!(
+            "group \"{name}\" has no member left and falls back to {DIRECT_TAG}"
+        ));
+        members.push(DIRECT_TAG.to_owned());
+    }
+
+    let mut outbound = JsonMap::new();
+    outbound.insert("type".into(), sing_box_type.into());
+    outbound.insert("tag".into(), name.into());
+    outbound.insert("outbounds".into(), members.into());
+    if sing_box_type == "urltest" {
+        set(&mut outbound, "url", group.get("url").and_then(Value::as_str));
+        set(
+            &mut outbound,
+            "interval",
+            group.get("interval").and_then(Value::as_u64).map(|s| format!("{s}s")),
+        );
+        set(
+            &mut outbound,
+            "tolerance",
+            group.get("tolerance").and_then(Value::as_u64),
+        );
+    }
+    Some(outbound.into())
+}
+
+fn export_route(config: &Mapping, tags: &HashSet<String>, warnings: &mut Vec<String>) -> JsonValue {
+    let mut rules = Vec::new();
+    let mut rule_sets = BTreeSet::new();
+    let mut final_outbound = None;
+    for rule in sequence(config, "rules").filter_map(Value::as_str) {
+        match export_rule(rule, tags, &mut rule_sets) {
+            Ok(RouteRule::Final(target)) => final_outbound = Some(target),
+            Ok(RouteRule::Rule(rule)) => rules.push(rule),
+            Err(reason) => warnings.push(format!("rule \"{rule}\" is left out: {reason}")),
+        }
+    }
+
+    let mut route = JsonMap::new();
+    route.insert("rules".into(), rules.into());
+    if !rule_sets.is_empty() {
+        let rule_sets = rule_sets
+            .into_iter()
+            .map(|tag: String| {
+                let base = if tag.starts_with("geoip-") {
+                    GEOIP_RULE_SETS
+                } else {
+                    GEOSITE_RULE_SETS
+                };
+                json!({ "type": "remote", "tag": tag, "format": "binary", "url": format!("{base}/{tag}.srs") })
+            })
+            .collect::<Vec<_>>();
+        route.insert("rule_set".into(), rule_sets.into());
+    }
+    set(&mut route, "final", final_outbound);
+    route.insert("auto_detect_interface".into(), true.into());
+    route.into()
+}
+
+enum RouteRule {
+    Rule(JsonValue),
+    /// `MATCH`, which sing-box writes as the route's `final`
+    Final(String),
+}
+
+fn export_rule(rule: &str, tags: &HashSet<String>, rule_sets: &mut BTreeSet<String>) -> Result<RouteRule, String> {
+    let parts = rule.split(',').map(str::trim).collect::<Vec<_>>();
+    let kind = parts.first().map(|kind| kind.to_ascii_uppercase()).unwrap_or_default();
+    if matches!(kind.as_str(), "MATCH" | "FINAL") {
+        let target = parts.get(1).copied().unwrap_or_default();
+        return if tags.contains(target) {
+            Ok(RouteRule::Final(target.to_owned()))
+        } else {
+            Err(format!("\"{target}\" is not exported"))
+        };
+    }
+    let (Some(payload), Some(target)) = (parts.get(1), parts.get(2)) else {
+        return Err(format!("sing-box has no \"{kind}\" rule"));
+    };
+
+    let mut out = JsonMap::new();
+    match kind.as_str() {
+        "DOMAIN" => set(&mut out, "domain", Some(*payload)),
+        "DOMAIN-SUFFIX" => set(&mut out, "domain_suffix", Some(*payload)),
+        "DOMAIN-KEYWORD" => set(&mut out, "domain_keyword", Some(*payload)),
+        "DOMAIN-REGEX" => set(&mut out, "domain_regex", Some(*payload)),
+        "IP-CIDR" | "IP-CIDR6" => set(&mut out, "ip_cidr", Some(*payload)),
+        "SRC-IP-CIDR" => set(&mut out, "source_ip_cidr", Some(*payload)),
+        "DST-PORT" => export_ports(&mut out, "port", payload)?,
+        "SRC-PORT" => export_ports(&mut out, "source_port", payload)?,
+        "PROCESS-NAME" => set(&mut out, "process_name", Some(*payload)),
+        "PROCESS-PATH" => set(&mut out, "process_path", Some(*payload)),
+        "PROCESS-PATH-REGEX" => set(&mut out, "process_path_regex", Some(*payload)),
+        "NETWORK" => set(&mut out, "network", Some(payload.to_ascii_lowercase())),
+        "GEOIP" if payload.eq_ignore_ascii_case("lan") || payload.eq_ignore_ascii_case("private") => {
+            out.insert("ip_is_private".into(), true.into());
+        }
+        "GEOIP" | "GEOSITE" => {
+            let tag = format!("{}-{}", kind.to_ascii_lowercase(), payload.to_ascii_lowercase());
+            out.insert("rule_set".into(), tag.clone().into());
+            rule_sets.insert(tag);
+        }
+        _ => return Err(format!("sing-box has no \"{kind}\" rule")),
+    }
+
+    match *target {
+        "REJECT" => set(&mut out, "action", Some("reject")),
+        "REJECT-DROP" => {
+            out.insert("action".into(), "reject".into());
+            out.insert("method".into(), "drop".into());
+        }
+        target if tags.contains(target) => {
+            out.insert("action".into(), "route".into());
+            out.insert("outbound".into(), target.into());
+        }
+        target => return Err(format!("\"{target}\" is not exported")),
+    }
+    Ok(RouteRule::Rule(out.into()))
+}
+
+/// `443/8000-9000` as sing-box `port` and `port_range` lists.
+fn export_ports(out: &mut JsonMap<String, JsonValue>, key: &str, payload: &str) -> Result<(), String> {
+    let mut ports = Vec::new();
+    let mut ranges = Vec::new();
+    for part in payload.split('/').map(str::trim) {
+        if let Some((start, end)) = part.split_once('-') {
+            ranges.push(format!("{}:{}", start.trim(), end.trim()));
+        } else {
+            ports.push(part.parse::<u16>().map_err(|_| format!("port \"{part}\""))?);
+        }
+    }
+    if !ports.is_empty() {
+        out.insert(key.into(), ports.into());
+    }
+    set_list(out, &format!("{key}_range"), ranges);
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::export_config;
+    use serde_json::json;
+    use serde_yaml_ng::Mapping;
+
+    fn config(yaml: &str) -> Mapping {
+        serde_yaml_ng::from_str(yaml).expect("fixture should parse")
+    }
+
+    #[test]
+    fn nodes_and_groups_become_outbounds() {
+        let export = export_config(&config(
+            r"
+mixed-port: 7897
+proxies:
+  - {name: ss, type: ss, server: s.example.com, port: 8388, cipher: aes-128-gcm, password: pw, udp: true}
+  - name: reality
+    type: vless
+    server: r.example.com
+    port: 443
+    uuid: id
+    flow: xtls-rprx-vision
+    tls: true
+    servername: www.example.com
+    reality-opts: {public-key: KEY, short-id: ab}
+    network: ws
+    ws-opts: {path: /ray, headers: {Host: cdn.example.com}}
+  - {name: wg, type: wireguard, server: w.example.com, port: 51820, ip: 172.16.0.2, private-key: priv, public-key: pub, reserved: [1, 2, 3]}
+  - {name: snell, type: snell, server: n.example.com, port: 443, psk: k}
+proxy-groups:
+  - {name: PROXY, type: select, proxies: [auto, ss, snell, DIRECT]}
+  - {name: auto, type: url-test, proxies: [ss, reality, wg], url: 'https://www.gstatic.com/generate_204', interval: 300}
+  - {name: chain, type: relay, proxies: [ss, reality]}
+",
+        ));
+        let config = &export.config;
+
+        assert_eq!(config["inbounds"][0]["listen_port"], 7897);
+        let outbounds = config["outbounds"].as_array().expect("outbounds");
+        let tags = outbounds
+            .iter()
+            .map(|o| o["tag"].as_str().unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(tags, vec!["PROXY", "auto", "ss", "reality", "DIRECT"]);
+        assert_eq!(outbounds[0]["outbounds"], json!(["auto", "ss", "DIRECT"]));
+        assert_eq!(outbounds[1]["type"], "urltest");
+        assert_eq!(outbounds[1]["interval"], "300s");
+
+        let reality = &outbounds[3];
+        assert_eq!(reality["tls"]["server_name"], "www.example.com");
+        assert_eq!(reality["tls"]["reality"]["public_key"], "KEY");
+        assert_eq!(reality["tls"]["utls"]["fingerprint"], "chrome");
+        assert_eq!(
+            reality["transport"],
+            json!({"type": "ws", "path": "/ray", "headers": {"Host": "cdn.example.com"}})
+        );
+
+        let wg = &config["endpoints"][0];
+        assert_eq!(wg["address"], json!(["172.16.0.2/32"]));
+        assert_eq!(wg["peers"][0]["address"], "w.example.com");
+        assert_eq!(wg["peers"][0]["reserved"], json!([1, 2, 3]));
+
+        assert_eq!(
+            export.warnings,
+            vec![
+                "proxy \"snell\" is left out: sing-box has no \"snell\" outbound",
+                "group \"PROXY\": member \"snell\" is left out",
+                "group \"chain\" is left out: sing-box has no \"relay\" group",
+            ]
+        );
+    }
+
+    #[test]
+    fn rules_become_route_rules() {
+        let export = export_config(&config(
+            r"
+proxies:
+  - {name: ss, type: ss, server: s.example.com, port: 8388, cipher: aes-128-gcm, password: pw}
+proxy-groups:
+  - {name: PROXY, type: select, proxies: [ss]}
+rules:
+  - DOMAIN-SUFFIX,ads.example.com,REJECT
+  - DST-PORT,443/8000-9000,PROXY
+  - GEOIP,LAN,DIRECT,no-resolve
+  - GEOSITE,CN,DIRECT
+  - RULE-SET,private,DIRECT
+  - DOMAIN,gone.example.com,missing
+  - MATCH,PROXY
+",
+        ));
+        let route = &export.config["route"];
+
+        assert_eq!(
+            route["rules"],
+            json!([
+                {"domain_suffix": "ads.example.com", "action": "reject"},
+                {"port": [443], "port_range": ["8000:9000"], "action": "route", "outbound": "PROXY"},
+                {"ip_is_private": true, "action": "route", "outbound": "DIRECT"},
+                {"rule_set": "geosite-cn", "action": "route", "outbound": "DIRECT"},
+            ])
+        );
+        assert_eq!(route["rule_set"][0]["tag"], "geosite-cn");
+        assert_eq!(route["final"], "PROXY");
+        assert_eq!(
+            export.warnings,
+            vec![
+                "rule \"RULE-SET,private,DIRECT\" is left out: sing-box has no \"RULE-SET\" rule",
+                "rule \"DOMAIN,gone.example.com,missing\" is left out: \"missing\" is not exported",
+            ]
+        );
+    }
+
+    #[test]
+    fn names_taken_by_built_ins_or_earlier_outbounds_are_left_out() {
+        let export = export_config(&config(
+            r"
+proxies:
+  - {name: DIRECT, type: ss, server: d.example.com, port: 8388, cipher: aes-128-gcm, password: pw}
+  - {name: REJECT, type: ss, server: r.example.com, port: 8388, cipher: aes-128-gcm, password: pw}
+  - {name: ss, type: ss, server: s.example.com, port: 8388, cipher: aes-128-gcm, password: pw}
+  - {name: ss, type: ss, server: t.example.com, port: 8388, cipher: aes-128-gcm, password: pw}
+proxy-groups:
+  - {name: PROXY, type: select, proxies: [ss, DIRECT]}
+  - {name: ss, type: select, proxies: [DIRECT]}
+",
+        ));
+        let outbounds = export.config["outbounds"].as_array().expect("outbounds");
+        let tags = outbounds
+            .iter()
+            .map(|o| o["tag"].as_str().unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(tags, vec!["PROXY", "ss", "DIRECT"]);
+        assert_eq!(outbounds[1]["server"], "s.example.com");
+        assert_eq!(outbounds[2]["type"], "direct");
+        assert_eq!(
+            export.warnings,
+            vec![
+                "proxy \"DIRECT\" is left out: the name belongs to a built-in outbound",
+                "proxy \"REJECT\" is left out: the name belongs to a built-in outbound",
+                "proxy \"ss\" is left out: the name is already used",
+                "group \"ss\" is left out: the name is already used",
+            ]
+        );
+    }
+
+    #[test]
+    fn settings_without_an_equivalent_are_listed() {
+        let export = export_config(&config(
+            r"
+proxies:
+  - {name: kcp, type: vmess, server: k.example.com, port: 443, uuid: id, network: kcp}
+  - {name: pinned, type: trojan, server: t.example.com, port: 443, password: pw, sni: t.example.com, fingerprint: abcd, ip-version: dual}
+  - {name: hy, type: hysteria2, server: h.example.com, port: 443, ports: 20000-30000, up: 100 Kbps, down: 200}
+",
+        ));
+
+        assert_eq!(export.config["outbounds"][1]["down_mbps"], 200);
+        assert_eq!(export.config["outbounds"][1]["server_ports"], json!(["20000:30000"]));
+        assert_eq!(
+            export.warnings,
+            vec![
+                "proxy \"kcp\" is left out: sing-box has no \"kcp\" transport",
+                "proxy \"pinned\": left out `fingerprint`",
+                "proxy \"hy\": left out `up`",
+            ]
+        );
+    }
+}
@@ -80,6 +80,14 @@ impl Handle {
         Self::send_event(FrontendEvent::ProfileUpdateCompleted { uid });
     }
 
+    /// Report one profile of an "update all" batch.
+    pub fn notify_profile_batch_progress(progress: &crate::feat::BatchProgress) {
+        let Ok(progress) = serde_json::to_value(progress) else {
+            return;
+        };
+        Self::send_event(FrontendEvent::ProfileBatchProgress { progress });
+    }
+
     pub fn notice_message<S: AsRef<str>, M: Into<String>>(status: S, msg: M) {
         let status_str = status.as_ref();
         let msg_str = msg.into();
@@ -1,4 +1,7 @@
-use super::{CoreManager, PROFILE_SELECTIONS_PENDING_COMMIT, RunningMode};
+use super::{
+    CoreManager, PROFILE_SELECTIONS_PENDING_COMMIT, RunningMode,
+    last_good::{falls_back_to_last_good, remember_last_good},
+};
 use crate::core::service::StageRequest;
 use crate::{
     config::{Config, ConfigType, IProfiles, runtime::IRuntime},
@@ -178,6 +181,7 @@ impl CoreManager {
                 config: Some(clash_config.to_owned()),
                 exists_keys: HashSet::new(),
                 chain_logs: Default::default(),
+                script_errors: Vec::new(),
             }
         });
 
@@ -330,15 +334,32 @@ impl CoreManager {
     ///
     /// Takes the transaction rather than opening one, so it covers the staging its callers did.
     /// Every way out of here other than the last line rolls that staging back.
+    ///
+    /// A rejection the core would also have raised — see [`falls_back_to_last_good`] — leaves the
+    /// previous config running and is recorded against the chain items it is blamed on.
     async fn validate_and_apply(&self, transaction: DraftTransaction<'_>) -> Result<ValidationOutcome> {
-        let outcome = CoreConfigValidator::global().validate_config_outcome().await?;
+        let mut outcome = CoreConfigValidator::global().validate_config_outcome().await?;
+        if !outcome.is_valid() {
+            // 核心拒绝的配置若由出错的脚本产生，把脚本错误一并带给前端
+            let script_error = Config::runtime().await.latest_arc().script_errors.first().cloned();
+            outcome = outcome.with_script_error(script_error);
+        }
+        if let ValidationOutcome::Invalid { kind, message, .. } = &outcome
+            && falls_back_to_last_good(*kind)
+        {
+            let rejected_logs = Config::runtime().await.latest_arc().chain_logs.clone();
+            transaction.rollback();
+            self.keep_last_good(*kind, message, &rejected_logs).await;
+            return Ok(outcome);
+        }
         if !outcome.is_valid() {
             return Ok(outcome);
         }
 
         let run_path = Config::generate_file(ConfigType::Run).await?;
-        self.apply_config(run_path).await?;
+        self.apply_config(run_path.clone()).await?;
         transaction.commit();
+        remember_last_good(&run_path).await;
         Ok(ValidationOutcome::Valid)
     }
 
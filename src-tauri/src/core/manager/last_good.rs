@@ -0,0 +1,263 @@
+//! The last runtime config the core accepted, and what happens when a new one is rejected.
+//!
+//! Every config the core takes is copied next to the runtime file. When a freshly enhanced config
+//! is rejected, the core simply keeps what it was running — nothing has been handed to it yet —
+//! and the rejection is pinned on the chain items that most likely caused it, so the profile page
+//! points at the culprit instead of only reporting a failed switch. At startup, with nothing
+//! running yet, the copy on disk stands in where the minimal default config used to.
+
+use super::CoreManager;
+use crate::{
+    config::{Config, IProfiles, runtime::IRuntime},
+    constants::files::{LAST_GOOD_CONFIG, RUNTIME_CONFIG},
+    core::{handle, validate::ValidationErrorKind},
+    utils::{dirs, help},
+};
+use anyhow::Result;
+use clash_verge_logging::{Type, logging};
+use regex::Regex;
+use smartstring::alias::String;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::LazyLock,
+};
+
+type ChainLogs = HashMap<String, Vec<(String, String)>>;
+
+/// Names the core quotes in its errors: `'HK 01'`, `"PROXY"`, `[MATCH,PROXY]`.
+#[allow(clippy::unwrap_used)]
+static QUOTED_NAME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"'([^']+)'|"([^"]+)"|\[([^\[\]]+)\]"#).unwrap());
+
+/// Whether a rejection of this kind is one a previous config can stand in for.
+pub(crate) const fn falls_back_to_last_good(kind: ValidationErrorKind) -> bool {
+    matches!(
+        kind,
+        ValidationErrorKind::CoreRejected | ValidationErrorKind::ScriptSyntax
+    )
+}
+
+/// Chain items a rejected config is blamed on.
+///
+/// A script failure already sits in the chain logs as an `exception` entry, so that item is the
+/// culprit. A core rejection quotes the proxy, group or rule it tripped over; the items whose
+/// content mentions that name are blamed. When nothing matches, the blame goes to `fallback`,
+/// normally the profile itself.
+pub(super) fn blame_chain_items(
+    kind: ValidationErrorKind,
+    message: &str,
+    chain_logs: &ChainLogs,
+    candidates: &[(String, String)],
+    fallback: Option<&String>,
+) -> Vec<String> {
+    if kind == ValidationErrorKind::ScriptSyntax {
+        let mut failed = chain_logs
+            .iter()
+            .filter(|(_, logs)| logs.iter().any(|(level, _)| level == "exception"))
+            .map(|(uid, _)| uid.clone())
+            .collect::<Vec<_>>();
+        if !failed.is_empty() {
+            failed.sort();
+            return failed;
+        }
+    }
+
+    let names = QUOTED_NAME
+        .captures_iter(message)
+        .filter_map(|caps| caps.iter().skip(1).flatten().next().map(|m| m.as_str().trim()))
+        .filter(|name| !name.is_empty() && !name.chars().all(|c| c.is_ascii_digit()))
+        .collect::<HashSet<_>>();
+    let blamed = candidates
+        .iter()
+        .filter(|(_, content)| names.iter().any(|name| content.contains(name)))
+        .map(|(uid, _)| uid.clone())
+        .collect::<Vec<_>>();
+
+    if blamed.is_empty() {
+        fallback.cloned().into_iter().collect()
+    } else {
+        blamed
+    }
+}
+
+/// The current profile's chain items with their file contents, the profile itself last.
+async fn chain_candidates(profiles: &IProfiles) -> (Vec<(String, String)>, Option<String>) {
+    let Some(current) = profiles.current.clone() else {
+        return (Vec::new(), None);
+    };
+    let Ok(item) = profiles.get_item(&current) else {
+        return (Vec::new(), Some(current));
+    };
+
+    let mut uids = item.option.as_ref().map_or_else(Vec::new, |option| {
+        [
+            option.merge.clone(),
+            option.script.clone(),
+            option.rules.clone(),
+            option.proxies.clone(),
+            option.groups.clone(),
+            option.filter.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+    });
+    uids.extend(["Merge".into(), "Script".into()]);
+
+    let mut candidates = Vec::new();
+    for uid in uids.iter().chain(std::iter::once(&current)) {
+        if let Ok(chain_item) = profiles.get_item(uid)
+            && let Ok(content) = chain_item.read_file().await
+        {
+            candidates.push((uid.clone(), content));
+        }
+    }
+    (candidates, Some(current))
+}
+
+fn mark_rejected(chain_logs: &mut ChainLogs, blamed: &[String], message: &str) {
+    for uid in blamed {
+        chain_logs
+            .entry(uid.clone())
+            .or_default()
+            .push(("rejected".into(), message.into()));
+    }
+}
+
+async fn blame_current_profile(kind: ValidationErrorKind, message: &str, chain_logs: &ChainLogs) -> Vec<String> {
+    let profiles = Config::profiles().await.latest_arc();
+    let (candidates, current) = chain_candidates(&profiles).await;
+    blame_chain_items(kind, message, chain_logs, &candidates, current.as_ref())
+}
+
+/// Copy a config the core just accepted into the last-known-good slot.
+pub(crate) async fn remember_last_good(run_path: &Path) {
+    let Ok(home) = dirs::app_home_dir() else {
+        return;
+    };
+    if let Err(err) = tokio::fs::copy(run_path, home.join(LAST_GOOD_CONFIG)).await {
+        logging!(warn, Type::Core, "保存上一份可用配置失败: {err}");
+    }
+}
+
+impl CoreManager {
+    /// Keep the committed runtime after `rejected_logs`' generation was rejected, and record the
+    /// rejection against the chain items it is blamed on.
+    pub(super) async fn keep_last_good(&self, kind: ValidationErrorKind, message: &str, rejected_logs: &ChainLogs) {
+        let blamed = blame_current_profile(kind, message, rejected_logs).await;
+        logging!(
+            warn,
+            Type::Core,
+            "新配置被拒绝，继续使用上一份可用配置，疑似出错项: {:?}, 错误: {}",
+            blamed,
+            message
+        );
+
+        let runtime = Config::runtime().await;
+        runtime.edit_draft(|d| mark_rejected(&mut d.chain_logs, &blamed, message));
+        runtime.apply();
+    }
+
+    /// Start from the last config the core accepted instead of the minimal default one.
+    ///
+    /// Returns `false` when there is no such config on disk, leaving the caller to fall back to
+    /// [`CoreManager::use_default_config`].
+    pub async fn use_last_good_config(
+        &self,
+        kind: ValidationErrorKind,
+        error_key: &str,
+        error_msg: &str,
+    ) -> Result<bool> {
+        let home = dirs::app_home_dir()?;
+        let last_good_path = home.join(LAST_GOOD_CONFIG);
+        if !falls_back_to_last_good(kind) || !tokio::fs::try_exists(&last_good_path).await.unwrap_or(false) {
+            return Ok(false);
+        }
+        let config = match help::read_mapping(&last_good_path).await {
+            Ok(config) => config,
+            Err(err) => {
+                logging!(warn, Type::Core, "读取上一份可用配置失败: {err}");
+                return Ok(false);
+            }
+        };
+
+        let runtime = Config::runtime().await;
+        let mut chain_logs = runtime.latest_arc().chain_logs.clone();
+        let script_errors = runtime.latest_arc().script_errors.clone();
+        let blamed = blame_current_profile(kind, error_msg, &chain_logs).await;
+        mark_rejected(&mut chain_logs, &blamed, error_msg);
+        logging!(
+            warn,
+            Type::Core,
+            "配置验证未通过，使用上一份可用配置启动，疑似出错项: {:?}",
+            blamed
+        );
+
+        help::save_yaml(&home.join(RUNTIME_CONFIG), &config, Some("# Clash Verge Runtime")).await?;
+        runtime.edit_draft(|d| {
+            *d = IRuntime {
+                config: Some(config),
+                exists_keys: HashSet::new(),
+                chain_logs,
+                script_errors,
+            }
+        });
+        handle::Handle::notice_message(error_key, error_msg);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChainLogs, blame_chain_items};
+    use crate::core::validate::ValidationErrorKind;
+    use smartstring::alias::String;
+
+    fn candidates() -> Vec<(String, String)> {
+        vec![
+            ("m1".into(), "prepend-proxy-groups:\n  - name: Streaming\n".into()),
+            ("r1".into(), "prepend:\n  - DOMAIN,a.example.com,Missing\n".into()),
+            ("R1".into(), "proxies: []\n".into()),
+        ]
+    }
+
+    #[test]
+    fn core_rejections_are_blamed_on_items_mentioning_the_quoted_name() {
+        let blamed = blame_chain_items(
+            ValidationErrorKind::CoreRejected,
+            "rules[0] [DOMAIN,a.example.com,Missing] error: proxy [Missing] not found",
+            &ChainLogs::new(),
+            &candidates(),
+            Some(&"R1".into()),
+        );
+        assert_eq!(blamed, vec!["r1"]);
+    }
+
+    #[test]
+    fn unattributable_rejections_fall_back_to_the_profile() {
+        let blamed = blame_chain_items(
+            ValidationErrorKind::CoreRejected,
+            "proxy group[3]: 'Elsewhere' not found",
+            &ChainLogs::new(),
+            &candidates(),
+            Some(&"R1".into()),
+        );
+        assert_eq!(blamed, vec!["R1"]);
+    }
+
+    #[test]
+    fn script_failures_are_blamed_on_the_script_that_threw() {
+        let mut logs = ChainLogs::new();
+        logs.insert("s1".into(), vec![("exception".into(), "SyntaxError".into())]);
+        logs.insert("Script".into(), vec![("log".into(), "ok".into())]);
+
+        let blamed = blame_chain_items(
+            ValidationErrorKind::ScriptSyntax,
+            "script syntax error",
+            &logs,
+            &candidates(),
+            Some(&"R1".into()),
+        );
+        assert_eq!(blamed, vec!["s1"]);
+    }
+}
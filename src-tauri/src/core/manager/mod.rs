@@ -1,4 +1,5 @@
 mod config;
+pub(crate) mod last_good;
 mod lifecycle;
 mod state;
 
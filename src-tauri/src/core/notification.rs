@@ -16,6 +16,7 @@ pub enum FrontendEvent<'a> {
     ProfileUpdateStarted { uid: &'a String },
     ProfileUpdateCompleted { uid: &'a String },
     RunStateChanged { state: serde_json::Value },
+    ProfileBatchProgress { progress: serde_json::Value },
 }
 
 #[derive(Debug)]
@@ -42,6 +43,7 @@ impl NotificationSystem {
             FrontendEvent::ProfileUpdateStarted { uid } => ("profile-update-started", Ok(json!({ "uid": uid }))),
             FrontendEvent::ProfileUpdateCompleted { uid } => ("profile-update-completed", Ok(json!({ "uid": uid }))),
             FrontendEvent::RunStateChanged { state } => ("verge://run-state-changed", Ok(state)),
+            FrontendEvent::ProfileBatchProgress { progress } => ("verge://profile-batch-progress", Ok(progress)),
         }
     }
 
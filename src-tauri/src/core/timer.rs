@@ -1,9 +1,9 @@
 use crate::{
-    config::{Config, PrfItem},
+    config::{Config, PrfItem, PrfOption},
     feat,
     process::AsyncHandler,
     singleton,
-    utils::resolve::is_resolve_done,
+    utils::{cron::CronSchedule, resolve::is_resolve_done},
 };
 use anyhow::Result;
 use clash_verge_logging::{Type, logging, logging_error};
@@ -11,6 +11,8 @@ use parking_lot::{Mutex, RwLock};
 use smartstring::alias::String;
 use std::{
     collections::HashMap,
+    fmt,
+    hash::{DefaultHasher, Hash as _, Hasher as _},
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
@@ -18,21 +20,145 @@ use std::{
     time::Duration,
 };
 use tokio::{
-    sync::mpsc,
+    sync::{mpsc, oneshot},
     time::{sleep, timeout},
 };
 use tokio_stream::StreamExt as _;
 use tokio_util::time::{DelayQueue, delay_queue::Key};
 
+/// how often subscription traffic and expiry are checked besides after each update
+const ALERT_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The queue runs on a monotonic clock that stops while the machine sleeps, so no task waits
+/// longer than this before its deadline is checked against the wall clock again.
+const DEADLINE_RECHECK: Duration = Duration::from_secs(5 * 60);
+
+const DEFAULT_CRON_JITTER_MINUTES: u64 = 5;
+
+/// When a profile is updated automatically.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateSchedule {
+    /// every `update_interval` minutes after the last run
+    Interval(u64),
+    /// at each firing of `update_cron`, delayed by up to `jitter_minutes`
+    Cron {
+        expr: String,
+        cron: CronSchedule,
+        jitter_minutes: u64,
+    },
+}
+
+impl UpdateSchedule {
+    /// `update_cron` when it parses, `update_interval` otherwise.
+    fn from_option(uid: &str, option: &PrfOption) -> Option<Self> {
+        if let Some(expr) = option.update_cron.as_ref().filter(|e| !e.trim().is_empty()) {
+            match CronSchedule::parse(expr) {
+                Ok(cron) => {
+                    return Some(Self::Cron {
+                        expr: expr.trim().into(),
+                        cron,
+                        jitter_minutes: option.update_jitter.unwrap_or(DEFAULT_CRON_JITTER_MINUTES),
+                    });
+                }
+                Err(err) => logging!(
+                    warn,
+                    Type::Timer,
+                    "Invalid update cron for uid={}, falling back to the interval: {}",
+                    uid,
+                    err
+                ),
+            }
+        }
+        option.update_interval.filter(|i| *i > 0).map(Self::Interval)
+    }
+
+    /// The first run after `now`, in unix seconds, for a task (re)scheduled at `now`.
+    pub fn next_run(&self, uid: &str, now: chrono::DateTime<chrono::Local>) -> Option<i64> {
+        match self {
+            Self::Interval(minutes) => Some(
+                now.timestamp()
+                    .saturating_add(i64::try_from(minutes.saturating_mul(60)).ok()?),
+            ),
+            Self::Cron {
+                cron, jitter_minutes, ..
+            } => {
+                // 抖动可能把上一次触发推迟到现在之后
+                let window = i64::try_from(jitter_minutes.saturating_mul(60)).ok()?;
+                let mut after = now - chrono::Duration::seconds(window);
+                loop {
+                    let fired = cron.next_after(after)?;
+                    let run = fired.timestamp() + cron_jitter(uid, fired.timestamp(), window);
+                    if run > now.timestamp() {
+                        return Some(run);
+                    }
+                    after = fired;
+                }
+            }
+        }
+    }
+
+    /// The run that follows one made at `last`: an interval later, or at the first firing after
+    /// `last`. Unlike [`Self::next_run`] this does not look back for a firing whose jitter is still
+    /// pending, since the run at `last` already covered it.
+    pub fn run_after(&self, uid: &str, last: chrono::DateTime<chrono::Local>) -> Option<i64> {
+        match self {
+            Self::Interval(_) => self.next_run(uid, last),
+            Self::Cron {
+                cron, jitter_minutes, ..
+            } => {
+                let window = i64::try_from(jitter_minutes.saturating_mul(60)).ok()?;
+                let fired = cron.next_after(last)?.timestamp();
+                Some(fired + cron_jitter(uid, fired, window))
+            }
+        }
+    }
+
+    /// Whether the run after the last one, made at `updated`, was due by `now`, as happens after
+    /// the app was closed.
+    pub fn is_overdue(&self, uid: &str, updated: i64, now: chrono::DateTime<chrono::Local>) -> bool {
+        let Some(updated) = chrono::DateTime::from_timestamp(updated, 0) else {
+            return true;
+        };
+        self.run_after(uid, updated.with_timezone(&chrono::Local))
+            .is_some_and(|run| run <= now.timestamp())
+    }
+}
+
+impl fmt::Display for UpdateSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Interval(minutes) => write!(f, "every {minutes}min"),
+            Self::Cron {
+                expr, jitter_minutes, ..
+            } => write!(f, "cron \"{expr}\" (+{jitter_minutes}min jitter)"),
+        }
+    }
+}
+
+/// The delay of the run for `fired`, in `0..=window` seconds. Stable for a profile and a firing,
+/// so the time reported to the UI is the one the task runs at, and spread across profiles.
+fn cron_jitter(uid: &str, fired: i64, window: i64) -> i64 {
+    if window <= 0 {
+        return 0;
+    }
+    let mut hasher = DefaultHasher::new();
+    uid.hash(&mut hasher);
+    fired.hash(&mut hasher);
+    i64::try_from(hasher.finish() % (window.unsigned_abs() + 1)).unwrap_or(0)
+}
+
 enum TimerCommand {
-    Apply(HashMap<String, u64>),
+    Apply(HashMap<String, UpdateSchedule>),
     RunNow(String),
     TaskFinished(String),
+    NextRun(String, oneshot::Sender<Option<i64>>),
 }
 
 struct TaskState {
     key: Option<Key>,
-    interval_minutes: u64,
+    schedule: UpdateSchedule,
+    /// unix seconds the queued run is due at
+    due: Option<i64>,
     running: bool,
 }
 
@@ -55,10 +181,11 @@ async fn run_timer_profile_update_transition<Started, Update, UpdateFuture, Fini
 }
 
 impl TaskState {
-    const fn new(key: Key, interval_minutes: u64) -> Self {
+    const fn new(schedule: UpdateSchedule) -> Self {
         Self {
-            key: Some(key),
-            interval_minutes,
+            key: None,
+            schedule,
+            due: None,
             running: false,
         }
     }
@@ -68,7 +195,7 @@ pub struct Timer {
     command_tx: mpsc::UnboundedSender<TimerCommand>,
     command_rx: Mutex<Option<mpsc::UnboundedReceiver<TimerCommand>>>,
     refresh_lock: tokio::sync::Mutex<()>,
-    pub timer_map: Arc<RwLock<HashMap<String, u64>>>,
+    pub timer_map: Arc<RwLock<HashMap<String, UpdateSchedule>>>,
     pub initialized: AtomicBool,
 }
 
@@ -113,27 +240,25 @@ impl Timer {
         {
             let timer_map = self.timer_map.read();
             logging!(debug, Type::Timer, "Registered timer task count: {}", timer_map.len());
-            for (uid, interval) in timer_map.iter() {
+            for (uid, schedule) in timer_map.iter() {
                 logging!(
                     debug,
                     Type::Timer,
-                    "Registered timer task: uid={}, interval={}min",
+                    "Registered timer task: uid={}, schedule={}",
                     uid,
-                    interval
+                    schedule
                 );
             }
         }
 
-        let cur_timestamp = chrono::Local::now().timestamp();
+        let now = chrono::Local::now();
+        let schedules = self.timer_map.read().clone();
         if let Some(items) = Config::profiles().await.data_arc().get_items() {
             for item in items.iter() {
-                if let Some(option) = item.option.as_ref()
-                    && option.allow_auto_update.unwrap_or(true)
-                    && let Some(interval) = option.update_interval
-                    && interval > 0
-                    && let Some(uid) = item.uid.as_ref()
+                if let Some(uid) = item.uid.as_ref()
+                    && let Some(schedule) = schedules.get(uid)
                     && let Some(updated) = item.updated
-                    && cur_timestamp - (updated as i64) >= (interval as i64) * 60
+                    && schedule.is_overdue(uid, updated as i64, now)
                 {
                     logging!(info, Type::Timer, "Running overdue timer task immediately: uid={}", uid);
                     let _ = self.command_tx.send(TimerCommand::RunNow(uid.clone()));
@@ -141,6 +266,11 @@ impl Timer {
             }
         }
 
+        AsyncHandler::spawn(|| async {
+            Self::wait_until_resolve_done(Duration::from_millis(5000)).await;
+            Self::run_alert_checks().await;
+        });
+
         logging!(info, Type::Timer, "Timer initialization completed");
         Ok(())
     }
@@ -169,7 +299,7 @@ impl Timer {
         Ok(())
     }
 
-    async fn gen_map(&self) -> HashMap<String, u64> {
+    async fn gen_map(&self) -> HashMap<String, UpdateSchedule> {
         if let Some(items) = Config::profiles().await.data_arc().get_items() {
             return Self::gen_map_from_items(items);
         }
@@ -177,16 +307,15 @@ impl Timer {
         HashMap::new()
     }
 
-    fn gen_map_from_items(items: &[PrfItem]) -> HashMap<String, u64> {
+    fn gen_map_from_items(items: &[PrfItem]) -> HashMap<String, UpdateSchedule> {
         let mut new_map = HashMap::new();
 
         for item in items {
-            if let Some(option) = item.option.as_ref()
-                && let (Some(interval), Some(uid)) = (option.update_interval, &item.uid)
+            if let (Some(option), Some(uid)) = (item.option.as_ref(), &item.uid)
                 && option.allow_auto_update.unwrap_or(true)
-                && interval > 0
+                && let Some(schedule) = UpdateSchedule::from_option(uid, option)
             {
-                new_map.insert(uid.clone(), interval);
+                new_map.insert(uid.clone(), schedule);
             }
         }
 
@@ -204,7 +333,7 @@ impl Timer {
             tokio::select! {
                 Some(expired) = queue.next() => {
                     let uid = expired.into_inner();
-                    Self::run_expired_task(&mut tasks, uid, command_tx.clone());
+                    Self::run_expired_task(&mut queue, &mut tasks, uid, command_tx.clone());
                 }
 
                 command = command_rx.recv() => {
@@ -218,6 +347,10 @@ impl Timer {
                         Some(TimerCommand::TaskFinished(uid)) => {
                             Self::finish_task(&mut queue, &mut tasks, uid);
                         }
+                        Some(TimerCommand::NextRun(uid, reply)) => {
+                            let due = tasks.get(&uid).and_then(|state| state.due);
+                            let _ = reply.send(due);
+                        }
                         None => break,
                     }
                 }
@@ -228,7 +361,7 @@ impl Timer {
     fn apply_timer_map(
         queue: &mut DelayQueue<String>,
         tasks: &mut HashMap<String, TaskState>,
-        new_map: HashMap<String, u64>,
+        new_map: HashMap<String, UpdateSchedule>,
     ) {
         tasks.retain(|uid, state| {
             if new_map.contains_key(uid) {
@@ -242,17 +375,17 @@ impl Timer {
             false
         });
 
-        for (uid, interval_minutes) in new_map {
+        for (uid, schedule) in new_map {
             let Some(state) = tasks.get_mut(&uid) else {
-                Self::insert_task(queue, tasks, uid, interval_minutes);
+                Self::insert_task(queue, tasks, uid, schedule);
                 continue;
             };
 
-            if state.interval_minutes == interval_minutes {
+            if state.schedule == schedule {
                 continue;
             }
 
-            Self::update_task_interval(queue, &uid, state, interval_minutes);
+            Self::update_task_schedule(queue, &uid, state, schedule);
         }
     }
 
@@ -260,38 +393,46 @@ impl Timer {
         queue: &mut DelayQueue<String>,
         tasks: &mut HashMap<String, TaskState>,
         uid: String,
-        interval_minutes: u64,
+        schedule: UpdateSchedule,
     ) {
-        let key = Self::schedule_task(queue, &uid, interval_minutes);
+        let mut state = TaskState::new(schedule);
+        Self::schedule_task(queue, &uid, &mut state);
         logging!(
             debug,
             Type::Timer,
-            "Added timer task: uid={}, interval={}min",
+            "Added timer task: uid={}, schedule={}",
             uid,
-            interval_minutes
+            state.schedule
         );
-        tasks.insert(uid, TaskState::new(key, interval_minutes));
+        tasks.insert(uid, state);
     }
 
-    fn update_task_interval(queue: &mut DelayQueue<String>, uid: &str, state: &mut TaskState, interval_minutes: u64) {
-        state.interval_minutes = interval_minutes;
+    fn update_task_schedule(
+        queue: &mut DelayQueue<String>,
+        uid: &str,
+        state: &mut TaskState,
+        schedule: UpdateSchedule,
+    ) {
+        state.schedule = schedule;
 
-        if let Some(key) = state.key.as_ref() {
-            queue.reset(key, Self::interval_duration(interval_minutes));
-        } else if !state.running {
-            state.key = Some(Self::schedule_task(queue, uid, interval_minutes));
+        if let Some(key) = state.key.take() {
+            queue.remove(&key);
+        }
+        if !state.running {
+            Self::schedule_task(queue, uid, state);
         }
 
         logging!(
             debug,
             Type::Timer,
-            "Updated timer task interval: uid={}, interval={}min",
+            "Updated timer task schedule: uid={}, schedule={}",
             uid,
-            interval_minutes
+            state.schedule
         );
     }
 
     fn run_expired_task(
+        queue: &mut DelayQueue<String>,
         tasks: &mut HashMap<String, TaskState>,
         uid: String,
         command_tx: mpsc::UnboundedSender<TimerCommand>,
@@ -301,6 +442,13 @@ impl Timer {
         };
 
         state.key = None;
+        // 只是到了重新核对的时间，截止时间未到
+        if let Some(due) = state.due
+            && chrono::Local::now().timestamp() < due
+        {
+            state.key = Some(Self::arm(queue, &uid, due));
+            return;
+        }
         if !Self::mark_task_running(state, &uid, false) {
             return;
         }
@@ -325,6 +473,7 @@ impl Timer {
         if let Some(key) = state.key.take() {
             queue.remove(&key);
         }
+        state.due = None;
         Self::spawn_update_task(uid, command_tx);
     }
 
@@ -353,8 +502,7 @@ impl Timer {
         };
 
         state.running = false;
-        let key = Self::schedule_task(queue, &uid, state.interval_minutes);
-        state.key = Some(key);
+        Self::schedule_task(queue, &uid, state);
     }
 
     fn spawn_update_task(uid: String, command_tx: mpsc::UnboundedSender<TimerCommand>) {
@@ -366,30 +514,39 @@ impl Timer {
         });
     }
 
-    const fn interval_duration(interval_minutes: u64) -> Duration {
-        Duration::from_secs(interval_minutes.saturating_mul(60))
+    /// Work out the next run of `state` from now and queue it.
+    fn schedule_task(queue: &mut DelayQueue<String>, uid: &str, state: &mut TaskState) {
+        state.due = state.schedule.next_run(uid, chrono::Local::now());
+        state.key = state.due.map(|due| Self::arm(queue, uid, due));
     }
 
-    fn schedule_task(queue: &mut DelayQueue<String>, uid: &str, interval_minutes: u64) -> Key {
-        queue.insert(String::from(uid), Self::interval_duration(interval_minutes))
+    fn arm(queue: &mut DelayQueue<String>, uid: &str, due: i64) -> Key {
+        let left = due
+            .saturating_sub(chrono::Local::now().timestamp())
+            .max(0)
+            .unsigned_abs();
+        queue.insert(String::from(uid), Duration::from_secs(left).min(DEADLINE_RECHECK))
     }
 
+    /// When the scheduler will next update `uid`, in unix seconds. `None` when the profile has no
+    /// schedule. While an update runs this is still the time that run was due at, and before the
+    /// queue is set up it is worked out from the profile's last update.
     pub async fn get_next_update_time(&self, uid: &str) -> Option<i64> {
         logging!(debug, Type::Timer, "Getting next update time, uid={}", uid);
 
-        let task_interval = *self.timer_map.read().get(uid)?;
-        let profiles = Config::profiles().await;
-        let profiles_guard = profiles.latest_arc();
-        let items = profiles_guard.get_items()?;
-
-        let profile = items.iter().find(|item| item.uid.as_deref() == Some(uid))?;
-        let updated = profile.updated.unwrap_or(0) as i64;
-
-        if updated > 0 {
-            Some(updated + (task_interval as i64 * 60))
-        } else {
-            None
+        if !self.initialized.load(Ordering::Acquire) {
+            return Self::run_after_last_update(uid).await;
         }
+        let (reply, due) = oneshot::channel();
+        self.command_tx.send(TimerCommand::NextRun(uid.into(), reply)).ok()?;
+        timeout(Duration::from_secs(1), due).await.ok()?.ok().flatten()
+    }
+
+    async fn run_after_last_update(uid: &str) -> Option<i64> {
+        let item = Config::profiles().await.latest_arc().get_item(uid).ok()?.clone();
+        let updated = chrono::DateTime::from_timestamp(i64::try_from(item.updated?).ok()?, 0)?;
+        let schedule = Self::gen_map_from_items(std::slice::from_ref(&item)).remove(uid)?;
+        schedule.run_after(uid, updated.with_timezone(&chrono::Local))
     }
 
     fn emit_update_event(uid: &String, is_start: bool) {
@@ -407,7 +564,7 @@ impl Timer {
         run_timer_profile_update_transition(
             || Self::emit_update_event(uid, true),
             || async {
-                let is_current = Config::profiles().await.latest_arc().current.as_ref() == Some(uid);
+                let is_current = Config::profiles().await.latest_arc().feeds_current_profile(uid);
                 logging!(
                     debug,
                     Type::Timer,
@@ -435,6 +592,16 @@ impl Timer {
         .await;
     }
 
+    /// Check subscription alerts now and then daily, which also covers profiles that are never
+    /// updated automatically.
+    async fn run_alert_checks() {
+        let mut ticker = tokio::time::interval(ALERT_CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            feat::check_subscription_alerts(None).await;
+        }
+    }
+
     async fn wait_until_resolve_done(max_wait: Duration) {
         let _ = timeout(max_wait, async {
             while !is_resolve_done() {
@@ -447,8 +614,9 @@ impl Timer {
 }
 
 #[cfg(test)]
+#[allow(clippy::expect_used)]
 mod tests {
-    use super::{Timer, run_timer_profile_update_transition};
+    use super::{Timer, UpdateSchedule, run_timer_profile_update_transition};
     use crate::config::{PrfItem, PrfOption};
     use parking_lot::Mutex;
     use std::{
@@ -500,8 +668,55 @@ mod tests {
         let map = Timer::gen_map_from_items(&items);
 
         assert_eq!(map.len(), 2);
-        assert_eq!(map.get("enabled"), Some(&30));
-        assert_eq!(map.get("missing-flag"), Some(&30));
+        assert_eq!(map.get("enabled"), Some(&UpdateSchedule::Interval(30)));
+        assert_eq!(map.get("missing-flag"), Some(&UpdateSchedule::Interval(30)));
+    }
+
+    #[test]
+    fn a_cron_schedule_runs_once_per_firing_within_its_jitter() {
+        let mut item = remote_profile("cron", Some(true), Some(30));
+        if let Some(option) = item.option.as_mut() {
+            option.update_cron = Some("0 6 * * *".into());
+            option.update_jitter = Some(10);
+        }
+        let map = Timer::gen_map_from_items(&[item]);
+        let schedule = map.get("cron").expect("a cron profile should be scheduled");
+        assert!(matches!(schedule, UpdateSchedule::Cron { jitter_minutes: 10, .. }));
+
+        let at = |h, m| {
+            chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+                .and_then(|d| d.and_hms_opt(h, m, 0))
+                .and_then(|t| t.and_local_timezone(chrono::Local).earliest())
+                .expect("fixture time should exist locally")
+        };
+        let run = schedule.next_run("cron", at(5, 0)).expect("the cron fires daily");
+        assert!((at(6, 0).timestamp()..=at(6, 10).timestamp()).contains(&run));
+        // the same firing is reported until it has run, then the next day's
+        let just_before = chrono::DateTime::from_timestamp(run - 1, 0)
+            .expect("fixture time should be valid")
+            .with_timezone(&chrono::Local);
+        assert_eq!(schedule.next_run("cron", just_before), Some(run));
+        let tomorrow = schedule.next_run("cron", at(6, 11)).expect("the cron fires daily");
+        assert!(tomorrow >= at(6, 0).timestamp() + 24 * 60 * 60);
+
+        assert!(schedule.is_overdue("cron", at(5, 0).timestamp() - 24 * 60 * 60, at(7, 0)));
+        assert!(!schedule.is_overdue("cron", at(6, 30).timestamp(), at(7, 0)));
+        // updated by hand after the firing, before its jittered run: that firing is covered
+        assert!(!schedule.is_overdue("cron", at(6, 1).timestamp(), at(7, 0)));
+        assert_eq!(
+            schedule.run_after("cron", at(6, 1)),
+            schedule.next_run("cron", at(6, 11))
+        );
+    }
+
+    #[test]
+    fn an_invalid_cron_falls_back_to_the_interval() {
+        let mut item = remote_profile("bad-cron", Some(true), Some(30));
+        if let Some(option) = item.option.as_mut() {
+            option.update_cron = Some("every morning".into());
+        }
+        let map = Timer::gen_map_from_items(&[item]);
+        assert_eq!(map.get("bad-cron"), Some(&UpdateSchedule::Interval(30)));
     }
 
     #[tokio::test(start_paused = true)]
@@ -296,6 +296,7 @@ impl Tray {
         };
 
         let mut current_profile_name = "None".into();
+        let mut current_extra = None;
         {
             let profiles = Config::profiles().await;
             let profiles = profiles.latest_arc();
@@ -306,8 +307,15 @@ impl Tray {
                     Some(profile_name) => profile_name.to_string(),
                     None => current_profile_name,
                 };
+                current_extra = profile.extra;
             }
         }
+        let subscription_line =
+            current_extra
+                .zip(feat::AlertSettings::from_verge(&verge))
+                .and_then(|(extra, settings)| {
+                    feat::subscription_tooltip(&extra, &settings, chrono::Local::now().timestamp())
+                });
 
         // Get localized strings before using them
         let sys_proxy_text = clash_verge_i18n::t!("tray.tooltip.systemProxy");
@@ -320,7 +328,7 @@ impl Tray {
             |(main, rest)| format!("{main}+{}", rest.split('.').next().unwrap_or("")),
         );
 
-        let tooltip = format!(
+        let mut tooltip = format!(
             "Clash Verge {}\n{}: {}\n{}: {}\n{}: {}",
             reassembled_version,
             sys_proxy_text,
@@ -330,6 +338,9 @@ impl Tray {
             profile_text,
             current_profile_name
         );
+        if let Some(line) = subscription_line {
+            tooltip.push_str(&format!(" ({line})"));
+        }
 
         let Some(tray) = app_handle.tray_by_id(TRAY_ID) else {
             logging!(warn, Type::Tray, "Failed to update tray tooltip: tray not found");
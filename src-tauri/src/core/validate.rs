@@ -11,6 +11,10 @@ use tokio::fs;
 
 use crate::config::{Config, ConfigType};
 use crate::core::handle;
+use crate::enhance::{
+    script::check_script,
+    script_error::{ScriptError, ScriptErrorKind},
+};
 use crate::singleton;
 use crate::utils::dirs;
 use clash_verge_logging::{Type, logging};
@@ -82,8 +86,16 @@ impl fmt::Display for ValidationSkipReason {
 #[serde(tag = "status", rename_all = "camelCase")]
 pub enum ValidationOutcome {
     Valid,
-    Invalid { kind: ValidationErrorKind, message: String },
-    Skipped { reason: ValidationSkipReason },
+    Invalid {
+        kind: ValidationErrorKind,
+        message: String,
+        /// 脚本错误的位置、上下文与调用栈
+        #[serde(skip_serializing_if = "Option::is_none")]
+        script: Option<Box<ScriptError>>,
+    },
+    Skipped {
+        reason: ValidationSkipReason,
+    },
     Busy,
 }
 
@@ -92,6 +104,32 @@ impl ValidationOutcome {
         Self::Invalid {
             kind,
             message: message.into(),
+            script: None,
+        }
+    }
+
+    pub fn script_invalid(error: ScriptError) -> Self {
+        let kind = match error.kind {
+            ScriptErrorKind::MissingMain => ValidationErrorKind::ScriptMissingMain,
+            _ => ValidationErrorKind::ScriptSyntax,
+        };
+        Self::Invalid {
+            kind,
+            message: error.to_string().into(),
+            script: Some(Box::new(error)),
+        }
+    }
+
+    /// 为未通过的结果附上脚本错误，其余结果原样返回
+    #[must_use]
+    pub fn with_script_error(self, error: Option<ScriptError>) -> Self {
+        match (self, error) {
+            (Self::Invalid { kind, message, .. }, Some(error)) => Self::Invalid {
+                kind,
+                message,
+                script: Some(Box::new(error)),
+            },
+            (outcome, _) => outcome,
         }
     }
 
@@ -240,40 +278,14 @@ impl CoreConfigValidator {
 
         logging!(debug, Type::Validate, "验证脚本文件: {}", path);
 
-        // 使用boa引擎进行基本语法检查
-        use boa_engine::{Context, Source};
-
-        let mut context = Context::default();
-        let _ = context.eval(Source::from_bytes(
-            "var console = Object.freeze({
-              log(...data){},
-              info(...data){},
-              error(...data){},
-              debug(...data){},
-            });",
-        ));
-        let result = context.eval(Source::from_bytes(&content));
-
-        match result {
-            Ok(_) => {
+        match check_script(&content) {
+            Ok(()) => {
                 logging!(debug, Type::Validate, "脚本语法验证通过: {}", path);
-
-                // 检查脚本是否包含main函数
-                if !content.contains("function main")
-                    && !content.contains("const main")
-                    && !content.contains("let main")
-                {
-                    let error_msg = "Script must contain a main function";
-                    logging!(warn, Type::Validate, "脚本缺少main函数: {}", path);
-                    return Ok(ValidationOutcome::invalid_from_message(error_msg));
-                }
-
                 Ok(ValidationOutcome::Valid)
             }
             Err(err) => {
-                let error_msg: String = format!("Script syntax error: {err}").into();
-                logging!(warn, Type::Validate, "脚本语法错误: {}", err);
-                Ok(ValidationOutcome::invalid_from_message(error_msg))
+                logging!(warn, Type::Validate, "脚本验证未通过: {}", err);
+                Ok(ValidationOutcome::script_invalid(err))
             }
         }
     }
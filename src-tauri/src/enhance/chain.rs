@@ -1,4 +1,4 @@
-use super::SeqMap;
+use super::{SeqMap, filter::NodeFilter};
 use crate::{
     config::PrfItem,
     utils::{dirs, help},
@@ -20,6 +20,7 @@ pub enum ChainType {
     Rules(SeqMap),
     Proxies(SeqMap),
     Groups(SeqMap),
+    Filter(NodeFilter),
 }
 
 #[derive(Debug, Clone)]
@@ -110,6 +111,13 @@ impl AsyncChainItemFrom for Option<ChainItem> {
                     data: ChainType::Groups(seq_map),
                 })
             }
+            "filter" => {
+                let filter = help::read_yaml(&path).await.ok()?;
+                Some(ChainItem {
+                    uid,
+                    data: ChainType::Filter(filter),
+                })
+            }
             _ => None,
         }
     }
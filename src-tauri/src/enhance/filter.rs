@@ -0,0 +1,292 @@
+//! Declarative node filtering and renaming.
+//!
+//! A filter item describes which subscription nodes to keep and how to name them, the things
+//! people otherwise write a script for:
+//!
+//! ```yaml
+//! include: "HK|JP|SG"          # keep only names matching this
+//! exclude: "剩余|到期|官网"      # then drop names matching this
+//! exclude-type: [ss]           # drop nodes by protocol
+//! rename:
+//!   - { pattern: '^\[(\w+)\]\s*(.+)$', replace: '$1 $2' }
+//! ```
+//!
+//! Groups and rules follow renamed nodes. Group references to dropped ones are removed by
+//! `cleanup_proxy_groups`, and rules sending traffic to them go too. Nodes inside
+//! `proxy-providers` are only known to the core and are left to the provider's own
+//! `filter`/`exclude-filter`.
+
+use super::cleanup_proxy_groups;
+use anyhow::{Context as _, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_yaml_ng::{Mapping, Sequence, Value};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct NodeFilter {
+    /// keep only nodes whose name matches
+    pub include: Option<String>,
+    /// drop nodes whose name matches
+    pub exclude: Option<String>,
+    /// keep only nodes of these types
+    #[serde(default)]
+    pub include_type: Vec<String>,
+    /// drop nodes of these types
+    #[serde(default)]
+    pub exclude_type: Vec<String>,
+    /// applied in order to every kept name; `replace` may use `$1`/`${name}` captures
+    #[serde(default)]
+    pub rename: Vec<RenameRule>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RenameRule {
+    pub pattern: String,
+    #[serde(default)]
+    pub replace: String,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FilterReport {
+    pub kept: usize,
+    pub dropped: usize,
+    pub renamed: usize,
+}
+
+impl NodeFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.as_deref().is_none_or(str::is_empty)
+            && self.exclude.as_deref().is_none_or(str::is_empty)
+            && self.include_type.is_empty()
+            && self.exclude_type.is_empty()
+            && self.rename.is_empty()
+    }
+}
+
+fn compile(pattern: Option<&str>, field: &str) -> Result<Option<Regex>> {
+    match pattern.filter(|p| !p.is_empty()) {
+        Some(p) => Regex::new(p)
+            .map(Some)
+            .with_context(|| format!("invalid `{field}` pattern \"{p}\"")),
+        None => Ok(None),
+    }
+}
+
+fn has_type(types: &[String], node: &Mapping) -> bool {
+    node.get("type")
+        .and_then(Value::as_str)
+        .is_some_and(|t| types.iter().any(|wanted| wanted.eq_ignore_ascii_case(t)))
+}
+
+/// Filter and rename `config`'s proxies. Fails only on a pattern that does not compile, before
+/// anything is touched.
+pub fn use_filter(filter: &NodeFilter, mut config: Mapping) -> Result<(Mapping, FilterReport)> {
+    let include = compile(filter.include.as_deref(), "include")?;
+    let exclude = compile(filter.exclude.as_deref(), "exclude")?;
+    let rename = filter
+        .rename
+        .iter()
+        .map(|rule| {
+            Regex::new(&rule.pattern)
+                .map(|re| (re, rule.replace.as_str()))
+                .with_context(|| format!("invalid `rename` pattern \"{}\"", rule.pattern))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let Some(Value::Sequence(proxies)) = config.remove("proxies") else {
+        return Ok((config, FilterReport::default()));
+    };
+
+    let mut report = FilterReport::default();
+    // names outside the proxy list a rename must not collide with
+    let mut taken = config
+        .get("proxy-groups")
+        .and_then(Value::as_sequence)
+        .map(|groups| {
+            groups
+                .iter()
+                .filter_map(|g| g.get("name").and_then(Value::as_str).map(str::to_owned))
+                .collect::<HashSet<_>>()
+        })
+        .unwrap_or_default();
+    let mut renamed = HashMap::new();
+    let mut dropped = HashSet::new();
+    let mut kept = Sequence::new();
+
+    for proxy in proxies {
+        let Value::Mapping(mut node) = proxy else {
+            kept.push(proxy);
+            continue;
+        };
+        let Some(name) = node.get("name").and_then(Value::as_str).map(str::to_owned) else {
+            kept.push(Value::Mapping(node));
+            continue;
+        };
+
+        let keep = include.as_ref().is_none_or(|re| re.is_match(&name))
+            && !exclude.as_ref().is_some_and(|re| re.is_match(&name))
+            && (filter.include_type.is_empty() || has_type(&filter.include_type, &node))
+            && !has_type(&filter.exclude_type, &node);
+        if !keep {
+            dropped.insert(name);
+            report.dropped += 1;
+            continue;
+        }
+
+        let mut new_name = rename.iter().fold(name.clone(), |current, (re, replace)| {
+            re.replace_all(&current, *replace).trim().to_owned()
+        });
+        if new_name.is_empty() {
+            new_name.clone_from(&name);
+        }
+        let base = new_name.clone();
+        let mut n = 2;
+        while taken.contains(&new_name) {
+            new_name = format!("{base} {n}");
+            n += 1;
+        }
+        taken.insert(new_name.clone());
+
+        if new_name != name {
+            node.insert("name".into(), new_name.clone().into());
+            renamed.entry(name).or_insert(new_name);
+            report.renamed += 1;
+        }
+        report.kept += 1;
+        kept.push(Value::Mapping(node));
+    }
+    config.insert("proxies".into(), Value::Sequence(kept));
+
+    if !renamed.is_empty() || !dropped.is_empty() {
+        follow_nodes(&mut config, &renamed, &dropped);
+    }
+    if report.dropped > 0 {
+        config = cleanup_proxy_groups(config);
+    }
+    Ok((config, report))
+}
+
+/// Point group members and rule policies at the renamed nodes, and drop the rules whose policy
+/// was a dropped node.
+fn follow_nodes(config: &mut Mapping, renamed: &HashMap<String, String>, dropped: &HashSet<String>) {
+    if let Some(Value::Sequence(groups)) = config.get_mut("proxy-groups") {
+        for group in groups.iter_mut() {
+            if let Some(Value::Sequence(members)) = group.get_mut("proxies") {
+                for member in members.iter_mut() {
+                    if let Some(new_name) = member.as_str().and_then(|name| renamed.get(name)) {
+                        *member = new_name.as_str().into();
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(Value::Sequence(rules)) = config.get_mut("rules") {
+        rules.retain_mut(|rule| {
+            let Some(text) = rule.as_str() else {
+                return true;
+            };
+            let mut parts = text.split(',').collect::<Vec<_>>();
+            // the policy is the last field, unless trailing options follow it
+            let Some(policy) = parts
+                .iter()
+                .rposition(|part| !matches!(part.trim(), "no-resolve" | "src"))
+            else {
+                return true;
+            };
+            if dropped.contains(parts[policy].trim()) {
+                return false;
+            }
+            if let Some(new_name) = renamed.get(parts[policy].trim()) {
+                parts[policy] = new_name.as_str();
+                *rule = parts.join(",").into();
+            }
+            true
+        });
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{FilterReport, NodeFilter, RenameRule, use_filter};
+    use serde_yaml_ng::{Mapping, Value};
+
+    fn config() -> Mapping {
+        serde_yaml_ng::from_str(
+            r"
+proxies:
+  - { name: '[HK] Hong Kong 01', type: vmess, server: a.example.com, port: 1 }
+  - { name: '[JP] Tokyo 01', type: ss, server: b.example.com, port: 1 }
+  - { name: 'Expires in 3 days', type: vmess, server: c.example.com, port: 1 }
+proxy-groups:
+  - { name: PROXY, type: select, proxies: ['[HK] Hong Kong 01', '[JP] Tokyo 01', 'Expires in 3 days'] }
+rules:
+  - DOMAIN,a.example.com,[HK] Hong Kong 01
+  - IP-CIDR,10.0.0.0/8,[JP] Tokyo 01,no-resolve
+  - MATCH,PROXY
+",
+        )
+        .expect("fixture should be valid yaml")
+    }
+
+    fn strings(value: &Value) -> Vec<&str> {
+        value
+            .as_sequence()
+            .expect("value should be a sequence")
+            .iter()
+            .filter_map(|v| v.as_str().or_else(|| v.get("name").and_then(Value::as_str)))
+            .collect()
+    }
+
+    #[test]
+    fn drops_and_renames_nodes_and_follows_them_in_groups_and_rules() {
+        let filter = NodeFilter {
+            exclude: Some("(?i)expire".into()),
+            exclude_type: vec!["SS".into()],
+            rename: vec![RenameRule {
+                pattern: r"^\[(\w+)\]\s*(.+)$".into(),
+                replace: "$1 $2".into(),
+            }],
+            ..NodeFilter::default()
+        };
+
+        let (config, report) = use_filter(&filter, config()).expect("filter should apply");
+        assert_eq!(
+            report,
+            FilterReport {
+                kept: 1,
+                dropped: 2,
+                renamed: 1
+            }
+        );
+        assert_eq!(strings(&config["proxies"]), ["HK Hong Kong 01"]);
+        assert_eq!(strings(&config["proxy-groups"][0]["proxies"]), ["HK Hong Kong 01"]);
+        assert_eq!(
+            strings(&config["rules"]),
+            ["DOMAIN,a.example.com,HK Hong Kong 01", "MATCH,PROXY"]
+        );
+    }
+
+    #[test]
+    fn include_keeps_only_matching_names() {
+        let filter = NodeFilter {
+            include: Some("JP".into()),
+            ..NodeFilter::default()
+        };
+        let (config, report) = use_filter(&filter, config()).expect("filter should apply");
+        assert_eq!(report.dropped, 2);
+        assert_eq!(strings(&config["proxies"]), ["[JP] Tokyo 01"]);
+    }
+
+    #[test]
+    fn an_invalid_pattern_is_reported_instead_of_applied() {
+        let filter = NodeFilter {
+            exclude: Some("(".into()),
+            ..NodeFilter::default()
+        };
+        assert!(use_filter(&filter, config()).is_err());
+    }
+}
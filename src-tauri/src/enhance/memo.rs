@@ -0,0 +1,265 @@
+//! Memoised chain stages.
+//!
+//! Switching profiles re-runs the whole chain, and on a large profile most of that time is boa
+//! running Script items, and Merge items deep-merging, over inputs that have not changed since
+//! the last switch. A stage is keyed by a sha256 of what it is (the script source or the merge
+//! mapping), the config it was given, and for scripts the profile they were told about and the
+//! core type, so any change to the item or to an earlier stage's output is a different key. Only
+//! the last few outputs are kept, in memory, up to a byte budget.
+
+use super::{
+    merge::use_merge,
+    script::{ScriptProfile, ScriptRun, run_script},
+    script_error::ScriptErrorKind,
+};
+use parking_lot::Mutex;
+use serde_yaml_ng::Mapping;
+use sha2::{Digest as _, Sha256};
+use smartstring::alias::String;
+use std::{collections::VecDeque, io, sync::LazyLock};
+
+/// each entry holds a full config, so keep this small
+const CAPACITY: usize = 8;
+
+/// the serialised size of the inputs kept at most, which the outputs roughly follow
+const MAX_BYTES: usize = 32 * 1024 * 1024;
+
+static CACHE: LazyLock<Mutex<MemoCache>> = LazyLock::new(|| Mutex::new(MemoCache::default()));
+
+type MemoKey = [u8; 32];
+
+/// What a chain script's output depends on besides its source and input.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptEnv {
+    pub profile: ScriptProfile,
+    pub core: Option<String>,
+}
+
+#[derive(Clone)]
+enum Output {
+    Script(ScriptRun),
+    Merge(Mapping),
+}
+
+struct Entry {
+    key: MemoKey,
+    /// bytes the key was hashed from
+    size: usize,
+    output: Output,
+}
+
+#[derive(Default)]
+struct MemoCache {
+    /// most recently used first
+    entries: VecDeque<Entry>,
+    bytes: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl MemoCache {
+    fn get(&mut self, key: &MemoKey) -> Option<Output> {
+        let index = self.entries.iter().position(|e| e.key == *key)?;
+        let entry = self.entries.remove(index)?;
+        let output = entry.output.clone();
+        self.entries.push_front(entry);
+        self.hits += 1;
+        Some(output)
+    }
+
+    fn insert(&mut self, key: MemoKey, size: usize, output: Output) {
+        self.entries.retain(|e| e.key != key);
+        self.entries.push_front(Entry { key, size, output });
+        self.bytes = self.entries.iter().map(|e| e.size).sum();
+        while self.entries.len() > CAPACITY || self.bytes > MAX_BYTES {
+            let Some(evicted) = self.entries.pop_back() else {
+                break;
+            };
+            self.bytes -= evicted.size;
+        }
+    }
+
+    fn log_entry(&self, hit: bool) -> (String, String) {
+        let outcome = if hit { "hit" } else { "miss" };
+        (
+            "info".into(),
+            format!("cache {outcome} ({} hits, {} misses so far)", self.hits, self.misses).into(),
+        )
+    }
+}
+
+/// A sha256 that counts the bytes fed to it.
+#[derive(Default)]
+struct KeyHasher {
+    hasher: Sha256,
+    bytes: usize,
+}
+
+impl KeyHasher {
+    fn part(&mut self, part: &str) {
+        self.hasher.update(part.len().to_le_bytes());
+        self.hasher.update(part.as_bytes());
+    }
+}
+
+impl io::Write for KeyHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.update(buf);
+        self.bytes += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The key of a script run and the size of its input, `None` when the input can not be
+/// serialised, in which case the run is not cached.
+fn memo_key(script: &str, config: &Mapping, env: &ScriptEnv) -> Option<(MemoKey, usize)> {
+    let mut hasher = KeyHasher::default();
+    for part in [
+        "script",
+        script,
+        env.profile.name.as_str(),
+        env.core.as_deref().unwrap_or_default(),
+    ] {
+        hasher.part(part);
+    }
+    serde_json::to_writer(&mut hasher, &env.profile.extra).ok()?;
+    serde_json::to_writer(&mut hasher, config).ok()?;
+    Some((hasher.hasher.finalize().into(), hasher.bytes))
+}
+
+/// The key of a merge and the size of its input, as for [`memo_key`].
+fn merge_key(merge: &Mapping, config: &Mapping) -> Option<(MemoKey, usize)> {
+    let mut hasher = KeyHasher::default();
+    hasher.part("merge");
+    serde_json::to_writer(&mut hasher, merge).ok()?;
+    serde_json::to_writer(&mut hasher, config).ok()?;
+    Some((hasher.hasher.finalize().into(), hasher.bytes))
+}
+
+/// `run_script`, answered from the cache when the same script last saw the same input. Also
+/// returns the entry reporting the cache for the chain logs.
+pub async fn run_script_memoised(script: String, config: Mapping, env: &ScriptEnv) -> (ScriptRun, (String, String)) {
+    let key = memo_key(&script, &config, env);
+    if let Some((key, _)) = &key {
+        let mut cache = CACHE.lock();
+        if let Some(Output::Script(run)) = cache.get(key) {
+            return (run, cache.log_entry(true));
+        }
+    }
+
+    let run = run_script(script, config, env.profile.clone()).await;
+    let mut cache = CACHE.lock();
+    cache.misses += 1;
+    // 超时等限制与机器负载有关，不缓存
+    let repeatable = !run.error.as_ref().is_some_and(|err| err.kind == ScriptErrorKind::Limit);
+    if let Some((key, size)) = key
+        && repeatable
+    {
+        cache.insert(key, size, Output::Script(run.clone()));
+    }
+    let entry = cache.log_entry(false);
+    drop(cache);
+    (run, entry)
+}
+
+/// `use_merge`, answered from the cache as [`run_script_memoised`] is.
+pub fn use_merge_memoised(merge: &Mapping, config: Mapping) -> (Mapping, (String, String)) {
+    let key = merge_key(merge, &config);
+    if let Some((key, _)) = &key {
+        let mut cache = CACHE.lock();
+        if let Some(Output::Merge(merged)) = cache.get(key) {
+            return (merged, cache.log_entry(true));
+        }
+    }
+
+    let merged = use_merge(merge, config);
+    let mut cache = CACHE.lock();
+    cache.misses += 1;
+    if let Some((key, size)) = key {
+        cache.insert(key, size, Output::Merge(merged.clone()));
+    }
+    let entry = cache.log_entry(false);
+    drop(cache);
+    (merged, entry)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{CAPACITY, MAX_BYTES, MemoCache, Output, ScriptEnv, memo_key, run_script_memoised, use_merge_memoised};
+    use serde_yaml_ng::Mapping;
+
+    const SCRIPT: &str = "function main(config) { config.memo = (config.memo || 0) + 1; return config; }";
+
+    fn config(yaml: &str) -> Mapping {
+        serde_yaml_ng::from_str(yaml).expect("fixture should be valid yaml")
+    }
+
+    #[test]
+    fn the_key_follows_script_input_and_core() {
+        let env = ScriptEnv::default();
+        let input = config("mode: rule");
+        let key = memo_key(SCRIPT, &input, &env);
+        assert_eq!(key, memo_key(SCRIPT, &input, &env));
+        assert_ne!(key, memo_key("function main(c) { return c; }", &input, &env));
+        assert_ne!(key, memo_key(SCRIPT, &config("mode: global"), &env));
+        let alpha = ScriptEnv {
+            core: Some("verge-mihomo-alpha".into()),
+            ..ScriptEnv::default()
+        };
+        assert_ne!(key, memo_key(SCRIPT, &input, &alpha));
+    }
+
+    #[tokio::test]
+    async fn a_repeated_run_is_a_hit_with_the_same_output() {
+        let env = ScriptEnv::default();
+        let input = config("memo-test: a-repeated-run");
+
+        let (first, entry) = run_script_memoised(SCRIPT.into(), input.clone(), &env).await;
+        assert_eq!(entry.0, "info");
+        assert!(entry.1.starts_with("cache miss"), "{entry:?}");
+        let (second, entry) = run_script_memoised(SCRIPT.into(), input, &env).await;
+        assert!(entry.1.starts_with("cache hit"), "{entry:?}");
+        assert_eq!(first.config, second.config);
+        assert!(
+            second
+                .config
+                .is_some_and(|c| c.get("memo").and_then(serde_yaml_ng::Value::as_u64) == Some(1))
+        );
+    }
+
+    #[test]
+    fn a_repeated_merge_is_a_hit_with_the_same_output() {
+        let merge = config("memo-test: a-repeated-merge");
+        let input = config("mode: rule");
+
+        let (first, entry) = use_merge_memoised(&merge, input.clone());
+        assert!(entry.1.starts_with("cache miss"), "{entry:?}");
+        let (second, entry) = use_merge_memoised(&merge, input);
+        assert!(entry.1.starts_with("cache hit"), "{entry:?}");
+        assert_eq!(first, second);
+        assert_eq!(second.get("memo-test"), Some(&"a-repeated-merge".into()));
+    }
+
+    #[test]
+    fn the_cache_stays_within_its_count_and_bytes() {
+        let mut cache = MemoCache::default();
+        for i in 0..=CAPACITY as u8 {
+            cache.insert([i; 32], 1, Output::Merge(Mapping::new()));
+        }
+        assert_eq!(cache.entries.len(), CAPACITY);
+        assert!(cache.get(&[0; 32]).is_none());
+
+        cache.insert([100; 32], MAX_BYTES, Output::Merge(Mapping::new()));
+        assert_eq!(cache.entries.len(), 1);
+        assert_eq!(cache.bytes, MAX_BYTES);
+
+        cache.insert([101; 32], MAX_BYTES + 1, Output::Merge(Mapping::new()));
+        assert!(cache.entries.is_empty());
+        assert_eq!(cache.bytes, 0);
+    }
+}
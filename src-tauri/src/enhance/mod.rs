@@ -1,21 +1,33 @@
 mod chain;
 pub mod field;
+mod filter;
+mod memo;
 mod merge;
-mod script;
+pub mod preview;
+mod region;
+mod rule_set;
+pub mod script;
+mod script_api;
+pub mod script_error;
 pub mod seq;
 mod tun;
 
 use self::{
     chain::{AsyncChainItemFrom as _, ChainItem, ChainType},
     field::{use_keys, use_lowercase, use_sort},
-    merge::use_merge,
-    script::use_script,
+    filter::{NodeFilter, use_filter},
+    memo::{ScriptEnv, run_script_memoised, use_merge_memoised},
+    preview::{EnhancePreview, StageTrace},
+    region::use_region_groups,
+    rule_set::{RuleSetSource, collect_rule_sets, use_rule_sets},
+    script::{ScriptProfile, use_script},
+    script_error::ScriptError,
     seq::{SeqMap, use_seq},
     tun::use_tun,
 };
 use crate::utils::dirs;
 use crate::{
-    config::{Config, IProfiles, IVerge, PrfItem},
+    config::{Config, IProfiles, IVerge, PrfItem, PrfRegionGroups},
     constants,
     utils::tmpl,
 };
@@ -27,6 +39,8 @@ use std::collections::{HashMap, HashSet};
 use tokio::fs;
 
 type ResultLog = Vec<(String, String)>;
+/// 最终配置、存在的键、各链项日志，以及链中脚本的结构化错误
+pub type Enhanced = (Mapping, HashSet<String>, HashMap<String, ResultLog>, Vec<ScriptError>);
 #[derive(Debug)]
 struct ConfigValues {
     clash_config: Mapping,
@@ -51,16 +65,21 @@ struct ProfileItems {
     rules_item: ChainItem,
     proxies_item: ChainItem,
     groups_item: ChainItem,
+    filter_item: ChainItem,
     global_merge: ChainItem,
     global_script: ChainItem,
-    profile_name: String,
+    profile: ScriptProfile,
+    region_groups: Option<PrfRegionGroups>,
+    rule_sets: Vec<RuleSetSource>,
 }
 
 impl Default for ProfileItems {
     fn default() -> Self {
         Self {
             config: Default::default(),
-            profile_name: Default::default(),
+            profile: ScriptProfile::default(),
+            region_groups: None,
+            rule_sets: Vec::new(),
             merge_item: ChainItem {
                 uid: "".into(),
                 data: ChainType::Merge(Mapping::new()),
@@ -81,6 +100,10 @@ impl Default for ProfileItems {
                 uid: "".into(),
                 data: ChainType::Groups(SeqMap::default()),
             },
+            filter_item: ChainItem {
+                uid: "".into(),
+                data: ChainType::Filter(NodeFilter::default()),
+            },
             global_merge: ChainItem {
                 uid: "Merge".into(),
                 data: ChainType::Merge(Mapping::new()),
@@ -189,10 +212,20 @@ async fn collect_profile_items(profiles: &IProfiles) -> Result<ProfileItems> {
         .current_groups()
         .cloned()
         .unwrap_or_else(|| "Groups".into());
+    let filter_uid = current_item
+        .current_filter()
+        .cloned()
+        .unwrap_or_else(|| "Filter".into());
 
     let name = current_item.name.clone().unwrap_or_default();
-
-    let (merge_item, script_item, rules_item, proxies_item, groups_item, global_merge, global_script) = tokio::join!(
+    let region_groups = current_item
+        .option
+        .as_ref()
+        .and_then(|o| o.region_groups.clone())
+        .filter(|r| r.enable);
+    let rule_sets = collect_rule_sets(profiles, current_item).await;
+
+    let (merge_item, script_item, rules_item, proxies_item, groups_item, filter_item, global_merge, global_script) = tokio::join!(
         chain_item_or_default(profiles.get_item(&merge_uid).ok(), || ChainItem {
             uid: "".into(),
             data: ChainType::Merge(Mapping::new()),
@@ -213,6 +246,10 @@ async fn collect_profile_items(profiles: &IProfiles) -> Result<ProfileItems> {
             uid: "".into(),
             data: ChainType::Groups(SeqMap::default()),
         },),
+        chain_item_or_default(profiles.get_item(&filter_uid).ok(), || ChainItem {
+            uid: "".into(),
+            data: ChainType::Filter(NodeFilter::default()),
+        },),
         chain_item_or_default(profiles.get_item("Merge").ok(), || ChainItem {
             uid: "Merge".into(),
             data: ChainType::Merge(Mapping::new()),
@@ -230,9 +267,15 @@ async fn collect_profile_items(profiles: &IProfiles) -> Result<ProfileItems> {
         rules_item,
         proxies_item,
         groups_item,
+        filter_item,
         global_merge,
         global_script,
-        profile_name: name,
+        profile: ScriptProfile {
+            name,
+            extra: current_item.extra,
+        },
+        region_groups,
+        rule_sets,
     })
 }
 
@@ -242,29 +285,95 @@ async fn process_global_items(
     mut result_map: HashMap<String, ResultLog>,
     global_merge: ChainItem,
     global_script: ChainItem,
-    profile_name: &String,
+    env: &ScriptEnv,
+    trace: &mut StageTrace,
 ) -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
     if let ChainType::Merge(merge) = global_merge.data {
         exists_keys.extend(use_keys(&merge));
-        config = use_merge(&merge, config);
+        let cache_entry;
+        (config, cache_entry) = use_merge_memoised(&merge, config);
+        result_map.insert(global_merge.uid, vec![cache_entry]);
+        trace.record("global_merge", &config);
     }
 
     if let ChainType::Script(script) = global_script.data {
-        let mut logs = vec![];
-        match use_script(script, config.clone(), profile_name.clone()).await {
-            Ok((res_config, res_logs)) => {
-                extend_changed_keys(&mut exists_keys, &config, &res_config);
-                config = res_config;
-                logs.extend(res_logs);
-            }
-            Err(err) => logs.push(("exception".into(), err.to_string().into())),
-        }
+        let logs;
+        (config, logs) = run_chain_script(
+            script,
+            config,
+            &mut exists_keys,
+            env,
+            ("global_script", &global_script.uid),
+            trace,
+        )
+        .await;
         result_map.insert(global_script.uid, logs);
     }
 
     (config, exists_keys, result_map)
 }
 
+/// 运行链中的脚本项：成功时采用其输出，失败时保留原配置，并把结构化错误与缓存命中情况记入日志
+async fn run_chain_script(
+    script: String,
+    config: Mapping,
+    exists_keys: &mut Vec<String>,
+    env: &ScriptEnv,
+    (stage, uid): (&'static str, &str),
+    trace: &mut StageTrace,
+) -> (Mapping, ResultLog) {
+    let (run, cache_entry) = run_script_memoised(script, config.clone(), env).await;
+    let mut logs = run.logs;
+    logs.push(cache_entry);
+    let config = match run.config {
+        Some(res_config) => {
+            extend_changed_keys(exists_keys, &config, &res_config);
+            res_config
+        }
+        None => config,
+    };
+    if let Some(err) = run.error {
+        let err = err.in_stage(stage, uid);
+        logging!(warn, Type::Core, "脚本 {} 执行失败: {}", uid, err);
+        logs.extend(err.log_entries());
+        trace.fail(err);
+    }
+    trace.record(stage, &config);
+    (config, logs)
+}
+
+/// 订阅节点的过滤与重命名，先于顺序项，使用户手动追加的节点不受影响
+fn process_filter_item(
+    config: Mapping,
+    mut result_map: HashMap<String, ResultLog>,
+    filter_item: ChainItem,
+) -> (Mapping, HashMap<String, ResultLog>) {
+    let ChainType::Filter(filter) = filter_item.data else {
+        return (config, result_map);
+    };
+    if filter.is_empty() {
+        return (config, result_map);
+    }
+
+    let (config, log) = match use_filter(&filter, config.clone()) {
+        Ok((config, report)) => {
+            let summary = format!(
+                "kept {} nodes, dropped {}, renamed {}",
+                report.kept, report.dropped, report.renamed
+            );
+            (config, ("info".into(), summary.into()))
+        }
+        Err(err) => {
+            logging!(warn, Type::Core, "节点过滤配置无效，已跳过: {err:#}");
+            (config, ("exception".into(), format!("{err:#}").into()))
+        }
+    };
+    if !filter_item.uid.is_empty() {
+        result_map.insert(filter_item.uid, vec![log]);
+    }
+    (config, result_map)
+}
+
 fn process_seq_items(
     mut config: Mapping,
     rules_item: ChainItem,
@@ -438,23 +547,30 @@ async fn process_profile_items(
     mut result_map: HashMap<String, ResultLog>,
     merge_item: ChainItem,
     script_item: ChainItem,
-    profile_name: &String,
+    env: &ScriptEnv,
+    trace: &mut StageTrace,
 ) -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
     if let ChainType::Merge(merge) = merge_item.data {
         exists_keys.extend(use_keys(&merge));
-        config = use_merge(&merge, config);
+        let cache_entry;
+        (config, cache_entry) = use_merge_memoised(&merge, config);
+        if !merge_item.uid.is_empty() {
+            result_map.insert(merge_item.uid, vec![cache_entry]);
+        }
+        trace.record("merge", &config);
     }
 
     if let ChainType::Script(script) = script_item.data {
-        let mut logs = vec![];
-        match use_script(script, config.clone(), profile_name.clone()).await {
-            Ok((res_config, res_logs)) => {
-                extend_changed_keys(&mut exists_keys, &config, &res_config);
-                config = res_config;
-                logs.extend(res_logs);
-            }
-            Err(err) => logs.push(("exception".into(), err.to_string().into())),
-        }
+        let logs;
+        (config, logs) = run_chain_script(
+            script,
+            config,
+            &mut exists_keys,
+            env,
+            ("script", &script_item.uid),
+            trace,
+        )
+        .await;
         result_map.insert(script_item.uid, logs);
     }
 
@@ -548,7 +664,7 @@ async fn apply_builtin_scripts(mut config: Mapping, clash_core: Option<String>,
         for item in items {
             logging!(debug, Type::Core, "run builtin script {}", item.uid);
             if let ChainType::Script(script) = item.data {
-                match use_script(script, config.clone(), String::from("")).await {
+                match use_script(script, config.clone(), ScriptProfile::default()).await {
                     Ok((res_config, _)) => {
                         config = res_config;
                     }
@@ -704,7 +820,24 @@ async fn apply_dns_settings(mut config: Mapping, enable_dns_settings: bool) -> M
 
 /// Enhance mode
 /// 返回最终订阅、该订阅包含的键、和script执行的结果
-pub async fn enhance(profiles: &IProfiles) -> Result<(Mapping, HashSet<String>, HashMap<String, ResultLog>)> {
+pub async fn enhance(profiles: &IProfiles) -> Result<Enhanced> {
+    run_enhance(profiles, &mut StageTrace::disabled()).await
+}
+
+/// 试运行增强流程，记录每个阶段之后的配置与改动，不写入运行时配置
+pub async fn preview(profiles: &IProfiles) -> Result<EnhancePreview> {
+    let mut trace = StageTrace::enabled();
+    let (_, exists_keys, logs, script_errors) = run_enhance(profiles, &mut trace).await?;
+    Ok(EnhancePreview {
+        stages: trace.into_stages(),
+        exists_keys,
+        logs,
+        script_errors,
+    })
+}
+
+#[allow(clippy::cognitive_complexity)]
+async fn run_enhance(profiles: &IProfiles, trace: &mut StageTrace) -> Result<Enhanced> {
     // gather config values
     let cfg_vals = get_config_values().await;
     let ConfigValues {
@@ -730,14 +863,31 @@ pub async fn enhance(profiles: &IProfiles) -> Result<(Mapping, HashSet<String>,
     let rules_item = profile.rules_item;
     let proxies_item = profile.proxies_item;
     let groups_item = profile.groups_item;
+    let filter_item = profile.filter_item;
     let global_merge = profile.global_merge;
     let global_script = profile.global_script;
-    let profile_name = profile.profile_name;
+    let region_groups = profile.region_groups;
+    let rule_sets = profile.rule_sets;
+    let script_env = ScriptEnv {
+        profile: profile.profile,
+        core: clash_core.clone(),
+    };
 
     let result_map = HashMap::new();
+    trace.record("profile", &config);
 
-    // 顺序项先于手动覆盖。
+    // 节点过滤先于顺序项，顺序项与地区分组先于手动覆盖。
+    let (config, mut result_map) = process_filter_item(config, result_map, filter_item);
+    trace.record("filter", &config);
     let config = process_seq_items(config, rules_item, proxies_item, groups_item);
+    trace.record("seq", &config);
+    let config = match region_groups {
+        Some(options) => use_region_groups(&options, config),
+        None => config,
+    };
+    trace.record("region_groups", &config);
+    let config = use_rule_sets(rule_sets, config, &mut result_map);
+    trace.record("rule_sets", &config);
     let exists_keys = use_keys(&config).collect::<Vec<_>>();
 
     // merge default clash config
@@ -752,11 +902,15 @@ pub async fn enhance(profiles: &IProfiles) -> Result<(Mapping, HashSet<String>,
         #[cfg(target_os = "linux")]
         tproxy_enabled,
     );
+    trace.record("clash_config", &config);
 
     // app 生成项先于手动覆盖。
     let config = apply_builtin_scripts(config, clash_core, enable_builtin).await;
+    trace.record("builtin", &config);
     let config = use_tun(config, enable_tun);
+    trace.record("tun", &config);
     let config = apply_dns_settings(config, enable_dns_settings).await;
+    trace.record("dns", &config);
 
     // 手动覆盖前锁定 app 权威字段,覆盖后由同一个值恢复。
     let authoritative = AuthoritativeFields::capture(&config, enable_dns_settings);
@@ -768,25 +922,36 @@ pub async fn enhance(profiles: &IProfiles) -> Result<(Mapping, HashSet<String>,
         result_map,
         global_merge,
         global_script,
-        &profile_name,
+        &script_env,
+        trace,
     )
     .await;
 
     // 当前 profile 手动覆盖。
-    let (config, exists_keys, result_map) =
-        process_profile_items(config, exists_keys, result_map, merge_item, script_item, &profile_name).await;
+    let (config, exists_keys, result_map) = process_profile_items(
+        config,
+        exists_keys,
+        result_map,
+        merge_item,
+        script_item,
+        &script_env,
+        trace,
+    )
+    .await;
 
     // 手动覆盖后恢复 app 权威字段。
     let config = authoritative.enforce(config);
     let config = ensure_lan_bind_address(config);
+    trace.record("enforce", &config);
 
     let config = cleanup_proxy_groups(config);
     let config = use_sort(config);
+    trace.record("cleanup", &config);
 
     let mut exists_keys_set = HashSet::new();
     exists_keys_set.extend(exists_keys);
 
-    Ok((config, exists_keys_set, result_map))
+    Ok((config, exists_keys_set, result_map, trace.take_errors()))
 }
 
 #[cfg(test)]
@@ -1252,8 +1417,8 @@ mod authoritative_field_tests {
 #[cfg(test)]
 mod tests {
     use super::{
-        ChainItem, ChainType, cleanup_proxy_groups, ensure_lan_bind_address, process_global_items,
-        process_profile_items, use_keys,
+        ChainItem, ChainType, ScriptEnv, ScriptProfile, StageTrace, cleanup_proxy_groups, ensure_lan_bind_address,
+        process_global_items, process_profile_items, use_keys,
     };
     use std::collections::HashMap;
 
@@ -1304,14 +1469,22 @@ mod tests {
             }"#,
         );
 
-        let profile_name = "test-profile".into();
+        let env = ScriptEnv {
+            profile: ScriptProfile {
+                name: "test-profile".into(),
+                extra: None,
+            },
+            core: None,
+        };
+        let mut trace = StageTrace::enabled();
         let (config, exists_keys, result_map) = process_global_items(
             config,
             exists_keys,
             HashMap::new(),
             global_merge,
             global_script,
-            &profile_name,
+            &env,
+            &mut trace,
         )
         .await;
         let (config, exists_keys, _) = process_profile_items(
@@ -1320,9 +1493,16 @@ mod tests {
             result_map,
             profile_merge,
             profile_script,
-            &profile_name,
+            &env,
+            &mut trace,
         )
         .await;
+        let stages = trace.into_stages();
+        assert_eq!(
+            stages.iter().map(|stage| stage.name).collect::<Vec<_>>(),
+            ["global_merge", "global_script", "merge", "script"]
+        );
+        assert_eq!(stages[3].diff.keys_changed, ["profile-script-wins", "nested"]);
 
         let string_value = |key| config.get(key).and_then(serde_yaml_ng::Value::as_str);
         assert_eq!(string_value("global-merge-wins"), Some("global-merge"));
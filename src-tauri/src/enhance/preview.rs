@@ -0,0 +1,152 @@
+//! Dry run of the enhance pipeline.
+//!
+//! `enhance` only hands back the final mapping. When a merge or script misbehaves, the question
+//! is which stage did it, so a preview keeps a snapshot of the config after every stage together
+//! with what that stage changed. Nothing is written to the runtime config.
+
+use super::{ResultLog, script_error::ScriptError};
+use crate::config::diff::{ProfileDiff, diff_profiles};
+use serde::Serialize;
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
+use std::collections::{HashMap, HashSet};
+
+/// keys `ProfileDiff` already describes entry by entry
+const LISTED_KEYS: &[&str] = &["proxies", "proxy-groups", "rules"];
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StageDiff {
+    #[serde(flatten)]
+    pub profile: ProfileDiff,
+    pub keys_added: Vec<String>,
+    pub keys_removed: Vec<String>,
+    /// top-level keys present on both sides whose value changed, other than the listed ones
+    pub keys_changed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnhanceStage {
+    pub name: &'static str,
+    pub config: Mapping,
+    /// against the previous stage, or an empty config for the first one
+    pub diff: StageDiff,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnhancePreview {
+    pub stages: Vec<EnhanceStage>,
+    pub exists_keys: HashSet<String>,
+    pub logs: HashMap<String, ResultLog>,
+    pub script_errors: Vec<ScriptError>,
+}
+
+/// Collects stage snapshots during a preview. Script errors are collected on every run.
+pub(super) struct StageTrace {
+    stages: Option<Vec<EnhanceStage>>,
+    errors: Vec<ScriptError>,
+}
+
+impl StageTrace {
+    pub(super) const fn disabled() -> Self {
+        Self {
+            stages: None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub(super) const fn enabled() -> Self {
+        Self {
+            stages: Some(Vec::new()),
+            errors: Vec::new(),
+        }
+    }
+
+    pub(super) fn record(&mut self, name: &'static str, config: &Mapping) {
+        let Some(stages) = self.stages.as_mut() else {
+            return;
+        };
+        let diff = match stages.last() {
+            Some(previous) => diff_stage(&previous.config, config),
+            None => diff_stage(&Mapping::new(), config),
+        };
+        stages.push(EnhanceStage {
+            name,
+            config: config.clone(),
+            diff,
+        });
+    }
+
+    pub(super) fn fail(&mut self, error: ScriptError) {
+        self.errors.push(error);
+    }
+
+    pub(super) fn take_errors(&mut self) -> Vec<ScriptError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub(super) fn into_stages(self) -> Vec<EnhanceStage> {
+        self.stages.unwrap_or_default()
+    }
+}
+
+/// What changed from `old` to `new`, down to entries for proxies, groups and rules and down to
+/// top-level keys for everything else.
+pub fn diff_stage(old: &Mapping, new: &Mapping) -> StageDiff {
+    let key = |k: &Value| k.as_str().filter(|k| !LISTED_KEYS.contains(k)).map(String::from);
+
+    let keys_added = new.keys().filter(|k| !old.contains_key(*k)).filter_map(key).collect();
+    let keys_removed = old.keys().filter(|k| !new.contains_key(*k)).filter_map(key).collect();
+    let keys_changed = new
+        .iter()
+        .filter(|(k, v)| old.get(*k).is_some_and(|previous| previous != *v))
+        .filter_map(|(k, _)| key(k))
+        .collect();
+
+    StageDiff {
+        profile: diff_profiles(old, new),
+        keys_added,
+        keys_removed,
+        keys_changed,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{StageDiff, StageTrace, diff_stage};
+    use serde_yaml_ng::Mapping;
+
+    fn config(yaml: &str) -> Mapping {
+        serde_yaml_ng::from_str(yaml).expect("fixture should be valid yaml")
+    }
+
+    #[test]
+    fn stage_diff_reports_entries_and_top_level_keys() {
+        let old = config("mode: rule\nlog-level: info\nrules:\n  - MATCH,DIRECT\n");
+        let new =
+            config("mode: global\ndns: { enable: true }\nrules:\n  - DOMAIN,example.com,DIRECT\n  - MATCH,DIRECT\n");
+
+        let diff = diff_stage(&old, &new);
+        assert_eq!(diff.keys_added, ["dns"]);
+        assert_eq!(diff.keys_removed, ["log-level"]);
+        assert_eq!(diff.keys_changed, ["mode"]);
+        assert_eq!(diff.profile.rules_added, ["DOMAIN,example.com,DIRECT"]);
+    }
+
+    #[test]
+    fn only_an_enabled_trace_keeps_snapshots() {
+        let mut disabled = StageTrace::disabled();
+        disabled.record("profile", &config("mode: rule"));
+        assert!(disabled.into_stages().is_empty());
+
+        let mut trace = StageTrace::enabled();
+        trace.record("profile", &config("mode: rule"));
+        trace.record("tun", &config("mode: rule"));
+        let stages = trace.into_stages();
+        assert_eq!(stages[0].diff.keys_added, ["mode"]);
+        assert_eq!(stages[1].diff, StageDiff::default());
+    }
+}
@@ -0,0 +1,315 @@
+//! Per-region groups generated from node names.
+//!
+//! Subscriptions mark a node's region with a flag emoji or a keyword (`🇭🇰`, `香港`, `HK`,
+//! `Tokyo`). Nodes are classified by the first flag in their name, or failing that by the
+//! keyword table below, and each region found gets a `url-test` (or `fallback`) group that is
+//! offered in a selector. Groups the profile already defines under the same name are left alone;
+//! a node that happens to carry the name pushes the group to a numbered one instead.
+//! Nodes behind `proxy-providers` are only known to the core and are not classified.
+
+use crate::config::PrfRegionGroups;
+use clash_verge_media_unlock::country_code_to_emoji;
+use serde_yaml_ng::{Mapping, Value};
+use smartstring::alias::String;
+use std::collections::HashSet;
+
+const DEFAULT_TEST_URL: &str = "https://www.gstatic.com/generate_204";
+const DEFAULT_INTERVAL: u64 = 300;
+
+/// ISO 3166 code, then the keywords that name the region. Short ASCII codes only match as a
+/// whole word and in upper case, so `US` does not fire on `Russia` or `plus`, and a single
+/// character like `港` only on its own, so it does not fire on `港口`.
+const REGIONS: &[(&str, &[&str])] = &[
+    ("HK", &["香港", "港", "HK", "Hong Kong", "HongKong"]),
+    ("TW", &["台湾", "臺灣", "台北", "TW", "Taiwan", "Taipei"]),
+    ("MO", &["澳门", "澳門", "MO", "Macau", "Macao"]),
+    ("JP", &["日本", "东京", "大阪", "JP", "Japan", "Tokyo", "Osaka"]),
+    ("KR", &["韩国", "韓國", "首尔", "KR", "Korea", "Seoul"]),
+    ("SG", &["新加坡", "狮城", "SG", "Singapore"]),
+    (
+        "US",
+        &[
+            "美国",
+            "美國",
+            "洛杉矶",
+            "硅谷",
+            "US",
+            "USA",
+            "United States",
+            "America",
+            "Los Angeles",
+            "San Jose",
+            "Seattle",
+        ],
+    ),
+    ("CA", &["加拿大", "CA", "Canada", "Toronto"]),
+    (
+        "GB",
+        &[
+            "英国",
+            "英國",
+            "伦敦",
+            "UK",
+            "GB",
+            "United Kingdom",
+            "Britain",
+            "London",
+        ],
+    ),
+    ("DE", &["德国", "德國", "法兰克福", "DE", "Germany", "Frankfurt"]),
+    ("FR", &["法国", "法國", "巴黎", "FR", "France", "Paris"]),
+    ("NL", &["荷兰", "荷蘭", "阿姆斯特丹", "NL", "Netherlands", "Amsterdam"]),
+    ("AU", &["澳大利亚", "澳洲", "悉尼", "AU", "Australia", "Sydney"]),
+    ("IN", &["印度", "孟买", "IN", "India", "Mumbai"]),
+    ("RU", &["俄罗斯", "俄羅斯", "莫斯科", "RU", "Russia", "Moscow"]),
+    ("TR", &["土耳其", "TR", "Turkey", "Istanbul"]),
+];
+
+/// The two letters behind the first flag emoji in `name`.
+fn flag_code(name: &str) -> Option<std::string::String> {
+    const INDICATOR_A: u32 = 0x1F1E6;
+    let letter = |c: char| {
+        let offset = (c as u32).checked_sub(INDICATOR_A).filter(|o| *o < 26)?;
+        char::from_u32('A' as u32 + offset)
+    };
+    let chars = name.chars().collect::<Vec<_>>();
+    chars
+        .windows(2)
+        .find_map(|pair| Some([letter(pair[0])?, letter(pair[1])?].iter().collect()))
+}
+
+fn contains_keyword(name: &str, keyword: &str) -> bool {
+    let ascii = keyword.is_ascii();
+    if ascii && keyword.len() > 3 {
+        return name.to_ascii_lowercase().contains(&keyword.to_ascii_lowercase());
+    }
+    if !ascii && keyword.chars().count() > 1 {
+        return name.contains(keyword);
+    }
+    let is_letter = |c: char| {
+        if ascii {
+            c.is_ascii_alphabetic()
+        } else {
+            c.is_alphabetic()
+        }
+    };
+    name.match_indices(keyword).any(|(start, matched)| {
+        let before = name[..start].chars().next_back();
+        let after = name[start + matched.len()..].chars().next();
+        !before.is_some_and(is_letter) && !after.is_some_and(is_letter)
+    })
+}
+
+/// Region code of a node, from its flag or the keyword table.
+pub fn classify(name: &str) -> Option<std::string::String> {
+    let flag = flag_code(name);
+    // the Taiwan flag is often swapped for the China one, the name's keywords tell them apart
+    if let Some(code) = flag.as_ref().filter(|code| *code != "CN") {
+        return Some(code.clone());
+    }
+    REGIONS
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|keyword| contains_keyword(name, keyword)))
+        .map(|(code, _)| (*code).to_owned())
+        .or(flag)
+}
+
+fn group_name(code: &str) -> String {
+    let flag = country_code_to_emoji(code);
+    if flag.is_empty() {
+        code.into()
+    } else {
+        format!("{flag} {code}").into()
+    }
+}
+
+/// Add one group per region found among `config`'s proxies and offer them in the selector.
+pub fn use_region_groups(options: &PrfRegionGroups, mut config: Mapping) -> Mapping {
+    let wanted = options
+        .regions
+        .as_ref()
+        .map(|codes| codes.iter().map(|c| c.to_ascii_uppercase()).collect::<HashSet<_>>());
+
+    // region code -> member names, in the order regions first appear
+    let mut regions: Vec<(std::string::String, Vec<Value>)> = Vec::new();
+    let names = config
+        .get("proxies")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.get("name").and_then(Value::as_str));
+    for name in names {
+        let Some(code) = classify(name) else {
+            continue;
+        };
+        if wanted.as_ref().is_some_and(|wanted| !wanted.contains(&code)) {
+            continue;
+        }
+        match regions.iter_mut().find(|(c, _)| *c == code) {
+            Some((_, members)) => members.push(name.into()),
+            None => regions.push((code, vec![name.into()])),
+        }
+    }
+    if regions.is_empty() {
+        return config;
+    }
+
+    let group_type = match options.group_type.as_deref() {
+        Some("fallback") => "fallback",
+        _ => "url-test",
+    };
+    let url = options.url.as_deref().unwrap_or(DEFAULT_TEST_URL);
+    let interval = options.interval.unwrap_or(DEFAULT_INTERVAL);
+
+    let mut groups = config
+        .get("proxy-groups")
+        .and_then(Value::as_sequence)
+        .cloned()
+        .unwrap_or_default();
+    let existing = groups
+        .iter()
+        .filter_map(|g| g.get("name").and_then(Value::as_str).map(String::from))
+        .collect::<HashSet<_>>();
+    // proxies and groups share one namespace in mihomo
+    let mut taken = config
+        .get("proxies")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.get("name").and_then(Value::as_str).map(String::from))
+        .chain(existing.iter().cloned())
+        .collect::<HashSet<_>>();
+
+    let mut added = Vec::new();
+    for (code, members) in regions {
+        let base = group_name(&code);
+        if existing.contains(&base) {
+            continue;
+        }
+        let mut name = base.clone();
+        let mut n = 2;
+        while !taken.insert(name.clone()) {
+            name = format!("{base} {n}").into();
+            n += 1;
+        }
+        let mut group = Mapping::new();
+        group.insert("name".into(), name.as_str().into());
+        group.insert("type".into(), group_type.into());
+        group.insert("proxies".into(), Value::Sequence(members));
+        group.insert("url".into(), url.into());
+        group.insert("interval".into(), interval.into());
+        groups.push(Value::Mapping(group));
+        added.push(Value::from(name.as_str()));
+    }
+
+    let selector = groups
+        .iter_mut()
+        .filter_map(Value::as_mapping_mut)
+        .find(|g| match options.selector.as_deref() {
+            Some(selector) => g.get("name").and_then(Value::as_str) == Some(selector),
+            None => g.get("type").and_then(Value::as_str) == Some("select"),
+        });
+    if let Some(selector) = selector
+        && let Some(members) = selector
+            .entry("proxies".into())
+            .or_insert_with(|| Value::Sequence(Vec::new()))
+            .as_sequence_mut()
+    {
+        members.extend(added);
+    }
+
+    config.insert("proxy-groups".into(), Value::Sequence(groups));
+    config
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{classify, use_region_groups};
+    use crate::config::PrfRegionGroups;
+    use serde_yaml_ng::{Mapping, Value};
+
+    #[test]
+    fn classifies_by_flag_then_keyword() {
+        assert_eq!(classify("🇯🇵 Osaka 02").as_deref(), Some("JP"));
+        assert_eq!(classify("香港 IPLC 01").as_deref(), Some("HK"));
+        assert_eq!(classify("US-LAX-01").as_deref(), Some("US"));
+        assert_eq!(classify("🇨🇳 台湾 01").as_deref(), Some("TW"));
+        assert_eq!(classify("Russia 1").as_deref(), Some("RU"));
+        assert_eq!(classify("Traffic left: 100G"), None);
+    }
+
+    #[test]
+    fn a_flag_alone_is_enough() {
+        assert_eq!(classify("🇸🇪 01").as_deref(), Some("SE"));
+        assert_eq!(classify("🇨🇳 01").as_deref(), Some("CN"));
+    }
+
+    #[test]
+    fn single_character_keywords_only_match_on_their_own() {
+        assert_eq!(classify("港 01").as_deref(), Some("HK"));
+        assert_eq!(classify("[港]IPLC").as_deref(), Some("HK"));
+        assert_eq!(classify("港口 01"), None);
+        assert_eq!(classify("成田空港 01"), None);
+    }
+
+    #[test]
+    fn region_groups_do_not_take_a_node_name() {
+        let config: Mapping = serde_yaml_ng::from_str(
+            r"
+proxies:
+  - { name: 🇭🇰 HK, type: ss, server: a.example.com, port: 1 }
+proxy-groups:
+  - { name: PROXY, type: select, proxies: [DIRECT] }
+",
+        )
+        .expect("fixture should be valid yaml");
+        let options = PrfRegionGroups {
+            enable: true,
+            ..PrfRegionGroups::default()
+        };
+
+        let config = use_region_groups(&options, config);
+        assert_eq!(config["proxy-groups"][1]["name"], Value::from("🇭🇰 HK 2"));
+        assert_eq!(
+            config["proxy-groups"][1]["proxies"],
+            serde_yaml_ng::from_str::<Value>("[🇭🇰 HK]").expect("valid yaml")
+        );
+    }
+
+    #[test]
+    fn builds_region_groups_and_offers_them_in_the_selector() {
+        let config: Mapping = serde_yaml_ng::from_str(
+            r"
+proxies:
+  - { name: 🇭🇰 HK 01, type: ss, server: a.example.com, port: 1 }
+  - { name: 日本 01, type: ss, server: b.example.com, port: 1 }
+  - { name: 香港 02, type: ss, server: c.example.com, port: 1 }
+proxy-groups:
+  - { name: PROXY, type: select, proxies: [DIRECT] }
+",
+        )
+        .expect("fixture should be valid yaml");
+        let options = PrfRegionGroups {
+            enable: true,
+            group_type: Some("fallback".into()),
+            ..PrfRegionGroups::default()
+        };
+
+        let config = use_region_groups(&options, config);
+        let groups = config["proxy-groups"]
+            .as_sequence()
+            .expect("groups should be a sequence");
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[1]["name"], Value::from("🇭🇰 HK"));
+        assert_eq!(groups[1]["type"], Value::from("fallback"));
+        assert_eq!(
+            groups[1]["proxies"],
+            serde_yaml_ng::from_str::<Value>("[🇭🇰 HK 01, 香港 02]").expect("valid yaml")
+        );
+        assert_eq!(
+            groups[0]["proxies"],
+            serde_yaml_ng::from_str::<Value>("[DIRECT, 🇭🇰 HK, 🇯🇵 JP]").expect("valid yaml")
+        );
+    }
+}
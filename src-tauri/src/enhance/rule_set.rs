@@ -0,0 +1,213 @@
+//! Rule-set subscriptions applied to the current profile.
+//!
+//! A `ruleset` item keeps its downloaded list in the profiles dir as a mihomo rule-provider file.
+//! Each rule set the profile lists in `rule_sets` is put in front of the profile's own rules,
+//! either as one `RULE-SET` rule over a `file` provider reading that list, or as the rules
+//! themselves when its mode is `inline`.
+
+use super::ResultLog;
+use crate::{
+    config::{IProfiles, PrfItem, PrfRuleSet},
+    convert::rule_list::{RuleList, parse_rule_list},
+};
+use clash_verge_logging::{Type, logging};
+use serde_yaml_ng::{Mapping, Sequence, Value};
+use smartstring::alias::String;
+use std::collections::{HashMap, HashSet};
+
+const BUILTIN_POLICIES: &[&str] = &["DIRECT", "REJECT", "REJECT-DROP", "PASS", "COMPATIBLE"];
+
+#[derive(Debug)]
+pub struct RuleSetSource {
+    pub uid: String,
+    pub name: String,
+    /// provider path, relative to the core's home dir
+    pub path: String,
+    pub option: PrfRuleSet,
+    pub list: RuleList,
+}
+
+/// The rule sets `item` lists, in order. Ones that can not be read are skipped with a warning;
+/// the profile still works without them.
+pub async fn collect_rule_sets(profiles: &IProfiles, item: &PrfItem) -> Vec<RuleSetSource> {
+    let uids = item
+        .option
+        .as_ref()
+        .and_then(|o| o.rule_sets.clone())
+        .unwrap_or_default();
+
+    let mut sources = Vec::new();
+    for uid in uids {
+        let Ok(rule_set) = profiles.get_item(&uid) else {
+            continue;
+        };
+        let (Some(file), Some(option)) = (
+            rule_set.file.as_ref(),
+            rule_set.option.as_ref().and_then(|o| o.rule_set.clone()),
+        ) else {
+            continue;
+        };
+        let list = match rule_set.read_file().await {
+            Ok(data) => parse_rule_list(&data),
+            Err(err) => {
+                logging!(warn, Type::Core, "读取规则集 {} 失败: {}", uid, err);
+                None
+            }
+        };
+        let Some(list) = list else {
+            continue;
+        };
+        sources.push(RuleSetSource {
+            name: rule_set.name.clone().unwrap_or_else(|| uid.clone()),
+            path: format!("./profiles/{file}").into(),
+            uid,
+            option,
+            list,
+        });
+    }
+    sources
+}
+
+fn policy_names(config: &Mapping) -> HashSet<&str> {
+    ["proxies", "proxy-groups"]
+        .iter()
+        .filter_map(|key| config.get(*key).and_then(Value::as_sequence))
+        .flatten()
+        .filter_map(|entry| entry.get("name").and_then(Value::as_str))
+        .chain(BUILTIN_POLICIES.iter().copied())
+        .collect()
+}
+
+/// Put `sources` in front of `config`'s rules, logging what each contributed under its uid.
+pub fn use_rule_sets(
+    sources: Vec<RuleSetSource>,
+    mut config: Mapping,
+    result_map: &mut HashMap<String, ResultLog>,
+) -> Mapping {
+    if sources.is_empty() {
+        return config;
+    }
+
+    let known_policies = policy_names(&config);
+    let mut providers = config
+        .get("rule-providers")
+        .and_then(Value::as_mapping)
+        .cloned()
+        .unwrap_or_default();
+    let mut rules = Sequence::new();
+
+    for source in sources {
+        let policy = source.option.policy.as_str();
+        let mut logs = Vec::new();
+        if !known_policies.contains(policy) {
+            logs.push((
+                "warn".into(),
+                format!("policy \"{policy}\" is not defined by the profile").into(),
+            ));
+        }
+
+        if source.option.mode.as_deref() == Some("inline") {
+            let inline = source.list.to_rules(policy);
+            logs.push((
+                "info".into(),
+                format!("{} inline rules to {policy}", inline.len()).into(),
+            ));
+            rules.extend(inline.into_iter().map(Value::from));
+        } else {
+            let mut name = source.name.clone();
+            let mut n = 2;
+            while providers.contains_key(name.as_str()) {
+                name = format!("{} {n}", source.name).into();
+                n += 1;
+            }
+            let mut provider = Mapping::new();
+            provider.insert("type".into(), "file".into());
+            provider.insert("behavior".into(), source.list.behavior.as_str().into());
+            provider.insert("format".into(), "yaml".into());
+            provider.insert("path".into(), source.path.as_str().into());
+            providers.insert(name.as_str().into(), Value::Mapping(provider));
+            rules.push(format!("RULE-SET,{name},{policy}").into());
+            logs.push((
+                "info".into(),
+                format!(
+                    "{} entries as rule provider \"{name}\" to {policy}",
+                    source.list.payload.len()
+                )
+                .into(),
+            ));
+        }
+        result_map.insert(source.uid, logs);
+    }
+
+    if !providers.is_empty() {
+        config.insert("rule-providers".into(), Value::Mapping(providers));
+    }
+    if let Some(Value::Sequence(existing)) = config.get("rules") {
+        rules.extend(existing.iter().cloned());
+    }
+    config.insert("rules".into(), Value::Sequence(rules));
+    config
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{RuleSetSource, use_rule_sets};
+    use crate::{config::PrfRuleSet, convert::rule_list::parse_rule_list};
+    use serde_yaml_ng::{Mapping, Value};
+    use std::collections::HashMap;
+
+    fn source(uid: &str, mode: Option<&str>) -> RuleSetSource {
+        RuleSetSource {
+            uid: uid.into(),
+            name: "Ads".into(),
+            path: format!("./profiles/{uid}.yaml").into(),
+            option: PrfRuleSet {
+                policy: "REJECT".into(),
+                mode: mode.map(Into::into),
+            },
+            list: parse_rule_list("ads.example.com\n.tracker.example.org\n").expect("list should parse"),
+        }
+    }
+
+    #[test]
+    fn rule_sets_go_in_front_of_the_profile_rules() {
+        let config: Mapping = serde_yaml_ng::from_str(
+            r"
+rule-providers:
+  Ads: { type: http, behavior: domain, url: https://example.com/ads.yaml, path: ./ads.yaml }
+rules:
+  - MATCH,DIRECT
+",
+        )
+        .expect("fixture should be valid yaml");
+        let mut logs = HashMap::new();
+
+        let config = use_rule_sets(
+            vec![source("S1", None), source("S2", Some("inline"))],
+            config,
+            &mut logs,
+        );
+        let rules = config["rules"]
+            .as_sequence()
+            .expect("rules should be a sequence")
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rules,
+            [
+                "RULE-SET,Ads 2,REJECT",
+                "DOMAIN,ads.example.com,REJECT",
+                "DOMAIN-SUFFIX,tracker.example.org,REJECT",
+                "MATCH,DIRECT"
+            ]
+        );
+        assert_eq!(
+            config["rule-providers"]["Ads 2"]["path"],
+            Value::from("./profiles/S1.yaml")
+        );
+        assert_eq!(config["rule-providers"]["Ads 2"]["behavior"], Value::from("domain"));
+        assert_eq!(logs.len(), 2);
+    }
+}
@@ -1,13 +1,20 @@
 use crate::process::AsyncHandler;
 
-use super::field::{use_lowercase, use_lowercase_owned};
-use anyhow::{Error, Result};
-use boa_engine::{Context, JsString, JsValue, Source, native_function::NativeFunction};
+use super::{
+    field::{use_lowercase, use_lowercase_owned},
+    script_api,
+    script_error::{ScriptError, ScriptErrorKind},
+};
+use crate::config::PrfExtra;
+use crate::utils::help;
+use anyhow::{Context as _, Error, Result};
+use boa_engine::{Context, JsError, JsResult, JsString, JsValue, Source, native_function::NativeFunction};
 use clash_verge_logging::{Type, logging_error};
 use parking_lot::Mutex;
-use serde_yaml_ng::Mapping;
+use serde::{Deserialize, Serialize};
+use serde_yaml_ng::{Mapping, Value};
 use smartstring::alias::String;
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 const MAX_OUTPUTS: usize = 1000;
 const MAX_OUTPUT_SIZE: usize = 1024 * 1024; // 1MB
@@ -15,16 +22,84 @@ const MAX_JSON_SIZE: usize = 10 * 1024 * 1024; // 10MB
 const MAX_LOOP_ITERATIONS: u64 = 10_000_000;
 const SCRIPT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
-pub async fn use_script(script: String, config: Mapping, name: String) -> Result<(Mapping, Vec<(String, String)>)> {
-    let handle = AsyncHandler::spawn_blocking(move || use_script_sync(script, &config, &name));
+/// The profile a script runs for: `main`'s second argument and `verge.profile`.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptProfile {
+    pub name: String,
+    pub extra: Option<PrfExtra>,
+}
+
+/// What a script run produced. `config` is `None` when it failed, leaving the caller's own.
+#[derive(Debug, Default, Clone)]
+pub struct ScriptRun {
+    pub config: Option<Mapping>,
+    pub logs: Vec<(String, String)>,
+    pub error: Option<ScriptError>,
+}
+
+impl ScriptRun {
+    fn failed(logs: Vec<(String, String)>, error: ScriptError) -> Self {
+        Self {
+            config: None,
+            logs,
+            error: Some(error),
+        }
+    }
+}
+
+/// Run `script` in its own engine under the output, loop and time limits.
+pub async fn run_script(script: String, config: Mapping, profile: ScriptProfile) -> ScriptRun {
+    let handle = AsyncHandler::spawn_blocking(move || run_script_sync(&script, &config, &profile));
     match tokio::time::timeout(SCRIPT_TIMEOUT, handle).await {
-        Ok(Ok(result)) => result,
-        Ok(Err(join_err)) => Err(anyhow::anyhow!("script task panicked: {join_err}")),
-        Err(_elapsed) => Err(anyhow::anyhow!("script execution timed out after {:?}", SCRIPT_TIMEOUT)),
+        Ok(Ok(run)) => run,
+        Ok(Err(join_err)) => ScriptRun::failed(
+            Vec::new(),
+            ScriptError::new(ScriptErrorKind::Limit, format!("script task panicked: {join_err}")),
+        ),
+        Err(_elapsed) => ScriptRun::failed(
+            Vec::new(),
+            ScriptError::new(
+                ScriptErrorKind::Limit,
+                format!("script execution timed out after {SCRIPT_TIMEOUT:?}"),
+            ),
+        ),
     }
 }
 
-fn use_script_sync(script: String, config: &Mapping, name: &String) -> Result<(Mapping, Vec<(String, String)>)> {
+pub async fn use_script(
+    script: String,
+    config: Mapping,
+    profile: ScriptProfile,
+) -> Result<(Mapping, Vec<(String, String)>)> {
+    let fallback = use_lowercase(&config);
+    into_result(run_script(script, config, profile).await, fallback)
+}
+
+/// The older shape of a run: a script that could not run at all is an error, one that threw
+/// leaves the config as it was with an `exception` in the logs.
+fn into_result(run: ScriptRun, fallback: Mapping) -> Result<(Mapping, Vec<(String, String)>)> {
+    let ScriptRun {
+        config,
+        mut logs,
+        error,
+    } = run;
+    match error {
+        Some(err) if err.is_fatal() => Err(err.into()),
+        Some(err) => {
+            logs.extend(err.log_entries());
+            Ok((config.unwrap_or(fallback), logs))
+        }
+        None => Ok((config.unwrap_or(fallback), logs)),
+    }
+}
+
+/// Parse `source` and check it defines `main`, without running it.
+pub fn check_script(source: &str) -> Result<(), ScriptError> {
+    let (mut context, _) = script_context(&ScriptProfile::default());
+    define_script(&mut context, source)
+}
+
+fn script_context(profile: &ScriptProfile) -> (Context, Arc<Mutex<Vec<(String, String)>>>) {
     let mut context = Context::default();
 
     context
@@ -87,57 +162,165 @@ fn use_script_sync(script: String, config: &Mapping, name: &String) -> Result<(M
       });"#,
     ));
 
-    let config = use_lowercase(config);
-    let config_str = serde_json::to_string(&config)?;
-    if config_str.len() > MAX_JSON_SIZE {
-        anyhow::bail!("Configuration size exceeds maximum allowed size");
+    let _ =
+        context.register_global_builtin_callable("__verge_call__".into(), 3, NativeFunction::from_fn_ptr(verge_call));
+    let profile_literal = script_api::profile_literal(&profile.name, profile.extra.as_ref());
+    let _ = context.eval(Source::from_bytes(
+        format!("var __verge_profile__ = {profile_literal};").as_str(),
+    ));
+    let _ = context.eval(Source::from_bytes(script_api::PRELUDE));
+
+    (context, outputs)
+}
+
+/// Evaluate the script on its own, so positions in a parse error are the script's own lines.
+fn define_script(context: &mut Context, source: &str) -> Result<(), ScriptError> {
+    if let Err(err) = context.eval(Source::from_bytes(source)) {
+        let message = err.to_string();
+        let error = if message.starts_with("SyntaxError") {
+            ScriptError::new(ScriptErrorKind::Syntax, format!("Script syntax error: {message}"))
+        } else {
+            ScriptError::new(ScriptErrorKind::Runtime, message)
+        };
+        return Err(error.locate(source));
     }
 
+    let has_main = context
+        .eval(Source::from_bytes("typeof main === 'function'"))
+        .is_ok_and(|value| value.as_boolean() == Some(true));
+    if !has_main {
+        return Err(ScriptError::new(
+            ScriptErrorKind::MissingMain,
+            "Script must contain a main function",
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct ThrownError {
+    message: std::string::String,
+    stack: Option<std::string::String>,
+}
+
+fn run_script_sync(script: &str, config: &Mapping, profile: &ScriptProfile) -> ScriptRun {
+    let name = &profile.name;
+    let (mut context, outputs) = script_context(profile);
+    let logs = || outputs.lock().to_vec();
+
+    let config = use_lowercase(config);
+    let config_str = match serde_json::to_string(&config) {
+        Ok(config_str) if config_str.len() <= MAX_JSON_SIZE => config_str,
+        Ok(_) => {
+            return ScriptRun::failed(
+                logs(),
+                ScriptError::new(
+                    ScriptErrorKind::Limit,
+                    "Configuration size exceeds maximum allowed size",
+                ),
+            );
+        }
+        Err(err) => return ScriptRun::failed(logs(), ScriptError::new(ScriptErrorKind::Limit, err.to_string())),
+    };
+
     // 仅处理 name 参数中的特殊字符
     let safe_name = escape_js_string_for_single_quote(name);
     if safe_name.len() > 1024 {
-        anyhow::bail!("Name parameter too long");
+        return ScriptRun::failed(
+            logs(),
+            ScriptError::new(ScriptErrorKind::Limit, "Name parameter too long"),
+        );
+    }
+
+    if let Err(err) = define_script(&mut context, script) {
+        return ScriptRun::failed(logs(), err);
     }
 
     let code = format!(
-        r"try{{
-        {script};
-        JSON.stringify(main({config_str},'{safe_name}')||'')
-      }} catch(err) {{
-        `__error_flag__ ${{err.toString()}}`
-      }}"
+        r#"(function () {{
+        try {{
+          return JSON.stringify(main({config_str},'{safe_name}')||'');
+        }} catch (err) {{
+          return "__error_flag__" + JSON.stringify({{
+            message: String(err),
+            stack: err && err.stack ? String(err.stack) : null,
+          }});
+        }}
+      }})()"#
     );
 
-    if let Ok(result) = context.eval(Source::from_bytes(code.as_str())) {
-        if !result.is_string() {
-            anyhow::bail!("main function should return object");
-        }
-        let result = result
-            .to_string(&mut context)
-            .map_err(|e| anyhow::anyhow!("Failed to convert JS result to string: {}", e))?;
-        let result = result
-            .to_std_string()
-            .map_err(|_| anyhow::anyhow!("Failed to convert JS string to std string"))?;
+    let result = match context.eval(Source::from_bytes(code.as_str())) {
+        Ok(result) => result,
+        Err(err) => return ScriptRun::failed(logs(), ScriptError::new(ScriptErrorKind::Limit, err.to_string())),
+    };
+    let Some(result) = result.as_string().and_then(|s| s.to_std_string().ok()) else {
+        return ScriptRun::failed(
+            logs(),
+            ScriptError::new(ScriptErrorKind::InvalidResult, "main function should return object"),
+        );
+    };
+
+    if let Some(thrown) = result.strip_prefix("__error_flag__") {
+        let error = match serde_json::from_str::<ThrownError>(thrown) {
+            Ok(thrown) => {
+                ScriptError::new(ScriptErrorKind::Runtime, thrown.message).with_stack(thrown.stack.map(Into::into))
+            }
+            Err(_) => ScriptError::new(ScriptErrorKind::Runtime, thrown),
+        };
+        return ScriptRun::failed(logs(), error.locate(script));
+    }
 
-        if result.len() > MAX_JSON_SIZE {
-            anyhow::bail!("Script result exceeds maximum allowed size");
+    if result.len() > MAX_JSON_SIZE {
+        return ScriptRun::failed(
+            logs(),
+            ScriptError::new(ScriptErrorKind::Limit, "Script result exceeds maximum allowed size"),
+        );
+    }
+
+    match parse_json_safely(&result) {
+        Ok(config) => ScriptRun {
+            config: Some(use_lowercase_owned(config)),
+            logs: logs(),
+            error: None,
+        },
+        Err(err) => {
+            logging_error!(Type::Config, "Script execution error: {}. Script name: {}", err, name);
+            ScriptRun::failed(
+                logs(),
+                ScriptError::new(
+                    ScriptErrorKind::InvalidResult,
+                    "Script execution failed: main should return an object",
+                ),
+            )
         }
+    }
+}
 
-        let res: Result<Mapping, Error> = parse_json_safely(&result);
+#[cfg(test)]
+fn use_script_sync(
+    script: String,
+    config: &Mapping,
+    profile: &ScriptProfile,
+) -> Result<(Mapping, Vec<(String, String)>)> {
+    into_result(run_script_sync(&script, config, profile), use_lowercase(config))
+}
 
-        match res {
-            Ok(config) => Ok((use_lowercase_owned(config), outputs.lock().to_vec())),
-            Err(err) => {
-                outputs
-                    .lock()
-                    .push(("exception".into(), "Script execution failed".into()));
-                logging_error!(Type::Config, "Script execution error: {}. Script name: {}", err, name);
-                Ok((config, outputs.lock().to_vec()))
-            }
-        }
-    } else {
-        anyhow::bail!("main function should return object");
+/// `__verge_call__(op, ...args)`: every argument is taken as a string, see `script_api::call`.
+fn verge_call(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let mut strings = Vec::with_capacity(args.len());
+    for arg in args {
+        let value = arg.to_string(context)?;
+        let value = value
+            .to_std_string()
+            .map_err(|_| JsError::from_opaque(JsString::from("Failed to convert argument to string").into()))?;
+        strings.push(value);
     }
+    let Some((op, rest)) = strings.split_first() else {
+        return Err(JsError::from_opaque(JsString::from("Missing helper name").into()));
+    };
+    script_api::call(op, rest, MAX_JSON_SIZE)
+        .map(|result| JsString::from(result.as_str()).into())
+        .map_err(|err| JsError::from_opaque(JsString::from(err.as_str()).into()))
 }
 
 fn parse_json_safely(json_str: &str) -> Result<Mapping, Error> {
@@ -178,6 +361,239 @@ fn escape_js_string_for_single_quote(s: &str) -> String {
         .into()
 }
 
+/// One case of a script test suite, written in YAML next to its fixtures:
+///
+/// ```yaml
+/// cases:
+///   - name: keeps only HK nodes
+///     fixture: fixtures/mixed.yaml      # relative to the suite file
+///     profile-name: Example
+///     expect:
+///       keys: [proxy-groups, dns.enable]
+///       absent-keys: [hosts]
+///       proxy-count: 2
+///       groups:
+///         - { name: HK, contains: [HK 01], excludes: [JP 01] }
+///       logs:
+///         - { level: info, contains: done }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScriptTestCase {
+    pub name: String,
+    pub fixture: Option<String>,
+    /// the fixture itself, used when `fixture` is not given
+    #[serde(default)]
+    pub config: Mapping,
+    #[serde(default)]
+    pub profile_name: String,
+    pub extra: Option<PrfExtra>,
+    #[serde(default)]
+    pub expect: ScriptExpectation,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScriptExpectation {
+    /// dotted paths that must be present in the output, `dns.enable`
+    #[serde(default)]
+    pub keys: Vec<String>,
+    #[serde(default)]
+    pub absent_keys: Vec<String>,
+    pub proxy_count: Option<usize>,
+    pub group_count: Option<usize>,
+    #[serde(default)]
+    pub groups: Vec<GroupExpectation>,
+    #[serde(default)]
+    pub logs: Vec<LogExpectation>,
+    /// the script is expected to fail with a message containing this
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GroupExpectation {
+    pub name: String,
+    #[serde(default)]
+    pub contains: Vec<String>,
+    #[serde(default)]
+    pub excludes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LogExpectation {
+    pub level: Option<String>,
+    pub contains: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScriptTestSuite {
+    cases: Vec<ScriptTestCase>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptCaseReport {
+    pub name: String,
+    pub passed: bool,
+    pub failures: Vec<String>,
+    pub logs: Vec<(String, String)>,
+    pub error: Option<ScriptError>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptTestReport {
+    pub passed: usize,
+    pub failed: usize,
+    pub cases: Vec<ScriptCaseReport>,
+}
+
+/// Read a suite file and load each case's fixture, relative to the suite.
+pub async fn read_script_tests(path: &Path) -> Result<Vec<ScriptTestCase>> {
+    let suite: ScriptTestSuite = help::read_yaml(&path.to_path_buf()).await?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut cases = suite.cases;
+    for case in &mut cases {
+        if let Some(fixture) = case.fixture.as_deref() {
+            case.config = help::read_mapping(&dir.join(fixture))
+                .await
+                .with_context(|| format!("case \"{}\": failed to read its fixture", case.name))?;
+        }
+    }
+    Ok(cases)
+}
+
+/// Run `script` against every case, each in its own engine under the usual limits.
+pub async fn run_script_tests(script: &str, cases: Vec<ScriptTestCase>) -> ScriptTestReport {
+    let mut report = ScriptTestReport::default();
+    for case in cases {
+        let started = std::time::Instant::now();
+        let profile = ScriptProfile {
+            name: case.profile_name.clone(),
+            extra: case.extra,
+        };
+        let run = run_script(script.into(), case.config.clone(), profile).await;
+        let mut logs = run.logs;
+        if let Some(err) = &run.error {
+            logs.extend(err.log_entries());
+        }
+        let failures = check_expectation(&case.expect, run.config.as_ref(), &logs);
+        let passed = failures.is_empty();
+        if passed {
+            report.passed += 1;
+        } else {
+            report.failed += 1;
+        }
+        report.cases.push(ScriptCaseReport {
+            name: case.name,
+            passed,
+            failures,
+            logs,
+            error: run.error,
+            duration_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+        });
+    }
+    report
+}
+
+fn lookup<'a>(config: &'a Mapping, path: &str) -> Option<&'a Value> {
+    let mut parts = path.split('.');
+    let mut value = config.get(parts.next()?)?;
+    for part in parts {
+        value = value.get(part)?;
+    }
+    Some(value)
+}
+
+fn named<'a>(config: &'a Mapping, key: &str) -> Vec<&'a Mapping> {
+    config
+        .get(key)
+        .and_then(Value::as_sequence)
+        .map(|seq| seq.iter().filter_map(Value::as_mapping).collect())
+        .unwrap_or_default()
+}
+
+/// Everything `output` and `logs` get wrong about `expect`; empty when the case passes.
+fn check_expectation(expect: &ScriptExpectation, output: Option<&Mapping>, logs: &[(String, String)]) -> Vec<String> {
+    let mut failures = Vec::new();
+    let exception = logs
+        .iter()
+        .find(|(level, _)| level.as_str() == "exception")
+        .map(|(_, data)| data.as_str());
+
+    match (&expect.error, exception) {
+        (Some(wanted), Some(got)) if !got.contains(wanted.as_str()) => {
+            failures.push(format!("expected an error containing \"{wanted}\", got \"{got}\"").into());
+        }
+        (Some(wanted), None) => failures.push(format!("expected an error containing \"{wanted}\"").into()),
+        (None, Some(got)) => failures.push(format!("script failed: {got}").into()),
+        _ => {}
+    }
+
+    if let Some(config) = output {
+        for key in &expect.keys {
+            if lookup(config, key).is_none() {
+                failures.push(format!("missing key \"{key}\"").into());
+            }
+        }
+        for key in &expect.absent_keys {
+            if lookup(config, key).is_some() {
+                failures.push(format!("unexpected key \"{key}\"").into());
+            }
+        }
+
+        let proxies = named(config, "proxies");
+        let groups = named(config, "proxy-groups");
+        for (what, wanted, actual) in [
+            ("proxies", expect.proxy_count, proxies.len()),
+            ("proxy groups", expect.group_count, groups.len()),
+        ] {
+            if let Some(wanted) = wanted
+                && wanted != actual
+            {
+                failures.push(format!("expected {wanted} {what}, got {actual}").into());
+            }
+        }
+
+        check_groups(&expect.groups, &groups, &mut failures);
+    }
+
+    for wanted in &expect.logs {
+        let found = logs.iter().any(|(level, data)| {
+            wanted.level.as_ref().is_none_or(|l| l == level) && data.contains(wanted.contains.as_str())
+        });
+        if !found {
+            let level = wanted.level.as_deref().unwrap_or("any");
+            failures.push(format!("no {level} log containing \"{}\"", wanted.contains).into());
+        }
+    }
+    failures
+}
+
+fn check_groups(expected: &[GroupExpectation], groups: &[&Mapping], failures: &mut Vec<String>) {
+    for group in expected {
+        let Some(found) = groups
+            .iter()
+            .find(|g| g.get("name").and_then(Value::as_str) == Some(group.name.as_str()))
+        else {
+            failures.push(format!("missing group \"{}\"", group.name).into());
+            continue;
+        };
+        let members = found
+            .get("proxies")
+            .and_then(Value::as_sequence)
+            .map(|seq| seq.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+            .unwrap_or_default();
+        for member in group.contains.iter().filter(|m| !members.contains(&m.as_str())) {
+            failures.push(format!("group \"{}\" lacks \"{member}\"", group.name).into());
+        }
+        for member in group.excludes.iter().filter(|m| members.contains(&m.as_str())) {
+            failures.push(format!("group \"{}\" still has \"{member}\"", group.name).into());
+        }
+    }
+}
+
 #[test]
 #[allow(unused_variables)]
 #[allow(clippy::expect_used)]
@@ -204,8 +620,8 @@ fn test_script() {
   ";
 
     let config = &serde_yaml_ng::from_str(config).expect("Failed to parse test config YAML");
-    let (config, results) =
-        use_script_sync(script.into(), config, &String::from("")).expect("Script execution should succeed in test");
+    let (config, results) = use_script_sync(script.into(), config, &ScriptProfile::default())
+        .expect("Script execution should succeed in test");
 
     let _ = serde_yaml_ng::to_string(&config).expect("Failed to serialize config to YAML");
     let yaml_config_size = std::mem::size_of_val(&config);
@@ -259,7 +675,122 @@ fn test_memory_limits() {
 
     #[allow(clippy::expect_used)]
     let config = &serde_yaml_ng::from_str("test: value").expect("Failed to parse test YAML");
-    let result = use_script_sync(script.into(), config, &String::from(""));
+    let result = use_script_sync(script.into(), config, &ScriptProfile::default());
     // 应该失败或被限制
     assert!(result.is_ok()); // 会被限制但不会 panic
 }
+
+#[test]
+#[allow(clippy::expect_used)]
+fn test_verge_helpers() {
+    let script = r#"
+    function main(config, profileName) {
+      config.hk = verge.filterNodes(config.proxies, "HK").map(p => p.name);
+      config.others = verge.filterNodes(config.proxies, "HK", { exclude: true }).length;
+      config.regions = Object.keys(verge.groupByRegion(config.proxies));
+      config.decoded = verge.base64Decode(verge.base64Encode(profileName));
+      config.yaml = verge.dumpYaml(verge.parseYaml("a: 1"));
+      config.total = verge.profile.extra.total;
+      try { verge.filterNodes(config.proxies, "("); } catch (err) { console.log(String(err)); }
+      return config;
+    }
+  "#;
+    let config = &serde_yaml_ng::from_str(
+        r"
+    proxies:
+      - { name: HK 01, type: ss }
+      - { name: 🇯🇵 Tokyo, type: ss }
+  ",
+    )
+    .expect("Failed to parse test config YAML");
+    let profile = ScriptProfile {
+        name: "订阅".into(),
+        extra: Some(PrfExtra {
+            total: 100,
+            ..PrfExtra::default()
+        }),
+    };
+
+    let (config, logs) = use_script_sync(script.into(), config, &profile).expect("Script execution should succeed");
+    let value = |key: &str| serde_json::to_string(&config[key]).expect("value should serialize");
+    assert_eq!(value("hk"), r#"["HK 01"]"#);
+    assert_eq!(value("others"), "1");
+    assert_eq!(value("regions"), r#"["HK","JP"]"#);
+    assert_eq!(value("decoded"), r#""订阅""#);
+    assert_eq!(value("yaml"), r#""a: 1\n""#);
+    assert_eq!(value("total"), "100");
+    assert!(logs.iter().any(|(_, data)| data.contains("invalid pattern")));
+}
+
+#[tokio::test]
+#[allow(clippy::expect_used)]
+async fn test_script_harness_report() {
+    let script = r#"
+    function main(config) {
+      config["proxy-groups"] = [{ name: "HK", type: "select", proxies: verge.filterNodes(config.proxies, "HK").map(p => p.name) }];
+      console.info("grouped");
+      return config;
+    }
+  "#;
+    let suite: ScriptTestSuite = serde_yaml_ng::from_str(
+        r"
+    cases:
+      - name: groups HK nodes
+        config:
+          proxies: [{ name: HK 01, type: ss }, { name: JP 01, type: ss }]
+        expect:
+          keys: [proxy-groups]
+          proxy-count: 2
+          groups: [{ name: HK, contains: [HK 01], excludes: [JP 01] }]
+          logs: [{ level: info, contains: grouped }]
+      - name: wrong expectations
+        config: { proxies: [] }
+        expect:
+          absent-keys: [proxy-groups]
+          proxy-count: 1
+          error: boom
+  ",
+    )
+    .expect("suite should parse");
+
+    let report = run_script_tests(script, suite.cases).await;
+    assert_eq!((report.passed, report.failed), (1, 1));
+    assert!(report.cases[0].passed, "{:?}", report.cases[0].failures);
+    assert_eq!(report.cases[1].failures.len(), 3);
+}
+
+#[test]
+#[allow(clippy::expect_used)]
+fn test_script_errors_carry_their_position() {
+    let config = Mapping::new();
+    let profile = ScriptProfile::default();
+
+    let run = run_script_sync("function main(config) {\n  return config +;\n}", &config, &profile);
+    let err = run.error.expect("a syntax error should be reported");
+    assert_eq!(err.kind, ScriptErrorKind::Syntax);
+    assert_eq!(err.line, Some(2));
+    assert!(err.excerpt.is_some_and(|excerpt| excerpt.contains("> 2 |")));
+    assert!(
+        use_script_sync(
+            "function main(config) {\n  return config +;\n}".into(),
+            &config,
+            &profile
+        )
+        .is_err()
+    );
+
+    let run = run_script_sync("function main(config) {\n  config.a.b = 1;\n}", &config, &profile);
+    let err = run.error.expect("a throw should be reported");
+    assert_eq!(err.kind, ScriptErrorKind::Runtime);
+    assert!(run.config.is_none());
+    let (_, logs) = use_script_sync(
+        "function main(config) {\n  config.a.b = 1;\n}".into(),
+        &config,
+        &profile,
+    )
+    .expect("a throw leaves the config as it was");
+    assert!(logs.iter().any(|(level, _)| level.as_str() == "exception"));
+
+    let err = check_script("const x = 1;").expect_err("main is required");
+    assert_eq!(err.kind, ScriptErrorKind::MissingMain);
+}
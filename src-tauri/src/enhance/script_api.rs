@@ -0,0 +1,152 @@
+//! The `verge` global offered to enhancement scripts.
+//!
+//! ```js
+//! function main(config, profileName) {
+//!   const hk = verge.filterNodes(config.proxies, "香港|HK");           // nodes whose name matches
+//!   const rest = verge.filterNodes(config.proxies, "剩余|到期", { exclude: true });
+//!   verge.region("🇯🇵 Tokyo 01");                                      // "JP", or null
+//!   verge.groupByRegion(config.proxies);                                // { HK: [...], JP: [...] }
+//!   verge.base64Encode("a"); verge.base64Decode("YQ==");
+//!   verge.urlEncode("a b"); verge.urlDecode("a%20b");
+//!   verge.parseYaml("a: 1"); verge.dumpYaml({ a: 1 });
+//!   verge.profile.extra?.total;                                         // read-only PrfExtra
+//!   return config;
+//! }
+//! ```
+//!
+//! Everything that parses or matches runs natively through `__verge_call__`: patterns use the
+//! `regex` crate, which can not backtrack catastrophically, and inputs and results are held to the
+//! same size limit as the config itself. The rest is plain JavaScript and runs under the script's
+//! loop limit and timeout. Typings for the editor live in `src/assets/verge-script.d.ts`.
+
+use super::region::classify;
+use crate::{config::PrfExtra, convert::share_link::decode_base64};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode};
+use regex::RegexBuilder;
+use serde_json::json;
+
+/// RFC 3986 unreserved characters stay as they are, everything else is escaped.
+const URL_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+const REGEX_SIZE_LIMIT: usize = 1024 * 1024;
+
+pub const PRELUDE: &str = r#"var verge = (function () {
+  const call = __verge_call__;
+  const profile = __verge_profile__;
+  const nameOf = function (p) { return typeof p === "string" ? p : (p && p.name) || ""; };
+  const list = function (proxies) { return Array.isArray(proxies) ? proxies : []; };
+  const region = function (name) { return call("region", String(name)) || null; };
+  return Object.freeze({
+    version: 1,
+    filterNodes(proxies, pattern, options) {
+      const nodes = list(proxies);
+      const hits = JSON.parse(call("match", String(pattern), JSON.stringify(nodes.map(nameOf))));
+      const exclude = !!(options && options.exclude);
+      return nodes.filter(function (_, i) { return hits[i] !== exclude; });
+    },
+    region,
+    groupByRegion(proxies) {
+      const groups = {};
+      for (const node of list(proxies)) {
+        const code = region(nameOf(node));
+        if (code) (groups[code] = groups[code] || []).push(node);
+      }
+      return groups;
+    },
+    base64Encode(value) { return call("base64Encode", String(value)); },
+    base64Decode(value) { return call("base64Decode", String(value)); },
+    urlEncode(value) { return call("urlEncode", String(value)); },
+    urlDecode(value) { return call("urlDecode", String(value)); },
+    parseYaml(value) { return JSON.parse(call("parseYaml", String(value))); },
+    dumpYaml(value) { return call("dumpYaml", JSON.stringify(value === undefined ? null : value)); },
+    profile: Object.freeze({
+      name: profile.name,
+      extra: profile.extra ? Object.freeze(profile.extra) : null,
+    }),
+  });
+})();"#;
+
+/// The object `__verge_profile__` is bound to while the prelude runs.
+pub fn profile_literal(name: &str, extra: Option<&PrfExtra>) -> String {
+    json!({ "name": name, "extra": extra }).to_string()
+}
+
+/// Run one helper. Errors become exceptions thrown inside the script.
+pub fn call(op: &str, args: &[String], max_size: usize) -> Result<String, String> {
+    if args.iter().map(String::len).sum::<usize>() > max_size {
+        return Err(format!("verge.{op}: input exceeds the size limit"));
+    }
+    let arg = |i: usize| {
+        args.get(i)
+            .map(String::as_str)
+            .ok_or_else(|| format!("verge.{op}: missing argument"))
+    };
+
+    let result = match op {
+        "match" => {
+            let re = RegexBuilder::new(arg(0)?)
+                .size_limit(REGEX_SIZE_LIMIT)
+                .build()
+                .map_err(|e| format!("verge.filterNodes: invalid pattern: {e}"))?;
+            let names = serde_json::from_str::<Vec<String>>(arg(1)?).map_err(|e| e.to_string())?;
+            let hits = names.iter().map(|name| re.is_match(name)).collect::<Vec<_>>();
+            serde_json::to_string(&hits).map_err(|e| e.to_string())?
+        }
+        "region" => classify(arg(0)?).unwrap_or_default(),
+        "base64Encode" => STANDARD.encode(arg(0)?),
+        "base64Decode" => decode_base64(arg(0)?).ok_or("verge.base64Decode: not base64 encoded UTF-8")?,
+        "urlEncode" => utf8_percent_encode(arg(0)?, URL_COMPONENT).to_string(),
+        "urlDecode" => percent_decode_str(arg(0)?)
+            .decode_utf8()
+            .map_err(|_| "verge.urlDecode: not percent encoded UTF-8")?
+            .into_owned(),
+        "parseYaml" => {
+            let value =
+                serde_yaml_ng::from_str::<serde_json::Value>(arg(0)?).map_err(|e| format!("verge.parseYaml: {e}"))?;
+            value.to_string()
+        }
+        "dumpYaml" => {
+            let value = serde_json::from_str::<serde_yaml_ng::Value>(arg(0)?).map_err(|e| e.to_string())?;
+            serde_yaml_ng::to_string(&value).map_err(|e| format!("verge.dumpYaml: {e}"))?
+        }
+        _ => return Err(format!("verge: unknown helper \"{op}\"")),
+    };
+
+    if result.len() > max_size {
+        return Err(format!("verge.{op}: result exceeds the size limit"));
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::call;
+
+    fn run(op: &str, args: &[&str]) -> Result<String, String> {
+        let args = args.iter().map(|a| (*a).to_owned()).collect::<Vec<_>>();
+        call(op, &args, 1024)
+    }
+
+    #[test]
+    fn helpers_round_trip() {
+        assert_eq!(
+            run("match", &["HK|JP", r#"["HK 01","US 01","JP 02"]"#]).as_deref(),
+            Ok("[true,false,true]")
+        );
+        assert_eq!(run("base64Encode", &["a b"]).as_deref(), Ok("YSBi"));
+        assert_eq!(run("base64Decode", &["YSBi"]).as_deref(), Ok("a b"));
+        assert_eq!(run("urlEncode", &["a b/ü"]).as_deref(), Ok("a%20b%2F%C3%BC"));
+        assert_eq!(run("urlDecode", &["a%20b%2F%C3%BC"]).as_deref(), Ok("a b/ü"));
+        assert_eq!(run("parseYaml", &["a: [1, x]"]).as_deref(), Ok(r#"{"a":[1,"x"]}"#));
+        assert_eq!(run("dumpYaml", &[r#"{"a":1}"#]).as_deref(), Ok("a: 1\n"));
+    }
+
+    #[test]
+    fn bad_input_and_oversized_input_are_errors() {
+        assert!(run("match", &["(", "[]"]).is_err());
+        assert!(run("urlDecode", &["%FF"]).is_err());
+        assert!(run("nope", &[]).is_err());
+        assert!(run("base64Encode", &["x".repeat(2048).as_str()]).is_err());
+    }
+}
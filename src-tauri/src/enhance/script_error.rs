@@ -0,0 +1,198 @@
+//! Structured errors for enhancement scripts.
+//!
+//! boa reports a parse failure as one line of text with the position folded in, and a throw from
+//! `main` as whatever the script threw. Both are turned into a `ScriptError` carrying the
+//! position, a few lines of source around it and the JS stack when the engine gives one, so the
+//! chain logs and `ValidationOutcome::Invalid` can point at the broken line.
+
+use regex::Regex;
+use serde::Serialize;
+use smartstring::alias::String;
+use std::{fmt, sync::LazyLock};
+
+/// `at line 3, col 14` in boa's parse errors, `line 3` alone elsewhere.
+#[allow(clippy::unwrap_used)]
+static POSITION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bline[: ]\s*(\d+)(?:,?\s*col(?:umn)?[: ]?\s*(\d+))?").unwrap());
+
+/// `file:3:14` at the end of a stack frame.
+#[allow(clippy::unwrap_used)]
+static STACK_POSITION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r":(\d+):(\d+)\)?\s*$").unwrap());
+
+const EXCERPT_BEFORE: usize = 2;
+const EXCERPT_AFTER: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScriptErrorKind {
+    /// the script does not parse
+    Syntax,
+    /// it parses, but defines no `main`
+    MissingMain,
+    /// something threw while the script ran
+    Runtime,
+    /// `main` returned something other than an object
+    InvalidResult,
+    /// a size, loop or time limit was hit, or the engine itself failed
+    Limit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptError {
+    pub kind: ScriptErrorKind,
+    pub message: String,
+    /// 1-based, in the script's own source
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// the lines around `line`, the failing one marked with `>`
+    pub excerpt: Option<String>,
+    pub stack: Option<String>,
+    /// the chain stage that ran the script: `script`, `global_script`...
+    pub stage: Option<String>,
+    pub uid: Option<String>,
+}
+
+impl ScriptError {
+    pub fn new(kind: ScriptErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            line: None,
+            column: None,
+            excerpt: None,
+            stack: None,
+            stage: None,
+            uid: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_stack(mut self, stack: Option<String>) -> Self {
+        self.stack = stack.filter(|s| !s.trim().is_empty());
+        self
+    }
+
+    /// Find the position in the message or the stack and cut the excerpt out of `source`.
+    #[must_use]
+    pub fn locate(mut self, source: &str) -> Self {
+        let from_message = POSITION.captures(&self.message);
+        let from_stack = self
+            .stack
+            .as_deref()
+            .and_then(|stack| stack.lines().find_map(|frame| STACK_POSITION.captures(frame)));
+        let Some(caps) = from_message.or(from_stack) else {
+            return self;
+        };
+        self.line = caps.get(1).and_then(|m| m.as_str().parse().ok());
+        self.column = caps.get(2).and_then(|m| m.as_str().parse().ok());
+        if let Some(line) = self.line {
+            self.excerpt = excerpt(source, line, self.column);
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn in_stage(mut self, stage: &str, uid: &str) -> Self {
+        self.stage = Some(stage.into());
+        self.uid = Some(uid.into()).filter(|u: &String| !u.is_empty());
+        self
+    }
+
+    /// Errors a chain run can not get past: the script never ran, or could not finish.
+    pub const fn is_fatal(&self) -> bool {
+        matches!(self.kind, ScriptErrorKind::Syntax | ScriptErrorKind::Limit)
+    }
+
+    /// The entries this error adds to the chain logs.
+    pub fn log_entries(&self) -> Vec<(String, String)> {
+        let mut logs = vec![("exception".into(), self.to_string().into())];
+        if let Some(excerpt) = &self.excerpt {
+            logs.push(("excerpt".into(), excerpt.clone()));
+        }
+        if let Some(stack) = &self.stack {
+            logs.push(("stack".into(), stack.clone()));
+        }
+        logs
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(line) = self.line
+            && !POSITION.is_match(&self.message)
+        {
+            match self.column {
+                Some(column) => write!(f, " (line {line}, column {column})")?,
+                None => write!(f, " (line {line})")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+fn excerpt(source: &str, line: usize, column: Option<usize>) -> Option<String> {
+    let lines = source.lines().collect::<Vec<_>>();
+    let index = line.checked_sub(1).filter(|i| *i < lines.len())?;
+    let first = index.saturating_sub(EXCERPT_BEFORE);
+    let last = (index + EXCERPT_AFTER).min(lines.len() - 1);
+    let width = (last + 1).to_string().len();
+
+    let mut out = std::string::String::new();
+    for (i, text) in lines.iter().enumerate().take(last + 1).skip(first) {
+        let marker = if i == index { '>' } else { ' ' };
+        out.push_str(&format!("{marker} {:>width$} | {text}\n", i + 1));
+        if i == index
+            && let Some(column) = column.filter(|c| *c > 0)
+        {
+            out.push_str(&format!("  {:>width$} | {}^\n", "", " ".repeat(column - 1)));
+        }
+    }
+    Some(out.trim_end().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScriptError, ScriptErrorKind};
+
+    const SOURCE: &str = "function main(config) {\n  const a = 1;\n  a.b.c = 2;\n  return config;\n}";
+
+    #[test]
+    fn position_comes_from_the_message_or_the_stack() {
+        let err = ScriptError::new(
+            ScriptErrorKind::Syntax,
+            "Script syntax error: unexpected token at line 3, col 5",
+        )
+        .locate(SOURCE);
+        assert_eq!((err.line, err.column), (Some(3), Some(5)));
+        assert_eq!(
+            err.excerpt.as_deref(),
+            Some(
+                "  1 | function main(config) {\n  2 |   const a = 1;\n> 3 |   a.b.c = 2;\n    |     ^\n  4 |   return config;"
+            )
+        );
+        assert_eq!(
+            err.to_string(),
+            "Script syntax error: unexpected token at line 3, col 5"
+        );
+
+        let err = ScriptError::new(ScriptErrorKind::Runtime, "TypeError: a.b is undefined")
+            .with_stack(Some("TypeError: a.b is undefined\n    at main (script.js:3:3)".into()))
+            .locate(SOURCE);
+        assert_eq!((err.line, err.column), (Some(3), Some(3)));
+        assert_eq!(err.to_string(), "TypeError: a.b is undefined (line 3, column 3)");
+    }
+
+    #[test]
+    fn an_error_without_a_position_keeps_its_message() {
+        let err = ScriptError::new(ScriptErrorKind::Limit, "script execution timed out")
+            .locate(SOURCE)
+            .in_stage("script", "s1");
+        assert_eq!(err.line, None);
+        assert_eq!(err.log_entries().len(), 1);
+        assert_eq!(err.uid.as_deref(), Some("s1"));
+    }
+}
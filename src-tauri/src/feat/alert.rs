@@ -0,0 +1,270 @@
+//! Traffic and expiry alerts for subscriptions.
+//!
+//! The `subscription-userinfo` header of a subscription ends up in `PrfItem::extra`. After each
+//! update and once a day, profiles are checked against the usage thresholds and the expiry window
+//! from the verge config. What was already shown is kept per profile in `PrfItem::alert`, so each
+//! threshold and each expiry date is notified once; a new billing cycle or a renewal starts over.
+
+use crate::{
+    config::{Config, IVerge, PrfAlertState, PrfExtra, profiles::record_alert_states},
+    core::tray,
+    utils::notification::{NotificationEvent, notify_event},
+};
+use clash_verge_logging::{Type, logging, logging_error};
+use smartstring::alias::String;
+
+const DEFAULT_THRESHOLDS: [u8; 2] = [80, 95];
+const DEFAULT_EXPIRE_DAYS: u64 = 3;
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlertSettings {
+    /// ascending, each within 1..=100
+    pub thresholds: Vec<u8>,
+    /// 0 only reports an expiry once it has happened
+    pub expire_days: u64,
+}
+
+impl AlertSettings {
+    /// `None` when alerts are turned off.
+    pub fn from_verge(verge: &IVerge) -> Option<Self> {
+        if !verge.enable_subscription_alerts.unwrap_or(true) {
+            return None;
+        }
+        let mut thresholds = verge
+            .subscription_alert_thresholds
+            .clone()
+            .unwrap_or_else(|| DEFAULT_THRESHOLDS.to_vec());
+        thresholds.retain(|t| (1..=100).contains(t));
+        thresholds.sort_unstable();
+        thresholds.dedup();
+        Some(Self {
+            thresholds,
+            expire_days: verge.subscription_alert_expire_days.unwrap_or(DEFAULT_EXPIRE_DAYS),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionAlert {
+    /// usage is at `percent`, past `threshold`
+    Usage {
+        threshold: u8,
+        percent: u64,
+    },
+    Expiring {
+        days: u64,
+    },
+    Expired,
+}
+
+impl SubscriptionAlert {
+    const fn event(self, profile: &str) -> NotificationEvent<'_> {
+        match self {
+            Self::Usage { percent, .. } => NotificationEvent::SubscriptionUsage { profile, percent },
+            Self::Expiring { days } => NotificationEvent::SubscriptionExpiring { profile, days },
+            Self::Expired => NotificationEvent::SubscriptionExpired { profile },
+        }
+    }
+
+    fn tooltip(self) -> std::string::String {
+        match self {
+            Self::Usage { percent, .. } => {
+                clash_verge_i18n::t!("tray.tooltip.subscriptionUsage").replace("{percent}", &percent.to_string())
+            }
+            Self::Expiring { days } => {
+                clash_verge_i18n::t!("tray.tooltip.subscriptionExpiring").replace("{days}", &days.to_string())
+            }
+            Self::Expired => clash_verge_i18n::t!("tray.tooltip.subscriptionExpired").into_owned(),
+        }
+    }
+}
+
+/// Share of the traffic allowance used, in percent. `None` when the subscription has no cap.
+pub fn usage_percent(extra: &PrfExtra) -> Option<u64> {
+    if extra.total == 0 {
+        return None;
+    }
+    let used = u128::from(extra.upload) + u128::from(extra.download);
+    u64::try_from(used * 100 / u128::from(extra.total)).ok()
+}
+
+/// Where the subscription stands at `now`, shown or not: the highest threshold its usage is past,
+/// and its expiry when that is within the window or over.
+pub fn alert_status(extra: &PrfExtra, settings: &AlertSettings, now: i64) -> Vec<SubscriptionAlert> {
+    let mut alerts = Vec::new();
+    if let Some(percent) = usage_percent(extra)
+        && let Some(threshold) = settings.thresholds.iter().rev().find(|t| percent >= u64::from(**t))
+    {
+        alerts.push(SubscriptionAlert::Usage {
+            threshold: *threshold,
+            percent,
+        });
+    }
+    if extra.expire > 0 {
+        match u64::try_from(now).ok().and_then(|now| extra.expire.checked_sub(now)) {
+            Some(left) if left > 0 => {
+                let days = left.div_ceil(SECS_PER_DAY);
+                if days <= settings.expire_days {
+                    alerts.push(SubscriptionAlert::Expiring { days });
+                }
+            }
+            _ => alerts.push(SubscriptionAlert::Expired),
+        }
+    }
+    alerts
+}
+
+/// The alerts `previous` has not shown yet, and the state to keep once they are.
+///
+/// The state follows the subscription down as well as up: usage falling back under a threshold
+/// arms it again, and a new `expire` is a new date to warn about.
+pub fn fresh_alerts(
+    extra: &PrfExtra,
+    settings: &AlertSettings,
+    previous: &PrfAlertState,
+    now: i64,
+) -> (Vec<SubscriptionAlert>, PrfAlertState) {
+    let mut state = PrfAlertState::default();
+    let mut fresh = Vec::new();
+    for alert in alert_status(extra, settings, now) {
+        let shown = match alert {
+            SubscriptionAlert::Usage { threshold, .. } => {
+                state.usage = Some(threshold);
+                previous.usage.is_some_and(|usage| usage >= threshold)
+            }
+            SubscriptionAlert::Expiring { .. } => {
+                state.expiring = Some(extra.expire);
+                previous.expiring == Some(extra.expire)
+            }
+            SubscriptionAlert::Expired => {
+                state.expired = Some(extra.expire);
+                previous.expired == Some(extra.expire)
+            }
+        };
+        if !shown {
+            fresh.push(alert);
+        }
+    }
+    (fresh, state)
+}
+
+/// Check `uid`, or every profile when `None`, notify what is new and refresh the tray tooltip.
+pub async fn check_subscription_alerts(uid: Option<&String>) {
+    let Some(settings) = AlertSettings::from_verge(&Config::verge().await.latest_arc()) else {
+        return;
+    };
+    let now = chrono::Local::now().timestamp();
+
+    let mut changed = Vec::new();
+    let profiles = Config::profiles().await.latest_arc();
+    for item in profiles.get_items().into_iter().flatten() {
+        let (Some(item_uid), Some(extra)) = (item.uid.as_ref(), item.extra.as_ref()) else {
+            continue;
+        };
+        if uid.is_some_and(|uid| uid != item_uid) {
+            continue;
+        }
+        let previous = item.alert.clone().unwrap_or_default();
+        let (fresh, state) = fresh_alerts(extra, &settings, &previous, now);
+        let name = item.name.as_deref().unwrap_or(item_uid);
+        for alert in fresh {
+            logging!(info, Type::Config, "[订阅提醒] {}: {:?}", name, alert);
+            notify_event(alert.event(name)).await;
+        }
+        if state != previous {
+            changed.push((item_uid.clone(), state));
+        }
+    }
+    drop(profiles);
+
+    if !changed.is_empty() {
+        logging_error!(Type::Config, record_alert_states(changed).await);
+    }
+    logging_error!(Type::Tray, tray::Tray::global().update_tooltip().await);
+}
+
+/// One line for the tray tooltip about the current profile, when it is past a threshold or
+/// close to its expiry.
+pub fn subscription_tooltip(extra: &PrfExtra, settings: &AlertSettings, now: i64) -> Option<std::string::String> {
+    let parts = alert_status(extra, settings, now)
+        .into_iter()
+        .map(SubscriptionAlert::tooltip)
+        .collect::<Vec<_>>();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AlertSettings, SubscriptionAlert, fresh_alerts, usage_percent};
+    use crate::config::{PrfAlertState, PrfExtra};
+
+    const NOW: i64 = 1_700_000_000;
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn settings() -> AlertSettings {
+        AlertSettings {
+            thresholds: vec![80, 95],
+            expire_days: 3,
+        }
+    }
+
+    const fn extra(used: u64, expire: u64) -> PrfExtra {
+        PrfExtra {
+            upload: 0,
+            download: used,
+            total: 100,
+            expire,
+        }
+    }
+
+    #[test]
+    fn usage_is_a_percentage_of_the_cap() {
+        assert_eq!(usage_percent(&extra(81, 0)), Some(81));
+        assert_eq!(usage_percent(&PrfExtra::default()), None);
+    }
+
+    #[test]
+    fn each_threshold_and_expiry_is_notified_once() {
+        let expire = NOW as u64 + 2 * DAY;
+        let (fresh, state) = fresh_alerts(&extra(85, expire), &settings(), &PrfAlertState::default(), NOW);
+        assert_eq!(
+            fresh,
+            [
+                SubscriptionAlert::Usage {
+                    threshold: 80,
+                    percent: 85
+                },
+                SubscriptionAlert::Expiring { days: 2 }
+            ]
+        );
+
+        let (fresh, state) = fresh_alerts(&extra(90, expire), &settings(), &state, NOW);
+        assert!(fresh.is_empty());
+
+        let (fresh, state) = fresh_alerts(&extra(96, expire), &settings(), &state, NOW);
+        assert_eq!(
+            fresh,
+            [SubscriptionAlert::Usage {
+                threshold: 95,
+                percent: 96
+            }]
+        );
+
+        let (fresh, _) = fresh_alerts(&extra(96, expire), &settings(), &state, NOW + 3 * DAY as i64);
+        assert_eq!(fresh, [SubscriptionAlert::Expired]);
+    }
+
+    #[test]
+    fn a_new_cycle_or_a_renewal_arms_the_alerts_again() {
+        let expire = NOW as u64 + DAY;
+        let (_, state) = fresh_alerts(&extra(96, expire), &settings(), &PrfAlertState::default(), NOW);
+
+        let (fresh, state) = fresh_alerts(&extra(10, expire + 30 * DAY), &settings(), &state, NOW);
+        assert!(fresh.is_empty());
+        assert_eq!(state, PrfAlertState::default());
+
+        let (fresh, _) = fresh_alerts(&extra(81, expire + 30 * DAY), &settings(), &state, NOW);
+        assert_eq!(fresh.len(), 1);
+    }
+}
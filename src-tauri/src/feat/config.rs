@@ -196,6 +196,12 @@ fn determine_update_flags(patch: &IVerge) -> UpdateFlags {
     if tray_inline_outbound_modes.is_some() {
         update_flags.insert(UpdateFlags::SYSTRAY_MENU);
     }
+    if patch.enable_subscription_alerts.is_some()
+        || patch.subscription_alert_thresholds.is_some()
+        || patch.subscription_alert_expire_days.is_some()
+    {
+        update_flags.insert(UpdateFlags::SYSTRAY_TOOLTIP);
+    }
 
     update_flags
 }
@@ -1,20 +1,24 @@
+mod alert;
 mod backup;
 mod clash;
 mod config;
 mod icon;
 mod listener;
 mod profile;
+mod profile_batch;
 mod proxy;
 mod tun;
 mod window;
 
 // Re-export all functions from modules
+pub use alert::*;
 pub use backup::*;
 pub use clash::*;
 pub use config::*;
 pub use icon::*;
 pub use listener::*;
 pub use profile::*;
+pub use profile_batch::*;
 pub use proxy::*;
 pub use tun::*;
 pub use window::*;
@@ -1,12 +1,30 @@
 use crate::{
     cmd,
-    config::{Config, PrfItem, PrfOption, profiles::profiles_draft_update_item_safe},
+    config::{
+        Config, MirrorReport, PrfChangeSummary, PrfHeldUpdate, PrfItem, PrfOption, RemoteFetch, RemoteKind,
+        UpdateErrorCategory, UpdateOutcome,
+        diff::diff_profiles,
+        guard,
+        profiles::{
+            hold_profile_update, profiles_draft_update_item_safe, profiles_patch_item_safe, record_update_result,
+            take_held_update,
+        },
+        revision,
+    },
     core::{CoreManager, handle, tray, validate::ValidationOutcome},
-    utils::help::{mask_err, mask_url},
+    utils::{
+        dirs,
+        help::{mask_err, mask_url},
+        notification::{NotificationEvent, notify_event},
+    },
 };
 use anyhow::{Result, bail};
+use backon::{ExponentialBuilder, Retryable as _};
 use clash_verge_logging::{Type, logging, logging_error};
+use parking_lot::Mutex;
+use serde_yaml_ng::Mapping;
 use smartstring::alias::String;
+use std::time::Duration;
 
 /// Toggle proxy profile
 pub async fn toggle_proxy_profile(profile_index: String) {
@@ -83,7 +101,7 @@ async fn should_update_profile(uid: &String, ignore_auto_update: bool) -> Result
     let profiles = Config::profiles().await;
     let profiles = profiles.latest_arc();
     let item = profiles.get_item(uid)?;
-    let is_remote = item.itype.as_ref().is_some_and(|s| s == "remote");
+    let is_remote = item.itype.as_ref().is_some_and(|s| s == "remote" || s == "ruleset");
 
     if !is_remote {
         logging!(info, Type::Config, "[订阅更新] {uid} 不是远程订阅，跳过更新");
@@ -113,18 +131,216 @@ async fn should_update_profile(uid: &String, ignore_auto_update: bool) -> Result
     }
 }
 
+/// What became of a fetched profile.
+enum Saved {
+    /// the file was replaced
+    Changed,
+    /// the server answered 304, only the update time and usage moved
+    Unchanged,
+    /// the download failed the profile's `update_guard` and was put aside, the profile is untouched
+    Held,
+}
+
+/// Store what a fetch brought back.
+///
+/// A 304 means the file on disk is still what the server serves, so there is nothing to re-enhance
+/// or reload. A download failing the profile's `update_guard` is held aside and leaves the profile,
+/// its update time included, as it was.
+async fn save_fetched_profile(uid: &String, fetched: RemoteFetch) -> Result<Saved> {
+    match fetched {
+        // 规则集没有节点可比，不经过 update_guard，也不记录变化摘要
+        RemoteFetch::Modified(mut item) if item.itype.as_ref().is_some_and(|t| t == "ruleset") => {
+            profiles_draft_update_item_safe(uid, &mut item).await?;
+            Ok(Saved::Changed)
+        }
+        RemoteFetch::Modified(mut item) => {
+            let old = read_stored_mapping(uid).await;
+            let new = item
+                .file_data
+                .as_deref()
+                .and_then(|data| serde_yaml_ng::from_str::<Mapping>(data).ok());
+            if let Some(new) = &new
+                && let Some(held) = check_update_guard(uid, &item, old.as_ref(), new).await
+            {
+                hold_fetched_profile(uid, held, item.file_data.take().unwrap_or_default()).await?;
+                return Ok(Saved::Held);
+            }
+            let summary = summarize_change(old.as_ref(), new.as_ref());
+            item.last_change = summary.clone();
+            profiles_draft_update_item_safe(uid, &mut item).await?;
+            discard_held_update(uid).await;
+            if let Some(summary) = summary {
+                notify_change_summary(uid, &summary).await;
+            }
+            Ok(Saved::Changed)
+        }
+        RemoteFetch::NotModified { extra } => {
+            logging!(
+                info,
+                Type::Config,
+                "[订阅更新] {} 订阅内容未变化 (304)，跳过内核重载",
+                uid
+            );
+            let checked = PrfItem {
+                extra,
+                updated: Some(chrono::Local::now().timestamp() as usize),
+                ..PrfItem::default()
+            };
+            profiles_patch_item_safe(uid, &checked).await?;
+            Ok(Saved::Unchanged)
+        }
+    }
+}
+
+/// The profile file of `uid` as it is before an update replaces it.
+async fn read_stored_mapping(uid: &String) -> Option<Mapping> {
+    let item = Config::profiles().await.latest_arc().get_item(uid).ok()?.clone();
+    let data = item.read_file().await.ok()?;
+    serde_yaml_ng::from_str(&data).ok()
+}
+
+/// `Some` when the download in `fetched`, parsed as `new`, does not pass the `update_guard` of
+/// `uid` against the stored `old`. Nodes that only changed their names are renames rather than
+/// removals, so they do not count toward `max_removed_percent`.
+async fn check_update_guard(
+    uid: &String,
+    fetched: &PrfItem,
+    old: Option<&Mapping>,
+    new: &Mapping,
+) -> Option<PrfHeldUpdate> {
+    let update_guard = Config::profiles()
+        .await
+        .latest_arc()
+        .get_item(uid)
+        .ok()?
+        .option
+        .as_ref()?
+        .update_guard
+        .clone()?;
+    let trip = guard::check_update(&update_guard, old, new)?;
+    Some(PrfHeldUpdate {
+        reason: trip.reason,
+        nodes_before: trip.nodes_before,
+        nodes_after: trip.nodes_after,
+        removed: trip.removed,
+        fetched_at: chrono::Local::now().timestamp(),
+        file: format!("{uid}.held.yaml").into(),
+        extra: fetched.extra,
+        home: fetched.home.clone(),
+        etag: fetched.etag.clone(),
+        last_modified: fetched.last_modified.clone(),
+        conversion: fetched.conversion.clone(),
+    })
+}
+
+async fn hold_fetched_profile(uid: &String, held: PrfHeldUpdate, data: String) -> Result<()> {
+    logging!(
+        warn,
+        Type::Config,
+        "Warning: [订阅更新] {} 新内容未通过检查 ({:?}, 节点 {} -> {})，已暂存等待确认",
+        uid,
+        held.reason,
+        held.nodes_before,
+        held.nodes_after
+    );
+    let (before, after) = (held.nodes_before, held.nodes_after);
+    if !hold_profile_update(uid, held, &data).await? {
+        let name = Config::profiles()
+            .await
+            .latest_arc()
+            .get_name_by_uid(uid)
+            .cloned()
+            .unwrap_or_else(|| uid.clone());
+        notify_event(NotificationEvent::SubscriptionHeld {
+            profile: &name,
+            before,
+            after,
+        })
+        .await;
+    }
+    Ok(())
+}
+
+/// What replacing `old` with `new` changed, when both could be read.
+fn summarize_change(old: Option<&Mapping>, new: Option<&Mapping>) -> Option<PrfChangeSummary> {
+    let (old, new) = old.zip(new)?;
+    Some(PrfChangeSummary::new(
+        &diff_profiles(old, new),
+        chrono::Local::now().timestamp(),
+    ))
+}
+
+async fn notify_change_summary(uid: &String, summary: &PrfChangeSummary) {
+    logging!(
+        info,
+        Type::Config,
+        "[订阅更新] {} 变化: 节点 {} -> {} (+{} -{} 改名 {}), 规则 {} -> {}",
+        uid,
+        summary.nodes_before,
+        summary.nodes_after,
+        summary.nodes_added.count,
+        summary.nodes_removed.count,
+        summary.nodes_renamed.count,
+        summary.rules_before,
+        summary.rules_after
+    );
+    let enabled = Config::verge()
+        .await
+        .latest_arc()
+        .notify_subscription_changes
+        .unwrap_or(false);
+    if !enabled || summary.is_empty() {
+        return;
+    }
+    let name = Config::profiles()
+        .await
+        .latest_arc()
+        .get_name_by_uid(uid)
+        .cloned()
+        .unwrap_or_else(|| uid.clone());
+    notify_event(NotificationEvent::SubscriptionChanged {
+        profile: &name,
+        summary,
+    })
+    .await;
+}
+
+/// A download that went through makes whatever was held before it stale.
+async fn discard_held_update(uid: &String) {
+    let held = Config::profiles()
+        .await
+        .latest_arc()
+        .get_item(uid)
+        .is_ok_and(|item| item.held.is_some());
+    if held {
+        logging!(info, Type::Config, "[订阅更新] {} 已正常更新，丢弃暂存的内容", uid);
+        logging_error!(Type::Config, take_held_update(uid).await.map(drop));
+    }
+}
+
+/// Retries of a scheduled update's direct fetch, before it falls back to the proxies.
+fn scheduled_update_backoff() -> ExponentialBuilder {
+    ExponentialBuilder::default()
+        .with_min_delay(Duration::from_secs(30))
+        .with_max_delay(Duration::from_secs(10 * 60))
+        .with_factor(2.0)
+        .with_max_times(3)
+}
+
 async fn perform_profile_update(
     uid: &String,
     url: &String,
     opt: Option<&PrfOption>,
     option: Option<&PrfOption>,
+    kind: RemoteKind,
     is_mannual_trigger: bool,
+    retry_transient: bool,
 ) -> Result<bool> {
     logging!(info, Type::Config, "[订阅更新] 开始下载新的订阅内容");
-    let mut merged_opt = PrfOption::merge(opt, option);
+    let merged_opt = PrfOption::merge(opt, option);
     let is_current = {
         let profiles = Config::profiles().await;
-        profiles.latest_arc().is_current_profile_index(uid)
+        profiles.latest_arc().feeds_current_profile(uid)
     };
     let profiles = Config::profiles().await;
     let profiles_arc = profiles.latest_arc();
@@ -132,36 +348,127 @@ async fn perform_profile_update(
         .get_name_by_uid(uid)
         .cloned()
         .unwrap_or_else(|| String::from("UnKnown Profile"));
+    let urls = profiles_arc
+        .get_item(uid)
+        .map_or_else(|_| vec![url.clone()], PrfItem::remote_urls);
+    // 文件缺失时不能接受 304，否则配置将没有内容
+    let cached = match profiles_arc.get_item(uid) {
+        Ok(item) if item.file_exists().await => Some(item.clone()),
+        _ => None,
+    };
 
-    let mut last_err;
-
-    match PrfItem::from_url(url, None, None, merged_opt.as_ref()).await {
-        Ok(mut item) => {
+    let attempted = chrono::Local::now().timestamp();
+    let report = Mutex::new(MirrorReport::default());
+    let direct = || fetch_direct(kind, &urls, merged_opt.as_ref(), cached.as_ref(), &report);
+    // 定时更新的直连下载按指数退避重试，手动和批量更新立即反馈结果，代理回退只在重试用尽后各试一次
+    let direct_fetched = if retry_transient {
+        direct
+            .retry(scheduled_update_backoff())
+            .when(|err| UpdateErrorCategory::classify(err).is_transient())
+            .notify(|err, after| {
+                logging!(
+                    warn,
+                    Type::Config,
+                    "Warning: [订阅更新] {} 直连更新失败，{:?} 后重试: {}",
+                    uid,
+                    after,
+                    mask_err(&err.to_string())
+                );
+            })
+            .await
+    } else {
+        direct().await
+    };
+    let mut report = report.into_inner();
+    let fetched = match direct_fetched {
+        Ok(fetched) => {
             logging!(info, Type::Config, "[订阅更新] 更新订阅配置成功");
-            profiles_draft_update_item_safe(uid, &mut item).await?;
-            return Ok(is_current);
+            Ok(fetched)
         }
         Err(err) => {
-            logging!(
-                warn,
+            fetch_through_proxies(
+                kind,
+                &urls,
+                merged_opt.as_ref(),
+                cached.as_ref(),
+                &profile_name,
+                err,
+                &mut report,
+            )
+            .await
+        }
+    };
+
+    match fetched {
+        Ok(fetched) => {
+            let saved = save_fetched_profile(uid, fetched).await?;
+            let outcome = match saved {
+                Saved::Held => UpdateOutcome::Held,
+                Saved::Changed | Saved::Unchanged => UpdateOutcome::Succeeded,
+            };
+            logging_error!(
                 Type::Config,
-                "Warning: [订阅更新] 正常更新失败: {}，尝试使用Clash代理更新",
-                mask_err(&err.to_string())
+                record_update_result(uid, attempted, outcome, Some(report)).await
             );
-            last_err = err;
+            Ok(matches!(saved, Saved::Changed) && is_current)
+        }
+        Err(err) => {
+            logging_error!(
+                Type::Config,
+                record_update_result(uid, attempted, UpdateOutcome::failed(&err), Some(report)).await
+            );
+            if is_mannual_trigger {
+                handle::Handle::notice_message("update_failed_even_with_clash", format!("{profile_name} - {err}"));
+            }
+            Ok(is_current)
         }
     }
+}
+
+/// Fetch directly as configured, trying every url. `report` ends up with how the urls answered
+/// in this attempt only, so a retry starts from a clean report.
+async fn fetch_direct(
+    kind: RemoteKind,
+    urls: &[String],
+    merged_opt: Option<&PrfOption>,
+    cached: Option<&PrfItem>,
+    report: &Mutex<MirrorReport>,
+) -> Result<RemoteFetch> {
+    let mut attempt = MirrorReport::default();
+    let fetched = PrfItem::from_url_if_modified(kind, urls, merged_opt, cached, &mut attempt).await;
+    *report.lock() = attempt;
+    fetched
+}
+
+/// After the direct fetch failed with `direct_err`, fetch through the core's own mixed port
+/// (`ProxyType::Localhost`), then through the system proxy, trying every url in each.
+async fn fetch_through_proxies(
+    kind: RemoteKind,
+    urls: &[String],
+    merged_opt: Option<&PrfOption>,
+    cached: Option<&PrfItem>,
+    profile_name: &String,
+    direct_err: anyhow::Error,
+    report: &mut MirrorReport,
+) -> Result<RemoteFetch> {
+    logging!(
+        warn,
+        Type::Config,
+        "Warning: [订阅更新] 正常更新失败: {}，尝试使用Clash代理更新",
+        mask_err(&direct_err.to_string())
+    );
+    let mut merged_opt = merged_opt.cloned();
+    let mut last_err = direct_err;
 
     merged_opt.get_or_insert_with(PrfOption::default).self_proxy = Some(true);
     merged_opt.get_or_insert_with(PrfOption::default).with_proxy = Some(false);
 
-    match PrfItem::from_url(url, None, None, merged_opt.as_ref()).await {
-        Ok(mut item) => {
+    match PrfItem::from_url_if_modified(kind, urls, merged_opt.as_ref(), cached, report).await {
+        Ok(fetched) => {
             logging!(info, Type::Config, "[订阅更新] 使用 Clash代理 更新订阅配置成功");
-            profiles_draft_update_item_safe(uid, &mut item).await?;
-            handle::Handle::notice_message("update_with_clash_proxy", profile_name);
+            handle::Handle::notice_message("update_with_clash_proxy", profile_name.clone());
             drop(last_err);
-            return Ok(is_current);
+            return Ok(fetched);
         }
         Err(err) => {
             logging!(
@@ -177,13 +484,12 @@ async fn perform_profile_update(
     merged_opt.get_or_insert_with(PrfOption::default).self_proxy = Some(false);
     merged_opt.get_or_insert_with(PrfOption::default).with_proxy = Some(true);
 
-    match PrfItem::from_url(url, None, None, merged_opt.as_ref()).await {
-        Ok(mut item) => {
+    match PrfItem::from_url_if_modified(kind, urls, merged_opt.as_ref(), cached, report).await {
+        Ok(fetched) => {
             logging!(info, Type::Config, "[订阅更新] 使用 系统代理 更新订阅配置成功");
-            profiles_draft_update_item_safe(uid, &mut item).await?;
-            handle::Handle::notice_message("update_with_clash_proxy", profile_name);
+            handle::Handle::notice_message("update_with_clash_proxy", profile_name.clone());
             drop(last_err);
-            return Ok(is_current);
+            Ok(fetched)
         }
         Err(err) => {
             logging!(
@@ -192,14 +498,10 @@ async fn perform_profile_update(
                 "Warning: [订阅更新] 系统代理更新失败: {}，所有重试均已失败",
                 mask_err(&err.to_string())
             );
-            last_err = err;
+            drop(last_err);
+            Err(err)
         }
     }
-
-    if is_mannual_trigger {
-        handle::Handle::notice_message("update_failed_even_with_clash", format!("{profile_name} - {last_err}"));
-    }
-    Ok(is_current)
 }
 
 pub async fn update_profile(
@@ -209,39 +511,126 @@ pub async fn update_profile(
     ignore_auto_update: bool,
     is_mannual_trigger: bool,
 ) -> Result<()> {
+    let current_changed =
+        download_profile_update(uid, option, ignore_auto_update, is_mannual_trigger, !is_mannual_trigger).await?;
+
+    if current_changed.unwrap_or(true) && auto_refresh {
+        reload_updated_config(is_mannual_trigger).await;
+    }
+
+    super::check_subscription_alerts(Some(uid)).await;
+    Ok(())
+}
+
+/// Download `uid` and store what came back, without touching the core.
+///
+/// `Some` tells whether the current profile's config may have changed, `None` that the item is
+/// not one updated from a url. `retry_transient` retries a failed direct download with backoff
+/// before falling back to the proxies.
+pub(super) async fn download_profile_update(
+    uid: &String,
+    option: Option<&PrfOption>,
+    ignore_auto_update: bool,
+    is_mannual_trigger: bool,
+    retry_transient: bool,
+) -> Result<Option<bool>> {
     logging!(info, Type::Config, "[订阅更新] 开始更新订阅 {}", uid);
     let url_opt = should_update_profile(uid, ignore_auto_update).await?;
 
-    let should_refresh = match url_opt {
-        Some((url, opt)) => {
-            perform_profile_update(uid, &url, opt.as_ref(), option, is_mannual_trigger).await? && auto_refresh
-        }
-        None => auto_refresh,
+    // 规则集与订阅走同一套条件请求、镜像、重试与代理回退
+    let kind = if Config::profiles()
+        .await
+        .latest_arc()
+        .get_item(uid)
+        .is_ok_and(|item| item.itype.as_ref().is_some_and(|t| t == "ruleset"))
+    {
+        RemoteKind::RuleSet
+    } else {
+        RemoteKind::Profile
     };
 
-    if should_refresh {
-        logging!(info, Type::Config, "[订阅更新] 更新内核配置");
-        match CoreManager::global().update_config_with_force(is_mannual_trigger).await {
-            Ok(outcome) if outcome.is_valid() => {
-                logging!(info, Type::Config, "[订阅更新] 更新成功");
-                handle::Handle::refresh_clash();
-            }
-            Ok(outcome @ (ValidationOutcome::Skipped { .. } | ValidationOutcome::Busy)) if !is_mannual_trigger => {
-                logging!(info, Type::Config, "[订阅更新] 本次配置刷新已跳过: {}", outcome);
-            }
-            Ok(outcome) => {
-                let message = outcome.to_string();
-                logging!(error, Type::Config, "[订阅更新] 更新失败: {}", message);
-                handle::Handle::notice_message("update_failed", message);
-            }
-            Err(err) => {
-                logging!(error, Type::Config, "[订阅更新] 更新失败: {}", err);
-                handle::Handle::notice_message("update_failed", format!("{err}"));
-                logging!(error, Type::Config, "{err}");
-            }
+    match url_opt {
+        Some((url, opt)) => Ok(Some(
+            perform_profile_update(
+                uid,
+                &url,
+                opt.as_ref(),
+                option,
+                kind,
+                is_mannual_trigger,
+                retry_transient,
+            )
+            .await?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Regenerate the runtime config after an update and hand it to the core.
+pub(super) async fn reload_updated_config(is_mannual_trigger: bool) {
+    logging!(info, Type::Config, "[订阅更新] 更新内核配置");
+    match CoreManager::global().update_config_with_force(is_mannual_trigger).await {
+        Ok(outcome) if outcome.is_valid() => {
+            logging!(info, Type::Config, "[订阅更新] 更新成功");
+            handle::Handle::refresh_clash();
+        }
+        Ok(outcome @ (ValidationOutcome::Skipped { .. } | ValidationOutcome::Busy)) if !is_mannual_trigger => {
+            logging!(info, Type::Config, "[订阅更新] 本次配置刷新已跳过: {}", outcome);
+        }
+        Ok(outcome) => {
+            let message = outcome.to_string();
+            logging!(error, Type::Config, "[订阅更新] 更新失败: {}", message);
+            handle::Handle::notice_message("update_failed", message);
+        }
+        Err(err) => {
+            logging!(error, Type::Config, "[订阅更新] 更新失败: {}", err);
+            handle::Handle::notice_message("update_failed", format!("{err}"));
+            logging!(error, Type::Config, "{err}");
         }
     }
+}
 
+/// Store the update held for `uid` as if it had passed its checks.
+pub async fn accept_held_update(uid: &String) -> Result<()> {
+    let Some((held, data)) = take_held_update(uid).await? else {
+        bail!("no update is held for the profile");
+    };
+    let Some(data) = data else {
+        bail!("the held update could not be read, update the profile again");
+    };
+    logging!(info, Type::Config, "[订阅更新] {} 已接受暂存的更新", uid);
+    let old = read_stored_mapping(uid).await;
+    let new = serde_yaml_ng::from_str::<Mapping>(&data).ok();
+    let summary = summarize_change(old.as_ref(), new.as_ref());
+    let mut item = PrfItem {
+        extra: held.extra,
+        updated: Some(held.fetched_at as usize),
+        home: held.home,
+        etag: held.etag,
+        last_modified: held.last_modified,
+        last_change: summary.clone(),
+        conversion: held.conversion,
+        file_data: Some(data),
+        ..PrfItem::default()
+    };
+    profiles_draft_update_item_safe(uid, &mut item).await?;
+    if let Some(summary) = summary {
+        notify_change_summary(uid, &summary).await;
+    }
+
+    let is_current = Config::profiles().await.latest_arc().feeds_current_profile(uid);
+    if is_current {
+        reload_updated_config(true).await;
+    }
+    Ok(())
+}
+
+/// Drop the update held for `uid`, keeping the profile as it is.
+pub async fn reject_held_update(uid: &String) -> Result<()> {
+    if take_held_update(uid).await?.is_none() {
+        bail!("no update is held for the profile");
+    }
+    logging!(info, Type::Config, "[订阅更新] {} 已拒绝暂存的更新", uid);
     Ok(())
 }
 
@@ -249,3 +638,18 @@ pub async fn update_profile(
 pub async fn enhance_profiles() -> Result<ValidationOutcome> {
     CoreManager::global().update_config_forced().await
 }
+
+/// Put a stored revision back in place of the profile file and rebuild the runtime config,
+/// restoring the file when the rebuild rejects it.
+pub async fn rollback_profile(uid: &String, revision_id: &str) -> Result<ValidationOutcome> {
+    let data = revision::read_revision(uid, revision_id).await?;
+    let file = {
+        let profiles = Config::profiles().await;
+        profiles.latest_arc().get_item(uid)?.file.clone()
+    }
+    .ok_or_else(|| anyhow::anyhow!("could not find the file"))?;
+    let path = dirs::app_profiles_dir()?.join(file.as_str());
+
+    logging!(info, Type::Config, "[订阅回滚] {} 回滚到版本 {}", uid, revision_id);
+    revision::rollback_file(&path, &data, enhance_profiles).await
+}
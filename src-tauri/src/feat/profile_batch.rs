@@ -0,0 +1,352 @@
+//! Updating every remote profile and rule set in one go.
+//!
+//! The profiles page used to call `update_profile` once per item, and each call ended in a core
+//! reload of its own. Here the downloads run a few at a time, each one reported through the
+//! `profile-update-*` events and a progress event, and the core is reloaded once at the end when
+//! the current profile was among them. Cancelling stops further downloads from starting; the ones
+//! already in flight finish and are reported.
+
+use super::profile::{download_profile_update, reload_updated_config};
+use crate::{
+    config::{Config, PrfItem, PrfUpdateState},
+    core::handle,
+};
+use anyhow::{Result, bail};
+use clash_verge_logging::{Type, logging};
+use futures::{StreamExt as _, stream};
+use parking_lot::Mutex;
+use scopeguard::defer;
+use serde::Serialize;
+use smartstring::alias::String;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio_util::sync::CancellationToken;
+
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+const MAX_BATCH_CONCURRENCY: usize = 16;
+
+/// set while a batch runs, so a second one is refused and a cancel reaches it
+static RUNNING: Mutex<Option<CancellationToken>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    Updated,
+    Failed,
+    /// the batch was cancelled before this profile started
+    Cancelled,
+}
+
+/// Sent after each profile of a batch.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchProgress {
+    pub uid: String,
+    pub status: BatchItemStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub updated: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    /// whether the core was reloaded at the end
+    pub reloaded: bool,
+}
+
+impl BatchSummary {
+    const fn count(&mut self, status: BatchItemStatus) {
+        match status {
+            BatchItemStatus::Updated => self.updated += 1,
+            BatchItemStatus::Failed => self.failed += 1,
+            BatchItemStatus::Cancelled => self.cancelled += 1,
+        }
+    }
+}
+
+/// Update the remote profiles and rule sets named in `uids`, or all of them, `concurrency` at a
+/// time. The frontend leaves out what it is already updating, so one profile is not downloaded
+/// twice at once.
+pub async fn update_all_profiles(uids: Option<Vec<String>>, concurrency: Option<usize>) -> Result<BatchSummary> {
+    let token = CancellationToken::new();
+    {
+        let mut running = RUNNING.lock();
+        if running.is_some() {
+            bail!("the profiles are already being updated");
+        }
+        *running = Some(token.clone());
+    }
+    defer! {
+        RUNNING.lock().take();
+    }
+
+    let uids = batch_uids(
+        Config::profiles()
+            .await
+            .latest_arc()
+            .get_items()
+            .map(Vec::as_slice)
+            .unwrap_or_default(),
+        uids.as_deref(),
+    );
+    let limit = concurrency
+        .unwrap_or(DEFAULT_BATCH_CONCURRENCY)
+        .clamp(1, MAX_BATCH_CONCURRENCY);
+    logging!(
+        info,
+        Type::Config,
+        "[批量更新] 开始更新 {} 个订阅，并发数 {}",
+        uids.len(),
+        limit
+    );
+
+    let (mut summary, current_changed) = run_batch(
+        uids,
+        limit,
+        &token,
+        update_one,
+        handle::Handle::notify_profile_batch_progress,
+    )
+    .await;
+    if current_changed {
+        reload_updated_config(true).await;
+        summary.reloaded = true;
+    }
+    handle::Handle::refresh_profiles();
+    logging!(info, Type::Config, "[批量更新] 完成: {:?}", summary);
+    Ok(summary)
+}
+
+/// Stop the running batch from starting more downloads. `false` when none is running.
+pub fn cancel_update_all_profiles() -> bool {
+    let Some(token) = RUNNING.lock().clone() else {
+        return false;
+    };
+    logging!(info, Type::Config, "[批量更新] 已请求取消");
+    token.cancel();
+    true
+}
+
+/// What a batch updates: the remote profiles and rule sets in list order, only those named in
+/// `requested` when it is given.
+fn batch_uids(items: &[PrfItem], requested: Option<&[String]>) -> Vec<String> {
+    items
+        .iter()
+        .filter(|item| matches!(item.itype.as_deref(), Some("remote" | "ruleset")))
+        .filter_map(|item| item.uid.clone())
+        .filter(|uid| requested.is_none_or(|requested| requested.contains(uid)))
+        .collect()
+}
+
+/// Run `update` over `uids`, `limit` at a time, reporting each one to `progress`. A uid not yet
+/// started when `token` is cancelled is reported as cancelled. Also tells whether any update may
+/// have changed the current profile's config.
+async fn run_batch<Update, UpdateFuture>(
+    uids: Vec<String>,
+    limit: usize,
+    token: &CancellationToken,
+    update: Update,
+    progress: impl Fn(&BatchProgress) + Sync,
+) -> (BatchSummary, bool)
+where
+    Update: Fn(String) -> UpdateFuture + Sync,
+    UpdateFuture: Future<Output = (bool, BatchItemStatus, Option<String>)> + Send,
+{
+    let total = uids.len();
+    let done = AtomicUsize::new(0);
+    let results = stream::iter(uids)
+        .map(|uid| {
+            let (update, progress, done) = (&update, &progress, &done);
+            async move {
+                let (current_changed, status, error) = if token.is_cancelled() {
+                    (false, BatchItemStatus::Cancelled, None)
+                } else {
+                    update(uid.clone()).await
+                };
+                progress(&BatchProgress {
+                    uid,
+                    status,
+                    error,
+                    done: done.fetch_add(1, Ordering::AcqRel) + 1,
+                    total,
+                });
+                (current_changed, status)
+            }
+        })
+        .buffer_unordered(limit)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut summary = BatchSummary {
+        total,
+        ..BatchSummary::default()
+    };
+    let mut current_changed = false;
+    for (changed, status) in results {
+        current_changed |= changed;
+        summary.count(status);
+    }
+    (summary, current_changed)
+}
+
+/// The error a download started at `started` left in `state`, when it failed. An older failure
+/// still on record does not count.
+fn failure_since(state: Option<&PrfUpdateState>, started: i64) -> Option<String> {
+    state
+        .filter(|state| {
+            state.last_attempt >= Some(started)
+                && state.consecutive_failures > 0
+                // 被暂存的下载不算失败，也不清零之前的失败次数
+                && state.last_held != state.last_attempt
+        })
+        .map(|state| state.last_error_message.clone().unwrap_or_default())
+}
+
+/// `(current profile changed, status, error)`. Downloads without the scheduled backoff, so a
+/// failing url does not hold a batch slot for minutes, and a failure lands in the batch's
+/// progress rather than in a toast of its own.
+async fn update_one(uid: String) -> (bool, BatchItemStatus, Option<String>) {
+    let started = chrono::Local::now().timestamp();
+    handle::Handle::notify_profile_update_started(&uid);
+    let result = download_profile_update(&uid, None, true, false, false).await;
+    super::check_subscription_alerts(Some(&uid)).await;
+    handle::Handle::notify_profile_update_completed(&uid);
+
+    match result {
+        Ok(changed) => {
+            // 下载失败已记录在 update_state 中，而不是作为错误返回
+            let failure = failure_since(
+                Config::profiles()
+                    .await
+                    .latest_arc()
+                    .get_item(&uid)
+                    .ok()
+                    .and_then(|item| item.update_state.as_ref()),
+                started,
+            );
+            match failure {
+                Some(message) => (false, BatchItemStatus::Failed, Some(message)),
+                None => (changed.unwrap_or(false), BatchItemStatus::Updated, None),
+            }
+        }
+        Err(err) => (false, BatchItemStatus::Failed, Some(format!("{err}").into())),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::panic, reason = "tests assert by panicking")]
+mod tests {
+    use super::{BatchItemStatus, BatchSummary, batch_uids, failure_since, run_batch};
+    use crate::config::{PrfItem, PrfUpdateState};
+    use parking_lot::Mutex;
+    use smartstring::alias::String;
+    use tokio_util::sync::CancellationToken;
+
+    fn item(uid: &str, itype: &str) -> PrfItem {
+        PrfItem {
+            uid: Some(uid.into()),
+            itype: Some(itype.into()),
+            ..PrfItem::default()
+        }
+    }
+
+    #[test]
+    fn a_batch_takes_remote_profiles_and_rule_sets() {
+        let items = [
+            item("local", "local"),
+            item("remote-a", "remote"),
+            item("rules", "ruleset"),
+            item("merge", "merge"),
+            item("remote-b", "remote"),
+        ];
+        assert_eq!(batch_uids(&items, None), ["remote-a", "rules", "remote-b"]);
+
+        // what the frontend asks for, minus what it is not allowed to update
+        let requested: [String; 3] = ["remote-b".into(), "local".into(), "gone".into()];
+        assert_eq!(batch_uids(&items, Some(&requested)), ["remote-b"]);
+        assert!(batch_uids(&items, Some(&[])).is_empty());
+    }
+
+    #[test]
+    fn only_a_failure_of_this_download_fails_the_item() {
+        let failed = PrfUpdateState {
+            last_attempt: Some(100),
+            consecutive_failures: 2,
+            last_error_message: Some("timed out".into()),
+            ..PrfUpdateState::default()
+        };
+        assert_eq!(failure_since(Some(&failed), 100).as_deref(), Some("timed out"));
+        // the failure on record is from before this download started
+        assert_eq!(failure_since(Some(&failed), 101), None);
+
+        let succeeded = PrfUpdateState {
+            last_attempt: Some(100),
+            last_success: Some(100),
+            ..PrfUpdateState::default()
+        };
+        assert_eq!(failure_since(Some(&succeeded), 100), None);
+        assert_eq!(failure_since(None, 100), None);
+
+        let held = PrfUpdateState {
+            last_held: Some(100),
+            ..failed
+        };
+        assert_eq!(failure_since(Some(&held), 100), None);
+    }
+
+    #[tokio::test]
+    async fn a_cancel_skips_the_profiles_not_yet_started() {
+        let token = CancellationToken::new();
+        let progress = Mutex::new(Vec::new());
+        let uids = ["a", "b", "c", "d"].map(String::from).to_vec();
+
+        let (summary, current_changed) = run_batch(
+            uids,
+            1,
+            &token,
+            |uid| {
+                let token = token.clone();
+                async move {
+                    match uid.as_str() {
+                        "a" => (true, BatchItemStatus::Updated, None),
+                        "b" => {
+                            token.cancel();
+                            (false, BatchItemStatus::Failed, Some("refused".into()))
+                        }
+                        _ => panic!("{uid} started after the cancel"),
+                    }
+                }
+            },
+            |event| {
+                progress
+                    .lock()
+                    .push((event.uid.clone(), event.status, event.done, event.total))
+            },
+        )
+        .await;
+
+        assert!(current_changed);
+        assert_eq!(
+            summary,
+            BatchSummary {
+                total: 4,
+                updated: 1,
+                failed: 1,
+                cancelled: 2,
+                reloaded: false,
+            }
+        );
+        assert_eq!(
+            progress.into_inner(),
+            [
+                ("a".into(), BatchItemStatus::Updated, 1, 4),
+                ("b".into(), BatchItemStatus::Failed, 2, 4),
+                ("c".into(), BatchItemStatus::Cancelled, 3, 4),
+                ("d".into(), BatchItemStatus::Cancelled, 4, 4),
+            ]
+        );
+    }
+}
@@ -4,6 +4,7 @@
 mod cmd;
 pub mod config;
 mod constants;
+mod convert;
 mod core;
 mod enhance;
 mod feat;
@@ -157,6 +158,9 @@ mod app_init {
             cmd::get_runtime_config,
             cmd::get_proxy_view,
             cmd::get_runtime_yaml,
+            cmd::export_runtime_sing_box,
+            cmd::export_proxy_share_link,
+            cmd::export_group_subscription,
             cmd::get_runtime_exists,
             cmd::get_runtime_logs,
             cmd::get_runtime_proxy_chain_config,
@@ -182,17 +186,28 @@ mod app_init {
             cmd::get_network_interfaces_info,
             cmd::get_profiles,
             cmd::enhance_profiles,
+            cmd::preview_enhance,
+            cmd::test_profile_script,
             cmd::patch_profiles_config,
             cmd::view_profile,
             cmd::patch_profile,
             cmd::create_profile,
             cmd::import_profile,
+            cmd::import_profile_from_qr,
             cmd::reorder_profile,
             cmd::update_profile,
+            cmd::update_all_profiles,
+            cmd::cancel_update_all_profiles,
+            cmd::get_profile_change_summary,
+            cmd::accept_held_update,
+            cmd::reject_held_update,
             cmd::delete_profile,
             cmd::read_profile_file,
             cmd::save_profile_file,
             cmd::get_next_update_time,
+            cmd::list_profile_revisions,
+            cmd::diff_profile_revisions,
+            cmd::rollback_profile_revision,
             cmd::script_validate_notice,
             cmd::validate_script_file,
             cmd::create_local_backup,
@@ -0,0 +1,268 @@
+//! A small parser and evaluator for five-field cron expressions.
+//!
+//! `minute hour day-of-month month day-of-week`, evaluated in local time. Each field takes `*`,
+//! numbers, `a-b` ranges, `/n` steps and comma lists; months and weekdays also take their English
+//! three-letter names and `7` is Sunday as well as `0`. As in vixie cron, when both day fields are
+//! restricted a day matching either one fires. `@hourly`, `@daily`, `@weekly`, `@monthly` and
+//! `@yearly` are accepted as shorthands.
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Datelike as _, Duration, Local, NaiveDate, TimeZone as _, Timelike as _};
+use std::str::FromStr;
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// how far ahead a firing is looked for; `0 0 29 2 *` can be eight years away
+const SEARCH_YEARS: i32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CronSchedule {
+    /// bit n set when minute n matches
+    minutes: u64,
+    hours: u32,
+    /// bits 1..=31
+    days: u32,
+    /// bits 1..=12
+    months: u16,
+    /// bits 0..=6, Sunday first
+    weekdays: u8,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl FromStr for CronSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        let expr = expr.trim();
+        let expanded = match expr.to_ascii_lowercase().as_str() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            _ => expr,
+        };
+        let fields = expanded.split_whitespace().collect::<Vec<_>>();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            bail!("a cron expression needs 5 fields, got {}: \"{expr}\"", fields.len());
+        };
+
+        let weekdays = parse_field(weekday, 0, 7, &WEEKDAYS, 0)?;
+        // 7 is Sunday too
+        let weekdays = (weekdays | (weekdays >> 7)) & 0x7f;
+        let schedule = Self {
+            minutes: parse_field(minute, 0, 59, &[], 0)?,
+            hours: parse_field(hour, 0, 23, &[], 0)? as u32,
+            days: parse_field(day, 1, 31, &[], 0)? as u32,
+            months: parse_field(month, 1, 12, &MONTHS, 1)? as u16,
+            weekdays: weekdays as u8,
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        };
+        if schedule.days_restricted && !schedule.weekdays_restricted && !schedule.any_valid_day() {
+            bail!("the cron expression never fires: \"{expr}\"");
+        }
+        Ok(schedule)
+    }
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self> {
+        expr.parse()
+    }
+
+    /// The first firing strictly after `after`. Minutes skipped by a daylight saving change do not
+    /// fire; a repeated hour fires once.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = NaiveDate::from_ymd_opt(start.year() + SEARCH_YEARS, 1, 1)?;
+        let mut t = start;
+        while t.date() < limit {
+            if !self.matches_month(t.month()) {
+                t = first_of_next_month(t.date())?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(t.date()) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_hour(t.hour()) {
+                t = t.with_minute(0)? + Duration::hours(1);
+            } else if !self.matches_minute(t.minute()) {
+                t += Duration::minutes(1);
+            } else {
+                match Local.from_local_datetime(&t).earliest() {
+                    Some(fired) if fired > after => return Some(fired),
+                    _ => t += Duration::minutes(1),
+                }
+            }
+        }
+        None
+    }
+
+    const fn matches_minute(&self, minute: u32) -> bool {
+        self.minutes & (1 << minute) != 0
+    }
+
+    const fn matches_hour(&self, hour: u32) -> bool {
+        self.hours & (1 << hour) != 0
+    }
+
+    const fn matches_month(&self, month: u32) -> bool {
+        self.months & (1 << month) != 0
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            (true, false) => day,
+            (false, true) => weekday,
+            (false, false) => true,
+        }
+    }
+
+    /// Whether some selected month has one of the selected days, so `0 0 31 2 *` is refused.
+    fn any_valid_day(&self) -> bool {
+        const LONGEST: [u32; 13] = [0, 31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+        (1..=12)
+            .any(|month| self.matches_month(month) && (1..=LONGEST[month as usize]).any(|d| self.days & (1 << d) != 0))
+    }
+}
+
+fn first_of_next_month(date: NaiveDate) -> Option<NaiveDate> {
+    match date.month() {
+        12 => NaiveDate::from_ymd_opt(date.year() + 1, 1, 1),
+        month => NaiveDate::from_ymd_opt(date.year(), month + 1, 1),
+    }
+}
+
+/// One field as a bit set over `min..=max`. `names[i]` stands for `i + name_base`.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], name_base: u32) -> Result<u64> {
+    let value = |s: &str| -> Result<u32> {
+        let lower = s.to_ascii_lowercase();
+        if let Some(index) = names.iter().position(|n| *n == lower) {
+            return Ok(index as u32 + name_base);
+        }
+        match s.parse::<u32>() {
+            Ok(v) if (min..=max).contains(&v) => Ok(v),
+            _ => bail!("invalid cron value \"{s}\", expected {min}-{max}"),
+        }
+    };
+
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => bail!("invalid cron step \"{step}\""),
+            },
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (value(start)?, value(end)?)
+        } else {
+            let start = value(range)?;
+            // `5/15` runs from 5 to the end
+            (start, if step > 1 { max } else { start })
+        };
+        if start > end {
+            bail!("invalid cron range \"{range}\"");
+        }
+        for v in (start..=end).step_by(step as usize) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::CronSchedule;
+    use chrono::{DateTime, Local, NaiveDate, TimeZone as _};
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Local> {
+        let naive = NaiveDate::from_ymd_opt(y, m, d)
+            .and_then(|date| date.and_hms_opt(h, min, 0))
+            .expect("fixture date should be valid");
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .expect("fixture time should exist locally")
+    }
+
+    fn next(expr: &str, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        CronSchedule::parse(expr)
+            .expect("fixture should parse")
+            .next_after(after)
+    }
+
+    #[test]
+    fn fields_take_lists_ranges_steps_and_names() {
+        assert_eq!(
+            next("0 6 * * *", local(2024, 5, 1, 5, 59)),
+            Some(local(2024, 5, 1, 6, 0))
+        );
+        assert_eq!(
+            next("0 6 * * *", local(2024, 5, 1, 6, 0)),
+            Some(local(2024, 5, 2, 6, 0))
+        );
+        assert_eq!(
+            next("*/15 * * * *", local(2024, 5, 1, 6, 1)),
+            Some(local(2024, 5, 1, 6, 15))
+        );
+        assert_eq!(
+            next("30 9-17/4 * * *", local(2024, 5, 1, 14, 0)),
+            Some(local(2024, 5, 1, 17, 30))
+        );
+        // 2024-05-04 is a Saturday
+        assert_eq!(
+            next("0 8 * * mon-fri", local(2024, 5, 3, 9, 0)),
+            Some(local(2024, 5, 6, 8, 0))
+        );
+        assert_eq!(
+            next("0 0 1 jan,jul *", local(2024, 2, 1, 0, 0)),
+            Some(local(2024, 7, 1, 0, 0))
+        );
+        assert_eq!(
+            next("@weekly", local(2024, 5, 1, 0, 0)),
+            next("0 0 * * 7", local(2024, 5, 1, 0, 0))
+        );
+    }
+
+    #[test]
+    fn either_restricted_day_field_fires() {
+        // the 13th, or any Friday
+        assert_eq!(
+            next("0 0 13 * 5", local(2024, 5, 1, 0, 0)),
+            Some(local(2024, 5, 3, 0, 0))
+        );
+    }
+
+    #[test]
+    fn leap_day_waits_for_the_next_leap_year() {
+        assert_eq!(
+            next("0 0 29 2 *", local(2024, 3, 1, 0, 0)),
+            Some(local(2028, 2, 29, 0, 0))
+        );
+    }
+
+    #[test]
+    fn malformed_or_impossible_expressions_are_refused() {
+        for expr in [
+            "",
+            "0 6 * *",
+            "60 * * * *",
+            "0 6 * * 8",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "0 0 31 2 *",
+        ] {
+            assert!(CronSchedule::parse(expr).is_err(), "{expr:?}");
+        }
+    }
+}
@@ -1,5 +1,6 @@
 #[cfg(target_os = "macos")]
 pub mod connections_stream;
+pub mod cron;
 pub mod dirs;
 pub mod help;
 pub mod init;
@@ -10,6 +11,7 @@ pub mod macos_launch_guard;
 pub mod network;
 pub mod notification;
 pub mod port;
+pub mod qr;
 pub mod resolve;
 #[cfg(target_os = "windows")]
 pub mod schtasks;
@@ -185,10 +185,11 @@ impl NetworkManager {
         timeout_secs: Option<u64>,
         user_agent: Option<String>,
         accept_invalid_certs: bool,
+        request_headers: &HeaderMap,
         tls_root_mode: TlsRootMode,
     ) -> Result<HttpResponse> {
         let mut parsed = Url::parse(url)?;
-        let mut extra_headers = HeaderMap::new();
+        let mut extra_headers = request_headers.clone();
 
         if !parsed.username().is_empty() {
             let username = percent_encoding::percent_decode_str(parsed.username())
@@ -286,6 +287,7 @@ impl NetworkManager {
         timeout_secs: Option<u64>,
         user_agent: Option<String>,
         accept_invalid_certs: bool,
+        request_headers: HeaderMap,
     ) -> Result<HttpResponse> {
         let platform_result = self
             .get_with_tls_mode(
@@ -294,6 +296,7 @@ impl NetworkManager {
                 timeout_secs,
                 user_agent.clone(),
                 accept_invalid_certs,
+                &request_headers,
                 TlsRootMode::PlatformVerifier,
             )
             .await;
@@ -307,6 +310,7 @@ impl NetworkManager {
                     timeout_secs,
                     user_agent,
                     accept_invalid_certs,
+                    &request_headers,
                     TlsRootMode::StaticWebpkiRoots,
                 )
                 .await
@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 
-use crate::core::handle;
+use crate::{config::PrfChangeSummary, core::handle};
 use clash_verge_i18n;
 use tauri_plugin_notification::NotificationExt as _;
 
@@ -13,6 +13,26 @@ pub enum NotificationEvent<'a> {
     TunModeToggled(bool),
     LightweightModeEntered,
     ProfilesReactivated,
+    SubscriptionUsage {
+        profile: &'a str,
+        percent: u64,
+    },
+    SubscriptionExpiring {
+        profile: &'a str,
+        days: u64,
+    },
+    SubscriptionExpired {
+        profile: &'a str,
+    },
+    SubscriptionHeld {
+        profile: &'a str,
+        before: usize,
+        after: usize,
+    },
+    SubscriptionChanged {
+        profile: &'a str,
+        summary: &'a PrfChangeSummary,
+    },
     AppQuit,
     #[cfg(target_os = "macos")]
     AppHidden,
@@ -68,6 +88,55 @@ pub async fn notify_event<'a>(event: NotificationEvent<'a>) {
             let body = clash_verge_i18n::t!("notifications.profilesReactivated.body");
             notify(title, body);
         }
+        NotificationEvent::SubscriptionUsage { profile, percent } => {
+            let title = clash_verge_i18n::t!("notifications.subscriptionUsage.title");
+            let body = clash_verge_i18n::t!("notifications.subscriptionUsage.body")
+                .replace("{profile}", profile)
+                .replace("{percent}", &percent.to_string())
+                .into();
+            notify(title, body);
+        }
+        NotificationEvent::SubscriptionExpiring { profile, days } => {
+            let title = clash_verge_i18n::t!("notifications.subscriptionExpiring.title");
+            let body = clash_verge_i18n::t!("notifications.subscriptionExpiring.body")
+                .replace("{profile}", profile)
+                .replace("{days}", &days.to_string())
+                .into();
+            notify(title, body);
+        }
+        NotificationEvent::SubscriptionExpired { profile } => {
+            let title = clash_verge_i18n::t!("notifications.subscriptionExpired.title");
+            let body = clash_verge_i18n::t!("notifications.subscriptionExpired.body")
+                .replace("{profile}", profile)
+                .into();
+            notify(title, body);
+        }
+        NotificationEvent::SubscriptionHeld { profile, before, after } => {
+            let title = clash_verge_i18n::t!("notifications.subscriptionHeld.title");
+            let body = clash_verge_i18n::t!("notifications.subscriptionHeld.body")
+                .replace("{profile}", profile)
+                .replace("{before}", &before.to_string())
+                .replace("{after}", &after.to_string())
+                .into();
+            notify(title, body);
+        }
+        NotificationEvent::SubscriptionChanged { profile, summary } => {
+            let groups = summary.groups_added.count + summary.groups_removed.count + summary.groups_changed.count;
+            let title = clash_verge_i18n::t!("notifications.subscriptionChanged.title");
+            let body = clash_verge_i18n::t!("notifications.subscriptionChanged.body")
+                .replace("{profile}", profile)
+                .replace("{before}", &summary.nodes_before.to_string())
+                .replace("{after}", &summary.nodes_after.to_string())
+                .replace("{added}", &summary.nodes_added.count.to_string())
+                .replace("{removed}", &summary.nodes_removed.count.to_string())
+                .replace("{renamed}", &summary.nodes_renamed.count.to_string())
+                .replace("{groups}", &groups.to_string())
+                .replace("{providers}", &summary.providers_added.count.to_string())
+                .replace("{rules_before}", &summary.rules_before.to_string())
+                .replace("{rules_after}", &summary.rules_after.to_string())
+                .into();
+            notify(title, body);
+        }
         NotificationEvent::AppQuit => {
             let title = clash_verge_i18n::t!("notifications.appQuit.title");
             let body = clash_verge_i18n::t!("notifications.appQuit.body");
@@ -0,0 +1,168 @@
+//! Reading subscription links out of QR code images, for providers that only hand them out that
+//! way.
+//!
+//! Images are decoded with `image` and the code itself with `rqrr`. `image` 0.25 and the
+//! `zune-jpeg` decoder behind its `jpeg` feature are already in `Cargo.lock` through tauri.
+
+use anyhow::{Result, anyhow};
+use image::GrayImage;
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+
+/// The text of the QR code in a PNG or JPEG file.
+pub fn decode_image(data: &[u8]) -> Result<String> {
+    decode_luma(&image::load_from_memory(data)?.to_luma8())
+}
+
+/// The text of the QR code in an RGBA image, which is what the clipboard hands out.
+pub fn decode_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<String> {
+    let image = image::RgbaImage::from_raw(width, height, rgba.to_vec())
+        .ok_or_else(|| anyhow!("the image is smaller than {width}x{height}"))?;
+    decode_luma(&image::DynamicImage::ImageRgba8(image).to_luma8())
+}
+
+/// Try the image as it is, then inverted for light-on-dark codes.
+fn decode_luma(luma: &GrayImage) -> Result<String> {
+    let (width, height) = (luma.width() as usize, luma.height() as usize);
+    let mut failure = None;
+    for invert in [false, true] {
+        let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(width, height, |x, y| {
+            let value = luma.get_pixel(x as u32, y as u32).0[0];
+            if invert { !value } else { value }
+        });
+        for grid in prepared.detect_grids() {
+            let mut bytes = Vec::new();
+            match grid.decode_to(&mut bytes) {
+                Ok(_) => return Ok(into_text(bytes)),
+                Err(err) => failure = Some(err),
+            }
+        }
+    }
+    Err(failure.map_or_else(
+        || anyhow!("no QR code found in the image"),
+        |err| anyhow!(err).context("found a QR code but could not read it"),
+    ))
+}
+
+/// UTF-8 as nearly every generator writes it, otherwise ISO 8859-1, the standard's default.
+fn into_text(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).unwrap_or_else(|err| err.into_bytes().into_iter().map(char::from).collect())
+}
+
+/// The deep link that imports what a QR code holds: `clash://` links as they are, and bare
+/// subscription URLs wrapped the way `clash://install-config` expects them. `None` for
+/// anything else, such as a single node's share link.
+pub fn subscription_link(text: &str) -> Option<String> {
+    let text = text.trim();
+    let (scheme, _) = text.split_once("://")?;
+    match scheme.to_ascii_lowercase().as_str() {
+        "clash" | "clash-verge" => Some(text.to_owned()),
+        "http" | "https" => Some(format!(
+            "clash://install-config?url={}",
+            utf8_percent_encode(text, NON_ALPHANUMERIC)
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{decode_image, decode_rgba, subscription_link};
+    use image::{ImageFormat, RgbaImage};
+    use std::io::Cursor;
+
+    /// `https://example.com/sub?token=abc`, version 3 at level M.
+    const SYMBOL: [&str; 29] = [
+        "#######..####..##.###.#######",
+        "#.....#...#.#....##.#.#.....#",
+        "#.###.#.#.##..#.#.#.#.#.###.#",
+        "#.###.#.###.#...###.#.#.###.#",
+        "#.###.#.#..#.###.#.##.#.###.#",
+        "#.....#.##.#.#####..#.#.....#",
+        "#######.#.#.#.#.#.#.#.#######",
+        "........####.#.#...#.........",
+        "#.#####.......##.#..#.#####..",
+        "#.##.#....##...##..##.###...#",
+        ".####.##.##.#.......##.##....",
+        "#.#.#....#..#.#.#.####.#.#.#.",
+        ".....##.###.#....#.#.....##..",
+        "###.#..##.######.###.####...#",
+        "..#...####...#####..#.#####..",
+        "##.###...##.##......##..#..#.",
+        ".###..##.###..##.#.#.....##..",
+        "#...##...###....#########.#.#",
+        "#.###.##.####..#.##.#...#.#..",
+        "#..##.....#.#.#....##......#.",
+        "#.##.##..###...#.#.######.###",
+        "........#.##.##.#.#.#...#####",
+        "#######..#...####..##.#.###..",
+        "#.....#.#.#.##.#...##...#....",
+        "#.###.#.##.#..##.#..#####.##.",
+        "#.###.#.##.#....######.#.####",
+        "#.###.#.##....####...#######.",
+        "#.....#....#...##.#.#.####.#.",
+        "#######.#..##...####.####.#..",
+    ];
+
+    /// Draw `SYMBOL` with a quiet zone, `scale` pixels per module, rotated a quarter turn.
+    fn render(scale: usize) -> (u32, Vec<u8>) {
+        let size = SYMBOL.len();
+        let side = (size + 8) * scale;
+        let mut rgba = vec![255u8; side * side * 4];
+        for y in 0..side {
+            for x in 0..side {
+                let (row, column) = ((side - 1 - x) / scale, y / scale);
+                let dark = (4..size + 4).contains(&row)
+                    && (4..size + 4).contains(&column)
+                    && SYMBOL[row - 4].as_bytes()[column - 4] == b'#';
+                if dark {
+                    rgba[(y * side + x) * 4..][..3].fill(0);
+                }
+            }
+        }
+        (side as u32, rgba)
+    }
+
+    #[test]
+    fn reads_a_rotated_symbol() {
+        for scale in [1, 4] {
+            let (side, rgba) = render(scale);
+            let text = decode_rgba(side, side, &rgba).map_err(|err| format!("{err:#}"));
+            assert_eq!(
+                text.as_deref(),
+                Ok("https://example.com/sub?token=abc"),
+                "scale {scale}"
+            );
+        }
+    }
+
+    #[test]
+    fn reads_png_and_jpeg_files() {
+        let (side, rgba) = render(4);
+        let image = RgbaImage::from_raw(side, side, rgba).expect("render should fill the image");
+        for format in [ImageFormat::Png, ImageFormat::Jpeg] {
+            let mut file = Cursor::new(Vec::new());
+            image::DynamicImage::ImageRgba8(image.clone())
+                .to_rgb8()
+                .write_to(&mut file, format)
+                .expect("encoding should succeed");
+            let text = decode_image(file.get_ref()).map_err(|err| format!("{err:#}"));
+            assert_eq!(text.as_deref(), Ok("https://example.com/sub?token=abc"), "{format:?}");
+        }
+        assert!(decode_image(b"not an image").is_err());
+    }
+
+    #[test]
+    fn bare_urls_are_wrapped_as_install_config_links() {
+        assert_eq!(
+            subscription_link(" https://example.com/sub?token=a&b=1\n").as_deref(),
+            Some("clash://install-config?url=https%3A%2F%2Fexample%2Ecom%2Fsub%3Ftoken%3Da%26b%3D1")
+        );
+        assert_eq!(
+            subscription_link("clash://install-config?url=https%3A%2F%2Fexample.com").as_deref(),
+            Some("clash://install-config?url=https%3A%2F%2Fexample.com")
+        );
+        assert_eq!(subscription_link("vmess://eyJhZGQiOiIxLjEuMS4xIn0="), None);
+        assert_eq!(subscription_link("just some text"), None);
+    }
+}
@@ -34,10 +34,20 @@ pub(super) async fn resolve_scheme(param: &str) -> Result<()> {
         return Ok(());
     };
 
-    import_subscription(&url, name.as_ref()).await;
+    if let Err(e) = import_subscription(&url, name.as_ref()).await {
+        handle::Handle::notice_message("import_sub_url::error", e.to_string());
+    }
     Ok(())
 }
 
+/// 导入 `clash://install-config` 链接中的订阅，与深链接不同，失败时返回错误而不只是发送通知
+pub async fn import_link(link: &str) -> Result<()> {
+    let link_parsed = Url::parse(link).map_err(|e| anyhow::anyhow!("failed to parse link: {e}"))?;
+    let (url, name) = extract_subscription_info(&link_parsed)
+        .ok_or_else(|| anyhow::anyhow!("missing url parameter in link: {}", help::mask_url(link)))?;
+    import_subscription(&url, name.as_ref()).await
+}
+
 fn extract_subscription_info(link_parsed: &Url) -> Option<(std::string::String, Option<String>)> {
     if !matches!(link_parsed.scheme(), "clash" | "clash-verge") {
         return None;
@@ -79,29 +89,26 @@ fn decode_subscription_url(raw_url: &str) -> std::string::String {
     candidate
 }
 
-async fn import_subscription(url: &str, name: Option<&String>) {
+async fn import_subscription(url: &str, name: Option<&String>) -> Result<()> {
     let had_current_profile = {
         let profiles = Config::profiles().await;
         profiles.latest_arc().current.is_some()
     };
 
-    let Some(mut item) = fetch_profile_item(url, name).await else {
-        return;
-    };
+    let mut item = PrfItem::from_url(url, name, None, None).await.inspect_err(|e| {
+        logging!(error, Type::Config, "failed to parse profile from url: {:?}", e);
+    })?;
 
     let uid = item.uid.clone().unwrap_or_default();
     if let Err(e) = profiles::profiles_append_item_safe(&mut item).await {
         logging!(error, Type::Config, "failed to import subscription url: {:?}", e);
         Config::profiles().await.discard();
-        handle::Handle::notice_message("import_sub_url::error", e.to_string());
-        return;
+        return Err(e);
     }
 
-    if let Err(e) = profiles::profiles_save_file_safe().await {
+    profiles::profiles_save_file_safe().await.inspect_err(|e| {
         logging!(error, Type::Config, "failed to save imported subscription: {}", e);
-        handle::Handle::notice_message("import_sub_url::error", e.to_string());
-        return;
-    }
+    })?;
     logging_error!(Type::Timer, Timer::global().refresh().await);
     handle::Handle::notice_message(
         "import_sub_url::ok",
@@ -109,17 +116,7 @@ async fn import_subscription(url: &str, name: Option<&String>) {
     );
 
     post_import_updates(&uid, had_current_profile).await;
-}
-
-async fn fetch_profile_item(url: &str, name: Option<&String>) -> Option<PrfItem> {
-    match PrfItem::from_url(url, name, None, None).await {
-        Ok(item) => Some(item),
-        Err(e) => {
-            logging!(error, Type::Config, "failed to parse profile from url: {:?}", e);
-            handle::Handle::notice_message("import_sub_url::error", e.to_string());
-            None
-        }
-    }
+    Ok(())
 }
 
 async fn post_import_updates(uid: &String, had_current_profile: bool) {
@@ -10,6 +10,11 @@ proxy-groups: []
 rules: []
 ";
 
+/// composite profile, assembled from its sources whenever the runtime config is generated
+pub const ITEM_COMPOSITE: &str = "# Composite Profile for Clash Verge
+# The proxies, groups and rules come from the source profiles, this file is not read.
+";
+
 /// enhanced profile
 pub const ITEM_MERGE: &str = "# Profile Enhancement Merge Template for Clash Verge
 
@@ -58,3 +63,15 @@ append: []
 
 delete: []
 ";
+
+/// node filter, picks and renames the subscription's nodes before the proxies/groups items run
+pub const ITEM_FILTER: &str = "# Profile Enhancement Filter Template for Clash Verge
+# include: keep only nodes whose name matches this regex
+# exclude: drop nodes whose name matches this regex
+# include-type / exclude-type: keep or drop nodes by type, e.g. [ss, vmess]
+# rename: rewrite names in order, replace may use capture groups like $1
+
+exclude-type: []
+
+rename: []
+";
@@ -0,0 +1,45 @@
+/**
+ * Helpers available to enhancement scripts as the `verge` global. Loaded into the
+ * script editor only, so it is kept out of the app's own type check.
+ * Mirrors `src-tauri/src/enhance/script_api.rs`.
+ */
+
+interface VergeScriptProxy {
+  name: string
+  type?: string
+  [key: string]: unknown
+}
+
+interface VergeScriptProfileExtra {
+  upload: number
+  download: number
+  total: number
+  expire: number
+}
+
+declare const verge: {
+  readonly version: number
+  /** Nodes whose name matches `pattern` (Rust regex syntax), or those that do not with `exclude`. */
+  filterNodes<T extends VergeScriptProxy | string>(
+    proxies: T[] | undefined,
+    pattern: string,
+    options?: { exclude?: boolean },
+  ): T[]
+  /** ISO 3166 code of the region a node name points at, from its flag or keywords. */
+  region(name: string): string | null
+  /** Nodes keyed by region code; unrecognised names are left out. */
+  groupByRegion<T extends VergeScriptProxy | string>(
+    proxies: T[] | undefined,
+  ): Record<string, T[]>
+  base64Encode(value: string): string
+  base64Decode(value: string): string
+  urlEncode(value: string): string
+  urlDecode(value: string): string
+  parseYaml(value: string): unknown
+  dumpYaml(value: unknown): string
+  /** The profile the script runs for; `extra` is its traffic and expiry info, if the provider sent one. */
+  readonly profile: Readonly<{
+    name: string
+    extra: Readonly<VergeScriptProfileExtra> | null
+  }>
+}
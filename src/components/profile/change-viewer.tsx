@@ -0,0 +1,129 @@
+import {
+  Button,
+  Dialog,
+  DialogActions,
+  DialogContent,
+  DialogTitle,
+  Divider,
+  Typography,
+} from '@mui/material'
+import dayjs from 'dayjs'
+import { Fragment, useEffect, useState } from 'react'
+import { useTranslation } from 'react-i18next'
+
+import { BaseEmpty } from '@/components/base'
+import { getProfileChangeSummary } from '@/services/cmds'
+import { showNotice } from '@/services/notice-service'
+import type { TranslationKey } from '@/types/generated/i18n-keys'
+
+interface Props {
+  open: boolean
+  uid: string
+  onClose: () => void
+}
+
+const SECTIONS: [keyof IProfileChangeSummary, TranslationKey][] = [
+  ['nodes_added', 'profiles.modals.changeViewer.sections.nodesAdded'],
+  ['nodes_removed', 'profiles.modals.changeViewer.sections.nodesRemoved'],
+  ['nodes_renamed', 'profiles.modals.changeViewer.sections.nodesRenamed'],
+  ['groups_added', 'profiles.modals.changeViewer.sections.groupsAdded'],
+  ['groups_removed', 'profiles.modals.changeViewer.sections.groupsRemoved'],
+  ['groups_changed', 'profiles.modals.changeViewer.sections.groupsChanged'],
+  ['providers_added', 'profiles.modals.changeViewer.sections.providersAdded'],
+]
+
+export const ChangeViewer = (props: Props) => {
+  const { open, uid, onClose } = props
+
+  const { t } = useTranslation()
+  const [summary, setSummary] = useState<IProfileChangeSummary | null>(null)
+
+  useEffect(() => {
+    if (!open) return
+    let cancelled = false
+    getProfileChangeSummary(uid)
+      .then((result) => {
+        if (!cancelled) setSummary(result)
+      })
+      .catch((err) => showNotice.error(err))
+    return () => {
+      cancelled = true
+    }
+  }, [open, uid])
+
+  const sections = summary
+    ? SECTIONS.flatMap(([key, label]) => {
+        const list = summary[key] as IProfileChangeList
+        return list.count > 0 ? [{ key, label, list }] : []
+      })
+    : []
+
+  return (
+    <Dialog open={open} onClose={onClose}>
+      <DialogTitle>{t('profiles.modals.changeViewer.title')}</DialogTitle>
+
+      <DialogContent
+        sx={{
+          width: 400,
+          maxHeight: 420,
+          overflowX: 'hidden',
+          userSelect: 'text',
+          pb: 1,
+        }}
+      >
+        {summary && (
+          <>
+            <Typography variant="body2" color="text.secondary">
+              {t('profiles.modals.changeViewer.updatedAt', {
+                time: dayjs(summary.updated_at * 1000).format(
+                  'YYYY-MM-DD HH:mm',
+                ),
+              })}
+            </Typography>
+            <Typography variant="body2">
+              {t('profiles.modals.changeViewer.nodes', {
+                before: summary.nodes_before,
+                after: summary.nodes_after,
+              })}
+            </Typography>
+            <Typography variant="body2">
+              {t('profiles.modals.changeViewer.rules', {
+                before: summary.rules_before,
+                after: summary.rules_after,
+              })}
+            </Typography>
+            <Divider sx={{ my: 1 }} />
+          </>
+        )}
+
+        {sections.map(({ key, label, list }) => (
+          <Fragment key={key}>
+            <Typography variant="subtitle2">
+              {t(label, { count: list.count })}
+            </Typography>
+            <Typography
+              variant="body2"
+              color="text.secondary"
+              sx={{ wordBreak: 'break-word' }}
+            >
+              {(list.names ?? []).join(', ')}
+              {list.count > (list.names?.length ?? 0) &&
+                ` ${t('profiles.modals.changeViewer.more', {
+                  count: list.count - (list.names?.length ?? 0),
+                })}`}
+            </Typography>
+            <Divider sx={{ my: 0.5 }} />
+          </Fragment>
+        ))}
+
+        {sections.length === 0 && <BaseEmpty />}
+      </DialogContent>
+
+      <DialogActions>
+        <Button onClick={onClose} variant="outlined">
+          {t('shared.actions.close')}
+        </Button>
+      </DialogActions>
+    </Dialog>
+  )
+}
@@ -0,0 +1,102 @@
+import {
+  Button,
+  Dialog,
+  DialogActions,
+  DialogContent,
+  DialogTitle,
+  Divider,
+  Typography,
+} from '@mui/material'
+import { useTranslation } from 'react-i18next'
+
+interface Props {
+  open: boolean
+  report: IConversionReport
+  onClose: () => void
+}
+
+// 订阅从其它格式转换时被跳过或忽略的内容
+export const ConversionViewer = (props: Props) => {
+  const { open, report, onClose } = props
+
+  const { t } = useTranslation()
+  const nodes = report.nodes ?? []
+  const dropped = report.dropped ?? []
+
+  return (
+    <Dialog open={open} onClose={onClose}>
+      <DialogTitle>{t('profiles.components.conversion.title')}</DialogTitle>
+
+      <DialogContent
+        sx={{
+          width: 400,
+          maxHeight: 420,
+          overflowX: 'hidden',
+          userSelect: 'text',
+          pb: 1,
+        }}
+      >
+        <Typography variant="body2">
+          {t('profiles.components.conversion.summary', {
+            converted: report.converted,
+            format: report.format,
+          })}
+        </Typography>
+        <Divider sx={{ my: 1 }} />
+
+        {nodes.map((node, index) => (
+          <Typography
+            key={`${index}-${node.name}`}
+            component="div"
+            variant="body2"
+            sx={{ mb: 0.5, wordBreak: 'break-word' }}
+          >
+            <strong>{node.name}</strong>
+            <Typography
+              component="span"
+              variant="body2"
+              color={node.skipped ? 'error' : 'text.secondary'}
+              sx={{ display: 'block' }}
+            >
+              {node.skipped
+                ? t('profiles.components.conversion.skipped', {
+                    reasons: node.unsupported.join(', '),
+                  })
+                : t('profiles.components.conversion.leftOut', {
+                    settings: node.unsupported.join(', '),
+                  })}
+            </Typography>
+          </Typography>
+        ))}
+
+        {dropped.length > 0 && (
+          <>
+            {nodes.length > 0 && <Divider sx={{ my: 1 }} />}
+            <Typography variant="subtitle2">
+              {t('profiles.components.conversion.dropped')}
+            </Typography>
+            <Typography
+              variant="body2"
+              color="text.secondary"
+              sx={{ wordBreak: 'break-word' }}
+            >
+              {dropped.join(', ')}
+            </Typography>
+          </>
+        )}
+
+        {nodes.length === 0 && dropped.length === 0 && (
+          <Typography variant="body2" color="text.secondary">
+            {t('profiles.components.conversion.complete')}
+          </Typography>
+        )}
+      </DialogContent>
+
+      <DialogActions>
+        <Button onClick={onClose} variant="outlined">
+          {t('shared.actions.close')}
+        </Button>
+      </DialogActions>
+    </Dialog>
+  )
+}
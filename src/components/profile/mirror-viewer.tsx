@@ -0,0 +1,93 @@
+import {
+  Button,
+  Dialog,
+  DialogActions,
+  DialogContent,
+  DialogTitle,
+  Divider,
+  Typography,
+} from '@mui/material'
+import { Fragment } from 'react'
+import { useTranslation } from 'react-i18next'
+
+interface Props {
+  open: boolean
+  item: IProfileItem
+  onClose: () => void
+}
+
+const sameUrl = (a?: string, b?: string) => !!a && a.trim() === b?.trim()
+
+// 订阅链接和镜像在上次更新中的结果
+export const MirrorViewer = (props: Props) => {
+  const { open, item, onClose } = props
+
+  const { t } = useTranslation()
+  const state = item.update_state
+  const errors = state?.mirror_errors ?? []
+  const urls = [item.url, ...(item.mirrors ?? [])].filter(
+    (url): url is string => !!url,
+  )
+  // last_mirror 只在使用镜像时记录，否则上次成功的是主链接
+  const lastUsed =
+    state?.last_mirror ??
+    (state?.last_success && !errors.some((e) => sameUrl(e.url, item.url))
+      ? item.url
+      : undefined)
+
+  return (
+    <Dialog open={open} onClose={onClose}>
+      <DialogTitle>{t('profiles.components.mirrors.title')}</DialogTitle>
+
+      <DialogContent
+        sx={{
+          width: 400,
+          maxHeight: 420,
+          overflowX: 'hidden',
+          userSelect: 'text',
+          pb: 1,
+        }}
+      >
+        {urls.map((url, index) => {
+          const error = errors.find((e) => sameUrl(e.url, url))
+          return (
+            <Fragment key={`${index}-${url}`}>
+              {index === 0 && (
+                <Typography variant="subtitle2">
+                  {t('profiles.components.mirrors.primary')}
+                </Typography>
+              )}
+              <Typography variant="body2" sx={{ wordBreak: 'break-all' }}>
+                {url}
+              </Typography>
+              {sameUrl(lastUsed, url) && (
+                <Typography variant="body2" color="success.main">
+                  {t('profiles.components.mirrors.lastUsed')}
+                </Typography>
+              )}
+              {error && (
+                <Typography
+                  variant="body2"
+                  color="error"
+                  sx={{ wordBreak: 'break-word' }}
+                >
+                  {t('profiles.components.mirrors.failed', {
+                    category: error.category,
+                    message: error.message,
+                  })}
+                </Typography>
+              )}
+              <Divider sx={{ my: 0.5 }} />
+            </Fragment>
+          )
+        })}
+      </DialogContent>
+
+      <DialogActions>
+        <Button onClick={onClose} variant="outlined">
+          {t('shared.actions.close')}
+        </Button>
+      </DialogActions>
+    </Dialog>
+  )
+}
@@ -6,7 +6,10 @@ import {
   CheckBoxOutlineBlankRounded,
   CheckBoxRounded,
   DragIndicatorRounded,
+  ErrorOutlineRounded,
   RefreshRounded,
+  SwapHorizRounded,
+  WarningAmberRounded,
 } from '@mui/icons-material'
 import {
   Box,
@@ -37,8 +40,10 @@ import { GroupsEditorViewer } from '@/components/profile/groups-editor-viewer'
 import { RulesEditorViewer } from '@/components/profile/rules-editor-viewer'
 import { useEditorDocument } from '@/hooks/use-editor-document'
 import {
+  acceptHeldUpdate,
   getNextUpdateTime,
   readProfileFile,
+  rejectHeldUpdate,
   saveProfileFile,
   updateProfile,
   viewProfile,
@@ -49,6 +54,9 @@ import type { TranslationKey } from '@/types/generated/i18n-keys'
 import { debugLog } from '@/utils/debug'
 import parseTraffic from '@/utils/parse-traffic'
 
+import { ChangeViewer } from './change-viewer'
+import { ConversionViewer } from './conversion-viewer'
+import { MirrorViewer } from './mirror-viewer'
 import { ProfileBox } from './profile-box'
 import { ProxiesEditorViewer } from './proxies-editor-viewer'
 import { QrViewer } from './qr-viewer'
@@ -253,9 +261,15 @@ const ProfileItemBase = (props: ProfileItemProps) => {
   const hasUrl = !!itemData.url
   const hasExtra = !!extra // only subscription url has extra info
   const hasHome = !!itemData.home // only subscription url has home page
+  // 规则集只被其它配置引用，没有启用和增强链相关的操作
+  const isRuleSet = itemData.type === 'ruleset'
 
   const { upload = 0, download = 0, total = 0 } = extra ?? {}
-  const from = parseUrl(itemData.url)
+  // 上次从镜像更新时展示镜像的来源
+  const lastMirror = itemData.update_state?.last_mirror
+  const mirrorErrors = itemData.update_state?.mirror_errors ?? []
+  const hasMirrors = !!itemData.mirrors?.length || mirrorErrors.length > 0
+  const from = parseUrl(lastMirror ?? itemData.url)
   const description = itemData.desc
   const expire = parseExpire(extra?.expire)
   const progress = Math.min(
@@ -264,6 +278,15 @@ const ProfileItemBase = (props: ProfileItemProps) => {
   )
 
   const loading = loadingCache.has(itemData.uid)
+  const failures = itemData.update_state?.consecutive_failures ?? 0
+  const failureMessage = itemData.update_state?.last_error_message
+  const conversion = itemData.conversion
+  // 刷新按钮左侧依次排列暂存、失败和转换提示，名称为它们留出位置
+  const badges = [!!itemData.held, failures > 0, !!conversion]
+  const badgeRight = (index: number) =>
+    21 + 26 * badges.slice(0, index).filter(Boolean).length
+  const titleInset =
+    (batchMode ? 56 : 36) + 26 * badges.filter(Boolean).length
 
   // interval update fromNow field
   const [, forceRefresh] = useReducer((value: number) => value + 1, 0)
@@ -302,8 +325,15 @@ const ProfileItemBase = (props: ProfileItemProps) => {
   const [groupsOpen, setGroupsOpen] = useState(false)
   const [mergeOpen, setMergeOpen] = useState(false)
   const [scriptOpen, setScriptOpen] = useState(false)
+  const [filterOpen, setFilterOpen] = useState(false)
   const [confirmOpen, setConfirmOpen] = useState(false)
   const [qrOpen, setQrOpen] = useState(false)
+  const [changeOpen, setChangeOpen] = useState(false)
+  const [mirrorsOpen, setMirrorsOpen] = useState(false)
+  const [failureOpen, setFailureOpen] = useState(false)
+  const [conversionOpen, setConversionOpen] = useState(false)
+  const [heldOpen, setHeldOpen] = useState(false)
+  const [resolvingHeld, setResolvingHeld] = useState(false)
 
   const loadProfileDocument = useCallback(() => readProfileFile(uid), [uid])
   const loadMergeDocument = useCallback(
@@ -314,6 +344,10 @@ const ProfileItemBase = (props: ProfileItemProps) => {
     () => readProfileFile(option?.script ?? ''),
     [option?.script],
   )
+  const loadFilterDocument = useCallback(
+    () => readProfileFile(option?.filter ?? ''),
+    [option?.filter],
+  )
 
   const profileDocument = useEditorDocument({
     open: fileOpen,
@@ -327,6 +361,10 @@ const ProfileItemBase = (props: ProfileItemProps) => {
     open: scriptOpen,
     load: loadScriptDocument,
   })
+  const filterDocument = useEditorDocument({
+    open: filterOpen,
+    load: loadFilterDocument,
+  })
 
   const onOpenHome = () => {
     setAnchorEl(null)
@@ -343,6 +381,16 @@ const ProfileItemBase = (props: ProfileItemProps) => {
     setQrOpen(true)
   }
 
+  const onViewChanges = () => {
+    setAnchorEl(null)
+    setChangeOpen(true)
+  }
+
+  const onViewMirrors = () => {
+    setAnchorEl(null)
+    setMirrorsOpen(true)
+  }
+
   const onEditFile = () => {
     setAnchorEl(null)
     setFileOpen(true)
@@ -373,6 +421,11 @@ const ProfileItemBase = (props: ProfileItemProps) => {
     setScriptOpen(true)
   }
 
+  const onEditFilter = () => {
+    setAnchorEl(null)
+    setFilterOpen(true)
+  }
+
   const onForceSelect = () => {
     setAnchorEl(null)
     onSelect(true)
@@ -424,6 +477,24 @@ const ProfileItemBase = (props: ProfileItemProps) => {
     }
   })
 
+  // 处理被 update_guard 暂存的更新
+  const onResolveHeld = useLockFn(async (accept: boolean) => {
+    setResolvingHeld(true)
+    try {
+      if (accept) {
+        await acceptHeldUpdate(uid)
+      } else {
+        await rejectHeldUpdate(uid)
+      }
+      setHeldOpen(false)
+      void mutateProfiles()
+    } catch (err) {
+      showNotice.error(err)
+    } finally {
+      setResolvingHeld(false)
+    }
+  })
+
   type ContextMenuItem = {
     label: string
     handler: () => void
@@ -441,7 +512,10 @@ const ProfileItemBase = (props: ProfileItemProps) => {
     editGroups: 'profiles.components.menu.editGroups',
     extendConfig: 'profiles.components.menu.extendConfig',
     extendScript: 'profiles.components.menu.extendScript',
+    editFilter: 'profiles.components.menu.editFilter',
     openFile: 'profiles.components.menu.openFile',
+    lastChange: 'profiles.components.menu.lastChange',
+    mirrors: 'profiles.components.menu.mirrors',
     update: 'profiles.components.menu.update',
     updateViaProxy: 'profiles.components.menu.updateViaProxy',
     delete: 'shared.actions.delete',
@@ -502,11 +576,26 @@ const ProfileItemBase = (props: ProfileItemProps) => {
       handler: onEditScript,
       disabled: !option?.script,
     },
+    {
+      label: menuLabels.editFilter,
+      handler: onEditFilter,
+      disabled: !option?.filter,
+    },
     {
       label: menuLabels.openFile,
       handler: onOpenFile,
       disabled: false,
     },
+    {
+      label: menuLabels.lastChange,
+      handler: onViewChanges,
+      disabled: !itemData.last_change,
+    },
+    {
+      label: menuLabels.mirrors,
+      handler: onViewMirrors,
+      disabled: !hasMirrors,
+    },
     {
       label: menuLabels.update,
       handler: () => onUpdate(0),
@@ -574,6 +663,11 @@ const ProfileItemBase = (props: ProfileItemProps) => {
       handler: onEditScript,
       disabled: !option?.script,
     },
+    {
+      label: menuLabels.editFilter,
+      handler: onEditFilter,
+      disabled: !option?.filter,
+    },
     {
       label: menuLabels.openFile,
       handler: onOpenFile,
@@ -596,6 +690,19 @@ const ProfileItemBase = (props: ProfileItemProps) => {
     },
   ]
 
+  const ruleSetHiddenMenu = new Set<string>([
+    menuLabels.select,
+    menuLabels.editRules,
+    menuLabels.editProxies,
+    menuLabels.editGroups,
+    menuLabels.extendConfig,
+    menuLabels.extendScript,
+    menuLabels.editFilter,
+  ])
+  const menuItems = (hasUrl ? urlModeMenu : fileModeMenu).filter(
+    (item) => !isRuleSet || !ruleSetHiddenMenu.has(item.label),
+  )
+
   const boxStyle = {
     height: 26,
     display: 'flex',
@@ -635,6 +742,17 @@ const ProfileItemBase = (props: ProfileItemProps) => {
     scriptDocument.markSaved(currentValue)
   })
 
+  const handleSaveFilterDocument = useLockFn(async () => {
+    const filterUid = option?.filter ?? ''
+    const currentValue = filterDocument.value
+    if (!(await saveProfileFile(filterUid, currentValue))) {
+      await filterDocument.reload()
+      return
+    }
+    onSave?.(filterDocument.savedValue, currentValue)
+    filterDocument.markSaved(currentValue)
+  })
+
   return (
     <Box sx={{ position: 'relative' }}>
       <ProfileBox
@@ -720,7 +838,7 @@ const ProfileItemBase = (props: ProfileItemProps) => {
 
             <Typography
               sx={{
-                width: batchMode ? 'calc(100% - 56px)' : 'calc(100% - 36px)',
+                width: `calc(100% - ${titleInset}px)`,
                 fontSize: '18px',
                 fontWeight: '600',
                 lineHeight: '26px',
@@ -760,6 +878,76 @@ const ProfileItemBase = (props: ProfileItemProps) => {
               <RefreshRounded color="inherit" />
             </IconButton>
           )}
+          {itemData.held && (
+            <IconButton
+              title={t('profiles.components.heldUpdate.title')}
+              sx={{
+                position: 'absolute',
+                p: '3px',
+                top: -1,
+                right: badgeRight(0),
+              }}
+              size="small"
+              color="warning"
+              onClick={(e) => {
+                e.stopPropagation()
+                setHeldOpen(true)
+              }}
+            >
+              <WarningAmberRounded color="inherit" />
+            </IconButton>
+          )}
+          {failures > 0 && (
+            <IconButton
+              title={[
+                t('profiles.components.updateFailure.count', {
+                  count: failures,
+                }),
+                failureMessage,
+              ]
+                .filter(Boolean)
+                .join('\n')}
+              sx={{
+                position: 'absolute',
+                p: '3px',
+                top: -1,
+                right: badgeRight(1),
+              }}
+              size="small"
+              color="error"
+              onClick={(e) => {
+                e.stopPropagation()
+                setFailureOpen(true)
+              }}
+            >
+              <ErrorOutlineRounded color="inherit" />
+            </IconButton>
+          )}
+          {conversion && (
+            <IconButton
+              title={t('profiles.components.conversion.badge', {
+                format: conversion.format,
+              })}
+              sx={{
+                position: 'absolute',
+                p: '3px',
+                top: -1,
+                right: badgeRight(2),
+              }}
+              size="small"
+              color={
+                conversion.nodes?.length || conversion.dropped?.length
+                  ? 'warning'
+                  : 'info'
+              }
+              onClick={(e) => {
+                e.stopPropagation()
+                setConversionOpen(true)
+              }}
+            >
+              <SwapHorizRounded color="inherit" />
+            </IconButton>
+          )}
         </Box>
         {/* the second line show url's info or description */}
         <Box sx={boxStyle}>
@@ -773,16 +961,43 @@ const ProfileItemBase = (props: ProfileItemProps) => {
                 >
                   {description}
                 </Typography>
+              ) : itemData.type === 'composite' ? (
+                <Typography noWrap sx={{ fontSize: '14px' }}>
+                  {t('profiles.components.profileItem.composite', {
+                    count: option?.sources?.length ?? 0,
+                  })}
+                </Typography>
               ) : (
                 hasUrl && (
                   <Typography
                     noWrap
-                    title={`${t('shared.labels.from')} ${from}`}
+                    title={
+                      lastMirror
+                        ? t('profiles.components.mirrors.fromMirror', {
+                            url: lastMirror,
+                          })
+                        : `${t('shared.labels.from')} ${from}`
+                    }
+                    color={lastMirror ? 'info.main' : undefined}
                   >
                     {from}
                   </Typography>
                 )
               )}
+              {mirrorErrors.length > 0 && (
+                <IconButton
+                  title={t('profiles.components.mirrors.hasErrors')}
+                  sx={{ p: '2px', ml: 0.5 }}
+                  size="small"
+                  color="warning"
+                  onClick={(e) => {
+                    e.stopPropagation()
+                    setMirrorsOpen(true)
+                  }}
+                >
+                  <WarningAmberRounded sx={{ fontSize: 16 }} />
+                </IconButton>
+              )}
               {hasUrl && (
                 <Box
                   sx={{
@@ -832,6 +1047,17 @@ const ProfileItemBase = (props: ProfileItemProps) => {
             </span>
             <span title={t('shared.labels.expireTime')}>{expire}</span>
           </Box>
+        ) : isRuleSet ? (
+          <Box sx={{ ...boxStyle, fontSize: 12 }}>
+            <span>
+              {t('profiles.components.profileItem.ruleSet', {
+                policy: option?.rule_set?.policy ?? '',
+              })}
+            </span>
+            <span title={t('shared.labels.updateTime')}>
+              {parseExpire(updated)}
+            </span>
+          </Box>
         ) : (
           <Box sx={{ ...boxStyle, fontSize: 12, justifyContent: 'flex-end' }}>
             <span title={t('shared.labels.updateTime')}>
@@ -859,7 +1085,7 @@ const ProfileItemBase = (props: ProfileItemProps) => {
           e.preventDefault()
         }}
       >
-        {(hasUrl ? urlModeMenu : fileModeMenu).map((item) => (
+        {menuItems.map((item) => (
           <MenuItem
             key={item.label}
             onClick={item.handler}
@@ -955,6 +1181,19 @@ const ProfileItemBase = (props: ProfileItemProps) => {
           onClose={() => setScriptOpen(false)}
         />
       )}
+      {filterOpen && (
+        <EditorViewer
+          open={true}
+          value={filterDocument.value}
+          language="yaml"
+          path={`filter:${option?.filter ?? ''}.yaml`}
+          loading={filterDocument.loading}
+          dirty={filterDocument.dirty}
+          onChange={filterDocument.setValue}
+          onSave={handleSaveFilterDocument}
+          onClose={() => setFilterOpen(false)}
+        />
+      )}
 
       <BaseDialog
         title={t('profiles.modals.confirmDelete.title')}
@@ -973,6 +1212,98 @@ const ProfileItemBase = (props: ProfileItemProps) => {
           {t('profiles.modals.confirmDelete.message')}
         </Typography>
       </BaseDialog>
+      {itemData.held && (
+        <BaseDialog
+          title={t('profiles.components.heldUpdate.title')}
+          open={heldOpen}
+          okBtn={t('profiles.components.heldUpdate.accept')}
+          cancelBtn={t('profiles.components.heldUpdate.reject')}
+          contentSx={{ width: { xs: 320, sm: 420 }, userSelect: 'text' }}
+          loading={resolvingHeld}
+          disableCancel={resolvingHeld}
+          onOk={() => onResolveHeld(true)}
+          onCancel={() => onResolveHeld(false)}
+          onClose={() => setHeldOpen(false)}
+        >
+          <Typography variant="body2" sx={{ wordBreak: 'break-word' }}>
+            {itemData.held.reason === 'too_few_nodes'
+              ? t('profiles.components.heldUpdate.tooFewNodes', {
+                  before: itemData.held.nodes_before,
+                  after: itemData.held.nodes_after,
+                })
+              : t('profiles.components.heldUpdate.tooManyRemoved', {
+                  before: itemData.held.nodes_before,
+                  removed: itemData.held.removed,
+                })}
+          </Typography>
+          <Typography
+            variant="body2"
+            color="text.secondary"
+            sx={{ mt: 1, wordBreak: 'break-word' }}
+          >
+            {t('profiles.components.heldUpdate.message', {
+              time: dayjs(itemData.held.fetched_at * 1000).format(
+                'YYYY-MM-DD HH:mm',
+              ),
+            })}
+          </Typography>
+        </BaseDialog>
+      )}
+      <ChangeViewer
+        open={changeOpen}
+        uid={uid}
+        onClose={() => setChangeOpen(false)}
+      />
+      {failures > 0 && (
+        <BaseDialog
+          title={t('profiles.components.updateFailure.title')}
+          open={failureOpen}
+          okBtn={t('profiles.components.updateFailure.retry')}
+          cancelBtn={t('shared.actions.close')}
+          contentSx={{ width: { xs: 320, sm: 420 }, userSelect: 'text' }}
+          disableOk={loading}
+          onOk={() => {
+            setFailureOpen(false)
+            onUpdate(0)
+          }}
+          onCancel={() => setFailureOpen(false)}
+          onClose={() => setFailureOpen(false)}
+        >
+          <Typography variant="body2">
+            {t('profiles.components.updateFailure.count', { count: failures })}
+          </Typography>
+          {itemData.update_state?.last_attempt && (
+            <Typography variant="body2" color="text.secondary">
+              {t('profiles.components.updateFailure.lastAttempt', {
+                time: dayjs(itemData.update_state.last_attempt * 1000).format(
+                  'YYYY-MM-DD HH:mm',
+                ),
+              })}
+            </Typography>
+          )}
+          {failureMessage && (
+            <Typography
+              variant="body2"
+              color="error"
+              sx={{ mt: 1, wordBreak: 'break-word' }}
+            >
+              {failureMessage}
+            </Typography>
+          )}
+        </BaseDialog>
+      )}
+      {conversion && (
+        <ConversionViewer
+          open={conversionOpen}
+          report={conversion}
+          onClose={() => setConversionOpen(false)}
+        />
+      )}
+      <MirrorViewer
+        open={mirrorsOpen}
+        item={itemData}
+        onClose={() => setMirrorsOpen(false)}
+      />
       {qrOpen && itemData.url && (
         <QrViewer
           open={true}
@@ -1,4 +1,5 @@
 import {
+  Autocomplete,
   Box,
   FormControl,
   InputAdornment,
@@ -31,6 +32,26 @@ export interface ProfileViewerRef {
   edit: (item: IProfileItem) => void
 }
 
+// 与后端地区关键字表一致，也可以填写其它地区代码
+const REGION_CODES = [
+  'HK',
+  'TW',
+  'MO',
+  'JP',
+  'KR',
+  'SG',
+  'US',
+  'CA',
+  'GB',
+  'DE',
+  'FR',
+  'NL',
+  'AU',
+  'IN',
+  'RU',
+  'TR',
+]
+
 // create or edit the profile
 // remote / local
 type ProfileViewerProps = Props & { ref?: Ref<ProfileViewerRef> }
@@ -94,9 +115,19 @@ export function ProfileViewer({ onChange, ref }: ProfileViewerProps) {
         if (!form.type) {
           throw new Error(t('profiles.modals.profileForm.errors.typeRequired'))
         }
-        if (form.type === 'remote' && !form.url) {
+        if ((form.type === 'remote' || form.type === 'ruleset') && !form.url) {
           throw new Error(t('profiles.modals.profileForm.errors.urlRequired'))
         }
+        if (form.type === 'ruleset' && !form.option?.rule_set?.policy) {
+          throw new Error(
+            t('profiles.modals.profileForm.errors.policyRequired'),
+          )
+        }
+        if (form.type === 'composite' && !form.option?.sources?.length) {
+          throw new Error(
+            t('profiles.modals.profileForm.errors.sourcesRequired'),
+          )
+        }
 
         // 处理表单数据
         const option = form.option ? { ...form.option } : undefined
@@ -110,13 +141,49 @@ export function ProfileViewer({ onChange, ref }: ProfileViewerProps) {
         } else if (option) {
           option.update_interval = undefined
         }
+        // cron 表达式由后端校验，留空时使用更新间隔
+        if (option) {
+          option.update_cron = option.update_cron?.trim() || undefined
+        }
+        if (option?.update_jitter || option?.update_jitter === 0) {
+          option.update_jitter = +option.update_jitter
+        } else if (option) {
+          option.update_jitter = undefined
+        }
         if (option?.user_agent === '') {
           option.user_agent = undefined
         }
+        // 主订阅必须是来源之一，未选择时后端使用第一个来源
+        if (option?.primary && !option.sources?.includes(option.primary)) {
+          option.primary = undefined
+        }
+        const regionGroups = option?.region_groups
+        if (option && regionGroups) {
+          option.region_groups = {
+            ...regionGroups,
+            selector: regionGroups.selector || undefined,
+            regions: regionGroups.regions?.length
+              ? regionGroups.regions
+              : undefined,
+            url: regionGroups.url || undefined,
+            interval: regionGroups.interval
+              ? +regionGroups.interval
+              : undefined,
+          }
+        }
+        // 规则集只挂到主配置上，不能互相引用
+        if (option && (form.type === 'ruleset' || !option.rule_sets?.length)) {
+          option.rule_sets = undefined
+        }
+
+        const isRemote = form.type === 'remote' || form.type === 'ruleset'
+        // 每行一个镜像链接，忽略空行
+        const mirrors = isRemote
+          ? (form.mirrors ?? []).map((url) => url.trim()).filter(Boolean)
+          : undefined
 
         const name = form.name || `${form.type} file`
-        const item = { ...form, name, option }
-        const isRemote = form.type === 'remote'
+        const item = { ...form, name, mirrors, option }
         const isUpdate = openType === 'edit'
 
         // 判断是否是当前激活的配置
@@ -230,6 +297,26 @@ export function ProfileViewer({ onChange, ref }: ProfileViewerProps) {
   const formType = watch('type')
   const isRemote = formType === 'remote'
   const isLocal = formType === 'local'
+  const isComposite = formType === 'composite'
+  const isRuleSet = formType === 'ruleset'
+
+  // 组合订阅只能由远程或本地订阅组成
+  const formUid = watch('uid')
+  const sourceItems = (profiles?.items ?? []).filter(
+    (item) =>
+      (item.type === 'remote' || item.type === 'local') &&
+      item.uid !== formUid,
+  )
+  const sources = watch('option.sources') ?? []
+  const sourceName = (uid: string) =>
+    sourceItems.find((item) => item.uid === uid)?.name ?? uid
+  const ruleSetItems = (profiles?.items ?? []).filter(
+    (item) => item.type === 'ruleset',
+  )
+  const ruleSetName = (uid: string) =>
+    ruleSetItems.find((item) => item.uid === uid)?.name ?? uid
+  const regionGroupsEnabled = watch('option.region_groups.enable') ?? false
+  const updateCron = watch('option.update_cron')
 
   return (
     <BaseDialog
@@ -266,6 +353,12 @@ export function ProfileViewer({ onChange, ref }: ProfileViewerProps) {
               <MenuItem value="local">
                 {t('profiles.modals.profileForm.types.local')}
               </MenuItem>
+              <MenuItem value="composite">
+                {t('profiles.modals.profileForm.types.composite')}
+              </MenuItem>
+              <MenuItem value="ruleset">
+                {t('profiles.modals.profileForm.types.ruleset')}
+              </MenuItem>
             </Select>
           </FormControl>
         )}
@@ -300,7 +393,238 @@ export function ProfileViewer({ onChange, ref }: ProfileViewerProps) {
         />
       )}
 
-      {isRemote && (
+      {isComposite && (
+        <>
+          <Controller
+            name="option.sources"
+            control={control}
+            render={({ field }) => (
+              <FormControl size="small" fullWidth sx={{ mt: 2, mb: 1 }}>
+                <InputLabel>
+                  {t('profiles.modals.profileForm.fields.sources')}
+                </InputLabel>
+                <Select
+                  multiple
+                  value={field.value ?? []}
+                  onChange={(e) => {
+                    const value = e.target.value
+                    field.onChange(
+                      typeof value === 'string' ? value.split(',') : value,
+                    )
+                  }}
+                  label={t('profiles.modals.profileForm.fields.sources')}
+                  renderValue={(selected) =>
+                    selected.map(sourceName).join(', ')
+                  }
+                >
+                  {sourceItems.map((item) => (
+                    <MenuItem key={item.uid} value={item.uid}>
+                      {item.name}
+                    </MenuItem>
+                  ))}
+                </Select>
+              </FormControl>
+            )}
+          />
+
+          <Controller
+            name="option.primary"
+            control={control}
+            render={({ field }) => (
+              <FormControl size="small" fullWidth sx={{ mt: 1, mb: 1 }}>
+                <InputLabel shrink>
+                  {t('profiles.modals.profileForm.fields.primary')}
+                </InputLabel>
+                <Select
+                  notched
+                  displayEmpty
+                  value={
+                    field.value && sources.includes(field.value)
+                      ? field.value
+                      : ''
+                  }
+                  onChange={(e) => field.onChange(e.target.value || undefined)}
+                  label={t('profiles.modals.profileForm.fields.primary')}
+                >
+                  <MenuItem value="">
+                    {t('profiles.modals.profileForm.fields.primaryDefault')}
+                  </MenuItem>
+                  {sources.map((uid) => (
+                    <MenuItem key={uid} value={uid}>
+                      {sourceName(uid)}
+                    </MenuItem>
+                  ))}
+                </Select>
+              </FormControl>
+            )}
+          />
+        </>
+      )}
+
+      {!isRuleSet && ruleSetItems.length > 0 && (
+        <Controller
+          name="option.rule_sets"
+          control={control}
+          render={({ field }) => (
+            <FormControl size="small" fullWidth sx={{ mt: 2, mb: 1 }}>
+              <InputLabel>
+                {t('profiles.modals.profileForm.fields.ruleSets')}
+              </InputLabel>
+              <Select
+                multiple
+                value={field.value ?? []}
+                onChange={(e) => {
+                  const value = e.target.value
+                  field.onChange(
+                    typeof value === 'string' ? value.split(',') : value,
+                  )
+                }}
+                label={t('profiles.modals.profileForm.fields.ruleSets')}
+                renderValue={(selected) => selected.map(ruleSetName).join(', ')}
+              >
+                {ruleSetItems.map((item) => (
+                  <MenuItem key={item.uid} value={item.uid}>
+                    {item.name}
+                  </MenuItem>
+                ))}
+              </Select>
+            </FormControl>
+          )}
+        />
+      )}
+
+      {!isRuleSet && (
+        <Controller
+          name="option.region_groups.enable"
+          control={control}
+          render={({ field }) => (
+            <StyledBox>
+              <InputLabel>
+                {t('profiles.modals.profileForm.fields.regionGroups')}
+              </InputLabel>
+              <Switch
+                checked={field.value ?? false}
+                onChange={(_, checked) => field.onChange(checked)}
+                color="primary"
+              />
+            </StyledBox>
+          )}
+        />
+      )}
+
+      {!isRuleSet && regionGroupsEnabled && (
+        <>
+          <Controller
+            name="option.region_groups.group_type"
+            control={control}
+            render={({ field }) => (
+              <FormControl size="small" fullWidth sx={{ mt: 1, mb: 1 }}>
+                <InputLabel>
+                  {t('profiles.modals.profileForm.fields.regionGroupType')}
+                </InputLabel>
+                <Select
+                  value={field.value ?? 'url-test'}
+                  onChange={(e) => field.onChange(e.target.value)}
+                  label={t(
+                    'profiles.modals.profileForm.fields.regionGroupType',
+                  )}
+                >
+                  <MenuItem value="url-test">url-test</MenuItem>
+                  <MenuItem value="fallback">fallback</MenuItem>
+                </Select>
+              </FormControl>
+            )}
+          />
+
+          <Controller
+            name="option.region_groups.selector"
+            control={control}
+            render={({ field }) => (
+              <TextField
+                {...text}
+                {...field}
+                value={field.value ?? ''}
+                placeholder={t(
+                  'profiles.modals.profileForm.fields.regionSelectorDefault',
+                )}
+                label={t('profiles.modals.profileForm.fields.regionSelector')}
+                slotProps={{ inputLabel: { shrink: true } }}
+              />
+            )}
+          />
+
+          <Controller
+            name="option.region_groups.regions"
+            control={control}
+            render={({ field }) => (
+              <Autocomplete
+                multiple
+                freeSolo
+                size="small"
+                sx={{ mt: 2, mb: 1 }}
+                options={REGION_CODES}
+                value={field.value ?? []}
+                disableCloseOnSelect
+                onChange={(_, value) =>
+                  field.onChange(value.map((code) => code.trim().toUpperCase()))
+                }
+                renderInput={(params) => (
+                  <TextField
+                    {...params}
+                    placeholder={
+                      field.value?.length
+                        ? undefined
+                        : t('profiles.modals.profileForm.fields.regionsDefault')
+                    }
+                    label={t('profiles.modals.profileForm.fields.regions')}
+                    slotProps={{ inputLabel: { shrink: true } }}
+                  />
+                )}
+              />
+            )}
+          />
+
+          <Controller
+            name="option.region_groups.url"
+            control={control}
+            render={({ field }) => (
+              <TextField
+                {...text}
+                {...field}
+                value={field.value ?? ''}
+                placeholder="https://www.gstatic.com/generate_204"
+                label={t('profiles.modals.profileForm.fields.regionTestUrl')}
+              />
+            )}
+          />
+
+          <Controller
+            name="option.region_groups.interval"
+            control={control}
+            render={({ field }) => (
+              <TextField
+                {...text}
+                {...field}
+                value={field.value ?? ''}
+                type="number"
+                placeholder="300"
+                label={t('profiles.modals.profileForm.fields.regionInterval')}
+                slotProps={{
+                  input: {
+                    endAdornment: (
+                      <InputAdornment position="end">
+                        {t('shared.units.seconds')}
+                      </InputAdornment>
+                    ),
+                  },
+                }}
+              />
+            )}
+          />
+        </>
+      )}
+
+      {(isRemote || isRuleSet) && (
         <>
           <Controller
             name="url"
@@ -310,11 +634,76 @@ export function ProfileViewer({ onChange, ref }: ProfileViewerProps) {
                 {...text}
                 {...field}
                 multiline
-                label={t('profiles.modals.profileForm.fields.subscriptionUrl')}
+                label={
+                  isRuleSet
+                    ? t('profiles.modals.profileForm.fields.ruleSetUrl')
+                    : t('profiles.modals.profileForm.fields.subscriptionUrl')
+                }
+              />
+            )}
+          />
+
+          <Controller
+            name="mirrors"
+            control={control}
+            render={({ field }) => (
+              <TextField
+                {...text}
+                multiline
+                value={(field.value ?? []).join('\n')}
+                onChange={(e) => field.onChange(e.target.value.split('\n'))}
+                placeholder={t(
+                  'profiles.modals.profileForm.fields.mirrorsPlaceholder',
+                )}
+                label={t('profiles.modals.profileForm.fields.mirrors')}
               />
             )}
           />
 
+          {isRuleSet && (
+            <>
+              <Controller
+                name="option.rule_set.policy"
+                control={control}
+                render={({ field }) => (
+                  <TextField
+                    {...text}
+                    {...field}
+                    value={field.value ?? ''}
+                    placeholder="DIRECT"
+                    label={t('profiles.modals.profileForm.fields.policy')}
+                  />
+                )}
+              />
+
+              <Controller
+                name="option.rule_set.mode"
+                control={control}
+                render={({ field }) => (
+                  <FormControl size="small" fullWidth sx={{ mt: 2, mb: 1 }}>
+                    <InputLabel>
+                      {t('profiles.modals.profileForm.fields.ruleSetMode')}
+                    </InputLabel>
+                    <Select
+                      value={field.value ?? 'provider'}
+                      onChange={(e) => field.onChange(e.target.value)}
+                      label={t(
+                        'profiles.modals.profileForm.fields.ruleSetMode',
+                      )}
+                    >
+                      <MenuItem value="provider">
+                        {t('profiles.modals.profileForm.ruleSetModes.provider')}
+                      </MenuItem>
+                      <MenuItem value="inline">
+                        {t('profiles.modals.profileForm.ruleSetModes.inline')}
+                      </MenuItem>
+                    </Select>
+                  </FormControl>
+                )}
+              />
+            </>
+          )}
+
           <Controller
             name="option.user_agent"
             control={control}
@@ -371,6 +760,47 @@ export function ProfileViewer({ onChange, ref }: ProfileViewerProps) {
               />
             )}
           />
+          <Controller
+            name="option.update_cron"
+            control={control}
+            render={({ field }) => (
+              <TextField
+                {...text}
+                {...field}
+                value={field.value ?? ''}
+                placeholder="0 6 * * *"
+                label={t('profiles.modals.profileForm.fields.updateCron')}
+                helperText={t(
+                  'profiles.modals.profileForm.fields.updateCronHelper',
+                )}
+              />
+            )}
+          />
+          {!!updateCron?.trim() && (
+            <Controller
+              name="option.update_jitter"
+              control={control}
+              render={({ field }) => (
+                <TextField
+                  {...text}
+                  {...field}
+                  value={field.value ?? ''}
+                  type="number"
+                  placeholder="5"
+                  label={t('profiles.modals.profileForm.fields.updateJitter')}
+                  slotProps={{
+                    input: {
+                      endAdornment: (
+                        <InputAdornment position="end">
+                          {t('shared.units.minutes')}
+                        </InputAdornment>
+                      ),
+                    },
+                  }}
+                />
+              )}
+            />
+          )}
           <Controller
             name="option.with_proxy"
             control={control}
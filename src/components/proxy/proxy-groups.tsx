@@ -41,6 +41,7 @@ import {
   ProxyGroupNavigator,
 } from './proxy-group-navigator'
 import { ProxyRender } from './proxy-render'
+import { ShareViewer, type ShareTarget } from './share-viewer'
 import {
   hasRenderableItems,
   type IRenderItem,
@@ -547,6 +548,9 @@ function NormalProxyGroups(props: { mode: string }) {
     [renderList],
   )
 
+  const [shareTarget, setShareTarget] = useState<ShareTarget | null>(null)
+  const closeShare = useCallback(() => setShareTarget(null), [])
+
   const renderGroupItem = useCallback(
     (item: IRenderItem, _index: number, stickyed: boolean) => (
       <ProxyRender
@@ -563,6 +567,7 @@ function NormalProxyGroups(props: { mode: string }) {
         }}
         onChangeProxy={handleChangeProxy}
         onGroupToggle={handleGroupToggle}
+        onShare={setShareTarget}
       />
     ),
     [
@@ -584,6 +589,7 @@ function NormalProxyGroups(props: { mode: string }) {
         onCheckAll={handleCheckAll}
         onHeadState={onHeadState}
         onChangeProxy={handleChangeProxy}
+        onShare={setShareTarget}
       />
     ),
     [handleChangeProxy, handleCheckAll, onHeadState, handleLocation],
@@ -614,6 +620,8 @@ function NormalProxyGroups(props: { mode: string }) {
           hoverDelay={verge?.hover_jump_navigator_delay ?? DEFAULT_HOVER_DELAY}
         />
       )}
+
+      <ShareViewer target={shareTarget} onClose={closeShare} />
     </div>
   )
 }
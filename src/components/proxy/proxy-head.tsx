@@ -2,6 +2,7 @@ import {
   AccessTimeRounded,
   MyLocationRounded,
   NetworkCheckRounded,
+  QrCode2Rounded,
   FilterAltRounded,
   FilterAltOffRounded,
   VisibilityRounded,
@@ -31,6 +32,7 @@ interface Props {
   onLocation: () => void
   onCheckDelay: () => void
   onHeadState: (val: Partial<HeadState>) => void
+  onShare?: () => void
 }
 
 const defaultSx: SxProps = {}
@@ -43,6 +45,7 @@ export const ProxyHead = ({
   onHeadState,
   onLocation,
   onCheckDelay,
+  onShare,
 }: Props) => {
   const {
     showType,
@@ -163,6 +166,17 @@ export const ProxyHead = ({
         )}
       </IconButton>
 
+      {onShare && (
+        <IconButton
+          size="small"
+          color="inherit"
+          title={t('proxies.page.tooltips.share')}
+          onClick={onShare}
+        >
+          <QrCode2Rounded />
+        </IconButton>
+      )}
+
       {textState === 'filter' && (
         <Box sx={{ ml: 0.5, flex: '1 1 auto' }}>
           <BaseSearchBox
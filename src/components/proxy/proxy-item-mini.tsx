@@ -18,11 +18,12 @@ interface Props {
   selected: boolean
   showType?: boolean
   onClick?: (member: ResolvedProxyMember) => void
+  onShare?: (member: ResolvedProxyMember) => void
 }
 
 // 多列布局
 export const ProxyItemMini = (props: Props) => {
-  const { group, member, selected, showType = true, onClick } = props
+  const { group, member, selected, showType = true, onClick, onShare } = props
   const details = memberDetails(member)
   const unresolved = member.kind === 'unresolved'
   const name = member.ref.name
@@ -45,6 +46,14 @@ export const ProxyItemMini = (props: Props) => {
       disabled={unresolved}
       selected={!unresolved && selected}
       onClick={unresolved ? undefined : () => onClick?.(member)}
+      onContextMenu={
+        unresolved || !onShare
+          ? undefined
+          : (e) => {
+              e.preventDefault()
+              onShare(member)
+            }
+      }
       sx={[
         {
           height: 56,
@@ -29,6 +29,7 @@ interface Props {
   showType?: boolean
   sx?: SxProps<Theme>
   onClick?: (member: ResolvedProxyMember) => void
+  onShare?: (member: ResolvedProxyMember) => void
 }
 
 const Widget = styled(Box)(() => ({
@@ -51,7 +52,8 @@ const TypeBox = styled('span')(({ theme }) => ({
 
 export const ProxyItem = (props: Props) => {
   const { t } = useTranslation()
-  const { group, member, selected, showType = true, sx, onClick } = props
+  const { group, member, selected, showType = true, sx, onClick, onShare } =
+    props
   const details = memberDetails(member)
   const unresolved = member.kind === 'unresolved'
   const name = member.ref.name
@@ -73,6 +75,14 @@ export const ProxyItem = (props: Props) => {
         disabled={unresolved}
         selected={!unresolved && selected}
         onClick={unresolved ? undefined : () => onClick?.(member)}
+        onContextMenu={
+          unresolved || !onShare
+            ? undefined
+            : (e) => {
+                e.preventDefault()
+                onShare(member)
+              }
+        }
         sx={[
           { borderRadius: 1 },
           ({ palette: { mode, primary } }) => {
@@ -25,6 +25,7 @@ import { ProxyGroupTools } from './proxy-group-tools'
 import { ProxyHead } from './proxy-head'
 import { ProxyItem } from './proxy-item'
 import { ProxyItemMini } from './proxy-item-mini'
+import type { ShareTarget } from './share-viewer'
 import type { HeadState } from './use-head-state'
 import type { IRenderItem } from './use-render-list'
 
@@ -40,6 +41,7 @@ interface RenderProps {
     member: ResolvedProxyMember,
   ) => void
   onGroupToggle?: (group: IRenderItem['group']) => void
+  onShare?: (target: ShareTarget) => void
 }
 
 export const ProxyRender = memo(function ProxyRender(props: RenderProps) {
@@ -52,6 +54,7 @@ export const ProxyRender = memo(function ProxyRender(props: RenderProps) {
     onHeadState,
     onChangeProxy,
     onGroupToggle,
+    onShare,
     isChainMode: _ = false,
   } = props
   const { type, group, headState, member, memberCol } = item
@@ -67,6 +70,18 @@ export const ProxyRender = memo(function ProxyRender(props: RenderProps) {
   })
 
   const showType = headState?.showType
+  // 节点导出为分享链接，嵌套的代理组导出为订阅
+  const onShareMember = useMemo(
+    () =>
+      onShare
+        ? (target: ResolvedProxyMember) =>
+            onShare({
+              kind: target.kind === 'group' ? 'group' : 'proxy',
+              name: target.ref.name,
+            })
+        : undefined,
+    [onShare],
+  )
   const memberColItemsMemo = useMemo(() => {
     if (type !== 4 || !memberCol) {
       return null
@@ -80,9 +95,18 @@ export const ProxyRender = memo(function ProxyRender(props: RenderProps) {
         selected={group.now === occurrence.member.ref.name}
         showType={showType}
         onClick={(nextMember) => onChangeProxy(group, nextMember)}
+        onShare={onShareMember}
       />
     ))
-  }, [type, memberCol, item.key, group, showType, onChangeProxy])
+  }, [
+    type,
+    memberCol,
+    item.key,
+    group,
+    showType,
+    onChangeProxy,
+    onShareMember,
+  ])
 
   if (type === 0) {
     return (
@@ -232,6 +256,11 @@ export const ProxyRender = memo(function ProxyRender(props: RenderProps) {
         onLocation={() => onLocation(group)}
         onCheckDelay={() => onCheckAll(group.name)}
         onHeadState={(p) => onHeadState(group.name, p)}
+        onShare={
+          onShare
+            ? () => onShare({ kind: 'group', name: group.name })
+            : undefined
+        }
       />
     )
   }
@@ -245,6 +274,7 @@ export const ProxyRender = memo(function ProxyRender(props: RenderProps) {
         showType={headState?.showType}
         sx={{ py: 0, pl: 2 }}
         onClick={(nextMember) => onChangeProxy(group, nextMember)}
+        onShare={onShareMember}
       />
     )
   }
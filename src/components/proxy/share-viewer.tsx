@@ -0,0 +1,187 @@
+import { ContentCopyRounded } from '@mui/icons-material'
+import {
+  Box,
+  Button,
+  Dialog,
+  DialogActions,
+  DialogContent,
+  DialogTitle,
+  Divider,
+  IconButton,
+  Typography,
+} from '@mui/material'
+import { writeText } from '@tauri-apps/plugin-clipboard-manager'
+import { QRCodeSVG } from 'qrcode.react'
+import { Fragment, useEffect, useState } from 'react'
+import { useTranslation } from 'react-i18next'
+
+import { BaseLoading } from '@/components/base'
+import { exportGroupSubscription, exportProxyShareLink } from '@/services/cmds'
+import { showNotice } from '@/services/notice-service'
+
+export interface ShareTarget {
+  kind: 'proxy' | 'group'
+  name: string
+}
+
+interface Props {
+  target: ShareTarget | null
+  onClose: () => void
+}
+
+// 二维码能容纳的内容有限，过长的内容只提供复制
+const QR_MAX_LENGTH = 2000
+
+const onCopy = async (text: string) => {
+  try {
+    await writeText(text)
+    showNotice.success('shared.feedback.notifications.common.copySuccess')
+  } catch (err) {
+    showNotice.error(err)
+  }
+}
+
+const ShareCode = ({ label, value }: { label: string; value: string }) => {
+  const { t } = useTranslation()
+
+  return (
+    <Box sx={{ display: 'flex', flexDirection: 'column', gap: 1 }}>
+      <Box sx={{ display: 'flex', alignItems: 'center' }}>
+        <Typography variant="subtitle2" noWrap sx={{ flex: 1 }} title={label}>
+          {label}
+        </Typography>
+        <IconButton
+          size="small"
+          title={t('proxies.components.share.copy')}
+          onClick={() => onCopy(value)}
+        >
+          <ContentCopyRounded sx={{ fontSize: '18px' }} />
+        </IconButton>
+      </Box>
+      {value.length <= QR_MAX_LENGTH ? (
+        <Box
+          sx={{
+            display: 'flex',
+            justifyContent: 'center',
+            alignSelf: 'center',
+            p: 2,
+            bgcolor: '#fff',
+            borderRadius: 1,
+          }}
+        >
+          <QRCodeSVG value={value} size={200} level="M" />
+        </Box>
+      ) : (
+        <Typography variant="body2" color="text.secondary">
+          {t('proxies.components.share.tooLong')}
+        </Typography>
+      )}
+      <Typography
+        variant="body2"
+        color="text.secondary"
+        sx={{
+          fontFamily: 'monospace',
+          wordBreak: 'break-all',
+          maxHeight: 72,
+          overflowY: 'auto',
+        }}
+      >
+        {value}
+      </Typography>
+    </Box>
+  )
+}
+
+export const ShareViewer = (props: Props) => {
+  const { target, onClose } = props
+
+  const { t } = useTranslation()
+  const [share, setShare] = useState<IGroupShare | null>(null)
+
+  useEffect(() => {
+    setShare(null)
+    if (!target) return
+    let cancelled = false
+    const load =
+      target.kind === 'proxy'
+        ? exportProxyShareLink(target.name).then((link) => ({
+            subscription: '',
+            links: [link],
+            skipped: [],
+          }))
+        : exportGroupSubscription(target.name)
+    load
+      .then((result) => {
+        if (!cancelled) setShare(result)
+      })
+      .catch((err) => {
+        showNotice.error(err)
+        if (!cancelled) onClose()
+      })
+    return () => {
+      cancelled = true
+    }
+  }, [target, onClose])
+
+  return (
+    <Dialog open={!!target} onClose={onClose}>
+      <DialogTitle>
+        {t('proxies.components.share.title', { name: target?.name ?? '' })}
+      </DialogTitle>
+
+      <DialogContent
+        sx={{
+          width: 360,
+          maxHeight: 520,
+          overflowX: 'hidden',
+          userSelect: 'text',
+          pb: 1,
+        }}
+      >
+        {!share && <BaseLoading />}
+
+        {share?.subscription && (
+          <>
+            <ShareCode
+              label={t('proxies.components.share.subscription')}
+              value={share.subscription}
+            />
+            <Divider sx={{ my: 1.5 }} />
+          </>
+        )}
+
+        {share?.links.map((link) => (
+          <Fragment key={link.name}>
+            <ShareCode label={link.name} value={link.link} />
+            {link.left_out.length > 0 && (
+              <Typography variant="caption" color="warning.main">
+                {t('proxies.components.share.leftOut', {
+                  settings: link.left_out.join(', '),
+                })}
+              </Typography>
+            )}
+            <Divider sx={{ my: 1.5 }} />
+          </Fragment>
+        ))}
+
+        {share && share.skipped.length > 0 && (
+          <Typography
+            variant="body2"
+            color="text.secondary"
+            sx={{ wordBreak: 'break-word' }}
+          >
+            {t('proxies.components.share.skipped', {
+              members: share.skipped.join('; '),
+            })}
+          </Typography>
+        )}
+      </DialogContent>
+
+      <DialogActions>
+        <Button onClick={onClose} variant="outlined">
+          {t('shared.actions.close')}
+        </Button>
+      </DialogActions>
+    </Dialog>
+  )
+}
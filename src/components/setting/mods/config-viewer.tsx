@@ -1,16 +1,26 @@
-import { Box, Chip } from '@mui/material'
+import { Box, Button, Chip, Typography } from '@mui/material'
+import { save } from '@tauri-apps/plugin-dialog'
+import { writeFile } from '@tauri-apps/plugin-fs'
+import { useLockFn } from 'ahooks'
 import { forwardRef, useImperativeHandle, useState } from 'react'
 import { useTranslation } from 'react-i18next'
 
-import { DialogRef } from '@/components/base'
+import { BaseDialog, DialogRef } from '@/components/base'
 import { EditorViewer } from '@/components/profile/editor-viewer'
-import { getRuntimeYaml } from '@/services/cmds'
+import { exportRuntimeSingBox, getRuntimeYaml } from '@/services/cmds'
+import { showNotice } from '@/services/notice-service'
+
+interface SingBoxExported {
+  path: string
+  warnings: string[]
+}
 
 export const ConfigViewer = forwardRef<DialogRef>((_, ref) => {
   const { t } = useTranslation()
   const [open, setOpen] = useState(false)
   const [loading, setLoading] = useState(false)
   const [runtimeConfig, setRuntimeConfig] = useState('')
+  const [exported, setExported] = useState<SingBoxExported | null>(null)
 
   useImperativeHandle(ref, () => ({
     open: () => {
@@ -31,22 +41,86 @@ export const ConfigViewer = forwardRef<DialogRef>((_, ref) => {
     close: () => setOpen(false),
   }))
 
+  // 导出为 sing-box 配置，无法转换的部分在保存后列出
+  const onExportSingBox = useLockFn(async () => {
+    try {
+      const { config, warnings } = await exportRuntimeSingBox()
+      const path = await save({
+        defaultPath: 'sing-box.json',
+        filters: [{ name: 'JSON', extensions: ['json'] }],
+      })
+      if (!path) return
+      await writeFile(
+        path,
+        new TextEncoder().encode(`${JSON.stringify(config, null, 2)}\n`),
+      )
+      if (warnings.length > 0) {
+        setExported({ path, warnings })
+      } else {
+        showNotice.success(
+          'settings.components.verge.advanced.notifications.singBoxExported',
+        )
+      }
+    } catch (err) {
+      showNotice.error(err)
+    }
+  })
+
   if (!open) return null
   return (
-    <EditorViewer
-      open={true}
-      title={
-        <Box sx={{ display: 'flex', alignItems: 'center', gap: 2 }}>
-          {t('settings.components.verge.advanced.fields.runtimeConfig')}
-          <Chip label={t('shared.labels.readOnly')} size="small" />
+    <>
+      <EditorViewer
+        open={true}
+        title={
+          <Box sx={{ display: 'flex', alignItems: 'center', gap: 2 }}>
+            {t('settings.components.verge.advanced.fields.runtimeConfig')}
+            <Chip label={t('shared.labels.readOnly')} size="small" />
+            <Button
+              size="small"
+              variant="outlined"
+              sx={{ ml: 'auto' }}
+              disabled={loading}
+              onClick={onExportSingBox}
+            >
+              {t('settings.components.verge.advanced.actions.exportSingBox')}
+            </Button>
+          </Box>
+        }
+        value={runtimeConfig}
+        readOnly
+        language="yaml"
+        path="runtime-config.yaml"
+        loading={loading}
+        onClose={() => setOpen(false)}
+      />
+      <BaseDialog
+        open={!!exported}
+        title={t('settings.components.verge.advanced.singBoxWarnings.title')}
+        cancelBtn={t('shared.actions.close')}
+        disableOk
+        contentSx={{ width: { xs: 320, sm: 420 }, userSelect: 'text' }}
+        onCancel={() => setExported(null)}
+        onClose={() => setExported(null)}
+      >
+        <Typography variant="body2" sx={{ wordBreak: 'break-word' }}>
+          {t('settings.components.verge.advanced.singBoxWarnings.message', {
+            path: exported?.path ?? '',
+          })}
+        </Typography>
+        <Box component="ul" sx={{ pl: 2.5, my: 1 }}>
+          {exported?.warnings.map((warning, index) => (
+            <Typography
+              key={index}
+              component="li"
+              variant="body2"
+              color="text.secondary"
+              sx={{ wordBreak: 'break-word' }}
+            >
+              {warning}
+            </Typography>
+          ))}
         </Box>
-      }
-      value={runtimeConfig}
-      readOnly
-      language="yaml"
-      path="runtime-config.yaml"
-      loading={loading}
-      onClose={() => setOpen(false)}
-    />
+      </BaseDialog>
+    </>
   )
 })
@@ -21,15 +21,25 @@ import {
   DeleteRounded,
   IndeterminateCheckBoxRounded,
   LocalFireDepartmentRounded,
+  QrCodeScannerRounded,
   RefreshRounded,
   TextSnippetOutlined,
 } from '@mui/icons-material'
-import { Box, Button, Divider, Grid, IconButton, Stack } from '@mui/material'
+import {
+  Box,
+  Button,
+  Divider,
+  Grid,
+  IconButton,
+  Menu,
+  MenuItem,
+  Stack,
+} from '@mui/material'
 import { TauriEvent } from '@tauri-apps/api/event'
 import { readText } from '@tauri-apps/plugin-clipboard-manager'
+import { open } from '@tauri-apps/plugin-dialog'
 import { readTextFile } from '@tauri-apps/plugin-fs'
 import { useLockFn } from 'ahooks'
-import { throttle } from 'lodash-es'
 import { useCallback, useEffect, useMemo, useRef, useState } from 'react'
 import { useTranslation } from 'react-i18next'
 import { useLocation } from 'react-router'
@@ -50,6 +60,7 @@ import { ConfigViewer } from '@/components/setting/mods/config-viewer'
 import { useListen } from '@/hooks/use-listen'
 import { useProfiles } from '@/hooks/use-profiles'
 import {
+  cancelUpdateAllProfiles,
   createProfile,
   deleteProfile,
   enhanceProfiles,
@@ -57,8 +68,9 @@ import {
   //restartCore,
   getRuntimeLogs,
   importProfile,
+  importProfileFromQr,
   reorderProfile,
-  updateProfile,
+  updateAllProfiles,
 } from '@/services/cmds'
 import { subscribeVergeEvents } from '@/services/events'
 import { showNotice } from '@/services/notice-service'
@@ -115,6 +127,11 @@ const profileRectSortingStrategy: SortingStrategy = ({
   }
 }
 
+interface BatchUpdateProgress {
+  done: number
+  total: number
+}
+
 interface ProfileSwitchRequest {
   profile: string
   notifySuccess: boolean
@@ -139,12 +156,16 @@ const ProfilePage = () => {
     string | null
   >(null)
   const [loading, setLoading] = useState(false)
+  const [qrAnchor, setQrAnchor] = useState<HTMLElement | null>(null)
   const [timerUpdateRevisions, setTimerUpdateRevisions] = useState<
     Map<string, number>
   >(() => new Map())
   const [completedUpdateRevisions, setCompletedUpdateRevisions] = useState<
     Map<string, number>
   >(() => new Map())
+  // “全部更新”进行中时的进度，null 表示没有批量更新
+  const [batchProgress, setBatchProgress] =
+    useState<BatchUpdateProgress | null>(null)
 
   // Batch selection states
   const [batchMode, setBatchMode] = useState(false)
@@ -267,7 +288,7 @@ const ProfilePage = () => {
   const profileItems = useMemo(() => {
     const items = profiles.items || []
 
-    const type1 = ['local', 'remote']
+    const type1 = ['local', 'remote', 'composite', 'ruleset']
 
     return items.filter((i) => i && type1.includes(i.type!))
   }, [profiles])
@@ -325,6 +346,30 @@ const ProfilePage = () => {
     }
   }
 
+  // 从二维码导入，不传路径时读取剪贴板中的图片；成功提示由后端的 import_sub_url::ok 通知发出
+  const onImportQr = useLockFn(async (fromFile: boolean) => {
+    setQrAnchor(null)
+    let path: string | undefined
+    if (fromFile) {
+      const selected = await open({
+        directory: false,
+        multiple: false,
+        filters: [{ name: 'QR Code', extensions: ['png', 'jpg', 'jpeg'] }],
+      })
+      if (!selected) return
+      path = `${selected}`
+    }
+    setLoading(true)
+    try {
+      await importProfileFromQr(path)
+      await performRobustRefresh()
+    } catch (err) {
+      showNotice.error(err)
+    } finally {
+      setLoading(false)
+    }
+  })
+
   // 强化的刷新策略
   // maxRetries 设为 1：useProfiles 内部 useQuery 已配置 retry:3，业务层只需 1 次额外重试
   const performRobustRefresh = async () => {
@@ -601,6 +646,11 @@ const ProfilePage = () => {
           })
           void mutateProfiles()
         },
+        'verge://profile-batch-progress': ({ uid, status, done, total }) => {
+          // 取消后未开始的订阅不会收到 profile-update-completed
+          if (status === 'cancelled') setLoadingProfiles([uid], false)
+          setBatchProgress((current) => current && { done, total })
+        },
         'verge://timer-updated': (uid) => {
           setTimerUpdateRevisions((current) => {
             const next = new Map(current)
@@ -612,50 +662,34 @@ const ProfilePage = () => {
     [mutateProfiles, setLoadingProfiles],
   )
 
-  const runProfileUpdates = useCallback(
-    async (uids: string[]) => {
-      if (uids.length === 0) return
-
-      const throttleMutate = throttle(mutateProfiles, 2000, {
-        trailing: true,
-      })
-      let cursor = 0
-
-      const updateOne = async (uid: string) => {
-        try {
-          await updateProfile(uid)
-          throttleMutate()
-        } catch (err: any) {
-          console.error(`更新订阅 ${uid} 失败:`, err)
-        }
-      }
-
-      const worker = async () => {
-        while (cursor < uids.length) {
-          const uid = uids[cursor++]
-          await updateOne(uid)
-        }
-      }
-
-      try {
-        const active = Math.min(PROFILE_UPDATE_WORKER_LIMIT, uids.length)
-        await Promise.allSettled(Array.from({ length: active }, worker))
-      } finally {
-        setLoadingProfiles(uids, false)
-        // 避免长时间批量更新后列表数据过晚刷新
-        void mutateProfiles()
-      }
-    },
-    [mutateProfiles, setLoadingProfiles],
-  )
   const onUpdateAll = useLockFn(async () => {
-    const items = profileItems.filter((e) => e.type === 'remote')
-    const target = items
+    const target = (profiles.items ?? [])
+      .filter((e) => e.type === 'remote' || e.type === 'ruleset')
       .map((item) => item.uid)
       .filter((uid) => !loadingCache.has(uid))
 
+    if (target.length === 0) return
+
     setLoadingProfiles(target, true)
-    await runProfileUpdates(target)
+    setBatchProgress({ done: 0, total: target.length })
+    try {
+      // 后端按并发上限逐个更新，结束后只重载一次内核；正在更新的订阅不再重复下载
+      await updateAllProfiles(target, PROFILE_UPDATE_WORKER_LIMIT)
+    } catch (err: any) {
+      console.error('批量更新订阅失败:', err)
+    } finally {
+      setBatchProgress(null)
+      setLoadingProfiles(target, false)
+      void mutateProfiles()
+    }
+  })
+
+  const onCancelUpdateAll = useLockFn(async () => {
+    try {
+      await cancelUpdateAllProfiles()
+    } catch (err: any) {
+      showNotice.error(err)
+    }
   })
 
   const onCopyLink = async () => {
@@ -784,14 +818,26 @@ const ProfilePage = () => {
                 <CheckBoxOutlineBlankRounded />
               </IconButton>
 
-              <IconButton
-                size="small"
-                color="inherit"
-                title={t('profiles.page.actions.updateAll')}
-                onClick={onUpdateAll}
-              >
-                <RefreshRounded />
-              </IconButton>
+              {batchProgress ? (
+                <Button
+                  size="small"
+                  color="inherit"
+                  title={t('profiles.page.actions.cancelUpdateAll')}
+                  startIcon={<ClearRounded />}
+                  onClick={onCancelUpdateAll}
+                >
+                  {t('profiles.page.batch.progress', batchProgress)}
+                </Button>
+              ) : (
+                <IconButton
+                  size="small"
+                  color="inherit"
+                  title={t('profiles.page.actions.updateAll')}
+                  onClick={onUpdateAll}
+                >
+                  <RefreshRounded />
+                </IconButton>
+              )}
 
               <IconButton
                 size="small"
@@ -941,6 +987,27 @@ const ProfilePage = () => {
         >
           {t('profiles.page.actions.import')}
         </Button>
+        <IconButton
+          size="small"
+          color="primary"
+          disabled={loading}
+          title={t('profiles.page.importForm.actions.qr')}
+          onClick={(e) => setQrAnchor(e.currentTarget)}
+        >
+          <QrCodeScannerRounded />
+        </IconButton>
+        <Menu
+          open={!!qrAnchor}
+          anchorEl={qrAnchor}
+          onClose={() => setQrAnchor(null)}
+        >
+          <MenuItem dense onClick={() => onImportQr(true)}>
+            {t('profiles.page.importForm.qr.fromFile')}
+          </MenuItem>
+          <MenuItem dense onClick={() => onImportQr(false)}>
+            {t('profiles.page.importForm.qr.fromClipboard')}
+          </MenuItem>
+        </Menu>
         <Button
           variant="contained"
           size="small"
@@ -989,10 +1056,19 @@ const ProfilePage = () => {
                         completedUpdateRevisions.get(item.uid) ?? 0
                       }
                       mutateProfiles={mutateProfiles}
-                      onSelect={(f) => onSelect(item.uid, f)}
+                      onSelect={(f) => {
+                        // 规则集不能作为当前配置启用
+                        if (item.type !== 'ruleset') onSelect(item.uid, f)
+                      }}
                       onEdit={() => viewerRef.current?.edit(item)}
                       onSave={async (prev, curr) => {
-                        if (prev !== curr && profiles.current === item.uid) {
+                        const current = profiles.items?.find(
+                          (e) => e.uid === profiles.current,
+                        )
+                        const inUse =
+                          profiles.current === item.uid ||
+                          !!current?.option?.rule_sets?.includes(item.uid)
+                        if (prev !== curr && inUse) {
                           await onEnhance(false)
                           //  await restartCore();
                           //   Notice.success(t("settings.feedback.notifications.clash.restartSuccess"), 1000);
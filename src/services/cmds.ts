@@ -56,6 +56,10 @@ export async function importProfile(url: string, option?: IProfileOption) {
   })
 }
 
+export async function importProfileFromQr(path?: string) {
+  return invoke<string>('import_profile_from_qr', { path })
+}
+
 export async function reorderProfile(activeId: string, overId: string) {
   return invoke<void>('reorder_profile', {
     activeId,
@@ -67,6 +71,42 @@ export async function updateProfile(index: string, option?: IProfileOption) {
   return invoke<void>('update_profile', { index, option })
 }
 
+export interface BatchUpdateSummary {
+  total: number
+  updated: number
+  failed: number
+  cancelled: number
+  reloaded: boolean
+}
+
+export async function updateAllProfiles(
+  uids?: string[],
+  concurrency?: number,
+) {
+  return invoke<BatchUpdateSummary>('update_all_profiles', {
+    uids,
+    concurrency,
+  })
+}
+
+export async function cancelUpdateAllProfiles() {
+  return invoke<boolean>('cancel_update_all_profiles')
+}
+
+export async function getProfileChangeSummary(index: string) {
+  return invoke<IProfileChangeSummary | null>('get_profile_change_summary', {
+    index,
+  })
+}
+
+export async function acceptHeldUpdate(index: string) {
+  return invoke<void>('accept_held_update', { index })
+}
+
+export async function rejectHeldUpdate(index: string) {
+  return invoke<void>('reject_held_update', { index })
+}
+
 export async function deleteProfile(index: string) {
   return invoke<void>('delete_profile', { index })
 }
@@ -97,6 +137,18 @@ export async function getRuntimeYaml() {
   return invoke<string | null>('get_runtime_yaml')
 }
 
+export async function exportRuntimeSingBox() {
+  return invoke<ISingBoxExport>('export_runtime_sing_box')
+}
+
+export async function exportProxyShareLink(name: string) {
+  return invoke<IShareLink>('export_proxy_share_link', { name })
+}
+
+export async function exportGroupSubscription(name: string) {
+  return invoke<IGroupShare>('export_group_subscription', { name })
+}
+
 export async function getRuntimeLogs() {
   return invoke<Record<string, [string, string][]>>('get_runtime_logs')
 }
@@ -29,6 +29,14 @@ interface VergeEvents {
   'profile-changed': string
   'profile-update-started': { uid?: string }
   'profile-update-completed': { uid?: string }
+  /** One profile of an "update all" batch finished, failed or was skipped by a cancel. */
+  'verge://profile-batch-progress': {
+    uid: string
+    status: 'updated' | 'failed' | 'cancelled'
+    error?: string
+    done: number
+    total: number
+  }
   /** Frontend to frontend: the home card asks the test page to run every test. */
   'verge://test-all': null
 }
@@ -56,6 +56,7 @@ export const loadMonacoEditor = () => {
     import('monaco-yaml'),
     import('meta-json-schema/schemas/meta-json-schema.json'),
     import('types-pac/pac.d.ts?raw'),
+    import('@/assets/verge-script.d.ts?raw'),
     import('@/utils/yaml.worker?worker'),
   ]).then(
     ([
@@ -67,6 +68,7 @@ export const loadMonacoEditor = () => {
       { configureMonacoYaml },
       { default: metaSchema },
       { default: pac },
+      { default: vergeScript },
       { default: YamlWorker },
     ]) => {
       const workers = {
@@ -93,6 +95,10 @@ export const loadMonacoEditor = () => {
 
           patchCreateWebWorker(monaco)
           monaco.typescript.javascriptDefaults.addExtraLib(pac, 'pac.d.ts')
+          monaco.typescript.javascriptDefaults.addExtraLib(
+            vergeScript,
+            'verge-script.d.ts',
+          )
 
           configureMonacoYaml(monaco, {
             validate: true,
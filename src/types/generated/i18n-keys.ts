@@ -147,6 +147,7 @@ export const translationKeys = [
   'profiles.page.actions.viewRuntimeConfig',
   'profiles.page.actions.reactivate',
   'profiles.page.actions.import',
+  'profiles.page.actions.cancelUpdateAll',
   'profiles.page.batch.actions.delete',
   'profiles.page.batch.actions.selectAll',
   'profiles.page.batch.actions.deselectAll',
@@ -154,8 +155,12 @@ export const translationKeys = [
   'profiles.page.batch.summary.selected',
   'profiles.page.batch.summary.items',
   'profiles.page.batch.title',
+  'profiles.page.batch.progress',
   'profiles.page.importForm.placeholder',
   'profiles.page.importForm.actions.paste',
+  'profiles.page.importForm.actions.qr',
+  'profiles.page.importForm.qr.fromFile',
+  'profiles.page.importForm.qr.fromClipboard',
   'profiles.page.feedback.errors.invalidUrl',
   'profiles.page.feedback.errors.onlyYaml',
   'profiles.page.feedback.errors.createFailed',
@@ -194,6 +199,9 @@ export const translationKeys = [
   'profiles.components.menu.openFile',
   'profiles.components.menu.update',
   'profiles.components.menu.updateViaProxy',
+  'profiles.components.menu.lastChange',
+  'profiles.components.menu.editFilter',
+  'profiles.components.menu.mirrors',
   'profiles.components.more.global.merge',
   'profiles.components.more.global.script',
   'profiles.components.more.chips.merge',
@@ -205,6 +213,31 @@ export const translationKeys = [
   'profiles.components.profileItem.status.noSchedule',
   'profiles.components.profileItem.status.unknown',
   'profiles.components.profileItem.status.autoUpdateDisabled',
+  'profiles.components.profileItem.composite',
+  'profiles.components.profileItem.ruleSet',
+  'profiles.components.heldUpdate.title',
+  'profiles.components.heldUpdate.tooFewNodes',
+  'profiles.components.heldUpdate.tooManyRemoved',
+  'profiles.components.heldUpdate.message',
+  'profiles.components.heldUpdate.accept',
+  'profiles.components.heldUpdate.reject',
+  'profiles.components.mirrors.title',
+  'profiles.components.mirrors.primary',
+  'profiles.components.mirrors.lastUsed',
+  'profiles.components.mirrors.failed',
+  'profiles.components.mirrors.fromMirror',
+  'profiles.components.mirrors.hasErrors',
+  'profiles.components.updateFailure.title',
+  'profiles.components.updateFailure.count',
+  'profiles.components.updateFailure.lastAttempt',
+  'profiles.components.updateFailure.retry',
+  'profiles.components.conversion.title',
+  'profiles.components.conversion.badge',
+  'profiles.components.conversion.summary',
+  'profiles.components.conversion.complete',
+  'profiles.components.conversion.skipped',
+  'profiles.components.conversion.leftOut',
+  'profiles.components.conversion.dropped',
   'profiles.modals.profileForm.title.create',
   'profiles.modals.profileForm.title.edit',
   'profiles.modals.profileForm.fields.type',
@@ -217,14 +250,40 @@ export const translationKeys = [
   'profiles.modals.profileForm.fields.useClashProxy',
   'profiles.modals.profileForm.fields.acceptInvalidCerts',
   'profiles.modals.profileForm.fields.allowAutoUpdate',
+  'profiles.modals.profileForm.fields.sources',
+  'profiles.modals.profileForm.fields.primary',
+  'profiles.modals.profileForm.fields.primaryDefault',
+  'profiles.modals.profileForm.fields.ruleSetUrl',
+  'profiles.modals.profileForm.fields.policy',
+  'profiles.modals.profileForm.fields.ruleSetMode',
+  'profiles.modals.profileForm.fields.ruleSets',
+  'profiles.modals.profileForm.fields.regionGroups',
+  'profiles.modals.profileForm.fields.regionGroupType',
+  'profiles.modals.profileForm.fields.regionSelector',
+  'profiles.modals.profileForm.fields.regionSelectorDefault',
+  'profiles.modals.profileForm.fields.regions',
+  'profiles.modals.profileForm.fields.regionsDefault',
+  'profiles.modals.profileForm.fields.regionTestUrl',
+  'profiles.modals.profileForm.fields.regionInterval',
+  'profiles.modals.profileForm.fields.mirrors',
+  'profiles.modals.profileForm.fields.mirrorsPlaceholder',
+  'profiles.modals.profileForm.fields.updateCron',
+  'profiles.modals.profileForm.fields.updateCronHelper',
+  'profiles.modals.profileForm.fields.updateJitter',
   'profiles.modals.profileForm.types.remote',
   'profiles.modals.profileForm.types.local',
+  'profiles.modals.profileForm.types.composite',
+  'profiles.modals.profileForm.types.ruleset',
   'profiles.modals.profileForm.errors.typeRequired',
   'profiles.modals.profileForm.errors.urlRequired',
   'profiles.modals.profileForm.errors.uidMissing',
   'profiles.modals.profileForm.errors.saveFailed',
+  'profiles.modals.profileForm.errors.sourcesRequired',
+  'profiles.modals.profileForm.errors.policyRequired',
   'profiles.modals.profileForm.feedback.notifications.creationRetry',
   'profiles.modals.profileForm.feedback.notifications.creationSuccess',
+  'profiles.modals.profileForm.ruleSetModes.provider',
+  'profiles.modals.profileForm.ruleSetModes.inline',
   'profiles.modals.proxiesEditor.title',
   'profiles.modals.proxiesEditor.placeholders.multiUri',
   'profiles.modals.proxiesEditor.actions.prepend',
@@ -260,6 +319,18 @@ export const translationKeys = [
   'profiles.modals.confirmDelete.message',
   'profiles.modals.logViewer.title',
   'profiles.modals.qrViewer.title',
+  'profiles.modals.changeViewer.title',
+  'profiles.modals.changeViewer.updatedAt',
+  'profiles.modals.changeViewer.nodes',
+  'profiles.modals.changeViewer.rules',
+  'profiles.modals.changeViewer.more',
+  'profiles.modals.changeViewer.sections.nodesAdded',
+  'profiles.modals.changeViewer.sections.nodesRemoved',
+  'profiles.modals.changeViewer.sections.nodesRenamed',
+  'profiles.modals.changeViewer.sections.groupsAdded',
+  'profiles.modals.changeViewer.sections.groupsRemoved',
+  'profiles.modals.changeViewer.sections.groupsChanged',
+  'profiles.modals.changeViewer.sections.providersAdded',
   'proxies.page.modes.rule',
   'proxies.page.modes.global',
   'proxies.page.modes.direct',
@@ -285,6 +356,7 @@ export const translationKeys = [
   'proxies.page.tooltips.showBasic',
   'proxies.page.tooltips.showDetail',
   'proxies.page.tooltips.filter',
+  'proxies.page.tooltips.share',
   'proxies.page.placeholders.delayCheckUrl',
   'proxies.page.empty.noAvailableGroups',
   'proxies.page.empty.noProxies',
@@ -327,6 +399,12 @@ export const translationKeys = [
   'proxies.components.enums.policies.REJECT',
   'proxies.components.enums.policies.REJECT-DROP',
   'proxies.components.enums.policies.PASS',
+  'proxies.components.share.title',
+  'proxies.components.share.subscription',
+  'proxies.components.share.copy',
+  'proxies.components.share.tooLong',
+  'proxies.components.share.leftOut',
+  'proxies.components.share.skipped',
   'rules.page.provider.trigger',
   'rules.page.provider.dialogTitle',
   'rules.page.provider.actions.updateAll',
@@ -480,8 +558,10 @@ export const translationKeys = [
   'settings.components.verge.advanced.tooltips.openConfDir',
   'settings.components.verge.advanced.tooltips.liteMode',
   'settings.components.verge.advanced.actions.copyVersion',
+  'settings.components.verge.advanced.actions.exportSingBox',
   'settings.components.verge.advanced.notifications.latestVersion',
   'settings.components.verge.advanced.notifications.versionCopied',
+  'settings.components.verge.advanced.notifications.singBoxExported',
   'settings.components.verge.advanced.fields.backupSetting',
   'settings.components.verge.advanced.fields.runtimeConfig',
   'settings.components.verge.advanced.fields.openConfDir',
@@ -493,6 +573,8 @@ export const translationKeys = [
   'settings.components.verge.advanced.fields.exit',
   'settings.components.verge.advanced.fields.exportDiagnostics',
   'settings.components.verge.advanced.fields.vergeVersion',
+  'settings.components.verge.advanced.singBoxWarnings.title',
+  'settings.components.verge.advanced.singBoxWarnings.message',
   'settings.components.verge.theme.title',
   'settings.components.verge.theme.fields.primaryColor',
   'settings.components.verge.theme.fields.secondaryColor',
@@ -255,11 +255,29 @@ export interface TranslationResources {
             clickToImport: string
           }
         }
+        conversion: {
+          badge: string
+          complete: string
+          dropped: string
+          leftOut: string
+          skipped: string
+          summary: string
+          title: string
+        }
         fileInput: {
           chooseFile: string
         }
+        heldUpdate: {
+          accept: string
+          message: string
+          reject: string
+          title: string
+          tooFewNodes: string
+          tooManyRemoved: string
+        }
         menu: {
           editFile: string
+          editFilter: string
           editGroups: string
           editInfo: string
           editProxies: string
@@ -267,12 +285,22 @@ export interface TranslationResources {
           extendConfig: string
           extendScript: string
           home: string
+          lastChange: string
+          mirrors: string
           openFile: string
           select: string
           shareQrCode: string
           update: string
           updateViaProxy: string
         }
+        mirrors: {
+          failed: string
+          fromMirror: string
+          hasErrors: string
+          lastUsed: string
+          primary: string
+          title: string
+        }
         more: {
           chips: {
             merge: string
@@ -284,6 +312,8 @@ export interface TranslationResources {
           }
         }
         profileItem: {
+          composite: string
+          ruleSet: string
           status: {
             autoUpdateDisabled: string
             lastUpdateFailed: string
@@ -296,8 +326,30 @@ export interface TranslationResources {
             showNext: string
           }
         }
+        updateFailure: {
+          count: string
+          lastAttempt: string
+          retry: string
+          title: string
+        }
       }
       modals: {
+        changeViewer: {
+          more: string
+          nodes: string
+          rules: string
+          sections: {
+            groupsAdded: string
+            groupsChanged: string
+            groupsRemoved: string
+            nodesAdded: string
+            nodesRemoved: string
+            nodesRenamed: string
+            providersAdded: string
+          }
+          title: string
+          updatedAt: string
+        }
         confirmDelete: {
           message: string
           title: string
@@ -350,7 +402,9 @@ export interface TranslationResources {
         }
         profileForm: {
           errors: {
+            policyRequired: string
             saveFailed: string
+            sourcesRequired: string
             typeRequired: string
             uidMissing: string
             urlRequired: string
@@ -366,20 +420,46 @@ export interface TranslationResources {
             allowAutoUpdate: string
             description: string
             httpTimeout: string
+            mirrors: string
+            mirrorsPlaceholder: string
+            policy: string
+            primary: string
+            primaryDefault: string
+            regionGroups: string
+            regionGroupType: string
+            regionInterval: string
+            regions: string
+            regionsDefault: string
+            regionSelector: string
+            regionSelectorDefault: string
+            regionTestUrl: string
+            ruleSetMode: string
+            ruleSets: string
+            ruleSetUrl: string
+            sources: string
             subscriptionUrl: string
             type: string
+            updateCron: string
+            updateCronHelper: string
             updateInterval: string
+            updateJitter: string
             useClashProxy: string
             userAgent: string
             useSystemProxy: string
           }
+          ruleSetModes: {
+            inline: string
+            provider: string
+          }
           title: {
             create: string
             edit: string
           }
           types: {
+            composite: string
             local: string
             remote: string
+            ruleset: string
           }
         }
         proxiesEditor: {
@@ -398,6 +478,7 @@ export interface TranslationResources {
       }
       page: {
         actions: {
+          cancelUpdateAll: string
           import: string
           reactivate: string
           updateAll: string
@@ -410,6 +491,7 @@ export interface TranslationResources {
             done: string
             selectAll: string
           }
+          progress: string
           summary: {
             items: string
             selected: string
@@ -451,8 +533,13 @@ export interface TranslationResources {
         importForm: {
           actions: {
             paste: string
+            qr: string
           }
           placeholder: string
+          qr: {
+            fromClipboard: string
+            fromFile: string
+          }
         }
         title: string
       }
@@ -474,6 +561,14 @@ export interface TranslationResources {
             'url-test': string
           }
         }
+        share: {
+          copy: string
+          leftOut: string
+          skipped: string
+          subscription: string
+          title: string
+          tooLong: string
+        }
       }
       feedback: {
         notifications: {
@@ -568,6 +663,7 @@ export interface TranslationResources {
           delayCheckUrl: string
           filter: string
           locate: string
+          share: string
           showBasic: string
           showDetail: string
           sortDefault: string
@@ -664,6 +760,7 @@ export interface TranslationResources {
           advanced: {
             actions: {
               copyVersion: string
+              exportSingBox: string
             }
             fields: {
               backupSetting: string
@@ -680,8 +777,13 @@ export interface TranslationResources {
             }
             notifications: {
               latestVersion: string
+              singBoxExported: string
               versionCopied: string
             }
+            singBoxWarnings: {
+              message: string
+              title: string
+            }
             title: string
             tooltips: {
               backupInfo: string
@@ -18,9 +18,20 @@ declare const OS_PLATFORM: Platform
 
 type ValidationOutcome =
   | { status: 'valid' | 'busy' }
-  | { status: 'invalid'; kind: string; message: string }
+  | { status: 'invalid'; kind: string; message: string; script?: IScriptError }
   | { status: 'skipped'; reason: string }
 
+interface IScriptError {
+  kind: 'syntax' | 'missingMain' | 'runtime' | 'invalidResult' | 'limit'
+  message: string
+  line: number | null
+  column: number | null
+  excerpt: string | null
+  stack: string | null
+  stage: string | null
+  uid: string | null
+}
+
 /**
  * Some interface for clash api
  */
@@ -230,11 +241,12 @@ interface IClashInfo {
 
 interface IProfileItem {
   uid: string
-  type?: 'local' | 'remote' | 'merge' | 'script'
+  type?: 'local' | 'remote' | 'composite' | 'ruleset' | 'merge' | 'script'
   name?: string
   desc?: string
   file?: string
   url?: string
+  mirrors?: string[]
   updated?: number
   selected?: {
     name?: string
@@ -248,6 +260,90 @@ interface IProfileItem {
   }
   option?: IProfileOption
   home?: string
+  alert?: {
+    usage?: number
+    expiring?: number
+    expired?: number
+  }
+  update_state?: {
+    last_attempt?: number
+    last_success?: number
+    consecutive_failures?: number
+    last_held?: number
+    last_error?:
+      | 'network'
+      | 'timeout'
+      | 'tls'
+      | 'server_error'
+      | 'client_error'
+      | 'invalid_content'
+      | 'other'
+    last_error_message?: string
+    last_mirror?: string
+    mirror_errors?: {
+      url: string
+      category: string
+      message: string
+    }[]
+  }
+  held?: {
+    reason: 'too_few_nodes' | 'too_many_removed'
+    nodes_before: number
+    nodes_after: number
+    removed: number
+    fetched_at: number
+    file: string
+  }
+  last_change?: IProfileChangeSummary
+  conversion?: IConversionReport
+}
+
+interface IConversionReport {
+  format: string
+  converted: number
+  nodes?: {
+    name: string
+    skipped: boolean
+    unsupported: string[]
+  }[]
+  dropped?: string[]
+}
+
+interface ISingBoxExport {
+  config: Record<string, any>
+  warnings: string[]
+}
+
+interface IShareLink {
+  name: string
+  link: string
+  left_out: string[]
+}
+
+interface IGroupShare {
+  subscription: string
+  links: IShareLink[]
+  skipped: string[]
+}
+
+interface IProfileChangeList {
+  count: number
+  names?: string[]
+}
+
+interface IProfileChangeSummary {
+  updated_at: number
+  nodes_before: number
+  nodes_after: number
+  nodes_added: IProfileChangeList
+  nodes_removed: IProfileChangeList
+  nodes_renamed: IProfileChangeList
+  groups_added: IProfileChangeList
+  groups_removed: IProfileChangeList
+  groups_changed: IProfileChangeList
+  providers_added: IProfileChangeList
+  rules_before: number
+  rules_after: number
 }
 
 interface IProfileOption {
@@ -255,6 +351,12 @@ interface IProfileOption {
   with_proxy?: boolean
   self_proxy?: boolean
   update_interval?: number
+  update_cron?: string
+  update_jitter?: number
+  update_guard?: {
+    min_nodes?: number
+    max_removed_percent?: number
+  }
   timeout_seconds?: number
   danger_accept_invalid_certs?: boolean
   allow_auto_update?: boolean
@@ -263,6 +365,26 @@ interface IProfileOption {
   rules?: string
   proxies?: string
   groups?: string
+  filter?: string
+  sources?: string[]
+  primary?: string
+  region_groups?: IProfileRegionGroups
+  rule_sets?: string[]
+  rule_set?: IProfileRuleSet
+}
+
+interface IProfileRegionGroups {
+  enable: boolean
+  group_type?: 'url-test' | 'fallback'
+  selector?: string
+  regions?: string[]
+  url?: string
+  interval?: number
+}
+
+interface IProfileRuleSet {
+  policy: string
+  mode?: 'provider' | 'inline'
 }
 
 interface IProfilesConfig {
@@ -896,6 +1018,10 @@ interface IVergeConfig {
   enable_tun_mode?: boolean
   enable_auto_light_weight_mode?: boolean
   auto_light_weight_minutes?: number
+  enable_subscription_alerts?: boolean
+  subscription_alert_thresholds?: number[]
+  subscription_alert_expire_days?: number
+  notify_subscription_changes?: boolean
   enable_auto_launch?: boolean
   enable_silent_start?: boolean
   enable_system_proxy?: boolean